@@ -0,0 +1,44 @@
+//! Compares the time taken to move to the next generation an Environment
+//! whose entities observe large neighborhoods, depending on the order in which
+//! its tiles are stored in memory.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use semeion::*;
+
+/// The side of the squared Environment.
+const SIDE: i32 = 512;
+
+/// Constructs a new Environment with the given order of tiles, populated with
+/// one Entity every few tiles, each counting the entities in its neighborhood.
+fn populate<'e>(order: TileOrder) -> Environment<'e, u8> {
+    let mut env = Environment::with_tile_order((SIDE, SIDE), order);
+    for y in (0..SIDE).step_by(4) {
+        for x in (0..SIDE).step_by(4) {
+            env.insert(QuickEntity::new(0).at((x, y)).scope(4).on_observe(
+                |_, neighborhood| {
+                    let count = neighborhood.map_or(0, |n| {
+                        n.tiles().map(TileView::count).sum::<usize>()
+                    });
+                    std::hint::black_box(count);
+                    Ok(())
+                },
+            ));
+        }
+    }
+    env
+}
+
+fn nextgen(c: &mut Criterion) {
+    let mut group = c.benchmark_group("nextgen");
+    for order in [TileOrder::RowMajor, TileOrder::Morton] {
+        let mut env = populate(order);
+        group.bench_function(
+            BenchmarkId::from_parameter(format!("{:?}", order)),
+            |b| b.iter(|| env.nextgen().unwrap()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, nextgen);
+criterion_main!(benches);
@@ -44,9 +44,8 @@ impl Grid {
     }
 }
 
-impl<'a> Entity<'a> for Grid {
+impl<'a> Entity<'a, Context> for Grid {
     type Kind = Kind;
-    type Context = Context;
 
     fn id(&self) -> Id {
         self.id
@@ -56,6 +55,14 @@ impl<'a> Entity<'a> for Grid {
         Kind::Grid
     }
 
+    fn drawable(&self) -> Option<&dyn Drawable<Context = Context>> {
+        Some(self)
+    }
+}
+
+impl Drawable for Grid {
+    type Context = Context;
+
     fn draw(
         &self,
         ctx: &mut Self::Context,
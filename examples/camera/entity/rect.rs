@@ -37,9 +37,8 @@ impl Rect {
     }
 }
 
-impl<'a> Entity<'a> for Rect {
+impl<'a> Entity<'a, Context> for Rect {
     type Kind = Kind;
-    type Context = Context;
 
     fn id(&self) -> Id {
         self.id
@@ -51,7 +50,7 @@ impl<'a> Entity<'a> for Rect {
 
     fn react(
         &mut self,
-        _: Option<Neighborhood<Self::Kind, Self::Context>>,
+        _: Option<Neighborhood<Self::Kind, Context>>,
     ) -> Result<(), Error> {
         // increase the angle to make the Rect rotate around its center at each
         // generation
@@ -59,19 +58,28 @@ impl<'a> Entity<'a> for Rect {
         Ok(())
     }
 
+    fn drawable(&self) -> Option<&dyn Drawable<Context = Context>> {
+        Some(self)
+    }
+}
+
+impl Drawable for Rect {
+    type Context = Context;
+
     fn draw(
         &self,
         ctx: &mut Self::Context,
         mut transform: Transform,
     ) -> Result<(), Error> {
-        // shift the center of the Rect to the center of the Tile
-        let half_size = ENTITY_SIZE / 2.0;
-        let center_offset = half_size - env::SIDE / 2.0;
-        let loc = self.location.to_pixel_coords(env::SIDE) - center_offset;
-
-        // translate according to the entity location and rotate around its center
-        transform *= Transform::translate(loc)
-            * Transform::rotate_around(self.angle, [half_size, half_size]);
+        // shift the center of the Rect to the center of the Tile, around which
+        // the Rect is rotated
+        let anchor = (env::SIDE - ENTITY_SIZE) / 2.0;
+        transform *= Transform::for_entity(
+            self.location,
+            env::SIDE,
+            self.angle,
+            [anchor, anchor],
+        );
 
         graphics::draw(
             ctx,
@@ -1,7 +1,9 @@
-//! Zoom and pan transformations driven by user inputs.
+//! Zoom, pan and rotation of a Camera driven by user inputs.
 //!
 //! Zoom with keys Up and Down.
 //! Pan with A, W, D, and S.
+//! Rotate with Q and E.
+//! Fit the whole environment in the window with F.
 
 use ggez::input::keyboard::*;
 use ggez::*;
@@ -15,8 +17,10 @@ mod env;
 struct GameState<'a> {
     // the environment where the simulation takes place
     env: Environment<'a, Kind, Context>,
-    // the global transformation matrix
-    transform: Transform,
+    // the fixed timestep of the generations
+    ticker: Ticker,
+    // the camera that gives the global transformation matrix
+    camera: Camera,
 }
 
 impl<'a> GameState<'a> {
@@ -28,21 +32,26 @@ impl<'a> GameState<'a> {
         // a grid as a static entity used only for drawing purposes in order to
         // show the white grid cells borders
         env.insert(Grid::new(grid::mesh(ctx)?));
-        env.insert(Rect::new(env::dimension().center(), rect::mesh(ctx)?));
+        env.set_passive(Kind::Grid, true);
+        env.insert(entity::Rect::new(
+            env::dimension().center(),
+            rect::mesh(ctx)?,
+        ));
+
+        let mut camera = Camera::new(env::size());
+        camera.set_zoom(0.9);
 
         Ok(Self {
             env,
-            transform: Transform::scale_around(
-                [0.9, 0.9],
-                env::size().center(),
-            ),
+            ticker: Ticker::with_rate(10),
+            camera,
         })
     }
 }
 
 impl<'a> event::EventHandler<GameError> for GameState<'a> {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        while timer::check_update_time(ctx, 10) {
+        while self.ticker.should_tick(timer::delta(ctx)) {
             self.env
                 .nextgen()
                 .expect("Cannot move to the next generation");
@@ -53,7 +62,7 @@ impl<'a> event::EventHandler<GameError> for GameState<'a> {
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         graphics::clear(ctx, [0.9, 0.9, 0.9, 1.0].into());
         self.env
-            .draw(ctx, self.transform)
+            .draw(ctx, self.camera.transform())
             .expect("Cannot draw the environment");
         graphics::present(ctx)?;
         timer::yield_now();
@@ -67,24 +76,23 @@ impl<'a> event::EventHandler<GameError> for GameState<'a> {
         _keymods: KeyMods,
         _repeat: bool,
     ) {
-        // compute the translation matrix
-        let t = match keycode {
-            KeyCode::A => Transform::translate([10.0, 0.0]),
-            KeyCode::D => Transform::translate([-10.0, 0.0]),
-            KeyCode::W => Transform::translate([0.0, 10.0]),
-            KeyCode::S => Transform::translate([0.0, -10.0]),
-            _ => Transform::identity(),
-        };
-
-        // compute the scaling matrix around the center of the window
         let center = env::size().center();
-        let s = match keycode {
-            KeyCode::Up => Transform::scale_around([1.1, 1.1], center),
-            KeyCode::Down => Transform::scale_around([0.9, 0.9], center),
-            _ => Transform::identity(),
-        };
-
-        self.transform *= t * s;
+        let camera = &mut self.camera;
+        match keycode {
+            KeyCode::A => camera.pan([10.0, 0.0]),
+            KeyCode::D => camera.pan([-10.0, 0.0]),
+            KeyCode::W => camera.pan([0.0, 10.0]),
+            KeyCode::S => camera.pan([0.0, -10.0]),
+            // zoom around the center of the window
+            KeyCode::Up => camera.zoom_around(1.1, center),
+            KeyCode::Down => camera.zoom_around(0.9, center),
+            KeyCode::Q => camera.set_rotation(camera.rotation() - 15.0),
+            KeyCode::E => camera.set_rotation(camera.rotation() + 15.0),
+            KeyCode::F => {
+                camera.fit(env::dimension(), TileGrid::new(env::SIDE))
+            }
+            _ => (),
+        }
     }
 }
 
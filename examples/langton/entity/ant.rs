@@ -22,19 +22,11 @@ pub fn mesh(ctx: &mut Context) -> Result<graphics::Mesh, GameError> {
     mesh.build(ctx)
 }
 
-/// The direction towards where the ant is looking.
-#[derive(Debug)]
-enum Direction {
-    Left,
-    Up,
-    Right,
-    Down,
-}
-
 #[derive(Debug)]
 pub struct Ant<'a> {
     id: Id,
-    direction: Direction,
+    // the direction towards where the ant is looking
+    orientation: Orientation,
     location: Location,
     mesh: graphics::Mesh,
     offspring_mesh: graphics::Mesh,
@@ -52,7 +44,7 @@ impl<'a> Ant<'a> {
             // IDs are simply randomly generated as the possibility of collisions
             // are very very low
             id: rand::random(),
-            direction: Direction::Left,
+            orientation: Orientation::West,
             mesh,
             location,
             offspring_mesh,
@@ -62,32 +54,21 @@ impl<'a> Ant<'a> {
 
     /// Turn the Ant 90° clockwise and move forwards of one tile.
     fn turn_right_and_move_forward(&mut self) {
-        let (offset, direction) = match self.direction {
-            Direction::Left => ((0, -1), Direction::Up),
-            Direction::Up => ((1, 0), Direction::Right),
-            Direction::Right => ((0, 1), Direction::Down),
-            Direction::Down => ((-1, 0), Direction::Left),
-        };
-        self.direction = direction;
-        self.location.translate(offset, env::dimension());
+        self.orientation = self.orientation.turn_right();
+        let forward = self.orientation.forward();
+        self.location.translate(forward, env::dimension());
     }
 
     /// Turn the Ant 90° counter-clockwise and move forwards of one tile.
     fn turn_left_and_move_forward(&mut self) {
-        let (offset, direction) = match self.direction {
-            Direction::Up => ((-1, 0), Direction::Left),
-            Direction::Right => ((0, -1), Direction::Up),
-            Direction::Down => ((1, 0), Direction::Right),
-            Direction::Left => ((0, 1), Direction::Down),
-        };
-        self.direction = direction;
-        self.location.translate(offset, env::dimension());
+        self.orientation = self.orientation.turn_left();
+        let forward = self.orientation.forward();
+        self.location.translate(forward, env::dimension());
     }
 }
 
-impl<'a> Entity<'a> for Ant<'a> {
+impl<'a> Entity<'a, Context> for Ant<'a> {
     type Kind = Kind;
-    type Context = Context;
 
     fn id(&self) -> Id {
         self.id
@@ -107,6 +88,10 @@ impl<'a> Entity<'a> for Ant<'a> {
         Some(Scope::empty())
     }
 
+    fn orientation(&self) -> Option<Orientation> {
+        Some(self.orientation)
+    }
+
     fn lifespan(&self) -> Option<Lifespan> {
         // The lifespan of the Ant is infinite.
         Some(Lifespan::Immortal)
@@ -119,7 +104,7 @@ impl<'a> Entity<'a> for Ant<'a> {
     ///     square, move forward one unit.
     fn react(
         &mut self,
-        neighborhood: Option<Neighborhood<Self::Kind, Self::Context>>,
+        neighborhood: Option<Neighborhood<Self::Kind, Context>>,
     ) -> Result<(), Error> {
         // given the scope of the Ant, we expect the seeable portion of the
         // environment to be just the tile where the Ant is currently located
@@ -152,13 +137,19 @@ impl<'a> Entity<'a> for Ant<'a> {
         Ok(())
     }
 
-    fn offspring(
-        &mut self,
-    ) -> Option<Offspring<'a, Self::Kind, Self::Context>> {
+    fn offspring(&mut self) -> Option<Offspring<'a, Self::Kind, Context>> {
         // release the offspring (if any) to the environment
         Some(self.offspring.drain())
     }
 
+    fn drawable(&self) -> Option<&dyn Drawable<Context = Context>> {
+        Some(self)
+    }
+}
+
+impl<'a> Drawable for Ant<'a> {
+    type Context = Context;
+
     fn draw(
         &self,
         ctx: &mut Self::Context,
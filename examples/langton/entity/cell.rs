@@ -36,9 +36,8 @@ impl Cell {
     }
 }
 
-impl<'a> Entity<'a> for Cell {
+impl<'a> Entity<'a, Context> for Cell {
     type Kind = Kind;
-    type Context = Context;
 
     fn id(&self) -> Id {
         self.id
@@ -61,6 +60,14 @@ impl<'a> Entity<'a> for Cell {
         Some(&mut self.lifespan)
     }
 
+    fn drawable(&self) -> Option<&dyn Drawable<Context = Context>> {
+        Some(self)
+    }
+}
+
+impl Drawable for Cell {
+    type Context = Context;
+
     fn draw(
         &self,
         ctx: &mut Self::Context,
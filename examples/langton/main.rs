@@ -11,6 +11,8 @@ mod env;
 struct GameState<'a> {
     // the environment where the simulation takes place
     env: Environment<'a, Kind, Context>,
+    // the fixed timestep of the generations
+    ticker: Ticker,
 }
 
 impl<'a> GameState<'a> {
@@ -22,19 +24,23 @@ impl<'a> GameState<'a> {
         // a grid as a static entity used only for drawing purposes in order to
         // show the white grid cells borders
         env.insert(Grid::new(grid::mesh(ctx)?));
+        env.set_passive(Kind::Grid, true);
 
         // the ant, placed in the center of the environment
         let location = env::dimension().center();
         env.insert(Ant::new(location, ant::mesh(ctx)?, cell::mesh(ctx)?));
 
-        Ok(Self { env })
+        Ok(Self {
+            env,
+            ticker: Ticker::with_rate(60),
+        })
     }
 
     /// Draw stats in the bottom-right corner of the screen.
     fn display_stats(&self, ctx: &mut Context) -> GameResult {
         use mint::Point2;
 
-        let text = format!("Generation: {:?}", self.env.generation());
+        let text = format!("Generation: {}", self.env.generation());
         let foreground = graphics::Color::new(0.1, 0.2, 0.3, 3.0);
         let fragment = graphics::TextFragment::new(text).color(foreground);
         let text = graphics::Text::new(fragment);
@@ -50,7 +56,7 @@ impl<'a> GameState<'a> {
 
 impl<'a> event::EventHandler<GameError> for GameState<'a> {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        while timer::check_update_time(ctx, 60) {
+        while self.ticker.should_tick(timer::delta(ctx)) {
             self.env
                 .nextgen()
                 .expect("Cannot move to the next generation");
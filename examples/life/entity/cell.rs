@@ -1,10 +1,6 @@
 use ggez::{graphics, mint::Point2, Context, GameError};
 use semeion::*;
-use std::{
-    cell::RefCell,
-    collections::HashSet,
-    rc::{Rc, Weak},
-};
+use std::rc::Rc;
 
 use super::Kind;
 use crate::env;
@@ -26,16 +22,11 @@ pub struct Cell<'a> {
     // all the Cells share the same Mesh
     mesh: Rc<graphics::Mesh>,
     offspring: Offspring<'a, Kind, Context>,
-    visited: Weak<RefCell<HashSet<Location>>>,
 }
 
 impl<'a> Cell<'a> {
     /// Constructs a new Cell.
-    pub fn new(
-        location: Location,
-        mesh: Rc<graphics::Mesh>,
-        visited: Weak<RefCell<HashSet<Location>>>,
-    ) -> Self {
+    pub fn new(location: Location, mesh: Rc<graphics::Mesh>) -> Self {
         Self {
             // ID are simply randomly generated as the possibility of collisions
             // are very very low
@@ -47,14 +38,12 @@ impl<'a> Cell<'a> {
             lifespan: Lifespan::Immortal,
             mesh,
             offspring: Offspring::default(),
-            visited,
         }
     }
 }
 
-impl<'a> Entity<'a> for Cell<'a> {
+impl<'a> Entity<'a, Context> for Cell<'a> {
     type Kind = Kind;
-    type Context = Context;
 
     fn id(&self) -> Id {
         self.id
@@ -91,7 +80,7 @@ impl<'a> Entity<'a> for Cell<'a> {
     ///     dead cells stay dead.
     fn react(
         &mut self,
-        neighborhood: Option<Neighborhood<Self::Kind, Self::Context>>,
+        neighborhood: Option<Neighborhood<Self::Kind, Context>>,
     ) -> Result<(), Error> {
         // given the scope of the Cell (= 2) we expect a valid neighborhood
         let neighborhood = neighborhood.expect("Invalid neighborhood");
@@ -122,16 +111,6 @@ impl<'a> Entity<'a> for Cell<'a> {
             let loc =
                 *self.location.clone().translate(offset, env::dimension());
 
-            // skip this tile if already visited by another Cell, or store this
-            // information in the cache of already visited tiles
-            if let Some(visited) = self.visited.upgrade() {
-                if visited.borrow().contains(&loc) {
-                    continue;
-                } else {
-                    visited.borrow_mut().insert(loc);
-                }
-            }
-
             // skip the tile if it already contains a living cell
             if !neighborhood.tile(offset).is_empty() {
                 // if there is an entity in this tile, it must be a single living
@@ -152,25 +131,28 @@ impl<'a> Entity<'a> for Cell<'a> {
             if count == 3 {
                 debug_assert!(neighborhood.tile(offset).is_empty());
                 // this Cell will introduce in the environment a new living cell
-                // as part of its offspring
-                self.offspring.insert(Cell::new(
-                    loc,
-                    Rc::clone(&self.mesh),
-                    Weak::clone(&self.visited),
-                ))
+                // as part of its offspring (deduplicated by the environment
+                // if other cells introduce the same one)
+                self.offspring.insert(Cell::new(loc, Rc::clone(&self.mesh)))
             }
         }
 
         Ok(())
     }
 
-    fn offspring(
-        &mut self,
-    ) -> Option<Offspring<'a, Self::Kind, Self::Context>> {
+    fn offspring(&mut self) -> Option<Offspring<'a, Self::Kind, Context>> {
         // release the offspring (if any) to the environment
         Some(self.offspring.drain())
     }
 
+    fn drawable(&self) -> Option<&dyn Drawable<Context = Context>> {
+        Some(self)
+    }
+}
+
+impl<'a> Drawable for Cell<'a> {
+    type Context = Context;
+
     fn draw(
         &self,
         ctx: &mut Self::Context,
@@ -46,9 +46,8 @@ impl Grid {
     }
 }
 
-impl<'a> Entity<'a> for Grid {
+impl<'a> Entity<'a, Context> for Grid {
     type Kind = Kind;
-    type Context = Context;
 
     fn id(&self) -> Id {
         self.id
@@ -58,6 +57,14 @@ impl<'a> Entity<'a> for Grid {
         Kind::Grid
     }
 
+    fn drawable(&self) -> Option<&dyn Drawable<Context = Context>> {
+        Some(self)
+    }
+}
+
+impl Drawable for Grid {
+    type Context = Context;
+
     fn draw(
         &self,
         context: &mut Self::Context,
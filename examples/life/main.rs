@@ -12,16 +12,16 @@
 //! current location, so that for each dead cell in their immediate surroundings
 //! (border), it is checked whether there are enough surroundings alive cells,
 //! that would allow the dead cell to become alive (as part of the offspring of
-//! the current alive cell in question). A cache of locations is shared with all
-//! the living cells to check if any of the cells in their border has been already
-//! visited during the current generation.
+//! the current alive cell in question). Since the same dead cell can be brought
+//! back to life by several living cells, the offspring are deduplicated by
+//! location by the environment.
 
 use ggez::*;
 use semeion::*;
-use std::{cell::RefCell, collections::HashSet, rc::Rc};
+use std::rc::Rc;
 
 use entity::*;
-use pattern::*;
+use pattern::Pattern;
 
 mod entity;
 mod env;
@@ -30,28 +30,26 @@ mod pattern;
 struct GameState<'a> {
     // the environment where the simulation takes place
     env: Environment<'a, Kind, Context>,
-    // shared cache for already visited dead cells locations per generation
-    visited: Rc<RefCell<HashSet<Location>>>,
 }
 
 impl<'a> GameState<'a> {
     /// Constructs the game state by populating the environment with the initial
     /// entities.
     fn new() -> Self {
-        let env = Environment::new(env::dimension());
+        let mut env = Environment::new(env::dimension());
         debug_assert!(env.is_empty());
+        // the same dead cell can become alive as part of the offspring of any
+        // of its living neighbors
+        env.set_offspring_policy(Kind::Cell, OffspringPolicy::UniqueByLocation);
 
-        Self {
-            env,
-            visited: Rc::new(RefCell::new(HashSet::new())),
-        }
+        Self { env }
     }
 
     /// Draw stats in the bottom-right corner of the screen.
     fn display_stats(&self, ctx: &mut Context) -> GameResult {
         use mint::Point2;
 
-        let text = format!("Generation: {:?}", self.env.generation());
+        let text = format!("Generation: {}", self.env.generation());
         let foreground = graphics::Color::new(0.1, 0.2, 0.3, 3.0);
         let fragment = graphics::TextFragment::new(text).color(foreground);
         let text = graphics::Text::new(fragment);
@@ -67,7 +65,6 @@ impl<'a> GameState<'a> {
 
 impl<'a> event::EventHandler<GameError> for GameState<'a> {
     fn update(&mut self, _ctx: &mut Context) -> GameResult {
-        self.visited.borrow_mut().clear();
         self.env
             .nextgen()
             .expect("Cannot move to the next generation");
@@ -96,13 +93,11 @@ fn main() -> GameResult {
 
     let mut game = GameState::new();
     game.env.insert(Grid::new(grid::mesh(&mut ctx)?));
+    game.env.set_passive(Kind::Grid, true);
 
     for location in Pattern::acorn() {
-        game.env.insert(Cell::new(
-            location,
-            Rc::new(cell::mesh(&mut ctx)?),
-            Rc::downgrade(&game.visited),
-        ));
+        game.env
+            .insert(Cell::new(location, Rc::new(cell::mesh(&mut ctx)?)));
     }
 
     event::run(ctx, events_loop, game)
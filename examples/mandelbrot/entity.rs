@@ -27,16 +27,16 @@ impl entity::State for State {
     }
 }
 
-impl State {
-    /// Gets the RGBA value that represents this State.
-    pub fn rgba(&self) -> [u8; 4] {
-        let r = (self.value as u32 * 15) as u8;
-        let g = (self.value as u32 * 10) as u8;
-        let b = (self.value as u32 * 5) as u8;
-        let a = 255;
-        [r, g, b, a]
-    }
+/// Gets the RGBA value that represents the given State value.
+pub fn rgba(value: u8) -> [u8; 4] {
+    let r = (value as u32 * 15) as u8;
+    let g = (value as u32 * 10) as u8;
+    let b = (value as u32 * 5) as u8;
+    let a = 255;
+    [r, g, b, a]
+}
 
+impl State {
     /// Sets the coordinates of the Pixel point in the complex plane.
     pub fn set_point(&mut self, point: Complex<f64>) {
         self.point = point
@@ -82,9 +82,8 @@ impl Pixel {
     }
 }
 
-impl<'a> Entity<'a> for Pixel {
+impl<'a> Entity<'a, Context> for Pixel {
     type Kind = ();
-    type Context = Context;
 
     fn id(&self) -> Id {
         self.id
@@ -104,9 +103,13 @@ impl<'a> Entity<'a> for Pixel {
         Some(&mut self.state)
     }
 
+    fn render_value(&self) -> Option<f32> {
+        Some(self.state.value as f32)
+    }
+
     fn react(
         &mut self,
-        _: Option<Neighborhood<Self::Kind, Self::Context>>,
+        _: Option<Neighborhood<Self::Kind, Context>>,
     ) -> Result<(), Error> {
         // compute the next value of the pixel state according to its escape time
         let time = self.state.escape_time(env::ESCAPE_TIME_LIMIT);
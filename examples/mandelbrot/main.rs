@@ -34,6 +34,9 @@ impl<'a> GameState<'a> {
         let dimension = env::dimension();
         let mut env = Environment::new(dimension);
         debug_assert!(env.is_empty());
+        // the value of each pixel is collected by the environment into a
+        // single buffer at the end of each generation
+        env.enable_render_values(());
 
         // populate the whole environment, where each pixel is represented by
         // its own entity
@@ -67,38 +70,21 @@ impl<'a> event::EventHandler<GameError> for GameState<'a> {
         // compute the state of each pixel after setting the coordinate it
         // represents in the complex plane, according to the current visible
         // plane bounds
-        for e in self.env.entities_mut() {
-            if let (Some(loc), Some(state)) = (e.location(), e.state_mut()) {
-                let state = state
-                    .as_any_mut()
-                    .downcast_mut::<entity::State>()
-                    .expect("Invalid state");
-                let point = env::location_to_point(loc, self.plane);
-                state.set_point(point);
-            }
-        }
+        let plane = self.plane;
+        self.env
+            .for_each_state_mut(&(), |loc, state: &mut entity::State| {
+                state.set_point(env::location_to_point(loc, plane));
+            });
 
         self.env
             .nextgen()
             .expect("Cannot move to the next generation");
 
-        // iterate over each pixel to get its current state and its RGBA value
+        // get the value of each pixel, in row-major order, and its RGBA value
         // that will be pushed into the new image data
-        let dimension = env::dimension();
-        for y in 0..dimension.y {
-            for x in 0..dimension.x {
-                let pixel = self
-                    .env
-                    .entities_at(Location { x, y })
-                    .next()
-                    .expect("Entity not found");
-                let state = pixel
-                    .state()
-                    .and_then(|s| s.as_any().downcast_ref::<entity::State>())
-                    .expect("Invalid state");
-
-                self.image.extend(&state.rgba());
-            }
+        let values = self.env.render_values(&()).expect("Render values");
+        for &value in values {
+            self.image.extend(&entity::rgba(value as u8));
         }
 
         Ok(())
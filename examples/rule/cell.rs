@@ -14,7 +14,6 @@ pub struct Cell<'a> {
     lifespan: Lifespan,
     state: State,
     is_frozen: bool,
-    age: u64,
     offspring: Offspring<'a, Kind, ggez::Context>,
     context: Rc<Context>,
 }
@@ -38,9 +37,6 @@ impl<'a> Cell<'a> {
             state,
             // a Cell is frozen only if its state cannot be changed anymore
             is_frozen: false,
-            // the number of generations this cell has been alive, the age will
-            // be used to determine the color of the cell
-            age: 0,
             // a cell will generate a single offspring, representing itself in a
             // new state
             offspring: Offspring::with_capacity(1),
@@ -73,9 +69,8 @@ impl<'a> Cell<'a> {
     }
 }
 
-impl<'a> Entity<'a> for Cell<'a> {
+impl<'a> Entity<'a, ggez::Context> for Cell<'a> {
     type Kind = Kind;
-    type Context = ggez::Context;
 
     fn id(&self) -> Id {
         self.id
@@ -106,7 +101,7 @@ impl<'a> Entity<'a> for Cell<'a> {
 
     fn react(
         &mut self,
-        neighborhood: Option<Neighborhood<Self::Kind, Self::Context>>,
+        neighborhood: Option<Neighborhood<Self::Kind, ggez::Context>>,
     ) -> Result<(), Error> {
         // each generation the Cell will age by a single unit of time
         self.lifespan.shorten();
@@ -143,10 +138,7 @@ impl<'a> Entity<'a> for Cell<'a> {
         // create a new cell just below this one with a state that represents the
         // state this cell will have in the following generation
         let below = *self.location.clone().translate((0, 1), env::dimension());
-        let mut child = Self::new(below, next_state, Rc::clone(&self.context));
-        if next_state == State::Alive {
-            child.age = self.age + 1;
-        }
+        let child = Self::new(below, next_state, Rc::clone(&self.context));
         self.offspring.insert(child);
 
         // freeze this cell in its current state
@@ -157,22 +149,38 @@ impl<'a> Entity<'a> for Cell<'a> {
 
     fn offspring(
         &mut self,
-    ) -> Option<Offspring<'a, Self::Kind, Self::Context>> {
+    ) -> Option<Offspring<'a, Self::Kind, ggez::Context>> {
         // release the offspring to the environment
         debug_assert!(self.offspring.count() <= 1);
         Some(self.offspring.drain())
     }
 
+    fn drawable(&self) -> Option<&dyn Drawable<Context = ggez::Context>> {
+        Some(self)
+    }
+}
+
+impl<'a> Drawable for Cell<'a> {
+    type Context = ggez::Context;
+
     fn draw(
         &self,
         ctx: &mut Self::Context,
         transform: Transform,
+    ) -> Result<(), Error> {
+        self.draw_with_context(ctx, transform.into())
+    }
+
+    fn draw_with_context(
+        &self,
+        ctx: &mut Self::Context,
+        draw: DrawContext,
     ) -> Result<(), Error> {
         // Draw the shape of the Cell without taking into consideration the
         // given transformation (that is always going to be equal to the Identity
         // matrix) since for the purposes of this simulation neither zoom or
         // panning are supported.
-        debug_assert_eq!(transform, Transform::identity());
+        debug_assert_eq!(draw.transform, Transform::identity());
 
         if self.state == State::Dead {
             // dead cells won't be drawn
@@ -186,8 +194,9 @@ impl<'a> Entity<'a> for Cell<'a> {
             y: offset.y,
         };
 
-        // get a new color according to the Cell age
-        let color = self.context.palette.get(self.age);
+        // get a new color according to the Cell age, that is the number of
+        // generations since the Cell was born, tracked by the Environment
+        let color = self.context.palette.get(draw.age);
         let param = graphics::DrawParam::default().color(color);
 
         graphics::draw(ctx, &self.context.cell_mesh, param.dest(offset))
@@ -57,22 +57,20 @@ impl State {
     }
 }
 
-/// Represents the current and the following (generation) state of the Cell.
-#[derive(Debug)]
-struct StateSnapshot {
-    current: State,
-    next: State,
-}
-
-impl StateSnapshot {
-    /// Constructs a new StateSnapshot where the current state and the following
-    /// state are equal to the given one.
-    fn new(state: State) -> Self {
-        Self {
-            current: state,
-            next: state,
-        }
-    }
+/// Gets the transitions of the Wireworld rules:
+/// - Empty → Empty,
+/// - Electron head → Electron tail,
+/// - Electron tail → Conductor,
+/// - Conductor → Electron head if exactly one or two of the neighboring
+///   cells are electron heads, otherwise remains conductor.
+pub fn transitions() -> Vec<Transition<State>> {
+    vec![
+        Transition::always(State::ElectronHead, State::ElectronTail),
+        Transition::always(State::ElectronTail, State::Conductor),
+        Transition::when(State::Conductor, State::ElectronHead, |counts| {
+            matches!(counts.get(&State::ElectronHead), 1 | 2)
+        }),
+    ]
 }
 
 #[derive(Debug)]
@@ -80,26 +78,30 @@ pub struct Cell {
     id: Id,
     location: Location,
     meshes: Rc<Meshes>,
-    state: StateSnapshot,
+    // the current and the following (generation) state of the Cell
+    state: StateMachine<State>,
 }
 
 impl Cell {
-    /// Constructs a new Cell.
-    pub fn new(location: Location, state: State, meshes: Rc<Meshes>) -> Self {
+    /// Constructs a new Cell with the given state machine.
+    pub fn new(
+        location: Location,
+        state: StateMachine<State>,
+        meshes: Rc<Meshes>,
+    ) -> Self {
         Self {
             // ID are simply randomly generated as the possibility of collisions
             // are very very low
             id: rand::random(),
             location,
             meshes,
-            state: StateSnapshot::new(state),
+            state,
         }
     }
 }
 
-impl<'a> Entity<'a> for Cell {
+impl<'a> Entity<'a, Context> for Cell {
     type Kind = Kind;
-    type Context = Context;
 
     fn id(&self) -> Id {
         self.id
@@ -120,62 +122,35 @@ impl<'a> Entity<'a> for Cell {
 
     fn state(&self) -> Option<&dyn entity::State> {
         // returns the current state of this Cell
-        Some(&self.state.current)
+        Some(self.state.current())
     }
 
-    /// Wireworld rules:
-    /// - Empty → Empty,
-    /// - Electron head → Electron tail,
-    /// - Electron tail → Conductor,
-    /// - Conductor → Electron head if exactly one or two of the neighboring
-    ///     cells are electron heads, otherwise remains conductor.
     fn observe(
         &mut self,
-        neighborhood: Option<Neighborhood<Self::Kind, Self::Context>>,
+        neighborhood: Option<Neighborhood<Self::Kind, Context>>,
     ) -> Result<(), Error> {
-        self.state.next = match self.state.current {
-            State::ElectronHead => State::ElectronTail,
-            State::ElectronTail => State::Conductor,
-            State::Conductor => {
-                // count the number of surrounding cells that are in the electron
-                // head state
-                let hood = neighborhood.expect("Invalid neighborhood");
-                let border = hood
-                    .immediate_border(Scope::with_magnitude(1))
-                    .expect("Invalid border");
-                let neighbors = border.iter().map(|t| t.entities()).flatten();
-
-                // count the number of neighbors that are electron heads
-                let count = neighbors
-                    .filter(|e| {
-                        let state = e
-                            .state()
-                            .and_then(|s| s.as_any().downcast_ref::<State>())
-                            .expect("Invalid state");
-                        state == &State::ElectronHead
-                    })
-                    .count();
-
-                if count == 1 || count == 2 {
-                    State::ElectronHead
-                } else {
-                    State::Conductor
-                }
-            }
-        };
-
+        // choose the following state according to the neighboring cells
+        self.state.observe(neighborhood);
         Ok(())
     }
 
     fn react(
         &mut self,
-        _: Option<Neighborhood<Self::Kind, Self::Context>>,
+        _: Option<Neighborhood<Self::Kind, Context>>,
     ) -> Result<(), Error> {
         // update the state of the Cell according to what was previously observed
-        self.state.current = self.state.next;
+        self.state.commit();
         Ok(())
     }
 
+    fn drawable(&self) -> Option<&dyn Drawable<Context = Context>> {
+        Some(self)
+    }
+}
+
+impl Drawable for Cell {
+    type Context = Context;
+
     fn draw(
         &self,
         ctx: &mut Self::Context,
@@ -196,7 +171,7 @@ impl<'a> Entity<'a> for Cell {
 
         let mesh = self
             .meshes
-            .get(self.state.current)
+            .get(*self.state.current())
             .unwrap_or_else(|| panic!("No mesh for state {:?}", self.state));
 
         let param = graphics::DrawParam::default();
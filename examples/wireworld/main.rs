@@ -6,7 +6,7 @@ use std::{collections::HashMap, rc::Rc};
 
 use entity::cell::State;
 use entity::*;
-use pattern::*;
+use pattern::Pattern;
 
 mod entity;
 mod env;
@@ -15,6 +15,8 @@ mod pattern;
 struct GameState<'a> {
     // the environment where the simulation takes place
     env: Environment<'a, Kind, Context>,
+    // the fixed timestep of the generations
+    ticker: Ticker,
 }
 
 /// Cache of mashes per Cell state.
@@ -46,14 +48,17 @@ impl<'a> GameState<'a> {
         let env = Environment::new(env::dimension());
         debug_assert!(env.is_empty());
 
-        Self { env }
+        Self {
+            env,
+            ticker: Ticker::with_rate(7),
+        }
     }
 
     /// Draw stats in the bottom-right corner of the screen.
     fn display_stats(&self, ctx: &mut Context) -> GameResult {
         use mint::Point2;
 
-        let text = format!("Generation: {:?}", self.env.generation());
+        let text = format!("Generation: {}", self.env.generation());
         let foreground = graphics::Color::WHITE;
         let fragment = graphics::TextFragment::new(text).color(foreground);
         let text = graphics::Text::new(fragment);
@@ -68,7 +73,7 @@ impl<'a> GameState<'a> {
 
 impl<'a> event::EventHandler<GameError> for GameState<'a> {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        while timer::check_update_time(ctx, 7) {
+        while self.ticker.should_tick(timer::delta(ctx)) {
             self.env
                 .nextgen()
                 .expect("Cannot move to the next generation");
@@ -103,7 +108,10 @@ fn main() -> GameResult {
     let meshes = Rc::new(Meshes::new(&mut ctx)?);
     let mut game = GameState::new();
 
+    // the state machine shared by all the cells
+    let machine = StateMachine::new(State::Conductor, cell::transitions());
     for (location, state) in Pattern::clock() {
+        let state = machine.with_state(state);
         game.env
             .insert(Cell::new(location, state, Rc::clone(&meshes)));
     }
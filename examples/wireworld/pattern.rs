@@ -6,48 +6,46 @@ pub struct Pattern;
 impl Pattern {
     /// Constructs the Double Clock pattern.
     pub fn clock() -> Vec<(Location, cell::State)> {
-        let mut offsets = vec![];
+        let origin = env::dimension().center();
+        // a wire between two offsets from the origin (both inclusive)
+        let wire = |from: (i32, i32), to: (i32, i32)| {
+            Line::new(origin + Offset::from(from), origin + Offset::from(to))
+        };
 
-        for &y in &[-1, 1] {
-            for x in 0..4 {
-                offsets.push(Offset { x, y });
-            }
-        }
-        for i in 3..14 {
-            offsets.push(Offset { x: i, y: 0 });
+        let mut wires = Union::new()
+            .with(wire((0, 0), (0, 0)))
+            .with(wire((0, -1), (3, -1)))
+            .with(wire((0, 1), (3, 1)))
+            .with(wire((3, 0), (13, 0)))
+            .with(wire((1, -2), (1, -2)))
+            .with(wire((1, 2), (1, 2)));
+        for y in [-3, 3] {
+            wires = wires.with(wire((-5, y), (0, y)));
         }
-        offsets.push(Offset { x: 1, y: -2 });
-        offsets.push(Offset { x: 1, y: 2 });
-        for &y in &[-3, 3] {
-            for x in -5..1 {
-                offsets.push(Offset { x, y });
-            }
+        for y in [-4, -2, 2, 4] {
+            wires = wires.with(wire((-13, y), (-6, y)));
         }
-        for &y in &[-4, -2, 2, 4] {
-            for x in -13..-5 {
-                offsets.push(Offset { x, y });
-            }
-        }
-        offsets.push(Offset { x: -14, y: -3 });
-        offsets.push(Offset { x: -14, y: 3 });
+        wires = wires
+            .with(wire((-14, -3), (-14, -3)))
+            .with(wire((-14, 3), (-14, 3)));
 
-        let origin = env::dimension().center();
-        let mut cells = Vec::with_capacity(offsets.len() + 1);
-        cells.push((origin, cell::State::Conductor));
-        cells.extend(offsets.iter().map(|&delta| {
-            let state = if delta == (Offset { x: -7, y: -2 })
-                || delta == (Offset { x: -8, y: 2 })
-            {
-                cell::State::ElectronTail
-            } else if delta == (Offset { x: -8, y: -2 })
-                || delta == (Offset { x: -9, y: 2 })
-            {
-                cell::State::ElectronHead
-            } else {
-                cell::State::Conductor
-            };
-            (origin + delta, state)
-        }));
-        cells
+        wires
+            .iter(env::dimension())
+            .map(|location| {
+                let delta = location - origin;
+                let state = if delta == (Offset { x: -7, y: -2 })
+                    || delta == (Offset { x: -8, y: 2 })
+                {
+                    cell::State::ElectronTail
+                } else if delta == (Offset { x: -8, y: -2 })
+                    || delta == (Offset { x: -9, y: 2 })
+                {
+                    cell::State::ElectronHead
+                } else {
+                    cell::State::Conductor
+                };
+                (location, state)
+            })
+            .collect()
     }
 }
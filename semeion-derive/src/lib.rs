@@ -0,0 +1,509 @@
+//! This crate provides the `#[derive(Entity)]` macro, re-exported by `semeion`
+//! when its `derive` feature is enabled.
+//!
+//! The derive macro generates the implementation of the `semeion::Entity`
+//! getters from the attributes of the struct fields, and forwards all the other
+//! methods of the Entity (observe, react, offspring, etc.) to the
+//! `semeion::Behavior` trait, that must be implemented by hand.
+//!
+//! The following attributes are supported:
+//!
+//! | Attribute                          | Target | Generated methods            |
+//! |------------------------------------|--------|------------------------------|
+//! | `#[entity(id)]`                    | field  | `id`                         |
+//! | `#[entity(kind)]`                  | field  | `kind` (cloned)              |
+//! | `#[entity(kind = "Kind::Cell")]`   | struct | `kind` (constant)            |
+//! | `#[entity(context = "Context")]`   | struct | the graphics Context type    |
+//! | `#[entity(location)]`              | field  | `location`, `location_mut`   |
+//! | `#[entity(scope)]`                 | field  | `scope`                      |
+//! | `#[entity(lifespan)]`              | field  | `lifespan`, `lifespan_mut`   |
+//! | `#[entity(state)]`                 | field  | `state`, `state_mut`         |
+//!
+//! The location, scope and lifespan fields can either be of the plain type or
+//! wrapped in an Option. The Kind type of a constant kind is the path of the
+//! kind without its last segment (`Kind` for `Kind::Cell`), unless specified
+//! with `#[entity(kind_type = "Type")]`, while the Context defaults to `()`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Expr, Fields,
+    GenericParam, Lifetime, LifetimeDef, Lit, Meta, NestedMeta, Path, Type,
+};
+
+/// Derives the implementation of the `semeion::Entity` trait.
+#[proc_macro_derive(Entity, attributes(entity))]
+pub fn derive_entity(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// The fields of the struct annotated with the entity attributes.
+#[derive(Default)]
+struct EntityFields<'a> {
+    id: Option<&'a syn::Field>,
+    kind: Option<&'a syn::Field>,
+    location: Option<&'a syn::Field>,
+    scope: Option<&'a syn::Field>,
+    lifespan: Option<&'a syn::Field>,
+    state: Option<&'a syn::Field>,
+}
+
+/// The attributes of the struct itself.
+#[derive(Default)]
+struct EntityAttrs {
+    kind: Option<Expr>,
+    kind_type: Option<Type>,
+    context: Option<Type>,
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let fields =
+        match &input.data {
+            Data::Struct(data) => match &data.fields {
+                Fields::Named(fields) => &fields.named,
+                _ => return Err(Error::new(
+                    input.span(),
+                    "Entity can only be derived for structs with named fields",
+                )),
+            },
+            _ => {
+                return Err(Error::new(
+                    input.span(),
+                    "Entity can only be derived for structs",
+                ))
+            }
+        };
+
+    let attrs = parse_struct_attrs(&input.attrs)?;
+    let mut entity = EntityFields::default();
+    for field in fields {
+        for name in parse_field_attrs(&field.attrs)? {
+            let slot = match name.as_str() {
+                "id" => &mut entity.id,
+                "kind" => &mut entity.kind,
+                "location" => &mut entity.location,
+                "scope" => &mut entity.scope,
+                "lifespan" => &mut entity.lifespan,
+                "state" => &mut entity.state,
+                _ => {
+                    return Err(Error::new(
+                        field.span(),
+                        format!("Unknown entity attribute `{}`", name),
+                    ))
+                }
+            };
+            if slot.replace(field).is_some() {
+                return Err(Error::new(
+                    field.span(),
+                    format!("Duplicate entity attribute `{}`", name),
+                ));
+            }
+        }
+    }
+
+    let id = entity.id.ok_or_else(|| {
+        Error::new(input.span(), "Missing `#[entity(id)]` field")
+    })?;
+    let id = id.ident.as_ref();
+
+    let (kind_type, kind) =
+        match (entity.kind, &attrs.kind) {
+            (Some(field), None) => {
+                let ty =
+                    attrs.kind_type.clone().unwrap_or_else(|| field.ty.clone());
+                let name = field.ident.as_ref();
+                (ty, quote! { ::std::clone::Clone::clone(&self.#name) })
+            }
+            (None, Some(expr)) => {
+                let ty = match &attrs.kind_type {
+                    Some(ty) => ty.clone(),
+                    None => kind_type_of(expr)?,
+                };
+                (ty, quote! { #expr })
+            }
+            (Some(field), Some(_)) => {
+                return Err(Error::new(
+                    field.span(),
+                    "The kind can be either a field or a constant, not both",
+                ))
+            }
+            (None, None) => return Err(Error::new(
+                input.span(),
+                "Missing `#[entity(kind)]` field or `#[entity(kind = \"..\")]`",
+            )),
+        };
+    let context = attrs.context.unwrap_or_else(|| syn::parse_quote! { () });
+
+    let mut methods = Vec::new();
+    if let Some(field) = entity.location {
+        let name = field.ident.as_ref();
+        methods.push(if is_option(&field.ty) {
+            quote! {
+                fn location(&self) -> ::std::option::Option<::semeion::Location> {
+                    self.#name
+                }
+
+                fn location_mut(
+                    &mut self,
+                ) -> ::std::option::Option<&mut ::semeion::Location> {
+                    self.#name.as_mut()
+                }
+            }
+        } else {
+            quote! {
+                fn location(&self) -> ::std::option::Option<::semeion::Location> {
+                    ::std::option::Option::Some(self.#name)
+                }
+
+                fn location_mut(
+                    &mut self,
+                ) -> ::std::option::Option<&mut ::semeion::Location> {
+                    ::std::option::Option::Some(&mut self.#name)
+                }
+            }
+        });
+    }
+    if let Some(field) = entity.scope {
+        let name = field.ident.as_ref();
+        let value = if is_option(&field.ty) {
+            quote! { self.#name }
+        } else {
+            quote! { ::std::option::Option::Some(self.#name) }
+        };
+        methods.push(quote! {
+            fn scope(&self) -> ::std::option::Option<::semeion::Scope> {
+                #value
+            }
+        });
+    }
+    if let Some(field) = entity.lifespan {
+        let name = field.ident.as_ref();
+        methods.push(if is_option(&field.ty) {
+            quote! {
+                fn lifespan(&self) -> ::std::option::Option<::semeion::Lifespan> {
+                    self.#name
+                }
+
+                fn lifespan_mut(
+                    &mut self,
+                ) -> ::std::option::Option<&mut ::semeion::Lifespan> {
+                    self.#name.as_mut()
+                }
+            }
+        } else {
+            quote! {
+                fn lifespan(&self) -> ::std::option::Option<::semeion::Lifespan> {
+                    ::std::option::Option::Some(self.#name)
+                }
+
+                fn lifespan_mut(
+                    &mut self,
+                ) -> ::std::option::Option<&mut ::semeion::Lifespan> {
+                    ::std::option::Option::Some(&mut self.#name)
+                }
+            }
+        });
+    }
+    if let Some(field) = entity.state {
+        let name = field.ident.as_ref();
+        methods.push(quote! {
+            fn state(&self) -> ::std::option::Option<&dyn ::semeion::State> {
+                ::std::option::Option::Some(&self.#name)
+            }
+
+            fn state_mut(
+                &mut self,
+            ) -> ::std::option::Option<&mut dyn ::semeion::State> {
+                ::std::option::Option::Some(&mut self.#name)
+            }
+        });
+    }
+
+    // the first lifetime of the struct is used as the lifetime bound of the
+    // Entity, otherwise a new one is introduced
+    let mut generics = input.generics.clone();
+    let lifetime = generics.lifetimes().next().map(|d| d.lifetime.clone());
+    let lifetime = match lifetime {
+        Some(lifetime) => lifetime,
+        None => {
+            let lifetime = Lifetime::new("'e", input.ident.span());
+            generics.params.insert(
+                0,
+                GenericParam::Lifetime(LifetimeDef::new(lifetime.clone())),
+            );
+            lifetime
+        }
+    };
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    let name = &input.ident;
+    let behavior =
+        quote! { <Self as ::semeion::Behavior<#lifetime, #context>> };
+
+    Ok(quote! {
+        impl #impl_generics ::semeion::Entity<#lifetime, #context>
+        for #name #ty_generics
+        #where_clause
+        {
+            type Kind = #kind_type;
+
+            fn id(&self) -> ::semeion::Id {
+                self.#id
+            }
+
+            fn assign_id(&mut self, id: ::semeion::Id) -> bool {
+                #behavior::assign_id(self, id)
+            }
+
+            fn kind(&self) -> Self::Kind {
+                #kind
+            }
+
+            #(#methods)*
+
+            fn reads(&self) -> ::semeion::Access {
+                #behavior::reads(self)
+            }
+
+            fn writes(&self) -> ::semeion::Access {
+                #behavior::writes(self)
+            }
+
+            fn wraps(&self) -> bool {
+                #behavior::wraps(self)
+            }
+
+            fn orientation(&self) -> ::std::option::Option<::semeion::Orientation> {
+                #behavior::orientation(self)
+            }
+
+            fn perception(&self) -> ::semeion::Perception {
+                #behavior::perception(self)
+            }
+
+            fn cooldowns_mut(
+                &mut self,
+            ) -> ::std::option::Option<&mut ::semeion::Cooldowns> {
+                #behavior::cooldowns_mut(self)
+            }
+
+            fn render_value(&self) -> ::std::option::Option<f32> {
+                #behavior::render_value(self)
+            }
+
+            fn digest(&self) -> ::std::option::Option<u64> {
+                #behavior::digest(self)
+            }
+
+            fn observe(
+                &mut self,
+                neighborhood: ::std::option::Option<
+                    ::semeion::Neighborhood<'_, #lifetime, Self::Kind, #context>,
+                >,
+            ) -> ::std::result::Result<(), ::semeion::Error> {
+                #behavior::observe(self, neighborhood)
+            }
+
+            fn react(
+                &mut self,
+                neighborhood: ::std::option::Option<
+                    ::semeion::Neighborhood<'_, #lifetime, Self::Kind, #context>,
+                >,
+            ) -> ::std::result::Result<(), ::semeion::Error> {
+                #behavior::react(self, neighborhood)
+            }
+
+            fn observe_with(
+                &mut self,
+                neighborhood: ::std::option::Option<
+                    ::semeion::Neighborhood<'_, #lifetime, Self::Kind, #context>,
+                >,
+                world: &::semeion::WorldView<'_, #lifetime, Self::Kind, #context>,
+            ) -> ::std::result::Result<(), ::semeion::Error> {
+                #behavior::observe_with(self, neighborhood, world)
+            }
+
+            fn react_with(
+                &mut self,
+                neighborhood: ::std::option::Option<
+                    ::semeion::Neighborhood<'_, #lifetime, Self::Kind, #context>,
+                >,
+                world: &::semeion::WorldView<'_, #lifetime, Self::Kind, #context>,
+            ) -> ::std::result::Result<(), ::semeion::Error> {
+                #behavior::react_with(self, neighborhood, world)
+            }
+
+            fn on_input(
+                &mut self,
+                input: ::semeion::AnyValue,
+            ) -> ::std::result::Result<(), ::semeion::Error> {
+                #behavior::on_input(self, input)
+            }
+
+            fn on_phase(
+                &mut self,
+                phase: &str,
+                neighborhood: ::std::option::Option<
+                    ::semeion::Neighborhood<'_, #lifetime, Self::Kind, #context>,
+                >,
+            ) -> ::std::result::Result<(), ::semeion::Error> {
+                #behavior::on_phase(self, phase, neighborhood)
+            }
+
+            fn on_expire(&mut self) -> ::std::result::Result<(), ::semeion::Error> {
+                #behavior::on_expire(self)
+            }
+
+            fn intent(&mut self) -> ::std::option::Option<::semeion::MoveIntent> {
+                #behavior::intent(self)
+            }
+
+            fn offspring(
+                &mut self,
+            ) -> ::std::option::Option<
+                ::semeion::Offspring<#lifetime, Self::Kind, #context>,
+            > {
+                #behavior::offspring(self)
+            }
+
+            fn commands(
+                &mut self,
+            ) -> ::std::vec::Vec<
+                ::semeion::ScheduledCommand<#lifetime, Self::Kind, #context>,
+            > {
+                #behavior::commands(self)
+            }
+
+            fn neighbor_commands(
+                &mut self,
+            ) -> ::std::vec::Vec<::semeion::TargetedCommand> {
+                #behavior::neighbor_commands(self)
+            }
+
+            fn notifications(
+                &mut self,
+            ) -> ::std::vec::Vec<::semeion::PropertyChange> {
+                #behavior::notifications(self)
+            }
+
+            fn cloneable(
+                &self,
+            ) -> ::std::option::Option<
+                &dyn ::semeion::CloneEntity<#lifetime, Self::Kind, #context>,
+            > {
+                #behavior::cloneable(self)
+            }
+
+            fn drawable(
+                &self,
+            ) -> ::std::option::Option<
+                &dyn ::semeion::Drawable<Context = #context>,
+            > {
+                #behavior::drawable(self)
+            }
+
+            fn z_order(&self) -> i32 {
+                #behavior::z_order(self)
+            }
+        }
+    })
+}
+
+/// Parses the `#[entity(..)]` attributes of the struct.
+fn parse_struct_attrs(attrs: &[Attribute]) -> Result<EntityAttrs, Error> {
+    let mut entity = EntityAttrs::default();
+    for meta in entity_metas(attrs)? {
+        let pair = match meta {
+            NestedMeta::Meta(Meta::NameValue(pair)) => pair,
+            meta => {
+                return Err(Error::new(
+                    meta.span(),
+                    "Expected `name = \"value\"` entity attribute",
+                ))
+            }
+        };
+        let value = match &pair.lit {
+            Lit::Str(value) => value,
+            lit => return Err(Error::new(lit.span(), "Expected a string")),
+        };
+        match pair.path.get_ident().map(|i| i.to_string()).as_deref() {
+            Some("kind") => entity.kind = Some(value.parse()?),
+            Some("kind_type") => entity.kind_type = Some(value.parse()?),
+            Some("context") => entity.context = Some(value.parse()?),
+            _ => {
+                return Err(Error::new(
+                    pair.path.span(),
+                    "Unknown entity attribute",
+                ))
+            }
+        }
+    }
+    Ok(entity)
+}
+
+/// Parses the `#[entity(..)]` attributes of a field, returning their names.
+fn parse_field_attrs(attrs: &[Attribute]) -> Result<Vec<String>, Error> {
+    entity_metas(attrs)?
+        .into_iter()
+        .map(|meta| match meta {
+            NestedMeta::Meta(Meta::Path(path)) => path
+                .get_ident()
+                .map(|ident| ident.to_string())
+                .ok_or_else(|| Error::new(path.span(), "Expected identifier")),
+            meta => Err(Error::new(meta.span(), "Expected identifier")),
+        })
+        .collect()
+}
+
+/// Gets the list of nested metas of all the `#[entity(..)]` attributes.
+fn entity_metas(attrs: &[Attribute]) -> Result<Vec<NestedMeta>, Error> {
+    let mut metas = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path.is_ident("entity")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => metas.extend(list.nested),
+            meta => {
+                return Err(Error::new(
+                    meta.span(),
+                    "Expected `#[entity(..)]` attribute",
+                ))
+            }
+        }
+    }
+    Ok(metas)
+}
+
+/// Gets the type of a constant kind, given by its path without the last
+/// segment.
+fn kind_type_of(expr: &Expr) -> Result<Type, Error> {
+    if let Expr::Path(expr) = expr {
+        let mut path: Path = expr.path.clone();
+        if path.segments.len() > 1 {
+            path.segments.pop();
+            // remove the trailing punctuation left by the popped segment
+            let last = path.segments.pop().expect("Empty path").into_value();
+            path.segments.push(last);
+            return Ok(syn::parse_quote! { #path });
+        }
+    }
+    Err(Error::new(
+        expr.span(),
+        "Cannot infer the kind type, use `#[entity(kind_type = \"..\")]`",
+    ))
+}
+
+/// Returns true only if the given type is an Option.
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(ty) => ty
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
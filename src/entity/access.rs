@@ -0,0 +1,79 @@
+use super::*;
+
+/// The portion of the Environment an Entity accesses while observing and
+/// reacting to its Neighborhood (or during any phase), as declared via
+/// `Entity::reads` and `Entity::writes`.
+///
+/// With the `parallel` feature, the Scheduler relies on the declared access to
+/// assign each Entity to its task: the entities that access only themselves
+/// run in parallel regardless of their Scope, the entities that access their
+/// neighbors run in parallel only if their Neighborhood does not cross the
+/// area of their task, and the entities that access the global state run
+/// serialized after all the others, in a deterministic order.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// struct Cell(Location);
+///
+/// impl<'e> Entity<'e> for Cell {
+///     type Kind = ();
+///
+///     fn id(&self) -> Id {
+///         0
+///     }
+///
+///     fn kind(&self) {}
+///
+///     fn location(&self) -> Option<Location> {
+///         Some(self.0)
+///     }
+///
+///     fn scope(&self) -> Option<Scope> {
+///         Some(Scope::with_magnitude(1))
+///     }
+///
+///     fn reads(&self) -> Access {
+///         Access::SelfOnly
+///     }
+///
+///     fn writes(&self) -> Access {
+///         Access::SelfOnly
+///     }
+///
+///     fn react(
+///         &mut self,
+///         neighborhood: Option<Neighborhood<'_, 'e, ()>>,
+///     ) -> Result<(), Error> {
+///         // the entities that access only themselves are given no neighborhood
+///         assert!(neighborhood.is_none());
+///         Ok(())
+///     }
+/// }
+///
+/// let mut env = Environment::new((5, 5));
+/// env.insert(Cell(Location { x: 2, y: 2 }));
+/// env.nextgen().unwrap();
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Access {
+    /// The Entity accesses only its own state, and it is not given any
+    /// Neighborhood, regardless of its Scope.
+    SelfOnly,
+    /// The Entity accesses the entities within its Neighborhood, according to
+    /// its Scope (the default).
+    #[default]
+    Neighbors,
+    /// The Entity accesses the state shared with entities outside of its
+    /// Neighborhood (such as a shared buffer behind interior mutability).
+    Global,
+}
+
+impl Access {
+    /// Gets the access of the given Entity, that is the widest between its
+    /// declared reads and writes.
+    pub fn of<'e, K, C>(entity: &EntityTrait<'e, K, C>) -> Self {
+        entity.reads().max(entity.writes())
+    }
+}
@@ -0,0 +1,275 @@
+use super::*;
+
+/// The Trait that describes the behavior of an Entity whose implementation of
+/// the Entity trait is derived with `#[derive(Entity)]`.
+///
+/// The derived Entity implementation generates the getters of the Entity from
+/// its annotated fields, and forwards all the other methods to this trait, that
+/// needs to be implemented by hand. All the methods have the same semantic and
+/// the same default behavior of their counterparts in the Entity trait, while
+/// the getters whose field is not annotated keep the default behavior of the
+/// Entity trait.
+///
+/// # Example
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// use semeion::*;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// enum Kind {
+///     Ant,
+/// }
+///
+/// #[derive(Entity)]
+/// #[entity(kind = "Kind::Ant")]
+/// struct Ant {
+///     #[entity(id)]
+///     id: Id,
+///     #[entity(location)]
+///     location: Location,
+///     #[entity(lifespan)]
+///     lifespan: Lifespan,
+///     heat: f32,
+/// }
+///
+/// impl<'e> Behavior<'e> for Ant {
+///     fn react(
+///         &mut self,
+///         _: Option<Neighborhood<'_, 'e, Kind>>,
+///     ) -> Result<(), Error> {
+///         self.location.x += 1;
+///         Ok(())
+///     }
+///
+///     fn on_input(&mut self, input: AnyValue) -> Result<(), Error> {
+///         if let Some(heat) = input.downcast_ref::<f32>() {
+///             self.heat += heat;
+///         }
+///         Ok(())
+///     }
+///
+///     fn render_value(&self) -> Option<f32> {
+///         Some(self.heat)
+///     }
+///
+///     fn z_order(&self) -> i32 {
+///         1
+///     }
+/// }
+///
+/// let mut env = Environment::new((10, 10));
+/// env.insert(Ant {
+///     id: 0,
+///     location: Location::origin(),
+///     lifespan: Lifespan::Immortal,
+///     heat: 0.0,
+/// });
+/// env.push_input(0, Box::new(2.0f32));
+/// env.nextgen().unwrap();
+///
+/// let ant = env.entities().next().unwrap();
+/// assert_eq!(ant.location(), Some(Location { x: 1, y: 0 }));
+/// assert_eq!(ant.render_value(), Some(2.0));
+/// assert_eq!(ant.z_order(), 1);
+/// assert!(ant.wraps());
+/// ```
+pub trait Behavior<'e, C = ()>: Entity<'e, C> {
+    /// Assigns the given ID to the Entity, if its ID is meant to be derived by
+    /// the Environment it is inserted into.
+    ///
+    /// See `Entity::assign_id`.
+    fn assign_id(&mut self, _id: Id) -> bool {
+        false
+    }
+
+    /// Gets the portion of the Environment the Entity reads.
+    ///
+    /// See `Entity::reads`.
+    fn reads(&self) -> Access {
+        Access::Neighbors
+    }
+
+    /// Gets the portion of the Environment the Entity writes.
+    ///
+    /// See `Entity::writes`.
+    fn writes(&self) -> Access {
+        Access::Neighbors
+    }
+
+    /// Returns true only if the Neighborhood of the Entity wraps around the
+    /// edges of the Environment.
+    ///
+    /// See `Entity::wraps`.
+    fn wraps(&self) -> bool {
+        true
+    }
+
+    /// Gets the Orientation of the Entity.
+    ///
+    /// See `Entity::orientation`.
+    fn orientation(&self) -> Option<Orientation> {
+        None
+    }
+
+    /// Gets the Perception of the Entity.
+    ///
+    /// See `Entity::perception`.
+    fn perception(&self) -> Perception {
+        Perception::perfect()
+    }
+
+    /// Gets a mutable reference to the cooldowns of the actions of the Entity.
+    ///
+    /// See `Entity::cooldowns_mut`.
+    fn cooldowns_mut(&mut self) -> Option<&mut Cooldowns> {
+        None
+    }
+
+    /// Gets the value the Entity contributes to the render values of its Kind.
+    ///
+    /// See `Entity::render_value`.
+    fn render_value(&self) -> Option<f32> {
+        None
+    }
+
+    /// Gets a digest of the state of the Entity.
+    ///
+    /// See `Entity::digest`.
+    fn digest(&self) -> Option<u64> {
+        None
+    }
+
+    /// Allows the Entity to observe the portion of surrounding Environment seen
+    /// by the Entity according to its scope.
+    ///
+    /// See `Entity::observe`.
+    fn observe(
+        &mut self,
+        _: Option<Neighborhood<'_, 'e, Self::Kind, C>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Allows to take an action that will affect the Entity itself, and its
+    /// neighbors, according to the portion of surrounding Environment seen by
+    /// the Entity according to its scope.
+    ///
+    /// See `Entity::react`.
+    fn react(
+        &mut self,
+        _: Option<Neighborhood<'_, 'e, Self::Kind, C>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Allows the Entity to observe its Neighborhood as well as the global
+    /// information of the whole Environment.
+    ///
+    /// See `Entity::observe_with`.
+    fn observe_with(
+        &mut self,
+        neighborhood: Option<Neighborhood<'_, 'e, Self::Kind, C>>,
+        _world: &WorldView<'_, 'e, Self::Kind, C>,
+    ) -> Result<(), Error> {
+        Behavior::observe(self, neighborhood)
+    }
+
+    /// Allows the Entity to react to its Neighborhood as well as to the global
+    /// information of the whole Environment.
+    ///
+    /// See `Entity::react_with`.
+    fn react_with(
+        &mut self,
+        neighborhood: Option<Neighborhood<'_, 'e, Self::Kind, C>>,
+        _world: &WorldView<'_, 'e, Self::Kind, C>,
+    ) -> Result<(), Error> {
+        Behavior::react(self, neighborhood)
+    }
+
+    /// Delivers to the Entity one of the external inputs pushed to it.
+    ///
+    /// See `Entity::on_input`.
+    fn on_input(&mut self, _input: AnyValue) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Allows the Entity to take an action during the given phase of the
+    /// current generation.
+    ///
+    /// See `Entity::on_phase`.
+    fn on_phase(
+        &mut self,
+        _phase: &str,
+        _: Option<Neighborhood<'_, 'e, Self::Kind, C>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Allows the Entity to take its last actions when its lifespan reaches
+    /// zero.
+    ///
+    /// See `Entity::on_expire`.
+    fn on_expire(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Gets the intent of the Entity to move to a new Location.
+    ///
+    /// See `Entity::intent`.
+    fn intent(&mut self) -> Option<MoveIntent> {
+        None
+    }
+
+    /// Gets the Offspring of the Entity.
+    ///
+    /// See `Entity::offspring`.
+    fn offspring(&mut self) -> Option<Offspring<'e, Self::Kind, C>> {
+        None
+    }
+
+    /// Takes the commands scheduled by this Entity during the current
+    /// generation.
+    ///
+    /// See `Entity::commands`.
+    fn commands(&mut self) -> Vec<ScheduledCommand<'e, Self::Kind, C>> {
+        Vec::new()
+    }
+
+    /// Takes the commands issued by this Entity to the entities of its
+    /// Neighborhood during the current generation.
+    ///
+    /// See `Entity::neighbor_commands`.
+    fn neighbor_commands(&mut self) -> Vec<TargetedCommand> {
+        Vec::new()
+    }
+
+    /// Takes the changes of the properties of this Entity notified during the
+    /// current generation.
+    ///
+    /// See `Entity::notifications`.
+    fn notifications(&mut self) -> Vec<PropertyChange> {
+        Vec::new()
+    }
+
+    /// Gets a reference to the CloneEntity trait implemented by this Entity.
+    ///
+    /// See `Entity::cloneable`.
+    fn cloneable(&self) -> Option<&dyn CloneEntity<'e, Self::Kind, C>> {
+        None
+    }
+
+    /// Gets a reference to the Drawable trait implemented by this Entity.
+    ///
+    /// See `Entity::drawable`.
+    fn drawable(&self) -> Option<&dyn Drawable<Context = C>> {
+        None
+    }
+
+    /// Gets the order in which this Entity is drawn relative to the other
+    /// entities of the same Kind.
+    ///
+    /// See `Entity::z_order`.
+    fn z_order(&self) -> i32 {
+        0
+    }
+}
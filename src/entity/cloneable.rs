@@ -0,0 +1,45 @@
+use super::*;
+
+/// The trait implemented by the entities that can be cloned into a new boxed
+/// Entity, so that the Environment they belong to can be cloned (see
+/// `Environment::try_clone`).
+///
+/// The trait is implemented for all the entities that implement Clone, that
+/// only need to return `Some(self)` from `Entity::cloneable`.
+#[cfg(not(feature = "parallel"))]
+pub trait CloneEntity<'e, K, C = ()> {
+    /// Clones the Entity into a new boxed Entity.
+    fn clone_boxed(&self) -> Box<EntityTrait<'e, K, C>>;
+}
+
+/// The trait implemented by the entities that can be cloned into a new boxed
+/// Entity, so that the Environment they belong to can be cloned (see
+/// `Environment::try_clone`).
+///
+/// The trait is implemented for all the entities that implement Clone, that
+/// only need to return `Some(self)` from `Entity::cloneable`.
+#[cfg(feature = "parallel")]
+pub trait CloneEntity<'e, K, C = ()>: Send + Sync {
+    /// Clones the Entity into a new boxed Entity.
+    fn clone_boxed(&self) -> Box<EntityTrait<'e, K, C>>;
+}
+
+#[cfg(not(feature = "parallel"))]
+impl<'e, C, T> CloneEntity<'e, T::Kind, C> for T
+where
+    T: Entity<'e, C> + Clone + 'e,
+{
+    fn clone_boxed(&self) -> Box<EntityTrait<'e, T::Kind, C>> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<'e, C, T> CloneEntity<'e, T::Kind, C> for T
+where
+    T: Entity<'e, C> + Clone + 'e + Send + Sync,
+{
+    fn clone_boxed(&self) -> Box<EntityTrait<'e, T::Kind, C>> {
+        Box::new(self.clone())
+    }
+}
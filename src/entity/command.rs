@@ -0,0 +1,119 @@
+use std::any::Any;
+
+use super::*;
+
+/// The type of the values that can replace the State of an Entity.
+#[cfg(not(feature = "parallel"))]
+pub type AnyValue = Box<dyn Any>;
+
+/// The type of the values that can replace the State of an Entity.
+#[cfg(feature = "parallel")]
+pub type AnyValue = Box<dyn Any + Send + Sync>;
+
+/// A command issued by an Entity to one of the entities of its Neighborhood,
+/// as a safer alternative to changing the neighbors directly via
+/// `Entity::lifespan_mut` and `Entity::state_mut` while they react.
+///
+/// The commands are collected via `Entity::neighbor_commands` and applied by
+/// the Environment once per generation, after all the entities reacted and the
+/// phases are over. The commands targeting the same Entity are applied in the
+/// order of the IDs of the entities that issued them (and in the order they
+/// were issued by the same Entity), with the following conflict resolution:
+/// - If any of the commands is a `Kill`, all the other commands are ignored.
+/// - `Damage` and `Heal` are all applied, so that they add up.
+/// - `SetStateAny` replaces the State once per command, so that the last one
+///   wins.
+/// - Only the first `Relocate` is applied, and the others are ignored.
+///
+/// The commands targeting an Entity that does not exist (anymore), or that
+/// does not allow other entities to change the affected property (for example
+/// because its `Entity::lifespan_mut` returns None) are ignored.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// // an Entity that damages all its neighbors, and pushes them to the right
+/// struct Bully(Vec<TargetedCommand>);
+///
+/// impl<'e> Entity<'e> for Bully {
+///     type Kind = char;
+///
+///     fn id(&self) -> Id {
+///         0
+///     }
+///
+///     fn kind(&self) -> char {
+///         'b'
+///     }
+///
+///     fn location(&self) -> Option<Location> {
+///         Some(Location::origin())
+///     }
+///
+///     fn scope(&self) -> Option<Scope> {
+///         Some(Scope::with_magnitude(1))
+///     }
+///
+///     fn react(
+///         &mut self,
+///         neighborhood: Option<Neighborhood<'_, 'e, char>>,
+///     ) -> Result<(), Error> {
+///         let neighborhood = neighborhood.unwrap();
+///         let entities = neighborhood.tiles().flat_map(|t| t.entities());
+///         for entity in entities.filter(|e| e.id() != 0) {
+///             self.0.push((entity.id(), NeighborCommand::Damage(2.into())));
+///             let push = NeighborCommand::Relocate(Offset { x: 1, y: 0 });
+///             self.0.push((entity.id(), push));
+///         }
+///         Ok(())
+///     }
+///
+///     fn neighbor_commands(&mut self) -> Vec<TargetedCommand> {
+///         std::mem::take(&mut self.0)
+///     }
+/// }
+///
+/// let mut env = Environment::new((5, 5));
+/// env.insert(Bully(Vec::new()));
+/// env.insert(QuickEntity::new('a').id(1).at((1, 1)).lifespan(3));
+/// env.insert(QuickEntity::new('a').id(2).at((4, 4)).lifespan(1));
+///
+/// env.nextgen().unwrap();
+/// assert_eq!(env.entities_at((2, 1)).count(), 1);
+/// let lifespan = env.entities_at((2, 1)).next().unwrap().lifespan();
+/// assert_eq!(lifespan.and_then(Lifespan::length), Some(1));
+/// assert_eq!(env.count_kind(&'a'), 1);
+/// ```
+pub enum NeighborCommand {
+    /// Clears the lifespan of the Entity (see `Lifespan::clear`), that will be
+    /// removed at the end of the generation.
+    Kill,
+    /// Shortens the lifespan of the Entity by the given Span.
+    Damage(Span),
+    /// Lengthens the lifespan of the Entity by the given Span.
+    Heal(Span),
+    /// Replaces the State of the Entity with the given value, via
+    /// `State::set_any`.
+    SetStateAny(AnyValue),
+    /// Moves the Entity by the given Offset from its current location (wrapped
+    /// around the Environment edges), via `Entity::location_mut`.
+    Relocate(Offset),
+}
+
+impl fmt::Debug for NeighborCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Kill => f.write_str("Kill"),
+            Self::Damage(span) => f.debug_tuple("Damage").field(span).finish(),
+            Self::Heal(span) => f.debug_tuple("Heal").field(span).finish(),
+            Self::SetStateAny(_) => f.write_str("SetStateAny(..)"),
+            Self::Relocate(offset) => {
+                f.debug_tuple("Relocate").field(offset).finish()
+            }
+        }
+    }
+}
+
+/// A command issued by an Entity, with the ID of the Entity it targets.
+pub type TargetedCommand = (Id, NeighborCommand);
@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+
+/// A cooldown of an action of an Entity (such as an attack), that once
+/// triggered is not ready again until the given number of generations is
+/// over.
+///
+/// Cooldowns are usually embedded into an Entity via Cooldowns, that the
+/// Environment ticks once per generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cooldown {
+    period: u64,
+    remaining: u64,
+}
+
+impl Cooldown {
+    /// Constructs a new ready Cooldown, that once triggered is not ready again
+    /// until the given number of generations is over.
+    pub fn generations(period: u64) -> Self {
+        Self {
+            period,
+            remaining: 0,
+        }
+    }
+
+    /// Returns true only if the Cooldown is over, and the action can therefore
+    /// be performed.
+    pub fn ready(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Gets the number of generations left until the Cooldown is ready.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Gets the number of generations the Cooldown lasts once triggered.
+    pub fn period(&self) -> u64 {
+        self.period
+    }
+
+    /// Triggers the Cooldown, regardless of whether it was ready, so that it
+    /// is not ready again until its period is over.
+    pub fn trigger(&mut self) {
+        self.remaining = self.period;
+    }
+
+    /// Triggers the Cooldown only if ready, and returns true only if it was.
+    pub fn try_trigger(&mut self) -> bool {
+        let ready = self.ready();
+        if ready {
+            self.trigger();
+        }
+        ready
+    }
+
+    /// Resets the Cooldown, so that it is ready again.
+    pub fn reset(&mut self) {
+        self.remaining = 0;
+    }
+
+    /// Moves the Cooldown forward by one generation.
+    pub fn tick(&mut self) {
+        self.remaining = self.remaining.saturating_sub(1);
+    }
+}
+
+/// The named cooldowns of the actions of an Entity, that can be embedded into
+/// the Entity so that the Environment ticks all of them automatically once
+/// per generation, instead of each Entity counting the generations since it
+/// last performed each action.
+///
+/// The Entity exposes its Cooldowns to the Environment via
+/// `Entity::cooldowns_mut`, and the Environment ticks them at the start of
+/// each generation (before the entities observe their Neighborhood), so that
+/// a Cooldown of N generations triggered while reacting is ready again N
+/// generations later.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// struct Wolf(u32, Cooldowns);
+///
+/// impl<'e> Entity<'e> for Wolf {
+///     type Kind = ();
+///
+///     fn id(&self) -> Id {
+///         0
+///     }
+///
+///     fn kind(&self) {}
+///
+///     fn react(
+///         &mut self,
+///         _: Option<Neighborhood<'_, 'e, ()>>,
+///     ) -> Result<(), Error> {
+///         if self.1.try_trigger("attack") {
+///             self.0 += 1;
+///         }
+///         Ok(())
+///     }
+///
+///     fn cooldowns_mut(&mut self) -> Option<&mut Cooldowns> {
+///         Some(&mut self.1)
+///     }
+/// }
+///
+/// let cooldowns = Cooldowns::new().with("attack", Cooldown::generations(3));
+/// let mut env = Environment::new((3, 3));
+/// env.insert(Wolf(0, cooldowns));
+///
+/// // the wolf attacks in the generations 0, 3 and 6
+/// for _ in 0..7 {
+///     env.nextgen().unwrap();
+/// }
+/// let wolf = env.entities_mut().next().unwrap();
+/// let cooldown = wolf.cooldowns_mut().unwrap().cooldown("attack").unwrap();
+/// assert_eq!(cooldown.remaining(), 3);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cooldowns {
+    cooldowns: BTreeMap<&'static str, Cooldown>,
+}
+
+impl Cooldowns {
+    /// Constructs a new empty Cooldowns.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the given Cooldown with the given name, replacing the one with the
+    /// same name, if any.
+    pub fn with(mut self, name: &'static str, cooldown: Cooldown) -> Self {
+        self.insert(name, cooldown);
+        self
+    }
+
+    /// Inserts the given Cooldown with the given name, and returns the one
+    /// previously inserted with the same name, if any.
+    pub fn insert(
+        &mut self,
+        name: &'static str,
+        cooldown: Cooldown,
+    ) -> Option<Cooldown> {
+        self.cooldowns.insert(name, cooldown)
+    }
+
+    /// Removes the Cooldown with the given name, and returns it.
+    pub fn remove(&mut self, name: &str) -> Option<Cooldown> {
+        self.cooldowns.remove(name)
+    }
+
+    /// Gets a reference to the Cooldown with the given name, if any.
+    pub fn cooldown(&self, name: &str) -> Option<&Cooldown> {
+        self.cooldowns.get(name)
+    }
+
+    /// Gets a mutable reference to the Cooldown with the given name, if any.
+    pub fn cooldown_mut(&mut self, name: &str) -> Option<&mut Cooldown> {
+        self.cooldowns.get_mut(name)
+    }
+
+    /// Returns true only if the Cooldown with the given name is ready, or if
+    /// there is no such Cooldown.
+    pub fn ready(&self, name: &str) -> bool {
+        self.cooldown(name).is_none_or(|c| c.ready())
+    }
+
+    /// Triggers the Cooldown with the given name only if ready, and returns
+    /// true only if it was (or if there is no such Cooldown).
+    pub fn try_trigger(&mut self, name: &str) -> bool {
+        self.cooldown_mut(name).is_none_or(|c| c.try_trigger())
+    }
+
+    /// Gets an iterator over all the cooldowns and their names, sorted by
+    /// name.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &Cooldown)> {
+        self.cooldowns
+            .iter()
+            .map(|(&name, cooldown)| (name, cooldown))
+    }
+
+    /// Gets the number of cooldowns.
+    pub fn len(&self) -> usize {
+        self.cooldowns.len()
+    }
+
+    /// Returns true only if there are no cooldowns.
+    pub fn is_empty(&self) -> bool {
+        self.cooldowns.is_empty()
+    }
+
+    /// Moves all the cooldowns forward by one generation.
+    pub fn tick(&mut self) {
+        self.cooldowns.values_mut().for_each(Cooldown::tick);
+    }
+}
@@ -0,0 +1,168 @@
+use std::any::Any;
+
+use super::*;
+
+/// The source of randomness given to a Genome to derive the genome of a child,
+/// that is seeded by the Environment (see `Environment::set_genome_seed`), so
+/// that the inheritance is reproducible across runs.
+#[derive(Debug, Clone)]
+pub struct GenomeRng(SplitMix64);
+
+impl GenomeRng {
+    /// Constructs a new GenomeRng with the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self(SplitMix64(seed))
+    }
+
+    /// Gets the next random number.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    /// Gets the next random number in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        self.0.next_f64()
+    }
+
+    /// Gets the next random number in [0, n).
+    ///
+    /// # Panics
+    /// Panics if n is zero.
+    pub fn next_below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "The upper bound cannot be zero");
+        self.0.next_below(n)
+    }
+
+    /// Returns true with the given probability.
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+}
+
+/// The trait that can be implemented by the inheritable State of the entities
+/// of evolutionary simulations, so that the Environment derives the State of a
+/// child from the states of its two parents (see `Offspring::insert_child_of`).
+///
+/// # Example
+/// ```
+/// use semeion::*;
+/// use std::any::Any;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// struct Speed(u32);
+///
+/// impl Genome for Speed {
+///     fn crossover(&self, other: &Self, _: &mut GenomeRng) -> Self {
+///         Self((self.0 + other.0) / 2)
+///     }
+///
+///     fn mutate(&mut self, rng: &mut GenomeRng) {
+///         if rng.chance(0.0) {
+///             self.0 += 1;
+///         }
+///     }
+/// }
+///
+/// impl State for Speed {
+///     fn as_any(&self) -> &dyn Any {
+///         self
+///     }
+///
+///     fn as_any_mut(&mut self) -> &mut dyn Any {
+///         self
+///     }
+///
+///     fn set_any(&mut self, value: AnyValue) -> bool {
+///         replace_any(self, value)
+///     }
+/// }
+///
+/// struct Bug<'e>(Id, Speed, Offspring<'e, ()>);
+///
+/// impl<'e> Entity<'e> for Bug<'e> {
+///     type Kind = ();
+///
+///     fn id(&self) -> Id {
+///         self.0
+///     }
+///
+///     fn kind(&self) {}
+///
+///     fn state(&self) -> Option<&dyn State> {
+///         Some(&self.1)
+///     }
+///
+///     fn state_mut(&mut self) -> Option<&mut dyn State> {
+///         Some(&mut self.1)
+///     }
+///
+///     fn react(
+///         &mut self,
+///         _: Option<Neighborhood<'_, 'e, ()>>,
+///     ) -> Result<(), Error> {
+///         if self.0 == 0 {
+///             // the child starts with any State, replaced by the inherited one
+///             let child = Bug(2, Speed(0), Offspring::default());
+///             self.2.insert_child_of(child, &self.1, &Speed(10));
+///         }
+///         Ok(())
+///     }
+///
+///     fn offspring(&mut self) -> Option<Offspring<'e, (), ()>> {
+///         Some(self.2.drain())
+///     }
+/// }
+///
+/// let mut env = Environment::new((3, 3));
+/// env.insert(Bug(0, Speed(4), Offspring::default()));
+/// env.nextgen().unwrap();
+///
+/// let child = env.entities().find(|e| e.id() == 2).unwrap();
+/// let speed = child.state().unwrap().as_any().downcast_ref::<Speed>();
+/// assert_eq!(speed, Some(&Speed(7)));
+/// ```
+#[cfg(not(feature = "parallel"))]
+pub trait Genome: Clone + Any {
+    /// Derives the genome of a child from the genomes of its two parents.
+    fn crossover(&self, other: &Self, rng: &mut GenomeRng) -> Self;
+
+    /// Mutates the genome of a child, once derived from its parents.
+    ///
+    /// By default the genome is left unchanged.
+    fn mutate(&mut self, _rng: &mut GenomeRng) {}
+}
+
+/// The trait that can be implemented by the inheritable State of the entities
+/// of evolutionary simulations, so that the Environment derives the State of a
+/// child from the states of its two parents (see `Offspring::insert_child_of`).
+#[cfg(feature = "parallel")]
+pub trait Genome: Clone + Any + Send + Sync {
+    /// Derives the genome of a child from the genomes of its two parents.
+    fn crossover(&self, other: &Self, rng: &mut GenomeRng) -> Self;
+
+    /// Mutates the genome of a child, once derived from its parents.
+    ///
+    /// By default the genome is left unchanged.
+    fn mutate(&mut self, _rng: &mut GenomeRng) {}
+}
+
+/// The inheritance of a child, that derives its genome from the ones of its
+/// parents.
+#[cfg(not(feature = "parallel"))]
+pub(crate) type Inheritance = Box<dyn FnOnce(&mut GenomeRng) -> AnyValue>;
+
+/// The inheritance of a child, that derives its genome from the ones of its
+/// parents.
+#[cfg(feature = "parallel")]
+pub(crate) type Inheritance =
+    Box<dyn FnOnce(&mut GenomeRng) -> AnyValue + Send + Sync>;
+
+/// Constructs the inheritance of a child of the given parents.
+pub(crate) fn inherit<G: Genome>(a: &G, b: &G) -> Inheritance {
+    let (a, b) = (a.clone(), b.clone());
+    Box::new(move |rng| {
+        let mut child = a.crossover(&b, rng);
+        child.mutate(rng);
+        Box::new(child)
+    })
+}
@@ -0,0 +1,166 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign};
+
+/// The trait that can be implemented by the Kind of the entities to group
+/// multiple kinds under the same category, so that entities can be queried by
+/// group rather than by listing each of their kinds.
+///
+/// The same Kind can implement this trait for multiple group types, to belong
+/// to different groupings at the same time (for example by taxonomy and by
+/// diet).
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// enum Kind {
+///     Grass,
+///     Rabbit,
+///     Fox,
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Group {
+///     Plant,
+///     Animal,
+/// }
+///
+/// impl KindGroup<Group> for Kind {
+///     fn group(&self) -> Group {
+///         match self {
+///             Self::Grass => Group::Plant,
+///             Self::Rabbit | Self::Fox => Group::Animal,
+///         }
+///     }
+/// }
+///
+/// let mut env = Environment::<Kind>::new((10, 10));
+/// env.insert(QuickEntity::new(Kind::Grass).at((0, 0)));
+/// env.insert(QuickEntity::new(Kind::Rabbit).at((1, 0)));
+/// env.insert(QuickEntity::new(Kind::Fox).at((2, 0)));
+/// assert_eq!(env.count_group(&Group::Animal), 2);
+/// assert_eq!(env.count_group(&Group::Plant), 1);
+/// ```
+pub trait KindGroup<G> {
+    /// Gets the group this Kind belongs to.
+    fn group(&self) -> G;
+}
+
+/// The trait that can be implemented by the Kind of the entities to assign
+/// each Kind a small index, so that the kinds of the entities in a tile can be
+/// summarized as a KindMask (see `TileView::kind_mask`).
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// enum Kind {
+///     Conductor,
+///     Head,
+///     Tail,
+/// }
+///
+/// impl KindIndex for Kind {
+///     fn kind_index(&self) -> usize {
+///         *self as usize
+///     }
+/// }
+///
+/// let mut env = Environment::<Kind>::new((3, 3));
+/// env.insert(QuickEntity::new(Kind::Head).id(0).at((0, 0)));
+/// env.insert(QuickEntity::new(Kind::Head).id(1).at((1, 0)));
+/// env.insert(QuickEntity::new(Kind::Tail).id(2).at((1, 0)));
+///
+/// // the number of tiles with at least one electron head around the center
+/// let neighborhood = env.neighborhood_at((1, 1), Scope::with_magnitude(1));
+/// let heads = KindMask::of(&Kind::Head);
+/// let count = neighborhood
+///     .unwrap()
+///     .tiles()
+///     .filter(|tile| !(tile.kind_mask() & heads).is_empty())
+///     .count();
+/// assert_eq!(count, 2);
+/// ```
+pub trait KindIndex {
+    /// Gets the index of this Kind, that must be lower than `KindMask::BITS`
+    /// for the Kind to be part of a KindMask.
+    fn kind_index(&self) -> usize;
+}
+
+/// A set of kinds, as a bitmask of their indices (see `KindIndex`), where the
+/// kinds whose index is not lower than `KindMask::BITS` are never included.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KindMask(u64);
+
+impl KindMask {
+    /// The maximum number of kinds a KindMask can distinguish.
+    pub const BITS: usize = u64::BITS as usize;
+
+    /// The KindMask without any Kind.
+    pub const EMPTY: Self = Self(0);
+
+    /// Constructs a new KindMask with only the given Kind.
+    pub fn of<K: KindIndex>(kind: &K) -> Self {
+        let mut mask = Self::EMPTY;
+        mask.insert(kind);
+        mask
+    }
+
+    /// Inserts the given Kind into the KindMask.
+    pub fn insert<K: KindIndex>(&mut self, kind: &K) {
+        let index = kind.kind_index();
+        if index < Self::BITS {
+            self.0 |= 1 << index;
+        }
+    }
+
+    /// Returns true only if the KindMask contains the given Kind.
+    pub fn contains<K: KindIndex>(self, kind: &K) -> bool {
+        !(self & Self::of(kind)).is_empty()
+    }
+
+    /// Gets the bits of the KindMask, where the bit of each Kind is the one at
+    /// its index.
+    pub fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// Gets the number of kinds in the KindMask.
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Returns true only if the KindMask has no kinds.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for KindMask {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for KindMask {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+impl BitAnd for KindMask {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+impl BitAndAssign for KindMask {
+    fn bitand_assign(&mut self, other: Self) {
+        self.0 &= other.0;
+    }
+}
@@ -0,0 +1,37 @@
+use super::*;
+
+/// The intent of an Entity to move to a new Location at the end of the current
+/// generation.
+///
+/// When multiple entities intend to move into the same tile during the same
+/// generation, the Environment resolves the conflict by granting the move only
+/// to the intent with the highest priority (or according to the resolver set
+/// via `Environment::set_move_resolver`), while all the other entities are left
+/// in their current location.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MoveIntent {
+    /// The priority of the intent, where higher values win over lower ones.
+    pub priority: i32,
+    /// The Location the Entity intends to move to.
+    pub destination: Location,
+}
+
+impl MoveIntent {
+    /// Constructs a new MoveIntent towards the given destination with default
+    /// priority (0).
+    pub fn new(destination: impl Into<Location>) -> Self {
+        Self::with_priority(destination, 0)
+    }
+
+    /// Constructs a new MoveIntent towards the given destination with the given
+    /// priority.
+    pub fn with_priority(
+        destination: impl Into<Location>,
+        priority: i32,
+    ) -> Self {
+        Self {
+            priority,
+            destination: destination.into(),
+        }
+    }
+}
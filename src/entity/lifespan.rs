@@ -1,3 +1,5 @@
+use super::*;
+
 /// The lifespan of an entity.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Lifespan {
@@ -8,6 +10,19 @@ pub enum Lifespan {
     /// not decrease, but it can still be killed by other entities since its
     /// lifespan is defined and can be altered.
     Immortal,
+    /// The Entity cannot be harmed (nor healed) until the Environment reaches
+    /// the given generation, since all the changes to its lifespan are ignored
+    /// in the meantime, such as for a newborn Entity that should not be killed
+    /// by its neighbors as soon as it appears. The span does not decrease while
+    /// protected, and the Environment turns the lifespan into an Ephemeral one
+    /// with the same span at the start of the given generation (via
+    /// `Entity::lifespan_mut`).
+    Protected {
+        /// The span of the lifespan, once the protection is over.
+        span: Span,
+        /// The generation the protection ends at.
+        until: Generation,
+    },
 }
 
 impl Lifespan {
@@ -16,29 +31,83 @@ impl Lifespan {
         Self::Ephemeral(span.into())
     }
 
+    /// Constructs an Ephemeral Lifespan with the given span value, and with the
+    /// given grace period.
+    ///
+    /// The grace period is the number of generations the Entity is kept in the
+    /// Environment after its lifespan reaches zero, before being removed. The
+    /// Entity can therefore still act (for example by spreading its last
+    /// offspring) during its grace period, or it can be brought back to life by
+    /// lengthening its lifespan.
+    pub fn with_grace(span: impl Into<Span>, grace: u64) -> Self {
+        let mut span = span.into();
+        span.grace = grace;
+        Self::Ephemeral(span)
+    }
+
+    /// Constructs a Protected Lifespan with the given span value, whose
+    /// changes are ignored until the Environment reaches the given generation.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((4, 4));
+    /// let until = env.generation() + 2;
+    /// env.insert(QuickEntity::new('a').at((1, 1)).lifespan(3));
+    /// env.entities_mut().for_each(|entity| {
+    ///     *entity.lifespan_mut().unwrap() = Lifespan::protected(3, until);
+    /// });
+    ///
+    /// // the entity cannot be killed until the generation 2 starts
+    /// for _ in 0..2 {
+    ///     env.entities_mut().for_each(|e| e.lifespan_mut().unwrap().clear());
+    ///     env.nextgen().unwrap();
+    /// }
+    /// assert_eq!(env.generation(), until);
+    /// env.nextgen().unwrap();
+    /// let lifespan = env.entities().next().unwrap().lifespan().unwrap();
+    /// assert_eq!(lifespan, Lifespan::with_span(3));
+    ///
+    /// env.entities_mut().for_each(|e| e.lifespan_mut().unwrap().clear());
+    /// env.nextgen().unwrap();
+    /// assert!(env.is_empty());
+    /// ```
+    pub fn protected(span: impl Into<Span>, until: Generation) -> Self {
+        Self::Protected {
+            span: span.into(),
+            until,
+        }
+    }
+
     /// Returns true only if there is lifespan left. It will always return true
-    /// if immortal.
+    /// if immortal or protected.
     pub fn is_alive(&self) -> bool {
         match self {
             Lifespan::Ephemeral(span) => span.length() > 0,
-            Lifespan::Immortal => true,
+            Lifespan::Immortal | Lifespan::Protected { .. } => true,
         }
     }
 
+    /// Returns true only if the Lifespan is protected from any change.
+    pub fn is_protected(&self) -> bool {
+        matches!(self, Lifespan::Protected { .. })
+    }
+
     /// Shorten the lifespan by a single unit of span, it has no effect if
-    /// immortal. Returns the Lifespan left.
+    /// immortal or protected. Returns the Lifespan left.
     pub fn shorten(&mut self) -> &Self {
         self.shorten_by(Span::with_length(1))
     }
 
     /// Lengthen the lifespan by a single unit of span, it has no effect if
-    /// immortal. Returns the Lifespan left.
+    /// immortal or protected. Returns the Lifespan left.
     pub fn lengthen(&mut self) -> &Self {
         self.lengthen_by(Span::with_length(1))
     }
 
     /// Shorten the lifespan by the given amount of span, it has no effect if
-    /// immortal. Returns the Lifespan left.
+    /// immortal or protected. Returns the Lifespan left.
     pub fn shorten_by(&mut self, amount: impl Into<Span>) -> &Self {
         let amount = amount.into();
         if let Lifespan::Ephemeral(span) = self {
@@ -48,7 +117,7 @@ impl Lifespan {
     }
 
     /// Lengthen the lifespan by the given amount of span, it has no effect if
-    /// immortal. Returns the Lifespan left.
+    /// immortal or protected. Returns the Lifespan left.
     pub fn lengthen_by(&mut self, amount: impl Into<Span>) -> &Self {
         let amount = amount.into();
         if let Lifespan::Ephemeral(span) = self {
@@ -59,26 +128,34 @@ impl Lifespan {
 
     /// Replaces the lifespan with a new empty one, by effectively representing
     /// the death of the entity. This method will have an effect also on an
-    /// immortal lifespan.
+    /// immortal lifespan, but not on a protected one.
     pub fn clear(&mut self) {
-        *self = Lifespan::Ephemeral(Span::empty())
+        if !self.is_protected() {
+            *self = Lifespan::Ephemeral(Span::empty())
+        }
     }
 
-    /// Gets the Span of the Lifespan if self is Ephemeral, otherwise returns
-    /// None.
+    /// Gets the Span of the Lifespan if self is Ephemeral or Protected,
+    /// otherwise returns None.
     pub fn span(self) -> Option<Span> {
-        if let Lifespan::Ephemeral(span) = self {
-            Some(span)
-        } else {
-            None
+        match self {
+            Lifespan::Ephemeral(span) | Lifespan::Protected { span, .. } => {
+                Some(span)
+            }
+            Lifespan::Immortal => None,
         }
     }
 
-    /// Gets the length of the Lifespan if self is Ephemeral, otherwise returns
-    /// None.
+    /// Gets the length of the Lifespan if self is Ephemeral or Protected,
+    /// otherwise returns None.
     pub fn length(self) -> Option<u64> {
         self.span().map(|span| span.length())
     }
+
+    /// Gets the grace period of the Lifespan, that is 0 if self is Immortal.
+    pub fn grace(self) -> u64 {
+        self.span().map_or(0, |span| span.grace())
+    }
 }
 
 /// The window of time span as seen by an entity, represented as discrete number
@@ -86,11 +163,13 @@ impl Lifespan {
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Span {
     length: u64,
+    // the number of generations the entity is kept after the span is over
+    grace: u64,
 }
 
 impl From<u64> for Span {
     fn from(length: u64) -> Self {
-        Self { length }
+        Self::with_length(length)
     }
 }
 
@@ -103,12 +182,12 @@ impl From<Span> for u64 {
 impl Span {
     /// Constructs a new Span of the given length.
     pub fn with_length(length: u64) -> Self {
-        Self { length }
+        Self { length, grace: 0 }
     }
 
     /// Constructs an empty lifespan.
     pub fn empty() -> Self {
-        Self { length: 0 }
+        Self::with_length(0)
     }
 
     /// Gets the length of this Span.
@@ -116,6 +195,11 @@ impl Span {
         self.length
     }
 
+    /// Gets the grace period of this Span (see `Lifespan::with_grace`).
+    pub fn grace(self) -> u64 {
+        self.grace
+    }
+
     /// Shorten the span by the given length of span.
     pub fn shorten_by(&mut self, length: u64) {
         self.length = self.length.saturating_sub(length);
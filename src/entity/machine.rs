@@ -0,0 +1,264 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use super::*;
+
+/// The number of neighbors in each state seen by a StateMachine, used to
+/// decide which of its transitions to take.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NeighborCounts<S> {
+    counts: Vec<(S, usize)>,
+}
+
+impl<S: PartialEq> NeighborCounts<S> {
+    /// Gets the number of neighbors in the given state.
+    pub fn get(&self, state: &S) -> usize {
+        self.counts
+            .iter()
+            .find(|(s, _)| s == state)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    /// Gets the total number of neighbors with a state of the same type.
+    pub fn total(&self) -> usize {
+        self.counts.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Counts one more neighbor in the given state.
+    fn add(&mut self, state: S) {
+        match self.counts.iter_mut().find(|(s, _)| *s == state) {
+            Some((_, count)) => *count += 1,
+            None => self.counts.push((state, 1)),
+        }
+    }
+}
+
+/// A transition of a StateMachine from a state to another, taken when the
+/// given predicate over the number of neighbors in each state holds.
+#[derive(Debug, Clone, Copy)]
+pub struct Transition<S> {
+    /// The state the transition starts from.
+    pub from: S,
+    /// The state the transition leads to.
+    pub to: S,
+    /// The predicate that must hold for the transition to be taken.
+    pub when: fn(&NeighborCounts<S>) -> bool,
+}
+
+impl<S> Transition<S> {
+    /// Constructs a new Transition that is taken regardless of the neighbors.
+    pub fn always(from: S, to: S) -> Self {
+        Self {
+            from,
+            to,
+            when: |_| true,
+        }
+    }
+
+    /// Constructs a new Transition that is taken only if the given predicate
+    /// over the number of neighbors in each state holds.
+    pub fn when(from: S, to: S, when: fn(&NeighborCounts<S>) -> bool) -> Self {
+        Self { from, to, when }
+    }
+}
+
+/// A finite state machine whose state is advanced by the number of neighbors
+/// in each state, as for the cells of Wireworld-like automata, that can be
+/// embedded into an Entity to implement its observe and react split.
+///
+/// Each generation, the StateMachine counts the neighbors of the Entity (all
+/// the entities of its Neighborhood but the ones in its own tile) by their
+/// state, and takes the first of its transitions that starts from the current
+/// state and whose predicate holds (if any) when the Entity observes its
+/// Neighborhood (see `StateMachine::observe`). The next state is committed only
+/// when the Entity reacts (see `StateMachine::commit`), so that all the
+/// entities see the states of their neighbors of the same generation.
+///
+/// The state of the neighbors is read from `Entity::state`, that the entities
+/// must therefore implement by returning the current state of the
+/// StateMachine (see `StateMachine::current`).
+///
+/// # Example
+/// ```
+/// use semeion::*;
+/// use std::any::Any;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Wire {
+///     Head,
+///     Tail,
+///     Conductor,
+/// }
+///
+/// impl State for Wire {
+///     fn as_any(&self) -> &dyn Any {
+///         self
+///     }
+///
+///     fn as_any_mut(&mut self) -> &mut dyn Any {
+///         self
+///     }
+/// }
+///
+/// struct Cell(Id, Location, StateMachine<Wire>);
+///
+/// impl<'e> Entity<'e> for Cell {
+///     type Kind = ();
+///
+///     fn id(&self) -> Id {
+///         self.0
+///     }
+///
+///     fn kind(&self) {}
+///
+///     fn location(&self) -> Option<Location> {
+///         Some(self.1)
+///     }
+///
+///     fn scope(&self) -> Option<Scope> {
+///         Some(Scope::with_magnitude(1))
+///     }
+///
+///     fn state(&self) -> Option<&dyn State> {
+///         Some(self.2.current())
+///     }
+///
+///     fn observe(
+///         &mut self,
+///         neighborhood: Option<Neighborhood<'_, 'e, ()>>,
+///     ) -> Result<(), Error> {
+///         self.2.observe(neighborhood);
+///         Ok(())
+///     }
+///
+///     fn react(
+///         &mut self,
+///         _: Option<Neighborhood<'_, 'e, ()>>,
+///     ) -> Result<(), Error> {
+///         self.2.commit();
+///         Ok(())
+///     }
+/// }
+///
+/// let transitions = vec![
+///     Transition::always(Wire::Head, Wire::Tail),
+///     Transition::always(Wire::Tail, Wire::Conductor),
+///     Transition::when(Wire::Conductor, Wire::Head, |counts| {
+///         matches!(counts.get(&Wire::Head), 1 | 2)
+///     }),
+/// ];
+/// let wire = [Wire::Tail, Wire::Head, Wire::Conductor, Wire::Conductor];
+/// let machine = StateMachine::new(Wire::Conductor, transitions);
+///
+/// let mut env = Environment::new((6, 3));
+/// for (x, state) in wire.into_iter().enumerate() {
+///     let location = Location { x: x as i32, y: 1 };
+///     env.insert(Cell(x, location, machine.with_state(state)));
+/// }
+///
+/// env.nextgen().unwrap();
+/// let states: Vec<_> = (0..4)
+///     .map(|x| env.entities_at((x, 1)).next().unwrap().state().unwrap())
+///     .map(|state| *state.as_any().downcast_ref::<Wire>().unwrap())
+///     .collect();
+/// let expected = [Wire::Conductor, Wire::Tail, Wire::Head, Wire::Conductor];
+/// assert_eq!(states, expected);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StateMachine<S> {
+    current: S,
+    // the state observed for the next generation, not yet committed
+    next: S,
+    transitions: Arc<[Transition<S>]>,
+}
+
+impl<S: Copy + PartialEq + Any> StateMachine<S> {
+    /// Constructs a new StateMachine in the given initial state, with the given
+    /// transitions, that are taken in the given order of priority.
+    pub fn new(
+        initial: S,
+        transitions: impl Into<Arc<[Transition<S>]>>,
+    ) -> Self {
+        Self {
+            current: initial,
+            next: initial,
+            transitions: transitions.into(),
+        }
+    }
+
+    /// Constructs a new StateMachine in the given state, that shares the
+    /// transitions of this one.
+    pub fn with_state(&self, state: S) -> Self {
+        Self {
+            current: state,
+            next: state,
+            transitions: Arc::clone(&self.transitions),
+        }
+    }
+
+    /// Gets the current state.
+    pub fn current(&self) -> &S {
+        &self.current
+    }
+
+    /// Gets the state observed for the next generation, that is equal to the
+    /// current state until the StateMachine observes its Neighborhood.
+    pub fn next(&self) -> &S {
+        &self.next
+    }
+
+    /// Sets the current state, discarding the state observed for the next
+    /// generation, if any.
+    pub fn set(&mut self, state: S) {
+        self.current = state;
+        self.next = state;
+    }
+
+    /// Observes the given Neighborhood, by counting the neighbors in each state
+    /// and choosing the next state, without committing it, and should be called
+    /// from `Entity::observe`. Without a Neighborhood, there are no neighbors.
+    pub fn observe<K, C>(
+        &mut self,
+        neighborhood: Option<Neighborhood<'_, '_, K, C>>,
+    ) {
+        let mut transitions = self
+            .transitions
+            .iter()
+            .filter(|transition| transition.from == self.current)
+            .peekable();
+        if transitions.peek().is_none() {
+            self.next = self.current;
+            return;
+        }
+
+        let mut counts = NeighborCounts { counts: Vec::new() };
+        if let Some(neighborhood) = neighborhood {
+            let center = neighborhood
+                .center_position()
+                .one_dimensional(neighborhood.dimension());
+            let neighbors = neighborhood
+                .tiles()
+                .enumerate()
+                .filter(|&(index, _)| index != center)
+                .flat_map(|(_, tile)| tile.entities());
+            for neighbor in neighbors {
+                let state = neighbor
+                    .state()
+                    .and_then(|state| state.as_any().downcast_ref::<S>());
+                if let Some(&state) = state {
+                    counts.add(state);
+                }
+            }
+        }
+
+        self.next = transitions
+            .find(|transition| (transition.when)(&counts))
+            .map_or(self.current, |transition| transition.to);
+    }
+
+    /// Commits the state observed for the next generation, and should be
+    /// called from `Entity::react`.
+    pub fn commit(&mut self) {
+        self.current = self.next;
+    }
+}
@@ -2,12 +2,40 @@ use std::fmt;
 
 use super::*;
 
+pub use access::*;
+pub use behavior::*;
+pub use cloneable::*;
+pub use command::*;
+pub use cooldown::*;
+pub use genome::*;
+pub use group::*;
+pub use intent::*;
 pub use lifespan::*;
+pub use machine::*;
+pub use notifier::*;
 pub use offspring::*;
+pub use perception::*;
+pub use quick::*;
+#[cfg(feature = "scripting")]
+pub use script::*;
 pub use state::*;
 
+pub mod access;
+pub mod behavior;
+pub mod cloneable;
+pub mod command;
+pub mod cooldown;
+pub mod genome;
+pub mod group;
+pub mod intent;
 pub mod lifespan;
+pub mod machine;
+pub mod notifier;
 pub mod offspring;
+pub mod perception;
+pub mod quick;
+#[cfg(feature = "scripting")]
+pub mod script;
 pub mod state;
 
 /// The type of the Entity unique ID.
@@ -29,13 +57,15 @@ pub type Id = usize;
 /// this Entity without requiring a `'static` lifetime.
 /// This lifetime bound does not apply to mutable references, since they cannot
 /// be copied without violate uniqueness.
-pub trait Entity<'e> {
+///
+/// The type parameter `C` is the type of the graphics Context used to draw the
+/// entities of the Environment (see `Drawable`), and it defaults to `()`, so
+/// that the entities that are never drawn (for example in headless
+/// simulations) don't need to know anything about it.
+pub trait Entity<'e, C = ()> {
     /// The type of the Entity kind.
     type Kind;
 
-    /// The type of the graphics Context used to draw the shape of the entities.
-    type Context;
-
     /// Gets the ID of the Entity.
     ///
     /// The ID must be unique for all the entities. It is considered a logic
@@ -43,6 +73,18 @@ pub trait Entity<'e> {
     /// behavior within the Environment is undefined.
     fn id(&self) -> Id;
 
+    /// Assigns the given ID to the Entity, if its ID is meant to be derived by
+    /// the Environment it is inserted into (such as the ID of a QuickEntity
+    /// that was not set explicitly).
+    ///
+    /// The Environment calls this method before inserting the Entity, with an
+    /// ID that does not belong to any of its entities, and the Entity should
+    /// return true only if it took the given ID. By default the ID of the
+    /// Entity is fixed, and the given ID is ignored.
+    fn assign_id(&mut self, _id: Id) -> bool {
+        false
+    }
+
     /// Gets the Entity type.
     ///
     /// Each Entity can belong to a specific kind that defines, besides the
@@ -55,11 +97,21 @@ pub trait Entity<'e> {
     /// If an Entity has no location, it should return None. An Entity can either
     /// have a location for its entire lifetime or no location; it is considered
     /// a logic error if this method returns None for an Entity that previously
-    /// had a location, and vice versa.
+    /// had a location, and vice versa (that causes a panic in debug builds).
     fn location(&self) -> Option<Location> {
         None
     }
 
+    /// Gets a mutable reference to the location of the Entity.
+    ///
+    /// This method is used by the Environment to move the Entity to the
+    /// destination of its granted MoveIntent. If the Entity has no location, or
+    /// it does not allow the Environment to change its location, None should be
+    /// returned.
+    fn location_mut(&mut self) -> Option<&mut Location> {
+        None
+    }
+
     /// Gets the scope of this Entity.
     ///
     /// The size of the scope defines its radius of influence, i.e. the portion
@@ -75,12 +127,60 @@ pub trait Entity<'e> {
     /// able to reach.
     /// Moreover, only entities that have a location in the Environment can
     /// interact with surrounding entities, therefore it is a logic error to
-    /// return Some from this method if `Entity::location()` returns None, but it
-    /// is perfectly valid for entities to have a location but no scope.
+    /// return Some from this method if `Entity::location()` returns None (that
+    /// causes a panic in debug builds), but it is perfectly valid for entities
+    /// to have a location but no scope.
     fn scope(&self) -> Option<Scope> {
         None
     }
 
+    /// Gets the portion of the Environment the Entity reads while observing and
+    /// reacting, or during any phase (see `Access`).
+    ///
+    /// By default the Entity reads its Neighborhood.
+    fn reads(&self) -> Access {
+        Access::Neighbors
+    }
+
+    /// Gets the portion of the Environment the Entity writes while observing
+    /// and reacting, or during any phase (see `Access`).
+    ///
+    /// By default the Entity writes its Neighborhood.
+    fn writes(&self) -> Access {
+        Access::Neighbors
+    }
+
+    /// Returns true only if the Neighborhood of the Entity wraps around the
+    /// edges of the Environment, as for every other query on its Torus.
+    ///
+    /// Entities that return false are given a Neighborhood clipped to the
+    /// edges of the Environment instead, as if the edges were walls, and whose
+    /// center is therefore not necessarily located in the middle of its grid
+    /// of tiles (see `Neighborhood::center_position`). All the entities wrap by
+    /// default.
+    fn wraps(&self) -> bool {
+        true
+    }
+
+    /// Gets the Orientation of the Entity, for the entities that face a
+    /// direction (such as ants, vehicles and turmites).
+    ///
+    /// The Orientation can be used to view the Neighborhood of the Entity from
+    /// its own local frame (see `Neighborhood::oriented`), and to draw the
+    /// Entity rotated accordingly. Entities have no Orientation by default.
+    fn orientation(&self) -> Option<Orientation> {
+        None
+    }
+
+    /// Gets the Perception of the Entity, that determines which of the
+    /// entities within its Neighborhood it actually perceives (see
+    /// `Perception`).
+    ///
+    /// All the entities have a perfect Perception by default.
+    fn perception(&self) -> Perception {
+        Perception::perfect()
+    }
+
     /// Gets the remaining lifespan of the Entity.
     ///
     /// If the concept of lifespan is meaningless for this Entity, it should
@@ -98,6 +198,15 @@ pub trait Entity<'e> {
         None
     }
 
+    /// Gets a mutable reference to the cooldowns of the actions of the Entity,
+    /// that the Environment ticks at the start of each generation (see
+    /// `Cooldowns`).
+    ///
+    /// If the Entity has no cooldowns, it should simply return None.
+    fn cooldowns_mut(&mut self) -> Option<&mut Cooldowns> {
+        None
+    }
+
     /// Gets a reference to a trait that is implemented by the object that
     /// represents the state of the Entity.
     ///
@@ -120,6 +229,26 @@ pub trait Entity<'e> {
         None
     }
 
+    /// Gets the value the Entity contributes to the render values of its Kind,
+    /// such as its temperature or its color index (see
+    /// `Environment::enable_render_values`).
+    ///
+    /// Entities that are not drawn as part of an image can simply return None.
+    fn render_value(&self) -> Option<f32> {
+        None
+    }
+
+    /// Gets a digest of the state of the Entity.
+    ///
+    /// The digest is combined with the kind and the location of each Entity to
+    /// compute the digest of the whole Environment, used to detect when the
+    /// simulation has stabilized. Entities whose state affects the evolution of
+    /// the simulation should return a hash of it, while entities with no
+    /// meaningful state can simply return None.
+    fn digest(&self) -> Option<u64> {
+        None
+    }
+
     /// Allows the Entity to observe the portion of surrounding Environment seen
     /// by the Entity according to its scope.
     ///
@@ -153,7 +282,7 @@ pub trait Entity<'e> {
     /// entities, only after all the `Entity::observe` have been called.
     fn observe(
         &mut self,
-        _: Option<Neighborhood<'_, 'e, Self::Kind, Self::Context>>,
+        _: Option<Neighborhood<'_, 'e, Self::Kind, C>>,
     ) -> Result<(), Error> {
         Ok(())
     }
@@ -186,11 +315,151 @@ pub trait Entity<'e> {
     /// entities of the previous generation.
     fn react(
         &mut self,
-        _: Option<Neighborhood<'_, 'e, Self::Kind, Self::Context>>,
+        _: Option<Neighborhood<'_, 'e, Self::Kind, C>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Allows the Entity to observe the portion of surrounding Environment seen
+    /// by the Entity according to its scope, as well as the global information
+    /// of the whole Environment.
+    ///
+    /// This is the method actually called by the Environment for each
+    /// generation, and by default it ignores the given WorldView and simply
+    /// calls `Entity::observe`. Entities whose behavior depends on global
+    /// information (such as the number of entities of a specific Kind, or any
+    /// of the global resources of the Environment) should override it instead.
+    fn observe_with(
+        &mut self,
+        neighborhood: Option<Neighborhood<'_, 'e, Self::Kind, C>>,
+        _world: &WorldView<'_, 'e, Self::Kind, C>,
+    ) -> Result<(), Error> {
+        self.observe(neighborhood)
+    }
+
+    /// Allows to take an action that will affect the Entity itself, and its
+    /// neighbors, according to the portion of surrounding Environment seen by
+    /// the Entity according to its scope, as well as the global information of
+    /// the whole Environment.
+    ///
+    /// This is the method actually called by the Environment for each
+    /// generation, and by default it ignores the given WorldView and simply
+    /// calls `Entity::react`.
+    fn react_with(
+        &mut self,
+        neighborhood: Option<Neighborhood<'_, 'e, Self::Kind, C>>,
+        _world: &WorldView<'_, 'e, Self::Kind, C>,
     ) -> Result<(), Error> {
+        self.react(neighborhood)
+    }
+
+    /// Delivers to the Entity one of the external inputs pushed to it with
+    /// `Environment::push_input` (such as the keys pressed by the player that
+    /// controls it).
+    ///
+    /// This method is called once for each input, in the order they were
+    /// pushed, at the start of the generation that follows their push, before
+    /// the Entity observes and reacts to its Neighborhood, so that it can
+    /// store the inputs and act on them when it reacts.
+    fn on_input(&mut self, _input: AnyValue) -> Result<(), Error> {
         Ok(())
     }
 
+    /// Allows the Entity to take an action during the given phase of the
+    /// current generation, according to the portion of surrounding Environment
+    /// seen by the Entity according to its scope.
+    ///
+    /// This method is called for each of the phases set with
+    /// `Environment::set_phases`, in order, after all the entities have reacted
+    /// to their Neighborhood. Unlike `Entity::observe` and `Entity::react`, the
+    /// given Neighborhood reflects the locations of the entities as updated by
+    /// the previous phase.
+    /// If the Entity has no scope the Neighborhood will be None.
+    fn on_phase(
+        &mut self,
+        _phase: &str,
+        _: Option<Neighborhood<'_, 'e, Self::Kind, C>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Allows the Entity to take its last actions when its lifespan reaches
+    /// zero.
+    ///
+    /// This method is called once, in the generation the lifespan of the Entity
+    /// is found to be over, after all the phases of the generation and before
+    /// the offspring of the entities are collected, so that the Entity can
+    /// still release its last offspring (for example its own corpse), or
+    /// restore its lifespan. The Entity is then removed from the Environment at
+    /// the end of the same generation, unless its lifespan has a grace period
+    /// (see `Lifespan::with_grace`), in which case it is removed only once the
+    /// grace period is over.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// struct Mayfly(Lifespan, bool);
+    ///
+    /// impl<'e> Entity<'e> for Mayfly {
+    ///     type Kind = u8;
+    ///
+    ///     fn id(&self) -> Id {
+    ///         0
+    ///     }
+    ///
+    ///     fn kind(&self) -> Self::Kind {
+    ///         0
+    ///     }
+    ///
+    ///     fn lifespan(&self) -> Option<Lifespan> {
+    ///         Some(self.0)
+    ///     }
+    ///
+    ///     fn react(
+    ///         &mut self,
+    ///         _: Option<Neighborhood<'_, 'e, u8>>,
+    ///     ) -> Result<(), Error> {
+    ///         self.0.shorten();
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn on_expire(&mut self) -> Result<(), Error> {
+    ///         assert!(!self.1, "expired twice");
+    ///         self.1 = true;
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut env = Environment::new((1, 1));
+    /// env.insert(Mayfly(Lifespan::with_grace(1, 2), false));
+    ///
+    /// // the lifespan is over after the first generation, but the entity is
+    /// // kept for the 2 generations of its grace period
+    /// for _ in 0..2 {
+    ///     env.nextgen().unwrap();
+    ///     assert_eq!(env.count(), 1);
+    /// }
+    /// env.nextgen().unwrap();
+    /// assert!(env.is_empty());
+    /// ```
+    fn on_expire(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Gets the intent of the Entity to move to a new Location.
+    ///
+    /// This method is called for each generation, after all the entities have
+    /// reacted to their neighborhood. Rather than changing its own location in
+    /// `Entity::react`, an Entity can submit its intent to move, and let the
+    /// Environment resolve the conflicts between entities that intend to move
+    /// into the same tile. Only the winning intents are committed, via
+    /// `Entity::location_mut`.
+    /// If the Entity does not intend to move, it should simply return None.
+    fn intent(&mut self) -> Option<MoveIntent> {
+        None
+    }
+
     /// Gets the Offspring of the Entity.
     ///
     /// The offspring of an Entity will be introduced in the Environment at
@@ -204,30 +473,175 @@ pub trait Entity<'e> {
     /// that the lifetime bound stipulated when creating the parent Entity, that
     /// may contain references as part of the type that implements this trait,
     /// will be kept unchanged.
-    fn offspring(
-        &mut self,
-    ) -> Option<Offspring<'e, Self::Kind, Self::Context>> {
+    fn offspring(&mut self) -> Option<Offspring<'e, Self::Kind, C>> {
+        None
+    }
+
+    /// Takes the commands scheduled by this Entity during the current
+    /// generation, each with the generation it is due at.
+    ///
+    /// The commands are collected once per generation, after the offspring
+    /// are inserted, and are executed by the Environment at the start of their
+    /// generation (see `Environment::schedule`).
+    fn commands(&mut self) -> Vec<ScheduledCommand<'e, Self::Kind, C>> {
+        Vec::new()
+    }
+
+    /// Takes the commands issued by this Entity to the entities of its
+    /// Neighborhood during the current generation, each with the ID of the
+    /// Entity it targets.
+    ///
+    /// The commands are collected once per generation, after all the phases
+    /// are over, and are applied by the Environment with the ordering and
+    /// conflict resolution described by `NeighborCommand`.
+    fn neighbor_commands(&mut self) -> Vec<TargetedCommand> {
+        Vec::new()
+    }
+
+    /// Takes the changes of the properties of this Entity notified during the
+    /// current generation (see `Notifier`).
+    ///
+    /// The changes are collected once per generation, after the commands
+    /// issued to the neighbors are applied, and are buffered by the
+    /// Environment only if the Entity or its Kind are subscribed (see
+    /// `Environment::notifications`).
+    fn notifications(&mut self) -> Vec<PropertyChange> {
+        Vec::new()
+    }
+
+    /// Gets a reference to the CloneEntity trait implemented by this Entity.
+    ///
+    /// Entities that implement Clone should return `Some(self)` from this
+    /// method, so that the Environment they belong to can be cloned (see
+    /// `Environment::try_clone`). If the Entity cannot be cloned it should
+    /// simply return None.
+    fn cloneable(&self) -> Option<&dyn CloneEntity<'e, Self::Kind, C>> {
+        None
+    }
+
+    /// Gets a reference to the Drawable trait implemented by this Entity.
+    ///
+    /// Entities that have a shape to draw should implement the Drawable trait
+    /// and return `Some(self)` from this method. If the Entity has no shape, as
+    /// for all the entities of a headless simulation, it should simply return
+    /// None, and it will be skipped by `Environment::draw`.
+    fn drawable(&self) -> Option<&dyn Drawable<Context = C>> {
         None
     }
 
-    /// Draws the Entity using the given graphics Context and according to the
+    /// Gets the order in which this Entity is drawn relative to the other
+    /// entities of the same Kind, where the entities with a lower z-order are
+    /// drawn first (below the others).
+    ///
+    /// The entities of the same Kind with the same z-order are drawn in the
+    /// order they are stored in the Environment. By default the z-order of
+    /// all the entities is 0.
+    fn z_order(&self) -> i32 {
+        0
+    }
+}
+
+/// The Trait that describes an object that can be drawn.
+///
+/// Drawing is kept separate from the Entity trait, so that the entities of a
+/// headless simulation don't need to know anything about graphics, nor about
+/// the type of its Context.
+pub trait Drawable {
+    /// The type of the graphics Context used to draw the shape of the object.
+    type Context;
+
+    /// Draws the object using the given graphics Context and according to the
     /// given transformation (matrix).
     ///
-    /// This method is called for each generation. If you wish to skip drawing
-    /// the shape of your Entity, this method should simply return `Ok(())`.
-    fn draw(&self, _: &mut Self::Context, _: Transform) -> Result<(), Error> {
-        Ok(())
+    /// This method is called for each generation by `Environment::draw`, for
+    /// all the entities that return Some from `Entity::drawable`.
+    fn draw(
+        &self,
+        ctx: &mut Self::Context,
+        transform: Transform,
+    ) -> Result<(), Error>;
+
+    /// Draws the object using the given graphics Context and according to the
+    /// given DrawContext, that includes the transformation (matrix) as well as
+    /// the parameters of the layer the object belongs to.
+    ///
+    /// This is the method actually called by `Environment::draw`, and by
+    /// default it ignores the layer parameters and simply calls
+    /// `Drawable::draw`. Objects that can be drawn with transparency should
+    /// override it to take into account the alpha of their layer.
+    fn draw_with_context(
+        &self,
+        ctx: &mut Self::Context,
+        draw: DrawContext,
+    ) -> Result<(), Error> {
+        self.draw(ctx, draw.transform)
+    }
+
+    /// Prepares the DrawCommand that describes how to draw the object given
+    /// the DrawContext, without drawing it.
+    ///
+    /// This method is called by `Environment::prepare_draw` and
+    /// `Environment::draw_prepared`, possibly in parallel for different
+    /// objects (with the `parallel` feature), so that the expensive math
+    /// required to draw each object can be computed ahead of the (serial)
+    /// submission of the draw calls to the graphics Context.
+    /// By default it returns None, in which case the object is drawn via
+    /// `Drawable::draw_with_context` by `Environment::draw_prepared`, and it is
+    /// skipped by `Environment::prepare_draw`.
+    fn prepare_draw(&self, _draw: DrawContext) -> Option<DrawCommand> {
+        None
+    }
+}
+
+/// The parameters given to a Drawable object when drawn by the Environment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawContext {
+    /// The transformation (matrix) to apply to the object.
+    pub transform: Transform,
+    /// The opacity of the layer (Kind) the object belongs to, in the range
+    /// [0, 1], where 0 is fully transparent and 1 is fully opaque.
+    pub alpha: f32,
+    /// The geometry of the grid of tiles of the Environment in pixels, used to
+    /// convert the location of the object to pixel coordinates.
+    pub grid: TileGrid,
+    /// The age of the Entity being drawn, as number of generations since it
+    /// was inserted into the Environment (see `Environment::age`).
+    pub age: u64,
+}
+
+/// The lightweight description of a draw call, prepared by a Drawable object
+/// via `Drawable::prepare_draw`, and submitted to the graphics Context later on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawCommand {
+    /// The user-defined identifier of the mesh to draw.
+    pub mesh: u32,
+    /// The transformation (matrix) to apply to the mesh.
+    pub transform: Transform,
+    /// The color of the mesh, as RGBA components in the range [0, 1].
+    pub color: [f32; 4],
+}
+
+impl From<Transform> for DrawContext {
+    /// Constructs a new fully opaque DrawContext with the given transformation,
+    /// the default TileGrid, and an age of 0.
+    fn from(transform: Transform) -> Self {
+        Self {
+            transform,
+            alpha: 1.0,
+            grid: TileGrid::default(),
+            age: 0,
+        }
     }
 }
 
 /// The Entity Trait type alias with explicit lifetime bound.
 #[cfg(not(feature = "parallel"))]
-pub type EntityTrait<'e, K, C> = dyn Entity<'e, Kind = K, Context = C> + 'e;
+pub type EntityTrait<'e, K, C = ()> = dyn Entity<'e, C, Kind = K> + 'e;
 
 /// The Entity Trait type alias with explicit lifetime bound.
 #[cfg(feature = "parallel")]
-pub type EntityTrait<'e, K, C> =
-    dyn Entity<'e, Kind = K, Context = C> + 'e + Send + Sync;
+pub type EntityTrait<'e, K, C = ()> =
+    dyn Entity<'e, C, Kind = K> + 'e + Send + Sync;
 
 impl<'e, K: fmt::Debug, C> fmt::Debug for EntityTrait<'e, K, C> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -0,0 +1,100 @@
+use std::any::Any;
+
+use super::*;
+
+/// The change of a named property of an Entity, made of the name of the
+/// property and of its new value.
+pub type PropertyChange = (&'static str, AnyValue);
+
+/// The buffer of the property changes of an Entity, that can be embedded into
+/// the Entity to notify the changes of its properties (such as its health) to
+/// the frontends subscribed with `Environment::subscribe_entity` or
+/// `Environment::subscribe_kind`, without the frontends polling every Entity.
+///
+/// The Entity notifies the changes while it reacts (or during any phase) with
+/// `Notifier::notify`, and hands them to the Environment by returning
+/// `Notifier::take` from `Entity::notifications`.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// struct Creature(u32, Notifier);
+///
+/// impl<'e> Entity<'e> for Creature {
+///     type Kind = char;
+///
+///     fn id(&self) -> Id {
+///         0
+///     }
+///
+///     fn kind(&self) -> char {
+///         'c'
+///     }
+///
+///     fn react(
+///         &mut self,
+///         _: Option<Neighborhood<'_, 'e, char>>,
+///     ) -> Result<(), Error> {
+///         self.0 -= 1;
+///         self.1.notify("health", self.0);
+///         Ok(())
+///     }
+///
+///     fn notifications(&mut self) -> Vec<PropertyChange> {
+///         self.1.take()
+///     }
+/// }
+///
+/// let mut env = Environment::new((3, 3));
+/// env.insert(Creature(10, Notifier::new()));
+/// env.subscribe_kind('c');
+///
+/// env.nextgen().unwrap();
+/// let notification = &env.notifications()[0];
+/// assert_eq!((notification.id, notification.property), (0, "health"));
+/// assert_eq!(notification.value.downcast_ref::<u32>(), Some(&9));
+/// ```
+#[derive(Default)]
+pub struct Notifier {
+    changes: Vec<PropertyChange>,
+}
+
+impl fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.changes.iter().map(|(property, _)| property))
+            .finish()
+    }
+}
+
+impl Notifier {
+    /// Constructs a new Notifier with no property changes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Notifies that the property with the given name changed to the given
+    /// value.
+    #[cfg(not(feature = "parallel"))]
+    pub fn notify(&mut self, property: &'static str, value: impl Any) {
+        self.changes.push((property, Box::new(value)));
+    }
+
+    /// Notifies that the property with the given name changed to the given
+    /// value.
+    #[cfg(feature = "parallel")]
+    pub fn notify(
+        &mut self,
+        property: &'static str,
+        value: impl Any + Send + Sync,
+    ) {
+        self.changes.push((property, Box::new(value)));
+    }
+
+    /// Takes all the property changes notified so far, in the order they were
+    /// notified.
+    pub fn take(&mut self) -> Vec<PropertyChange> {
+        std::mem::take(&mut self.changes)
+    }
+}
@@ -1,9 +1,21 @@
 use super::*;
 
 /// The Entity offspring.
-#[derive(Debug)]
-pub struct Offspring<'e, K, C> {
+pub struct Offspring<'e, K, C = ()> {
     entities: Vec<Box<EntityTrait<'e, K, C>>>,
+    // the offset from the parent location of each entity, if any
+    offsets: Vec<Option<Offset>>,
+    // the inheritance of the genome of each entity from its parents, if any
+    inheritances: Vec<Option<Inheritance>>,
+}
+
+impl<'e, K: fmt::Debug, C> fmt::Debug for Offspring<'e, K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Offspring")
+            .field("entities", &self.entities)
+            .field("offsets", &self.offsets)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<'e, K, C> Default for Offspring<'e, K, C> {
@@ -11,6 +23,8 @@ impl<'e, K, C> Default for Offspring<'e, K, C> {
     fn default() -> Self {
         Self {
             entities: Vec::default(),
+            offsets: Vec::default(),
+            inheritances: Vec::default(),
         }
     }
 }
@@ -20,6 +34,8 @@ impl<'e, K, C> Offspring<'e, K, C> {
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             entities: Vec::with_capacity(capacity),
+            offsets: Vec::with_capacity(capacity),
+            inheritances: Vec::with_capacity(capacity),
         }
     }
 
@@ -28,9 +44,66 @@ impl<'e, K, C> Offspring<'e, K, C> {
     pub fn insert<E>(&mut self, entity: E)
     where
         // Trait aliases https://github.com/rust-lang/rust/issues/41517
-        E: Entity<'e, Kind = K, Context = C> + 'e,
+        E: Entity<'e, C, Kind = K> + 'e,
+    {
+        self.push(Box::new(entity), None);
+    }
+
+    /// Inserts a new Entity into the Offspring, as the child of the parents
+    /// with the given genomes.
+    ///
+    /// When the Offspring is inserted, the Environment derives the genome of
+    /// the child via `Genome::crossover` and `Genome::mutate`, and replaces
+    /// the State of the Entity with it via `State::set_any` (so that the State
+    /// the Entity is constructed with is discarded, unless it cannot be
+    /// replaced by a value of the genome type).
+    #[cfg(not(feature = "parallel"))]
+    pub fn insert_child_of<E, G>(&mut self, entity: E, a: &G, b: &G)
+    where
+        E: Entity<'e, C, Kind = K> + 'e,
+        G: Genome,
+    {
+        self.push(Box::new(entity), None);
+        *self.inheritances.last_mut().expect("Empty offspring") =
+            Some(inherit(a, b));
+    }
+
+    /// Inserts a new Entity into the Offspring, that will be located at the
+    /// given offset from the location of its parent.
+    ///
+    /// The final location of the Entity is resolved by the Environment when
+    /// the Offspring is inserted, by wrapping it around the Environment edges,
+    /// and it is set via `Entity::location_mut`. If the parent has no location,
+    /// the location of the Entity is left unchanged.
+    #[cfg(not(feature = "parallel"))]
+    pub fn insert_at<E>(&mut self, entity: E, offset: impl Into<Offset>)
+    where
+        E: Entity<'e, C, Kind = K> + 'e,
+    {
+        self.push(Box::new(entity), Some(offset.into()));
+    }
+
+    /// Inserts the given number of new entities, constructed by the given
+    /// factory, into the Offspring, that will be scattered around the location
+    /// of their parent within the given Scope.
+    ///
+    /// The entities are spread as evenly as possible over the tiles that
+    /// surround the parent (excluding the tile of the parent itself), from the
+    /// top-left to the bottom-right corner, and if there are more entities
+    /// than tiles, multiple entities will share the same tile. The final
+    /// locations are resolved as for `Offspring::insert_at`.
+    #[cfg(not(feature = "parallel"))]
+    pub fn insert_scattered<E>(
+        &mut self,
+        count: usize,
+        scope: impl Into<Scope>,
+        mut factory: impl FnMut() -> E,
+    ) where
+        E: Entity<'e, C, Kind = K> + 'e,
     {
-        self.entities.push(Box::new(entity));
+        for offset in scattered_offsets(count, scope.into()) {
+            self.push(Box::new(factory()), Some(offset));
+        }
     }
 
     /// Inserts a new Entity into the Offspring.
@@ -38,9 +111,66 @@ impl<'e, K, C> Offspring<'e, K, C> {
     pub fn insert<E>(&mut self, entity: E)
     where
         // Trait aliases https://github.com/rust-lang/rust/issues/41517
-        E: Entity<'e, Kind = K, Context = C> + 'e + Send + Sync,
+        E: Entity<'e, C, Kind = K> + 'e + Send + Sync,
     {
-        self.entities.push(Box::new(entity));
+        self.push(Box::new(entity), None);
+    }
+
+    /// Inserts a new Entity into the Offspring, as the child of the parents
+    /// with the given genomes.
+    ///
+    /// When the Offspring is inserted, the Environment derives the genome of
+    /// the child via `Genome::crossover` and `Genome::mutate`, and replaces
+    /// the State of the Entity with it via `State::set_any` (so that the State
+    /// the Entity is constructed with is discarded, unless it cannot be
+    /// replaced by a value of the genome type).
+    #[cfg(feature = "parallel")]
+    pub fn insert_child_of<E, G>(&mut self, entity: E, a: &G, b: &G)
+    where
+        E: Entity<'e, C, Kind = K> + 'e + Send + Sync,
+        G: Genome,
+    {
+        self.push(Box::new(entity), None);
+        *self.inheritances.last_mut().expect("Empty offspring") =
+            Some(inherit(a, b));
+    }
+
+    /// Inserts a new Entity into the Offspring, that will be located at the
+    /// given offset from the location of its parent.
+    ///
+    /// The final location of the Entity is resolved by the Environment when
+    /// the Offspring is inserted, by wrapping it around the Environment edges,
+    /// and it is set via `Entity::location_mut`. If the parent has no location,
+    /// the location of the Entity is left unchanged.
+    #[cfg(feature = "parallel")]
+    pub fn insert_at<E>(&mut self, entity: E, offset: impl Into<Offset>)
+    where
+        E: Entity<'e, C, Kind = K> + 'e + Send + Sync,
+    {
+        self.push(Box::new(entity), Some(offset.into()));
+    }
+
+    /// Inserts the given number of new entities, constructed by the given
+    /// factory, into the Offspring, that will be scattered around the location
+    /// of their parent within the given Scope.
+    ///
+    /// The entities are spread as evenly as possible over the tiles that
+    /// surround the parent (excluding the tile of the parent itself), from the
+    /// top-left to the bottom-right corner, and if there are more entities
+    /// than tiles, multiple entities will share the same tile. The final
+    /// locations are resolved as for `Offspring::insert_at`.
+    #[cfg(feature = "parallel")]
+    pub fn insert_scattered<E>(
+        &mut self,
+        count: usize,
+        scope: impl Into<Scope>,
+        mut factory: impl FnMut() -> E,
+    ) where
+        E: Entity<'e, C, Kind = K> + 'e + Send + Sync,
+    {
+        for offset in scattered_offsets(count, scope.into()) {
+            self.push(Box::new(factory()), Some(offset));
+        }
     }
 
     /// Gets the number of entities in the Offspring.
@@ -61,11 +191,79 @@ impl<'e, K, C> Offspring<'e, K, C> {
     pub fn drain(&mut self) -> Self {
         Self {
             entities: self.entities.drain(..).collect(),
+            offsets: self.offsets.drain(..).collect(),
+            inheritances: self.inheritances.drain(..).collect(),
         }
     }
 
-    /// Takes the entities out of the Offspring consuming self.
-    pub(crate) fn take_entities(self) -> Vec<Box<EntityTrait<'e, K, C>>> {
-        self.entities
+    /// Takes the entities out of the Offspring consuming self, locating them
+    /// relative to the given parent location within an Environment of the given
+    /// dimension, and deriving their genomes with the given GenomeRng.
+    pub(crate) fn take_entities(
+        self,
+        parent: Option<Location>,
+        dimension: Dimension,
+        rng: &mut GenomeRng,
+    ) -> Vec<Box<EntityTrait<'e, K, C>>> {
+        let mut entities = self.entities;
+        for (entity, inheritance) in entities.iter_mut().zip(self.inheritances)
+        {
+            if let Some(inheritance) = inheritance {
+                let genome = inheritance(rng);
+                if let Some(state) = entity.state_mut() {
+                    state.set_any(genome);
+                }
+            }
+        }
+        if let Some(parent) = parent {
+            for (entity, offset) in entities.iter_mut().zip(self.offsets) {
+                if let (Some(offset), Some(location)) =
+                    (offset, entity.location_mut())
+                {
+                    *location = parent;
+                    location.translate(offset, dimension);
+                }
+            }
+        }
+        entities
     }
+
+    /// Pushes the given Entity, with its optional offset from the parent.
+    fn push(
+        &mut self,
+        entity: Box<EntityTrait<'e, K, C>>,
+        offset: Option<Offset>,
+    ) {
+        self.entities.push(entity);
+        self.offsets.push(offset);
+        self.inheritances.push(None);
+    }
+}
+
+/// Gets the offsets of the given number of entities scattered as evenly as
+/// possible over the tiles surrounding a central tile within the given Scope.
+fn scattered_offsets(
+    count: usize,
+    scope: Scope,
+) -> impl Iterator<Item = Offset> {
+    let delta = scope.magnitude() as i32;
+    let side = delta * 2 + 1;
+    // the offsets of all the surrounding tiles, excluding the central one
+    let tiles: Vec<Offset> = (0..side * side)
+        .map(move |i| Offset {
+            x: i % side - delta,
+            y: i / side - delta,
+        })
+        .filter(|&offset| offset != Offset::origin())
+        .collect();
+
+    (0..count).map(move |i| {
+        if tiles.is_empty() {
+            Offset::origin()
+        } else if count <= tiles.len() {
+            tiles[i * tiles.len() / count]
+        } else {
+            tiles[i % tiles.len()]
+        }
+    })
 }
@@ -0,0 +1,98 @@
+/// The model of the perception of an Entity, that determines which of the
+/// entities within its Neighborhood it actually perceives, so that limited
+/// sensing can be modelled by the Environment rather than by each Entity
+/// ignoring part of its Neighborhood on its own.
+///
+/// An Entity can miss each of its neighbors with a given probability, and it
+/// can perceive at most a given number of neighbors, in which case the
+/// nearest ones are perceived (and the ones with the lowest ID between
+/// neighbors at the same distance). The misses are drawn from a source of
+/// randomness seeded by the Environment (see
+/// `Environment::set_perception_seed`), so that the same Entity perceives
+/// the same neighbors when observing and reacting within a generation, and
+/// the perception is reproducible across runs.
+///
+/// The entities that are not perceived are hidden by all the tiles of the
+/// Neighborhood, while the Entity is always perfectly aware of itself.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let perception = Perception::perfect().with_max_count(2);
+/// let mut env = Environment::<char>::new((7, 7));
+/// for x in 1..6 {
+///     env.insert(QuickEntity::new('b').id(x as Id).at((x, 1)));
+/// }
+/// env.insert(
+///     QuickEntity::new('a')
+///         .id(9)
+///         .at((3, 3))
+///         .scope(2)
+///         .perception(perception)
+///         .on_react(|_, neighborhood| {
+///             let neighborhood = neighborhood.unwrap();
+///             let seen = neighborhood.tiles().flat_map(|t| t.entities());
+///             let ids: Vec<_> = seen.map(|e| e.id()).collect();
+///             assert_eq!(ids, [2, 3]);
+///             Ok(())
+///         }),
+/// );
+/// env.nextgen().unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Perception {
+    miss_rate: f64,
+    max_count: Option<usize>,
+}
+
+impl Default for Perception {
+    fn default() -> Self {
+        Self::perfect()
+    }
+}
+
+impl Perception {
+    /// Constructs a new perfect Perception, that perceives all the entities
+    /// within the Neighborhood.
+    pub const fn perfect() -> Self {
+        Self {
+            miss_rate: 0.0,
+            max_count: None,
+        }
+    }
+
+    /// Sets the probability of missing each of the neighbors.
+    ///
+    /// # Panics
+    /// Panics if the probability is not within [0, 1].
+    pub fn with_miss_rate(mut self, miss_rate: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&miss_rate),
+            "The miss rate must be within [0, 1]"
+        );
+        self.miss_rate = miss_rate;
+        self
+    }
+
+    /// Sets the maximum number of neighbors perceived.
+    pub fn with_max_count(mut self, max_count: usize) -> Self {
+        self.max_count = Some(max_count);
+        self
+    }
+
+    /// Gets the probability of missing each of the neighbors.
+    pub fn miss_rate(&self) -> f64 {
+        self.miss_rate
+    }
+
+    /// Gets the maximum number of neighbors perceived, if limited.
+    pub fn max_count(&self) -> Option<usize> {
+        self.max_count
+    }
+
+    /// Returns true only if all the neighbors are perceived.
+    pub fn is_perfect(&self) -> bool {
+        self.miss_rate == 0.0 && self.max_count.is_none()
+    }
+}
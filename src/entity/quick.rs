@@ -0,0 +1,425 @@
+use super::*;
+
+/// The type of the closures called by a QuickEntity to observe or react to its
+/// Neighborhood.
+#[cfg(not(feature = "parallel"))]
+type Callback<'e, K, C> = Box<
+    dyn FnMut(
+            &mut QuickEntity<'e, K, C>,
+            Option<Neighborhood<'_, 'e, K, C>>,
+        ) -> Result<(), Error>
+        + 'e,
+>;
+
+/// The type of the closures called by a QuickEntity to observe or react to its
+/// Neighborhood.
+#[cfg(feature = "parallel")]
+type Callback<'e, K, C> = Box<
+    dyn FnMut(
+            &mut QuickEntity<'e, K, C>,
+            Option<Neighborhood<'_, 'e, K, C>>,
+        ) -> Result<(), Error>
+        + 'e
+        + Send
+        + Sync,
+>;
+
+/// The type of the closure called by a QuickEntity to draw itself.
+#[cfg(not(feature = "parallel"))]
+type DrawCallback<'e, K, C> = Box<
+    dyn Fn(&QuickEntity<'e, K, C>, &mut C, Transform) -> Result<(), Error> + 'e,
+>;
+
+/// The type of the closure called by a QuickEntity to draw itself.
+#[cfg(feature = "parallel")]
+type DrawCallback<'e, K, C> = Box<
+    dyn Fn(&QuickEntity<'e, K, C>, &mut C, Transform) -> Result<(), Error>
+        + 'e
+        + Send
+        + Sync,
+>;
+
+/// An Entity whose behavior is defined by closures rather than by a dedicated
+/// implementation of the Entity trait.
+///
+/// The QuickEntity is constructed with a builder like syntax, and it is meant
+/// to be used for small experiments and prototypes, where writing a new type
+/// for each kind of Entity would be overkill.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let mut env = Environment::<u8>::new((10, 10));
+/// env.insert(
+///     QuickEntity::new(0)
+///         .at((2, 2))
+///         .scope(1)
+///         .lifespan(5)
+///         .on_react(|entity, _neighborhood| {
+///             entity.set_location(Location { x: 3, y: 2 });
+///             Ok(())
+///         }),
+/// );
+///
+/// env.nextgen().unwrap();
+/// assert_eq!(env.entities_at((3, 2)).count(), 1);
+/// ```
+pub struct QuickEntity<'e, K, C> {
+    // the ID, if set explicitly or assigned by the Environment
+    id: Option<Id>,
+    kind: K,
+    location: Option<Location>,
+    scope: Option<Scope>,
+    wraps: bool,
+    perception: Perception,
+    z_order: i32,
+    lifespan: Option<Lifespan>,
+    on_observe: Option<Callback<'e, K, C>>,
+    on_react: Option<Callback<'e, K, C>>,
+    on_draw: Option<DrawCallback<'e, K, C>>,
+}
+
+impl<'e, K, C> QuickEntity<'e, K, C> {
+    /// Constructs a new QuickEntity of the given Kind, with no location, scope
+    /// or lifespan, and with no behavior.
+    ///
+    /// Unless set with `QuickEntity::id`, the ID of the Entity is assigned by
+    /// the Environment it is inserted into (see `Entity::assign_id`), and it
+    /// is 0 until then.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// // the IDs assigned by each Environment skip the ones already in use
+    /// let mut env = Environment::<char>::new((4, 4));
+    /// env.insert(QuickEntity::new('a').id(0));
+    /// env.insert(QuickEntity::new('b'));
+    /// env.insert(QuickEntity::new('c'));
+    /// let ids: Vec<_> = env.entities().map(|e| e.id()).collect();
+    /// assert_eq!(ids, vec![0, 1, 2]);
+    ///
+    /// let mut other = Environment::<char>::new((4, 4));
+    /// other.insert(QuickEntity::new('d'));
+    /// assert_eq!(other.entities().next().map(|e| e.id()), Some(0));
+    /// ```
+    pub fn new(kind: K) -> Self {
+        Self {
+            id: None,
+            kind,
+            location: None,
+            scope: None,
+            wraps: true,
+            perception: Perception::perfect(),
+            z_order: 0,
+            lifespan: None,
+            on_observe: None,
+            on_react: None,
+            on_draw: None,
+        }
+    }
+
+    /// Sets the ID of the Entity.
+    pub fn id(mut self, id: Id) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Sets the Location of the Entity.
+    pub fn at(mut self, location: impl Into<Location>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+
+    /// Sets the Scope of the Entity.
+    pub fn scope(mut self, scope: impl Into<Scope>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// Sets whether the Neighborhood of the Entity wraps around the edges of
+    /// the Environment, or it is clipped to them (see `Entity::wraps`).
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8>::new((10, 10));
+    /// env.insert(QuickEntity::new(0).at((0, 0)).scope(1).wraps(false).on_react(
+    ///     |_, neighborhood| {
+    ///         let neighborhood = neighborhood.unwrap();
+    ///         assert_eq!(neighborhood.dimension(), Dimension { x: 2, y: 2 });
+    ///         assert_eq!(neighborhood.center_position(), Location::origin());
+    ///         Ok(())
+    ///     },
+    /// ));
+    /// env.nextgen().unwrap();
+    /// ```
+    pub fn wraps(mut self, wraps: bool) -> Self {
+        self.wraps = wraps;
+        self
+    }
+
+    /// Sets the Perception of the Entity (see `Entity::perception`).
+    pub fn perception(mut self, perception: Perception) -> Self {
+        self.perception = perception;
+        self
+    }
+
+    /// Sets the order in which the Entity is drawn relative to the other
+    /// entities of the same Kind (see `Entity::z_order`).
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8, Vec<i32>>::new((10, 10));
+    /// for z in [2, -1, 0] {
+    ///     env.insert(QuickEntity::new(0).at((1, 1)).z_order(z).on_draw(
+    ///         move |_, drawn: &mut Vec<i32>, _| {
+    ///             drawn.push(z);
+    ///             Ok(())
+    ///         },
+    ///     ));
+    /// }
+    ///
+    /// let mut drawn = Vec::new();
+    /// env.draw(&mut drawn, Transform::identity()).unwrap();
+    /// assert_eq!(drawn, vec![-1, 0, 2]);
+    /// ```
+    pub fn z_order(mut self, z_order: i32) -> Self {
+        self.z_order = z_order;
+        self
+    }
+
+    /// Sets an Ephemeral Lifespan of the given span for the Entity.
+    pub fn lifespan(mut self, span: impl Into<Span>) -> Self {
+        self.lifespan = Some(Lifespan::with_span(span));
+        self
+    }
+
+    /// Sets an Immortal Lifespan for the Entity.
+    pub fn immortal(mut self) -> Self {
+        self.lifespan = Some(Lifespan::Immortal);
+        self
+    }
+
+    /// Sets the closure called by `Entity::observe`.
+    #[cfg(not(feature = "parallel"))]
+    pub fn on_observe<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(
+                &mut QuickEntity<'e, K, C>,
+                Option<Neighborhood<'_, 'e, K, C>>,
+            ) -> Result<(), Error>
+            + 'e,
+    {
+        self.on_observe = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the closure called by `Entity::observe`.
+    #[cfg(feature = "parallel")]
+    pub fn on_observe<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(
+                &mut QuickEntity<'e, K, C>,
+                Option<Neighborhood<'_, 'e, K, C>>,
+            ) -> Result<(), Error>
+            + 'e
+            + Send
+            + Sync,
+    {
+        self.on_observe = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the closure called by `Entity::react`.
+    #[cfg(not(feature = "parallel"))]
+    pub fn on_react<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(
+                &mut QuickEntity<'e, K, C>,
+                Option<Neighborhood<'_, 'e, K, C>>,
+            ) -> Result<(), Error>
+            + 'e,
+    {
+        self.on_react = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the closure called by `Entity::react`.
+    #[cfg(feature = "parallel")]
+    pub fn on_react<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(
+                &mut QuickEntity<'e, K, C>,
+                Option<Neighborhood<'_, 'e, K, C>>,
+            ) -> Result<(), Error>
+            + 'e
+            + Send
+            + Sync,
+    {
+        self.on_react = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the closure called by `Drawable::draw`.
+    #[cfg(not(feature = "parallel"))]
+    pub fn on_draw<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&QuickEntity<'e, K, C>, &mut C, Transform) -> Result<(), Error>
+            + 'e,
+    {
+        self.on_draw = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the closure called by `Drawable::draw`.
+    #[cfg(feature = "parallel")]
+    pub fn on_draw<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&QuickEntity<'e, K, C>, &mut C, Transform) -> Result<(), Error>
+            + 'e
+            + Send
+            + Sync,
+    {
+        self.on_draw = Some(Box::new(f));
+        self
+    }
+
+    /// Sets the Location of the Entity, usually called from within its
+    /// `on_react` closure.
+    ///
+    /// It is a logic error to set the location of an Entity that was inserted
+    /// into the Environment without a location.
+    pub fn set_location(&mut self, location: impl Into<Location>) {
+        self.location = Some(location.into());
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+impl<'e, K: Clone + 'e, C: 'e> QuickEntity<'e, K, C> {
+    /// Consumes the QuickEntity and returns it as a boxed Entity trait object.
+    pub fn boxed(self) -> Box<EntityTrait<'e, K, C>> {
+        Box::new(self)
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<'e, K: Clone + Send + Sync + 'e, C: 'e> QuickEntity<'e, K, C> {
+    /// Consumes the QuickEntity and returns it as a boxed Entity trait object.
+    pub fn boxed(self) -> Box<EntityTrait<'e, K, C>> {
+        Box::new(self)
+    }
+}
+
+impl<'e, K: fmt::Debug, C> fmt::Debug for QuickEntity<'e, K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuickEntity")
+            .field("id", &self.id)
+            .field("kind", &self.kind)
+            .field("location", &self.location)
+            .field("scope", &self.scope)
+            .field("lifespan", &self.lifespan)
+            .finish()
+    }
+}
+
+impl<'e, K: Clone, C> Entity<'e, C> for QuickEntity<'e, K, C> {
+    type Kind = K;
+
+    fn id(&self) -> Id {
+        self.id.unwrap_or_default()
+    }
+
+    fn assign_id(&mut self, id: Id) -> bool {
+        let assigned = self.id.is_none();
+        if assigned {
+            self.id = Some(id);
+        }
+        assigned
+    }
+
+    fn kind(&self) -> Self::Kind {
+        self.kind.clone()
+    }
+
+    fn location(&self) -> Option<Location> {
+        self.location
+    }
+
+    fn location_mut(&mut self) -> Option<&mut Location> {
+        self.location.as_mut()
+    }
+
+    fn scope(&self) -> Option<Scope> {
+        self.scope
+    }
+
+    fn wraps(&self) -> bool {
+        self.wraps
+    }
+
+    fn perception(&self) -> Perception {
+        self.perception
+    }
+
+    fn z_order(&self) -> i32 {
+        self.z_order
+    }
+
+    fn lifespan(&self) -> Option<Lifespan> {
+        self.lifespan
+    }
+
+    fn lifespan_mut(&mut self) -> Option<&mut Lifespan> {
+        self.lifespan.as_mut()
+    }
+
+    fn observe(
+        &mut self,
+        neighborhood: Option<Neighborhood<'_, 'e, K, C>>,
+    ) -> Result<(), Error> {
+        // the closure is taken out of self for the duration of the call, so
+        // that it can be given a mutable reference to the Entity
+        if let Some(mut f) = self.on_observe.take() {
+            let result = f(self, neighborhood);
+            self.on_observe = Some(f);
+            result
+        } else {
+            Ok(())
+        }
+    }
+
+    fn react(
+        &mut self,
+        neighborhood: Option<Neighborhood<'_, 'e, K, C>>,
+    ) -> Result<(), Error> {
+        if let Some(mut f) = self.on_react.take() {
+            let result = f(self, neighborhood);
+            self.on_react = Some(f);
+            result
+        } else {
+            Ok(())
+        }
+    }
+
+    fn drawable(&self) -> Option<&dyn Drawable<Context = C>> {
+        self.on_draw
+            .as_ref()
+            .map(|_| self as &dyn Drawable<Context = C>)
+    }
+}
+
+impl<'e, K, C> Drawable for QuickEntity<'e, K, C> {
+    type Context = C;
+
+    fn draw(&self, ctx: &mut C, transform: Transform) -> Result<(), Error> {
+        match &self.on_draw {
+            Some(f) => f(self, ctx, transform),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,551 @@
+//! This module contains the scriptable entities, whose behavior is defined by
+//! Rhai scripts, so that new rules can be prototyped without recompiling.
+//!
+//! The scripts of each Kind are compiled once by the ScriptEngine, and they can
+//! define any of the following functions, called by the ScriptEntity with the
+//! persistent variables of the Entity bound to `this`:
+//! - `observe(sight)` and `react(sight)`, called when the Entity observes and
+//!   reacts to its Neighborhood;
+//! - `draw()`, called when the Entity is drawn, whose result is given to the
+//!   painter of the ScriptEngine.
+//!
+//! The `sight` given to the scripts exposes a safe API to query the Neighborhood
+//! and to act on the Environment:
+//! - `sight.x`, `sight.y` and `sight.generation`;
+//! - `sight.count()`, `sight.count(kind)` and `sight.count_at(dx, dy, kind)`,
+//!   to count the entities seen (excluding the Entity itself);
+//! - `sight.move_by(dx, dy)`, to move to the given offset;
+//! - `sight.spawn_at(kind, dx, dy)`, to generate an offspring at the given
+//!   offset;
+//! - `sight.die()`, to end the lifespan of the Entity.
+
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use rhai::{CallFnOptions, Dynamic, ImmutableString, Map, AST};
+
+use super::*;
+
+/// The type of the closure used to draw the scriptable entities.
+#[cfg(not(feature = "parallel"))]
+type Painter<C> = Box<dyn Fn(&Dynamic, &mut C, Transform) -> Result<(), Error>>;
+
+/// The type of the closure used to draw the scriptable entities.
+#[cfg(feature = "parallel")]
+type Painter<C> =
+    Box<dyn Fn(&Dynamic, &mut C, Transform) -> Result<(), Error> + Send + Sync>;
+
+/// The compiled script of a Kind.
+#[derive(Debug)]
+struct ScriptKind<K> {
+    name: ImmutableString,
+    kind: K,
+    ast: AST,
+    // whether the script defines the observe, react and draw functions
+    observe: bool,
+    react: bool,
+    draw: bool,
+}
+
+/// The engine that compiles and runs the scripts of the scriptable entities,
+/// shared by all the ScriptEntity instances.
+///
+/// # Example
+/// ```
+/// use std::sync::Arc;
+/// use semeion::*;
+///
+/// let mut engine = ScriptEngine::<u8>::new();
+/// engine
+///     .register(
+///         "walker",
+///         0,
+///         r#"
+///             fn react(sight) {
+///                 this.steps += 1;
+///                 sight.move_by(1, 0);
+///                 if this.steps == 3 {
+///                     sight.spawn_at("walker", 0, 1);
+///                 }
+///             }
+///         "#,
+///     )
+///     .unwrap();
+/// let engine = Arc::new(engine);
+///
+/// let mut walker = ScriptEntity::new(&engine, "walker").unwrap();
+/// walker.set_var("steps", 0_i64.into());
+///
+/// let mut env = Environment::new((10, 10));
+/// env.insert(walker.at((0, 0)).scope(1));
+/// for _ in 0..3 {
+///     env.nextgen().unwrap();
+/// }
+/// assert_eq!(env.entities_at((3, 0)).count(), 1);
+/// assert_eq!(env.entities_at((3, 1)).count(), 1);
+/// ```
+pub struct ScriptEngine<K, C = ()> {
+    engine: rhai::Engine,
+    kinds: Vec<ScriptKind<K>>,
+    painter: Option<Painter<C>>,
+}
+
+impl<K: fmt::Debug, C> fmt::Debug for ScriptEngine<K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScriptEngine")
+            .field("kinds", &self.kinds)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, C> Default for ScriptEngine<K, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, C> ScriptEngine<K, C> {
+    /// Constructs a new ScriptEngine with no scripts.
+    pub fn new() -> Self {
+        let mut engine = rhai::Engine::new();
+        engine
+            .register_type_with_name::<Sight>("Sight")
+            .register_get("x", |s: &mut Sight| s.location.x as i64)
+            .register_get("y", |s: &mut Sight| s.location.y as i64)
+            .register_get("generation", |s: &mut Sight| s.generation as i64)
+            .register_fn("count", |s: &mut Sight| s.seen.len() as i64)
+            .register_fn("count", |s: &mut Sight, kind: ImmutableString| {
+                s.seen.iter().filter(|(_, name)| *name == kind).count() as i64
+            })
+            .register_fn(
+                "count_at",
+                |s: &mut Sight, dx: i64, dy: i64, kind: ImmutableString| {
+                    let offset = Offset {
+                        x: dx as i32,
+                        y: dy as i32,
+                    };
+                    s.seen
+                        .iter()
+                        .filter(|(o, name)| *o == offset && *name == kind)
+                        .count() as i64
+                },
+            )
+            .register_fn("move_by", |s: &mut Sight, dx: i64, dy: i64| {
+                s.actions().movement = Some(Offset {
+                    x: dx as i32,
+                    y: dy as i32,
+                });
+            })
+            .register_fn(
+                "spawn_at",
+                |s: &mut Sight, kind: ImmutableString, dx: i64, dy: i64| {
+                    let offset = Offset {
+                        x: dx as i32,
+                        y: dy as i32,
+                    };
+                    s.actions().spawns.push((kind.to_string(), offset));
+                },
+            )
+            .register_fn("die", |s: &mut Sight| s.actions().die = true);
+
+        Self {
+            engine,
+            kinds: Vec::new(),
+            painter: None,
+        }
+    }
+
+    /// Compiles the given script for the entities of the given Kind, that will
+    /// be identified by the given name within the scripts, replacing any script
+    /// previously registered with the same name.
+    ///
+    /// Returns an error if the script cannot be compiled.
+    pub fn register(
+        &mut self,
+        name: &str,
+        kind: K,
+        script: &str,
+    ) -> Result<(), Error> {
+        let ast = self.engine.compile(script).map_err(Error::with_message)?;
+        let defines = |f: &str| ast.iter_functions().any(|m| m.name == f);
+        let script = ScriptKind {
+            name: name.into(),
+            kind,
+            observe: defines("observe"),
+            react: defines("react"),
+            draw: defines("draw"),
+            ast,
+        };
+        match self.kinds.iter_mut().find(|k| k.name == name) {
+            Some(existing) => *existing = script,
+            None => self.kinds.push(script),
+        }
+        Ok(())
+    }
+
+    /// Sets the closure used to draw the scriptable entities, given the result
+    /// of the `draw()` function of their script.
+    #[cfg(not(feature = "parallel"))]
+    pub fn set_painter(
+        &mut self,
+        painter: impl Fn(&Dynamic, &mut C, Transform) -> Result<(), Error> + 'static,
+    ) {
+        self.painter = Some(Box::new(painter));
+    }
+
+    /// Sets the closure used to draw the scriptable entities, given the result
+    /// of the `draw()` function of their script.
+    #[cfg(feature = "parallel")]
+    pub fn set_painter(
+        &mut self,
+        painter: impl Fn(&Dynamic, &mut C, Transform) -> Result<(), Error>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.painter = Some(Box::new(painter));
+    }
+
+    /// Gets the index of the script with the given name.
+    fn index_of(&self, name: &str) -> Result<usize, Error> {
+        self.kinds
+            .iter()
+            .position(|k| k.name == name)
+            .ok_or_else(|| {
+                Error::with_message(format!("Unknown script kind {:?}", name))
+            })
+    }
+}
+
+/// The actions taken by a script during a single call.
+#[derive(Debug, Default)]
+struct Actions {
+    movement: Option<Offset>,
+    spawns: Vec<(String, Offset)>,
+    die: bool,
+}
+
+/// The view of the Neighborhood given to the scripts, through which they can
+/// also act on the Environment.
+#[derive(Debug, Clone)]
+struct Sight {
+    location: Location,
+    generation: u64,
+    // the entities seen, as their offset from the center and the name of their
+    // Kind, excluding the entities whose Kind has no script
+    seen: Vec<(Offset, ImmutableString)>,
+    actions: Arc<Mutex<Actions>>,
+}
+
+impl Sight {
+    /// Gets the actions taken by the script.
+    fn actions(&self) -> MutexGuard<'_, Actions> {
+        lock(&self.actions)
+    }
+}
+
+/// Locks the given actions, ignoring whether the lock is poisoned.
+fn lock(actions: &Mutex<Actions>) -> MutexGuard<'_, Actions> {
+    actions.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// An Entity whose behavior is defined by a script compiled by a ScriptEngine.
+///
+/// Each ScriptEntity has its own set of persistent variables, bound to `this`
+/// within its script, that can be read and written from Rust with
+/// `ScriptEntity::var` and `ScriptEntity::set_var`.
+pub struct ScriptEntity<K, C = ()> {
+    // the ID, if set explicitly or assigned by the Environment
+    id: Option<Id>,
+    engine: Arc<ScriptEngine<K, C>>,
+    // the index of the script of the Entity within the engine
+    script: usize,
+    location: Option<Location>,
+    scope: Option<Scope>,
+    lifespan: Option<Lifespan>,
+    // the persistent variables of the Entity (a Map)
+    this: Dynamic,
+    intent: Option<MoveIntent>,
+    spawns: Vec<(usize, Offset)>,
+    _context: PhantomData<fn(&mut C)>,
+}
+
+impl<K: fmt::Debug, C> fmt::Debug for ScriptEntity<K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScriptEntity")
+            .field("id", &self.id)
+            .field("kind", &self.engine.kinds[self.script].kind)
+            .field("location", &self.location)
+            .field("scope", &self.scope)
+            .field("lifespan", &self.lifespan)
+            .field("this", &self.this)
+            .finish()
+    }
+}
+
+impl<K, C> ScriptEntity<K, C> {
+    /// Constructs a new ScriptEntity whose behavior is defined by the script
+    /// registered with the given name, with no location, scope or lifespan,
+    /// and with no variables.
+    ///
+    /// Unless set with `ScriptEntity::id`, the ID of the Entity is assigned by
+    /// the Environment it is inserted into (see `Entity::assign_id`), and it
+    /// is 0 until then.
+    ///
+    /// Returns an error if no script was registered with the given name.
+    pub fn new(
+        engine: &Arc<ScriptEngine<K, C>>,
+        name: &str,
+    ) -> Result<Self, Error> {
+        let script = engine.index_of(name)?;
+        Ok(Self::with_script(Arc::clone(engine), script))
+    }
+
+    /// Sets the ID of the Entity.
+    pub fn id(mut self, id: Id) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Sets the Location of the Entity.
+    pub fn at(mut self, location: impl Into<Location>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+
+    /// Sets the Scope of the Entity.
+    pub fn scope(mut self, scope: impl Into<Scope>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// Sets an Ephemeral Lifespan of the given span for the Entity.
+    pub fn lifespan(mut self, span: impl Into<Span>) -> Self {
+        self.lifespan = Some(Lifespan::with_span(span));
+        self
+    }
+
+    /// Gets the value of the variable of the Entity with the given name.
+    pub fn var(&self, name: &str) -> Option<Dynamic> {
+        self.this.read_lock::<Map>()?.get(name).cloned()
+    }
+
+    /// Sets the value of the variable of the Entity with the given name.
+    pub fn set_var(&mut self, name: &str, value: Dynamic) {
+        if let Some(mut this) = self.this.write_lock::<Map>() {
+            this.insert(name.into(), value);
+        }
+    }
+
+    /// Constructs a new ScriptEntity with the script at the given index.
+    fn with_script(engine: Arc<ScriptEngine<K, C>>, script: usize) -> Self {
+        Self {
+            id: None,
+            engine,
+            script,
+            location: None,
+            scope: None,
+            lifespan: None,
+            this: Map::new().into(),
+            intent: None,
+            spawns: Vec::new(),
+            _context: PhantomData,
+        }
+    }
+}
+
+impl<K: PartialEq, C> ScriptEntity<K, C> {
+    /// Calls the function of the script with the given name, if defined,
+    /// and applies the actions it takes.
+    fn call<'e>(
+        &mut self,
+        function: &str,
+        neighborhood: Option<Neighborhood<'_, 'e, K, C>>,
+        generation: u64,
+    ) -> Result<(), Error> {
+        let engine = Arc::clone(&self.engine);
+        let script = &engine.kinds[self.script];
+
+        let seen = neighborhood.map_or_else(Vec::new, |neighborhood| {
+            let dimension = neighborhood.dimension();
+            let center = neighborhood.center_position();
+            neighborhood
+                .tiles()
+                .enumerate()
+                .flat_map(|(index, tile)| {
+                    let position =
+                        Location::from_one_dimensional(index, dimension);
+                    let offset = Offset {
+                        x: position.x - center.x,
+                        y: position.y - center.y,
+                    };
+                    tile.entities().map(move |e| (offset, e))
+                })
+                .filter_map(|(offset, entity)| {
+                    let kind = entity.kind();
+                    let script =
+                        engine.kinds.iter().find(|k| k.kind == kind)?;
+                    Some((offset, script.name.clone()))
+                })
+                .collect()
+        });
+        let actions = Arc::default();
+        let sight = Sight {
+            location: self.location.unwrap_or_default(),
+            generation,
+            seen,
+            actions: Arc::clone(&actions),
+        };
+
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.this);
+        let _: Dynamic = engine
+            .engine
+            .call_fn_with_options(
+                options,
+                &mut rhai::Scope::new(),
+                &script.ast,
+                function,
+                (sight,),
+            )
+            .map_err(Error::with_message)?;
+
+        let actions = std::mem::take(&mut *lock(&actions));
+        if let (Some(offset), Some(location)) =
+            (actions.movement, self.location)
+        {
+            let destination = Location {
+                x: location.x + offset.x,
+                y: location.y + offset.y,
+            };
+            self.intent = Some(MoveIntent::new(destination));
+        }
+        for (name, offset) in actions.spawns {
+            self.spawns.push((engine.index_of(&name)?, offset));
+        }
+        if actions.die {
+            self.lifespan = Some(Lifespan::with_span(0));
+        }
+        Ok(())
+    }
+}
+
+impl<'e, K, C> Entity<'e, C> for ScriptEntity<K, C>
+where
+    K: Clone + Ord + Send + Sync + 'e,
+    C: 'e,
+{
+    type Kind = K;
+
+    fn id(&self) -> Id {
+        self.id.unwrap_or_default()
+    }
+
+    fn assign_id(&mut self, id: Id) -> bool {
+        let assigned = self.id.is_none();
+        if assigned {
+            self.id = Some(id);
+        }
+        assigned
+    }
+
+    fn kind(&self) -> Self::Kind {
+        self.engine.kinds[self.script].kind.clone()
+    }
+
+    fn location(&self) -> Option<Location> {
+        self.location
+    }
+
+    fn location_mut(&mut self) -> Option<&mut Location> {
+        self.location.as_mut()
+    }
+
+    fn scope(&self) -> Option<Scope> {
+        self.scope
+    }
+
+    fn lifespan(&self) -> Option<Lifespan> {
+        self.lifespan
+    }
+
+    fn lifespan_mut(&mut self) -> Option<&mut Lifespan> {
+        self.lifespan.as_mut()
+    }
+
+    fn observe_with(
+        &mut self,
+        neighborhood: Option<Neighborhood<'_, 'e, K, C>>,
+        world: &WorldView<'_, 'e, K, C>,
+    ) -> Result<(), Error> {
+        if self.engine.kinds[self.script].observe {
+            self.call("observe", neighborhood, world.generation().get())?;
+        }
+        Ok(())
+    }
+
+    fn react_with(
+        &mut self,
+        neighborhood: Option<Neighborhood<'_, 'e, K, C>>,
+        world: &WorldView<'_, 'e, K, C>,
+    ) -> Result<(), Error> {
+        if self.engine.kinds[self.script].react {
+            self.call("react", neighborhood, world.generation().get())?;
+        }
+        Ok(())
+    }
+
+    fn intent(&mut self) -> Option<MoveIntent> {
+        self.intent.take()
+    }
+
+    fn offspring(&mut self) -> Option<Offspring<'e, K, C>> {
+        if self.spawns.is_empty() {
+            return None;
+        }
+        let mut offspring = Offspring::with_capacity(self.spawns.len());
+        for (script, offset) in std::mem::take(&mut self.spawns) {
+            let mut child = Self::with_script(Arc::clone(&self.engine), script);
+            child.location = self.location;
+            child.scope = self.scope;
+            offspring.insert_at(child, offset);
+        }
+        Some(offspring)
+    }
+
+    fn drawable(&self) -> Option<&dyn Drawable<Context = C>> {
+        let draw = self.engine.kinds[self.script].draw;
+        (draw && self.engine.painter.is_some())
+            .then_some(self as &dyn Drawable<Context = C>)
+    }
+}
+
+impl<K, C> Drawable for ScriptEntity<K, C> {
+    type Context = C;
+
+    fn draw(&self, ctx: &mut C, transform: Transform) -> Result<(), Error> {
+        let Some(painter) = &self.engine.painter else {
+            return Ok(());
+        };
+        let script = &self.engine.kinds[self.script];
+        // the variables are cloned, since the script is given a mutable
+        // reference to them while the Entity is only borrowed immutably
+        let mut this = self.this.clone();
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut this);
+        let value = self
+            .engine
+            .engine
+            .call_fn_with_options::<Dynamic>(
+                options,
+                &mut rhai::Scope::new(),
+                &script.ast,
+                "draw",
+                (),
+            )
+            .map_err(Error::with_message)?;
+        painter(&value, ctx, transform)
+    }
+}
@@ -1,4 +1,7 @@
 use std::any::Any;
+use std::fmt::Debug;
+
+use super::AnyValue;
 
 /// The trait that is implemented by the object that represents the State of an
 /// Entity. It exposes methods that enable dynamic typing of any `'static` type
@@ -14,4 +17,37 @@ pub trait State {
     /// Gets a mutable reference to self via the Any trait, used to emulate dynamic
     /// typing and downcast this trait to its concrete type.
     fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Gets a reference to self via the Debug trait, if the State can be
+    /// formatted for debugging purposes (for example by
+    /// `Environment::debug_dump`).
+    ///
+    /// By default it returns None, but states that implement Debug should
+    /// simply return `Some(self)`.
+    fn as_debug(&self) -> Option<&dyn Debug> {
+        None
+    }
+
+    /// Replaces self with the given value, used to apply the
+    /// `NeighborCommand::SetStateAny` commands. Returns true only if the State
+    /// was replaced.
+    ///
+    /// By default the State cannot be replaced, but states that can be
+    /// replaced by values of their own type should simply return
+    /// `replace_any(self, value)`.
+    fn set_any(&mut self, _value: AnyValue) -> bool {
+        false
+    }
+}
+
+/// Replaces the given State with the given value, if the value has the same
+/// concrete type of the State. Returns true only if the State was replaced.
+pub fn replace_any<T: Any>(state: &mut T, value: AnyValue) -> bool {
+    match value.downcast::<T>() {
+        Ok(value) => {
+            *state = *value;
+            true
+        }
+        Err(_) => false,
+    }
 }
@@ -0,0 +1,75 @@
+use std::cell::RefCell;
+
+/// The minimum number of items that can be stored in a single chunk.
+const MIN_CHUNK_CAPACITY: usize = 64;
+
+/// A typed bump allocator, used to allocate the tiles of all the neighborhoods
+/// seen by the entities during a single generation.
+///
+/// The items are stored in a list of chunks, where each chunk is a vector that
+/// is never reallocated once created, so that the slices handed out by the
+/// Arena remain valid for as long as the Arena itself. All the items are
+/// released at once when the Arena is dropped.
+#[derive(Debug)]
+pub(crate) struct Arena<T> {
+    chunks: RefCell<Vec<Vec<T>>>,
+}
+
+impl<T> Default for Arena<T> {
+    /// Constructs a new empty Arena.
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+impl<T> Arena<T> {
+    /// Constructs a new Arena that can store the given number of items before
+    /// allocating a new chunk.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let chunk = Vec::with_capacity(capacity.max(MIN_CHUNK_CAPACITY));
+        Self {
+            chunks: RefCell::new(vec![chunk]),
+        }
+    }
+
+    /// Gets the total number of items allocated in this Arena.
+    pub fn len(&self) -> usize {
+        self.chunks.borrow().iter().map(Vec::len).sum()
+    }
+
+    /// Allocates all the items of the given iterator into a contiguous slice.
+    // each call returns a mutable slice of distinct items owned by the Arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_extend(
+        &self,
+        items: impl ExactSizeIterator<Item = T>,
+    ) -> &mut [T] {
+        let len = items.len();
+        let mut chunks = self.chunks.borrow_mut();
+        let chunk = chunks.last().expect("Arena without chunks");
+        if chunk.capacity() - chunk.len() < len {
+            let capacity = len.max(chunk.capacity().saturating_mul(2));
+            chunks.push(Vec::with_capacity(capacity));
+        }
+
+        let chunk = chunks.last_mut().expect("Arena without chunks");
+        let start = chunk.len();
+        let capacity = chunk.capacity();
+        // the chunk has enough spare capacity to store all the items, and it
+        // will never be reallocated as long as at most `len` items are pushed
+        for item in items.take(len) {
+            chunk.push(item);
+        }
+        debug_assert_eq!(capacity, chunk.capacity());
+
+        let len = chunk.len() - start;
+        // Creating a slice that outlives the borrow of the chunks is safe since
+        // the items of a chunk are never moved (the chunk is never reallocated),
+        // and the chunk is never dropped before the Arena itself. Moreover, each
+        // slice covers a range of items that does not overlap with any other
+        // slice handed out by this Arena.
+        unsafe {
+            std::slice::from_raw_parts_mut(chunk.as_mut_ptr().add(start), len)
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use super::*;
+
+impl<'e, K: Ord + Clone, C> Environment<'e, K, C> {
+    /// Clones the Environment together with all its entities, so that the
+    /// simulation can be branched: the clone can be moved forwards by any
+    /// number of generations (such as to evaluate the outcome of a decision)
+    /// and then discarded, without affecting the original Environment.
+    ///
+    /// All the entities must be cloneable (see `Entity::cloneable`), and they
+    /// are inserted in the clone in the same order. The clone shares the same
+    /// generation, statistics, rules, phases, kinds settings, time scale,
+    /// seeds and subscriptions to the notifications of the Environment, but
+    /// since their type is erased it does not include the components and
+    /// columns of the entities, the global resources, the data attached to the
+    /// tiles, the move resolver, the offspring policies, the scheduled
+    /// commands, the pending inputs and notifications, the subscribers to the
+    /// changes of the occupancy, the history, nor the chunk streaming (and
+    /// therefore the evicted chunks).
+    ///
+    /// Returns an error if any of the entities cannot be cloned.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// #[derive(Clone)]
+    /// struct Walker(Id, Location);
+    ///
+    /// impl<'e> Entity<'e> for Walker {
+    ///     type Kind = ();
+    ///
+    ///     fn id(&self) -> Id {
+    ///         self.0
+    ///     }
+    ///
+    ///     fn kind(&self) {}
+    ///
+    ///     fn location(&self) -> Option<Location> {
+    ///         Some(self.1)
+    ///     }
+    ///
+    ///     fn location_mut(&mut self) -> Option<&mut Location> {
+    ///         Some(&mut self.1)
+    ///     }
+    ///
+    ///     fn react(
+    ///         &mut self,
+    ///         _: Option<Neighborhood<'_, 'e, ()>>,
+    ///     ) -> Result<(), Error> {
+    ///         self.1.translate(Offset { x: 1, y: 0 }, Dimension { x: 5, y: 5 });
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn cloneable(&self) -> Option<&dyn CloneEntity<'e, (), ()>> {
+    ///         Some(self)
+    ///     }
+    /// }
+    ///
+    /// let mut env = Environment::new((5, 5));
+    /// env.insert(Walker(0, Location { x: 0, y: 0 }));
+    ///
+    /// // simulate 3 generations ahead on a branch of the world
+    /// let mut branch = env.try_clone().unwrap();
+    /// for _ in 0..3 {
+    ///     branch.nextgen().unwrap();
+    /// }
+    /// assert_eq!(branch.entities_at((3, 0)).count(), 1);
+    /// assert_eq!(branch.generation().get(), 3);
+    ///
+    /// // while the original world is unaffected
+    /// assert_eq!(env.entities_at((0, 0)).count(), 1);
+    /// assert_eq!(env.generation().get(), 0);
+    /// ```
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        let mut env =
+            Self::with_tile_order(self.dimension(), self.tile_order());
+        env.generation = self.generation;
+        for entity in self.entities() {
+            let entity = entity.cloneable().ok_or_else(|| {
+                Error::with_message(format!(
+                    "Entity {} cannot be cloned",
+                    entity.id()
+                ))
+            })?;
+            env.insert_boxed(entity.clone_boxed());
+        }
+
+        env.arena_capacity = self.arena_capacity;
+        env.kinds = self.kinds.clone();
+        env.layers = self.layers.clone();
+        env.passive = self.passive.clone();
+        env.active_region = self.active_region;
+        env.ticks = self.ticks.clone();
+        env.grid = self.grid;
+        env.expiring = self.expiring.clone();
+        env.births = self.births.clone();
+        env.stats = self.stats.clone();
+        env.previous_locations = self.previous_locations.clone();
+        env.phases = self.phases.clone();
+        env.digests = self.digests.clone();
+        env.limits = self.limits.clone();
+        env.clock = self.clock;
+        env.genome_rng = self.genome_rng.clone();
+        env.set_perception_seed(self.perception_seed);
+        env.render_values = self.render_values.clone();
+        env.rules = self.rules.clone();
+        env.subscriptions = self.subscriptions.clone();
+        env.next_id = self.next_id;
+        Ok(env)
+    }
+}
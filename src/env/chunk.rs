@@ -0,0 +1,485 @@
+//! This module contains the streaming of the chunks of the Environment to
+//! disk, for very long runs where most of the entities are not needed.
+//!
+//! Each evicted chunk is stored in its own file, that starts with the magic
+//! bytes of the format, followed by the number of its entities and by each of
+//! them, made of its encoded Kind, its encoded blob, and its generation of
+//! birth.
+
+use std::fs;
+use std::path::PathBuf;
+
+use super::*;
+use snapshot::{push_blob, push_len, read_blob, read_u32, read_u64};
+
+/// The magic bytes that identify the file of an evicted chunk.
+const CHUNK_MAGIC: [u8; 4] = *b"SMNK";
+
+/// The position of a chunk in the grid of chunks of the Environment.
+type ChunkKey = (i32, i32);
+
+/// The streaming of the chunks of the Environment to disk.
+pub(super) struct Streaming<'e, K, C> {
+    codec: HistoryCodec<'e, K, C>,
+    directory: PathBuf,
+    // the side of each (square) chunk, in tiles
+    side: i32,
+    // the maximum number of chunks with entities kept in memory
+    max_resident: usize,
+    // the generation each resident chunk was last needed by an active Entity
+    touched: BTreeMap<ChunkKey, Generation>,
+    evicted: BTreeSet<ChunkKey>,
+    // the IDs of the evicted entities, reserved until they are reloaded
+    evicted_ids: BTreeSet<Id>,
+}
+
+impl<'e, K, C> fmt::Debug for Streaming<'e, K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Streaming")
+            .field("directory", &self.directory)
+            .field("side", &self.side)
+            .field("max_resident", &self.max_resident)
+            .field("evicted", &self.evicted)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'e, K, C> Streaming<'e, K, C> {
+    /// Gets the path of the file of the given chunk.
+    fn path(&self, key: ChunkKey) -> PathBuf {
+        self.directory.join(format!("{}_{}.chunk", key.0, key.1))
+    }
+
+    /// Gets the chunk that contains the given location.
+    fn key(&self, location: Location) -> ChunkKey {
+        (location.x / self.side, location.y / self.side)
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Enables the streaming of the chunks of the Environment to disk, so that
+    /// very long runs (such as a Langton's ant that wanders for weeks) do not
+    /// exhaust the memory.
+    ///
+    /// The Environment is divided in square chunks with the given side, and
+    /// at the end of each generation the chunks that are not needed by any of
+    /// the active entities (that is, the chunks that neither contain nor are
+    /// adjacent to an Entity whose Kind is not passive) are evicted to the
+    /// given directory, starting from the least recently needed ones, until
+    /// at most the given number of chunks with entities are kept in memory.
+    /// The entities of an evicted chunk are encoded in its own file with the
+    /// given Codec, and they are transparently reloaded as soon as an active
+    /// Entity wanders back into the chunk or next to it. The neighborhoods of
+    /// the active entities are therefore always complete, as long as their
+    /// Scope does not exceed the side of the chunks.
+    ///
+    /// While evicted, the entities are not part of the Environment (they are
+    /// not counted, observed, drawn or saved), and their removal and reload
+    /// are not recorded as deaths and births, while their IDs are never
+    /// assigned to other entities (see `Entity::assign_id`). Their components
+    /// and columns
+    /// are not stored, since their type is erased. The entities without a
+    /// location are never evicted.
+    ///
+    /// An Entity cannot be inserted into an evicted chunk (see
+    /// `Environment::insert` and `Environment::apply`), while the entities
+    /// that end up into an evicted chunk in any other way (such as the
+    /// offspring, or the entities relocated there) are kept in memory, and
+    /// stored together with the evicted ones once the chunk is evicted again.
+    ///
+    /// Returns an error if the directory cannot be created, or if the
+    /// streaming is already enabled.
+    ///
+    /// # Panics
+    /// Panics if the side of the chunks is not positive.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// struct CellCodec;
+    ///
+    /// impl<'e> Codec<'e, char> for CellCodec {
+    ///     fn encode_kind(&self, kind: &char) -> Result<Vec<u8>, Error> {
+    ///         Ok(vec![*kind as u8])
+    ///     }
+    ///
+    ///     fn decode_kind(&self, bytes: &[u8]) -> Result<char, Error> {
+    ///         bytes.first().map(|&b| b as char).ok_or(Error::Unknown)
+    ///     }
+    ///
+    ///     fn encode(
+    ///         &self,
+    ///         entity: &EntityTrait<'e, char>,
+    ///     ) -> Result<Vec<u8>, Error> {
+    ///         let location = entity.location().ok_or(Error::Unknown)?;
+    ///         Ok(vec![entity.id() as u8, location.x as u8, location.y as u8])
+    ///     }
+    ///
+    ///     fn decode(
+    ///         &self,
+    ///         kind: &char,
+    ///         blob: &[u8],
+    ///     ) -> Result<Box<EntityTrait<'e, char>>, Error> {
+    ///         let &[id, x, y] = blob else {
+    ///             return Err(Error::Unknown);
+    ///         };
+    ///         let entity = QuickEntity::new(*kind).id(id as Id);
+    ///         Ok(entity.at((x as i32, y as i32)).boxed())
+    ///     }
+    /// }
+    ///
+    /// let directory = std::env::temp_dir().join("semeion-chunks-example");
+    /// let mut env = Environment::<char>::new((32, 32));
+    /// env.enable_chunk_streaming(&directory, 8, 0, CellCodec).unwrap();
+    /// env.set_passive('c', true);
+    /// env.insert(QuickEntity::new('a').id(0).at((1, 1)));
+    /// env.insert(QuickEntity::new('c').id(1).at((12, 12)));
+    /// env.insert(QuickEntity::new('c').id(2).at((20, 20)));
+    ///
+    /// // the chunk of the farthest cell is evicted
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.count(), 2);
+    /// assert_eq!(env.evicted_chunks(), 1);
+    ///
+    /// // the IDs of the evicted entities are not assigned to other entities
+    /// env.insert(QuickEntity::new('c').at((2, 2)));
+    /// assert_eq!(env.entities_at((2, 2)).next().map(|e| e.id()), Some(3));
+    ///
+    /// // no Entity can be inserted into it, but the other cell can join it
+    /// let cell = QuickEntity::new('c').id(3).at((21, 21));
+    /// assert!(env.apply(|editor| editor.insert(cell)).is_err());
+    /// assert!(env.relocate(1, (21, 21)));
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.count(), 2);
+    /// assert_eq!(env.evicted_chunks(), 1);
+    ///
+    /// // and reloaded once the active entity wanders next to it
+    /// assert!(env.relocate(0, (20, 14)));
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.count(), 3);
+    /// assert_eq!(env.entities_at((20, 20)).count(), 1);
+    ///
+    /// env.disable_chunk_streaming().unwrap();
+    /// let mut ids: Vec<_> = env.entities().map(|e| e.id()).collect();
+    /// ids.sort_unstable();
+    /// assert_eq!(ids, vec![0, 1, 2, 3]);
+    /// std::fs::remove_dir_all(&directory).unwrap();
+    /// ```
+    #[cfg(not(feature = "parallel"))]
+    pub fn enable_chunk_streaming(
+        &mut self,
+        directory: impl Into<PathBuf>,
+        side: i32,
+        max_resident: usize,
+        codec: impl Codec<'e, K, C> + 'e,
+    ) -> Result<(), Error> {
+        self.start_streaming(
+            directory.into(),
+            side,
+            max_resident,
+            Box::new(codec),
+        )
+    }
+
+    /// Enables the streaming of the chunks of the Environment to disk, so that
+    /// very long runs (such as a Langton's ant that wanders for weeks) do not
+    /// exhaust the memory.
+    ///
+    /// The Environment is divided in square chunks with the given side, and
+    /// at the end of each generation the chunks that are not needed by any of
+    /// the active entities (that is, the chunks that neither contain nor are
+    /// adjacent to an Entity whose Kind is not passive) are evicted to the
+    /// given directory, starting from the least recently needed ones, until
+    /// at most the given number of chunks with entities are kept in memory.
+    /// The entities of an evicted chunk are encoded in its own file with the
+    /// given Codec, and they are transparently reloaded as soon as an active
+    /// Entity wanders back into the chunk or next to it. The neighborhoods of
+    /// the active entities are therefore always complete, as long as their
+    /// Scope does not exceed the side of the chunks.
+    ///
+    /// While evicted, the entities are not part of the Environment (they are
+    /// not counted, observed, drawn or saved), and their removal and reload
+    /// are not recorded as deaths and births, while their IDs are never
+    /// assigned to other entities (see `Entity::assign_id`). Their components
+    /// and columns
+    /// are not stored, since their type is erased. The entities without a
+    /// location are never evicted.
+    ///
+    /// An Entity cannot be inserted into an evicted chunk (see
+    /// `Environment::insert` and `Environment::apply`), while the entities
+    /// that end up into an evicted chunk in any other way (such as the
+    /// offspring, or the entities relocated there) are kept in memory, and
+    /// stored together with the evicted ones once the chunk is evicted again.
+    ///
+    /// Returns an error if the directory cannot be created, or if the
+    /// streaming is already enabled.
+    ///
+    /// # Panics
+    /// Panics if the side of the chunks is not positive.
+    #[cfg(feature = "parallel")]
+    pub fn enable_chunk_streaming(
+        &mut self,
+        directory: impl Into<PathBuf>,
+        side: i32,
+        max_resident: usize,
+        codec: impl Codec<'e, K, C> + Send + Sync + 'e,
+    ) -> Result<(), Error> {
+        self.start_streaming(
+            directory.into(),
+            side,
+            max_resident,
+            Box::new(codec),
+        )
+    }
+
+    /// Disables the streaming of the chunks of the Environment to disk, after
+    /// reloading all the evicted chunks.
+    ///
+    /// Returns an error if the streaming is not enabled, or if any of the
+    /// evicted chunks cannot be reloaded, in which case the streaming is left
+    /// enabled.
+    pub fn disable_chunk_streaming(&mut self) -> Result<(), Error> {
+        let mut streaming = self.streaming.take().ok_or_else(|| {
+            Error::with_message("Chunk streaming not enabled")
+        })?;
+        let evicted: Vec<_> = streaming.evicted.iter().copied().collect();
+        let result = evicted
+            .into_iter()
+            .try_for_each(|key| self.reload_chunk(&mut streaming, key));
+        self.streaming = result.is_err().then_some(streaming);
+        result
+    }
+
+    /// Gets the number of chunks currently evicted to disk.
+    pub fn evicted_chunks(&self) -> usize {
+        self.streaming.as_ref().map_or(0, |s| s.evicted.len())
+    }
+
+    /// Returns true only if the given Entity is located in a chunk evicted to
+    /// disk.
+    pub(super) fn is_evicted(&self, entity: &EntityTrait<'e, K, C>) -> bool {
+        let Some(streaming) = self.streaming.as_ref() else {
+            return false;
+        };
+        entity.location().is_some_and(|location| {
+            streaming.evicted.contains(&streaming.key(location))
+        })
+    }
+
+    /// Returns true only if the given ID belongs to an Entity evicted to disk.
+    pub(super) fn is_evicted_id(&self, id: Id) -> bool {
+        self.streaming
+            .as_ref()
+            .is_some_and(|streaming| streaming.evicted_ids.contains(&id))
+    }
+
+    /// Starts streaming the chunks to disk with the given Codec.
+    fn start_streaming(
+        &mut self,
+        directory: PathBuf,
+        side: i32,
+        max_resident: usize,
+        codec: HistoryCodec<'e, K, C>,
+    ) -> Result<(), Error> {
+        assert!(side > 0, "The side of the chunks must be positive");
+        if self.streaming.is_some() {
+            return Err(Error::with_message("Chunk streaming already enabled"));
+        }
+        fs::create_dir_all(&directory).map_err(Error::with_message)?;
+        self.streaming = Some(Streaming {
+            codec,
+            directory,
+            side,
+            max_resident,
+            touched: BTreeMap::new(),
+            evicted: BTreeSet::new(),
+            evicted_ids: BTreeSet::new(),
+        });
+        Ok(())
+    }
+
+    /// Reloads the evicted chunks needed by the active entities, and evicts
+    /// the least recently needed chunks in excess, if the streaming of the
+    /// chunks is enabled.
+    ///
+    /// Returns an error if any of the chunks cannot be written or read.
+    pub(super) fn stream_chunks(&mut self) -> Result<(), Error> {
+        let Some(mut streaming) = self.streaming.take() else {
+            return Ok(());
+        };
+        let result = self.stream_with(&mut streaming);
+        self.streaming = Some(streaming);
+        result
+    }
+
+    /// Reloads and evicts the chunks with the given Streaming.
+    fn stream_with(
+        &mut self,
+        streaming: &mut Streaming<'e, K, C>,
+    ) -> Result<(), Error> {
+        // the chunks needed by the active entities, that is the ones where
+        // they are located and the ones around them
+        let side = streaming.side;
+        let columns = (self.dimension().x + side - 1) / side;
+        let rows = (self.dimension().y + side - 1) / side;
+        let mut needed = BTreeSet::new();
+        let active = self
+            .entities
+            .iter()
+            .filter(|(kind, _)| !self.passive.contains(kind))
+            .flat_map(|(_, entities)| entities);
+        for location in active.filter_map(|e| e.location()) {
+            let (x, y) = streaming.key(location);
+            for (dx, dy) in (-1..=1).flat_map(|y| (-1..=1).map(move |x| (x, y)))
+            {
+                let key =
+                    ((x + dx).rem_euclid(columns), (y + dy).rem_euclid(rows));
+                needed.insert(key);
+            }
+        }
+
+        let reloaded: Vec<_> =
+            streaming.evicted.intersection(&needed).copied().collect();
+        for key in reloaded {
+            self.reload_chunk(streaming, key)?;
+        }
+        for &key in &needed {
+            streaming.touched.insert(key, self.generation);
+        }
+
+        // the chunks with entities, from the least recently needed
+        let mut resident: Vec<_> = self
+            .entities
+            .values()
+            .flatten()
+            .filter_map(|e| e.location())
+            .map(|location| streaming.key(location))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|key| (streaming.touched.get(&key).copied(), key))
+            .collect();
+        streaming.touched.retain(|key, _| {
+            resident.iter().any(|(_, resident)| resident == key)
+        });
+        let excess = resident.len().saturating_sub(streaming.max_resident);
+        resident.sort();
+        for (_, key) in resident
+            .into_iter()
+            .filter(|(_, key)| !needed.contains(key))
+            .take(excess)
+        {
+            self.evict_chunk(streaming, key)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the entities of the given chunk from the Environment, without
+    /// recording their death, and writes them into the file of the chunk.
+    ///
+    /// If the chunk is already evicted (because some entities were moved into
+    /// it in the meantime), it is reloaded first, so that its file is replaced
+    /// by one with both the entities evicted before and the new ones.
+    fn evict_chunk(
+        &mut self,
+        streaming: &mut Streaming<'e, K, C>,
+        key: ChunkKey,
+    ) -> Result<(), Error> {
+        if streaming.evicted.contains(&key) {
+            self.reload_chunk(streaming, key)?;
+        }
+        let in_chunk = |e: &EntityTrait<'e, K, C>| {
+            e.location().is_some_and(|l| streaming.key(l) == key)
+        };
+        let mut bytes = CHUNK_MAGIC.to_vec();
+        let mut count = 0;
+        let mut entries = Vec::new();
+        let mut evicted_ids = Vec::new();
+        for entity in
+            self.entities.values().flatten().filter(|e| in_chunk(&***e))
+        {
+            push_blob(
+                &mut entries,
+                &streaming.codec.encode_kind(&entity.kind())?,
+            );
+            push_blob(&mut entries, &streaming.codec.encode(&**entity)?);
+            let birth = self.births.get(&entity.id()).copied();
+            entries.extend_from_slice(
+                &birth.unwrap_or(self.generation).get().to_le_bytes(),
+            );
+            evicted_ids.push(entity.id());
+            count += 1;
+        }
+        push_len(&mut bytes, count);
+        bytes.extend_from_slice(&entries);
+        fs::write(streaming.path(key), bytes).map_err(Error::with_message)?;
+
+        let tiles = &mut self.tiles;
+        let components = &mut self.components;
+        let columns = &mut self.columns;
+        let expiring = &mut self.expiring;
+        let births = &mut self.births;
+        for entities in self.entities.values_mut() {
+            entities.retain(|entity| {
+                if !in_chunk(&**entity) {
+                    return true;
+                }
+                if let Some(location) = entity.location() {
+                    tiles.remove(entity.id(), location);
+                }
+                components.remove_entity(entity.id());
+                columns.remove_entity(entity.id());
+                expiring.remove(&entity.id());
+                births.remove(&entity.id());
+                false
+            });
+        }
+        streaming.touched.remove(&key);
+        streaming.evicted.insert(key);
+        streaming.evicted_ids.extend(evicted_ids);
+        Ok(())
+    }
+
+    /// Reads the entities of the given evicted chunk and inserts them into the
+    /// Environment, without recording their birth, removing the file of the
+    /// chunk.
+    ///
+    /// All the entities are decoded before any of them is inserted, and the
+    /// chunk is evicted until its file is removed, so that the chunk can be
+    /// reloaded again if any of these steps fails, without losing or
+    /// duplicating any of its entities.
+    fn reload_chunk(
+        &mut self,
+        streaming: &mut Streaming<'e, K, C>,
+        key: ChunkKey,
+    ) -> Result<(), Error> {
+        let path = streaming.path(key);
+        let bytes = fs::read(&path).map_err(Error::with_message)?;
+        let mut reader = bytes.as_slice();
+        if reader.get(..CHUNK_MAGIC.len()) != Some(&CHUNK_MAGIC) {
+            return Err(Error::with_message("Invalid chunk magic bytes"));
+        }
+        reader = &reader[CHUNK_MAGIC.len()..];
+        let count = read_u32(&mut reader)?;
+        let mut entities = Vec::new();
+        for _ in 0..count {
+            let kind = streaming.codec.decode_kind(&read_blob(&mut reader)?)?;
+            let entity =
+                streaming.codec.decode(&kind, &read_blob(&mut reader)?)?;
+            let birth = Generation::from(read_u64(&mut reader)?);
+            #[cfg(debug_assertions)]
+            Self::check_entity_contract(&*entity);
+            entities.push((entity, birth));
+        }
+        fs::remove_file(&path).map_err(Error::with_message)?;
+        streaming.evicted.remove(&key);
+
+        for (mut entity, birth) in entities {
+            streaming.evicted_ids.remove(&entity.id());
+            self.tiles.insert(&mut *entity);
+            self.births.insert(entity.id(), birth);
+            self.entities.entry(entity.kind()).or_default().push(entity);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use super::*;
+
+/// The default wall-clock duration of a generation, at the default time scale.
+pub const DEFAULT_GENERATION_DURATION: Duration = Duration::from_secs(1);
+
+/// The pacing of the generations of an Environment, used by the entities whose
+/// behavior depends on the wall-clock time (such as animations, or cooldowns
+/// measured in seconds) rather than on the number of generations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Clock {
+    // the wall-clock duration of a generation, at the time scale 1
+    duration: Duration,
+    // the factor the duration of each generation is scaled by
+    scale: f32,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            duration: DEFAULT_GENERATION_DURATION,
+            scale: 1.0,
+        }
+    }
+}
+
+impl Clock {
+    /// Gets the duration of a generation, scaled by the time scale.
+    pub(crate) fn dt(self) -> Duration {
+        // scales the nanoseconds rather than the (inexact) seconds
+        let nanos = self.duration.as_nanos() as f64 * f64::from(self.scale);
+        Duration::from_nanos(nanos.round() as u64)
+    }
+
+    /// Gets the time scale.
+    pub(crate) fn scale(self) -> f32 {
+        self.scale
+    }
+}
+
+impl<'e, K, C> Environment<'e, K, C> {
+    /// Sets the factor the duration of each generation is scaled by, as seen
+    /// by the entities via `WorldView::dt`, such as 2 for a simulation that
+    /// runs twice as fast, or 0 for a paused one.
+    ///
+    /// # Panics
+    /// Panics if the scale is negative or not finite.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    /// use std::time::Duration;
+    ///
+    /// let mut env = Environment::<char>::new((4, 4));
+    /// // the host ticks 10 generations per second
+    /// let ticker = Ticker::with_rate(10);
+    /// env.set_generation_duration(ticker.timestep());
+    /// env.set_time_scale(0.5);
+    /// assert_eq!(env.time_scale(), 0.5);
+    /// assert_eq!(env.dt(), Duration::from_millis(50));
+    /// ```
+    pub fn set_time_scale(&mut self, scale: f32) {
+        assert!(
+            scale.is_finite() && scale >= 0.0,
+            "Invalid time scale {}",
+            scale
+        );
+        self.clock.scale = scale;
+    }
+
+    /// Gets the factor the duration of each generation is scaled by.
+    pub fn time_scale(&self) -> f32 {
+        self.clock.scale()
+    }
+
+    /// Sets the wall-clock duration of a generation at the time scale 1, such
+    /// as the timestep of the Ticker that drives the generations, or the time
+    /// elapsed since the previous generation when their length is variable.
+    pub fn set_generation_duration(&mut self, duration: Duration) {
+        self.clock.duration = duration;
+    }
+
+    /// Gets the wall-clock duration of a generation at the time scale 1,
+    /// that is `DEFAULT_GENERATION_DURATION` unless set otherwise.
+    pub fn generation_duration(&self) -> Duration {
+        self.clock.duration
+    }
+
+    /// Gets the duration of a generation, scaled by the time scale, as seen
+    /// by the entities via `WorldView::dt`.
+    pub fn dt(&self) -> Duration {
+        self.clock.dt()
+    }
+}
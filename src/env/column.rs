@@ -0,0 +1,308 @@
+use std::any::Any;
+
+use super::*;
+
+/// A dense column of values of the same type, such as the State of all the
+/// entities of a Kind, stored contiguously (struct-of-arrays) rather than
+/// within each Entity, and accessed via the ID of the Entity each value
+/// belongs to.
+///
+/// Since the values are stored in a single slice, bulk passes over the whole
+/// column (such as the update of millions of cells, or the computation of
+/// statistics) are cache friendly and easily vectorized. The values are kept
+/// dense by moving the last value into the slot of a removed one, therefore
+/// the order of the values is arbitrary, and the ID of the Entity of each
+/// value is given by the slice of IDs at the same index.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let mut column = Column::new();
+/// column.insert(3, 1.5f32);
+/// column.insert(5, 2.5);
+/// column.insert(8, 4.0);
+///
+/// // bulk update of all the values
+/// column.values_mut().iter_mut().for_each(|v| *v *= 2.0);
+/// assert_eq!(column.values().iter().sum::<f32>(), 16.0);
+///
+/// assert_eq!(column.remove(3), Some(3.0));
+/// assert_eq!(column.get(8), Some(&8.0));
+/// assert_eq!(column.ids(), [8, 5]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column<T> {
+    values: Vec<T>,
+    // the ID of the Entity of each value, at the same index
+    ids: Vec<Id>,
+    // the index of the value of each Entity
+    index: HashMap<Id, usize>,
+}
+
+impl<T> Default for Column<T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            ids: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Column<T> {
+    /// Constructs a new empty Column.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts the value of the Entity with the given ID, and returns the
+    /// value previously inserted for the same Entity, if any.
+    pub fn insert(&mut self, id: Id, value: T) -> Option<T> {
+        match self.index.get(&id) {
+            Some(&index) => {
+                Some(std::mem::replace(&mut self.values[index], value))
+            }
+            None => {
+                self.index.insert(id, self.values.len());
+                self.values.push(value);
+                self.ids.push(id);
+                None
+            }
+        }
+    }
+
+    /// Removes the value of the Entity with the given ID, and returns it.
+    ///
+    /// The last value of the Column is moved into the slot of the removed one.
+    pub fn remove(&mut self, id: Id) -> Option<T> {
+        let index = self.index.remove(&id)?;
+        let value = self.values.swap_remove(index);
+        self.ids.swap_remove(index);
+        if let Some(&moved) = self.ids.get(index) {
+            self.index.insert(moved, index);
+        }
+        Some(value)
+    }
+
+    /// Gets a reference to the value of the Entity with the given ID, if any.
+    pub fn get(&self, id: Id) -> Option<&T> {
+        self.index.get(&id).map(|&index| &self.values[index])
+    }
+
+    /// Gets a mutable reference to the value of the Entity with the given ID,
+    /// if any.
+    pub fn get_mut(&mut self, id: Id) -> Option<&mut T> {
+        self.index.get(&id).map(|&index| &mut self.values[index])
+    }
+
+    /// Returns true only if the Column contains the value of the Entity with
+    /// the given ID.
+    pub fn contains(&self, id: Id) -> bool {
+        self.index.contains_key(&id)
+    }
+
+    /// Gets all the values of the Column, in arbitrary order.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Gets all the mutable values of the Column, in arbitrary order.
+    pub fn values_mut(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+
+    /// Gets the IDs of the entities of all the values of the Column, in the
+    /// same order as the values.
+    pub fn ids(&self) -> &[Id] {
+        &self.ids
+    }
+
+    /// Gets an iterator over all the values of the Column together with the
+    /// IDs of their entities, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (Id, &T)> {
+        self.ids.iter().copied().zip(&self.values)
+    }
+
+    /// Gets an iterator over all the mutable values of the Column together
+    /// with the IDs of their entities, in arbitrary order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Id, &mut T)> {
+        self.ids.iter().copied().zip(&mut self.values)
+    }
+
+    /// Gets the number of values of the Column.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns true only if the Column has no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// The type erased Column of a Kind.
+#[cfg(not(feature = "parallel"))]
+trait AnyColumn {
+    /// Removes the value of the Entity with the given ID, if any.
+    fn remove_entity(&mut self, id: Id);
+
+    /// Gets a reference to the Column as Any, to downcast it to its type.
+    fn as_any(&self) -> &dyn Any;
+
+    /// Gets a mutable reference to the Column as Any, to downcast it to its
+    /// type.
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// The type erased Column of a Kind.
+#[cfg(feature = "parallel")]
+trait AnyColumn: Send + Sync {
+    /// Removes the value of the Entity with the given ID, if any.
+    fn remove_entity(&mut self, id: Id);
+
+    /// Gets a reference to the Column as Any, to downcast it to its type.
+    fn as_any(&self) -> &dyn Any;
+
+    /// Gets a mutable reference to the Column as Any, to downcast it to its
+    /// type.
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+#[cfg(not(feature = "parallel"))]
+impl<T: Any> AnyColumn for Column<T> {
+    fn remove_entity(&mut self, id: Id) {
+        self.remove(id);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<T: Any + Send + Sync> AnyColumn for Column<T> {
+    fn remove_entity(&mut self, id: Id) {
+        self.remove(id);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// The columns of the kinds of the Environment stored in SoA mode.
+pub(crate) struct Columns<K> {
+    columns: BTreeMap<K, Box<dyn AnyColumn>>,
+}
+
+impl<K> Default for Columns<K> {
+    fn default() -> Self {
+        Self {
+            columns: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: fmt::Debug> fmt::Debug for Columns<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.columns.keys()).finish()
+    }
+}
+
+impl<K> Columns<K> {
+    /// Removes the values of the Entity with the given ID from all the
+    /// columns.
+    pub fn remove_entity(&mut self, id: Id) {
+        for column in self.columns.values_mut() {
+            column.remove_entity(id);
+        }
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Enables the SoA storage mode for the given Kind, by creating an empty
+    /// Column of values of the given type (such as the State of its entities)
+    /// owned by the Environment, that replaces the Column previously enabled
+    /// for the same Kind, if any.
+    ///
+    /// The entities of the Kind can then be lightweight shells (such as a
+    /// QuickEntity with a location), whose values are inserted into the Column
+    /// by ID, and updated with bulk passes over the whole Column (see
+    /// `Environment::column_mut`). The value of an Entity is dropped when the
+    /// Entity is removed from the Environment.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((10, 10));
+    /// env.enable_column::<u8>('c');
+    /// for (id, x) in (0..10).enumerate() {
+    ///     env.insert(QuickEntity::new('c').id(id as Id).at((x, 0)));
+    ///     env.column_mut::<u8>(&'c').unwrap().insert(id as Id, x as u8);
+    /// }
+    ///
+    /// // a bulk pass over the states of all the entities of the Kind
+    /// let column = env.column_mut::<u8>(&'c').unwrap();
+    /// column.values_mut().iter_mut().for_each(|v| *v = v.saturating_sub(5));
+    /// assert_eq!(column.get(9), Some(&4));
+    ///
+    /// env.retain(|entity| entity.id() > 4);
+    /// let column = env.column::<u8>(&'c').unwrap();
+    /// assert_eq!(column.len(), 5);
+    /// assert_eq!(column.values().iter().map(|&v| v as u32).sum::<u32>(), 10);
+    /// ```
+    #[cfg(not(feature = "parallel"))]
+    pub fn enable_column<T: Any>(&mut self, kind: K) {
+        self.columns
+            .columns
+            .insert(kind, Box::new(Column::<T>::new()));
+    }
+
+    /// Enables the SoA storage mode for the given Kind, by creating an empty
+    /// Column of values of the given type (such as the State of its entities)
+    /// owned by the Environment, that replaces the Column previously enabled
+    /// for the same Kind, if any.
+    ///
+    /// The entities of the Kind can then be lightweight shells (such as a
+    /// QuickEntity with a location), whose values are inserted into the Column
+    /// by ID, and updated with bulk passes over the whole Column (see
+    /// `Environment::column_mut`). The value of an Entity is dropped when the
+    /// Entity is removed from the Environment.
+    #[cfg(feature = "parallel")]
+    pub fn enable_column<T: Any + Send + Sync>(&mut self, kind: K) {
+        self.columns
+            .columns
+            .insert(kind, Box::new(Column::<T>::new()));
+    }
+
+    /// Disables the SoA storage mode for the given Kind, dropping its Column.
+    pub fn disable_column(&mut self, kind: &K) {
+        self.columns.columns.remove(kind);
+    }
+
+    /// Gets a reference to the Column of the given Kind, if its SoA storage
+    /// mode is enabled with values of the given type.
+    pub fn column<T: Any>(&self, kind: &K) -> Option<&Column<T>> {
+        self.columns.columns.get(kind)?.as_any().downcast_ref()
+    }
+
+    /// Gets a mutable reference to the Column of the given Kind, if its SoA
+    /// storage mode is enabled with values of the given type.
+    pub fn column_mut<T: Any>(&mut self, kind: &K) -> Option<&mut Column<T>> {
+        self.columns
+            .columns
+            .get_mut(kind)?
+            .as_any_mut()
+            .downcast_mut()
+    }
+}
@@ -0,0 +1,140 @@
+use std::any::{Any, TypeId};
+
+use super::*;
+
+/// The type of the components attached to the entities.
+#[cfg(not(feature = "parallel"))]
+type Component = Box<dyn Any>;
+
+/// The type of the components attached to the entities.
+#[cfg(feature = "parallel")]
+type Component = Box<dyn Any + Send + Sync>;
+
+/// The map of components attached to the entities of the Environment, where
+/// each Entity can have at most one component of each type.
+#[derive(Debug, Default)]
+pub(crate) struct Components {
+    components: HashMap<Id, HashMap<TypeId, Component>>,
+}
+
+impl Components {
+    /// Removes all the components attached to the Entity with the given ID.
+    pub fn remove_entity(&mut self, id: Id) {
+        if !self.components.is_empty() {
+            self.components.remove(&id);
+        }
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Attaches the given component to the Entity with the given ID.
+    ///
+    /// Components allow to decorate the entities of the Environment with
+    /// additional data (such as selection flags or AI memory) without changing
+    /// their types. Each Entity can have at most one component of each type,
+    /// therefore the component of the same type previously attached to the
+    /// Entity, if any, is replaced and returned.
+    /// All the components of an Entity are dropped when the Entity is removed
+    /// from the Environment.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// struct Selected;
+    ///
+    /// let mut env = Environment::<u8>::new((10, 10));
+    /// let id = 7;
+    /// env.insert(QuickEntity::new(0).at((1, 1)).id(id));
+    ///
+    /// assert!(env.attach(id, Selected).is_none());
+    /// assert_eq!(env.attach(id, 42u32), None);
+    /// assert!(env.component::<Selected>(id).is_some());
+    /// assert_eq!(env.component::<u32>(id), Some(&42));
+    /// assert_eq!(env.detach::<u32>(id), Some(42));
+    /// assert!(env.component::<u32>(id).is_none());
+    /// ```
+    #[cfg(not(feature = "parallel"))]
+    pub fn attach<T: Any>(&mut self, id: Id, component: T) -> Option<T> {
+        self.insert_component(id, Box::new(component))
+    }
+
+    /// Attaches the given component to the Entity with the given ID.
+    ///
+    /// Components allow to decorate the entities of the Environment with
+    /// additional data (such as selection flags or AI memory) without changing
+    /// their types. Each Entity can have at most one component of each type,
+    /// therefore the component of the same type previously attached to the
+    /// Entity, if any, is replaced and returned.
+    /// All the components of an Entity are dropped when the Entity is removed
+    /// from the Environment.
+    #[cfg(feature = "parallel")]
+    pub fn attach<T: Any + Send + Sync>(
+        &mut self,
+        id: Id,
+        component: T,
+    ) -> Option<T> {
+        self.insert_component(id, Box::new(component))
+    }
+
+    /// Inserts the given component of the given type into the components of
+    /// the Entity with the given ID.
+    fn insert_component<T: Any>(
+        &mut self,
+        id: Id,
+        component: Component,
+    ) -> Option<T> {
+        self.components
+            .components
+            .entry(id)
+            .or_default()
+            .insert(TypeId::of::<T>(), component)
+            .and_then(|c| c.downcast().ok())
+            .map(|c| *c)
+    }
+
+    /// Detaches the component of the given type from the Entity with the given
+    /// ID, and returns it.
+    pub fn detach<T: Any>(&mut self, id: Id) -> Option<T> {
+        let components = self.components.components.get_mut(&id)?;
+        let component = components.remove(&TypeId::of::<T>())?;
+        if components.is_empty() {
+            self.components.components.remove(&id);
+        }
+        component.downcast().ok().map(|c| *c)
+    }
+
+    /// Gets a reference to the component of the given type attached to the
+    /// Entity with the given ID.
+    pub fn component<T: Any>(&self, id: Id) -> Option<&T> {
+        self.components
+            .components
+            .get(&id)?
+            .get(&TypeId::of::<T>())?
+            .downcast_ref()
+    }
+
+    /// Gets a mutable reference to the component of the given type attached to
+    /// the Entity with the given ID.
+    pub fn component_mut<T: Any>(&mut self, id: Id) -> Option<&mut T> {
+        self.components
+            .components
+            .get_mut(&id)?
+            .get_mut(&TypeId::of::<T>())?
+            .downcast_mut()
+    }
+
+    /// Gets an iterator over all the IDs of the entities with a component of
+    /// the given type attached, together with the component itself.
+    ///
+    /// The components will be returned in an arbitrary order.
+    pub fn components<T: Any>(&self) -> impl Iterator<Item = (Id, &T)> {
+        let type_id = TypeId::of::<T>();
+        self.components
+            .components
+            .iter()
+            .filter_map(move |(&id, c)| {
+                c.get(&type_id)?.downcast_ref().map(|c| (id, c))
+            })
+    }
+}
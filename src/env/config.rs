@@ -0,0 +1,395 @@
+//! This module contains the declarative description of a simulation, that can
+//! be loaded from TOML or RON, and the loader that builds the Environment it
+//! describes via the registered entity factories.
+//!
+//! A description made of the Environment dimension, the seed of the random
+//! placements, and of the initial patterns and parameters of each Kind, looks
+//! like the following (in TOML):
+//!
+//! ```toml
+//! dimension = [20, 20]
+//! seed = 42
+//!
+//! [[kinds]]
+//! name = "cell"
+//! rule = "B3/S23"
+//! patterns = [{ at = [1, 1], cells = [".#.", "..#", "###"] }]
+//!
+//! [[kinds]]
+//! name = "rabbit"
+//! density = 0.1
+//! params = { energy = 5 }
+//! ```
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use super::*;
+
+/// The value of a parameter of a Kind.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Param {
+    /// A boolean parameter.
+    Bool(bool),
+    /// An integer parameter.
+    Int(i64),
+    /// A floating point parameter.
+    Float(f64),
+    /// A string parameter.
+    Text(String),
+}
+
+impl Param {
+    /// Gets the parameter as a boolean, if it is one.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Gets the parameter as an integer, if it is one.
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Gets the parameter as a floating point number, if it is a number.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Int(value) => Some(*value as f64),
+            Self::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Gets the parameter as a string, if it is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Text(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// The parameters of a Kind, by name.
+pub type Params = BTreeMap<String, Param>;
+
+/// The declarative description of a simulation.
+///
+/// All the fields are public, so that the description loaded from a file can
+/// be changed programmatically (for example to sweep the parameters of an
+/// experiment) before building the Environment with a ConfigLoader.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Config {
+    /// The number of columns and rows of the Environment.
+    pub dimension: (i32, i32),
+    /// The seed of the random placement of the entities.
+    #[serde(default)]
+    pub seed: u64,
+    /// The initial configuration of each Kind.
+    #[serde(default)]
+    pub kinds: Vec<KindConfig>,
+}
+
+/// The initial configuration of the entities of a Kind.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct KindConfig {
+    /// The name the Kind is registered with in the ConfigLoader.
+    pub name: String,
+    /// The Life-like rule (in B/S notation) attached for the Kind, in which
+    /// case the Kind is placed as cells of the rule rather than as entities.
+    #[serde(default)]
+    pub rule: Option<String>,
+    /// The patterns of entities placed in the Environment.
+    #[serde(default)]
+    pub patterns: Vec<PatternConfig>,
+    /// The probability of placing an Entity in each tile of the Environment.
+    #[serde(default)]
+    pub density: f64,
+    /// The parameters given to the EntityFactory of the Kind.
+    #[serde(default)]
+    pub params: Params,
+}
+
+/// A pattern of entities, described as a list of rows, where each `#`, `O` or
+/// `*` character places an Entity, and any other character leaves the tile
+/// empty.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PatternConfig {
+    /// The location of the top-left corner of the pattern.
+    pub at: (i32, i32),
+    /// The rows of the pattern, from top to bottom.
+    pub cells: Vec<String>,
+}
+
+impl PatternConfig {
+    /// Gets an iterator over the locations of the entities of the pattern.
+    fn locations(&self) -> impl Iterator<Item = Location> + '_ {
+        self.cells.iter().enumerate().flat_map(move |(y, row)| {
+            row.chars()
+                .enumerate()
+                .filter(|(_, c)| matches!(c, '#' | 'O' | '*'))
+                .map(move |(x, _)| Location {
+                    x: self.at.0 + x as i32,
+                    y: self.at.1 + y as i32,
+                })
+        })
+    }
+}
+
+impl Config {
+    /// Parses the description of a simulation from the given TOML string.
+    pub fn from_toml(config: &str) -> Result<Self, Error> {
+        toml::from_str(config).map_err(Error::with_message)
+    }
+
+    /// Parses the description of a simulation from the given RON string.
+    pub fn from_ron(config: &str) -> Result<Self, Error> {
+        ron::from_str(config).map_err(Error::with_message)
+    }
+}
+
+/// The trait implemented by the factories of the entities of a Kind, used by
+/// the ConfigLoader to construct the entities described by a Config.
+///
+/// The trait is implemented by all the closures with a matching signature.
+pub trait EntityFactory<'e, K, C = ()> {
+    /// Constructs a new Entity at the given Location, with the given parameters
+    /// of its Kind.
+    fn create(
+        &self,
+        location: Location,
+        params: &Params,
+    ) -> Result<Box<EntityTrait<'e, K, C>>, Error>;
+}
+
+impl<'e, K, C, F> EntityFactory<'e, K, C> for F
+where
+    F: Fn(Location, &Params) -> Result<Box<EntityTrait<'e, K, C>>, Error>,
+{
+    fn create(
+        &self,
+        location: Location,
+        params: &Params,
+    ) -> Result<Box<EntityTrait<'e, K, C>>, Error> {
+        self(location, params)
+    }
+}
+
+/// A Kind registered in the ConfigLoader.
+struct RegisteredKind<'e, K, C> {
+    kind: K,
+    factory: Option<Box<dyn EntityFactory<'e, K, C> + 'e>>,
+}
+
+/// The loader that builds an Environment from a Config, by mapping the names
+/// of the kinds of the Config to the registered kinds and entity factories.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let mut loader = ConfigLoader::<char>::new();
+/// loader.register("rabbit", 'r', |location: Location, params: &Params| {
+///     let span = params["lifespan"].as_int().unwrap_or(1) as u64;
+///     Ok(QuickEntity::new('r').at(location).lifespan(span).boxed())
+/// });
+/// loader.register_kind("cell", 'c');
+///
+/// let env = loader
+///     .load_toml(
+///         r#"
+///             dimension = [10, 10]
+///
+///             [[kinds]]
+///             name = "rabbit"
+///             params = { lifespan = 3 }
+///             patterns = [{ at = [1, 1], cells = ["O.O"] }]
+///
+///             [[kinds]]
+///             name = "cell"
+///             rule = "B3/S23"
+///             patterns = [{ at = [5, 5], cells = ["OOO"] }]
+///         "#,
+///     )
+///     .unwrap();
+///
+/// assert_eq!(env.count_kind(&'r'), 2);
+/// assert_eq!(env.entities_at((3, 1)).count(), 1);
+/// assert_eq!(env.rule_population(&'c'), 3);
+/// ```
+pub struct ConfigLoader<'e, K, C = ()> {
+    kinds: BTreeMap<String, RegisteredKind<'e, K, C>>,
+}
+
+impl<'e, K, C> fmt::Debug for ConfigLoader<'e, K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfigLoader")
+            .field("kinds", &self.kinds.keys())
+            .finish()
+    }
+}
+
+impl<'e, K, C> Default for ConfigLoader<'e, K, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'e, K, C> ConfigLoader<'e, K, C> {
+    /// Constructs a new ConfigLoader with no registered kinds.
+    pub fn new() -> Self {
+        Self {
+            kinds: BTreeMap::new(),
+        }
+    }
+
+    /// Registers the given Kind with the given name, with the factory used to
+    /// construct its entities, replacing any Kind previously registered with
+    /// the same name.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        kind: K,
+        factory: impl EntityFactory<'e, K, C> + 'e,
+    ) {
+        let factory = Some(Box::new(factory) as Box<_>);
+        self.kinds
+            .insert(name.into(), RegisteredKind { kind, factory });
+    }
+
+    /// Registers the given Kind with the given name, without any factory, so
+    /// that it can only be placed as cells of a Rule, replacing any Kind
+    /// previously registered with the same name.
+    pub fn register_kind(&mut self, name: impl Into<String>, kind: K) {
+        let factory = None;
+        self.kinds
+            .insert(name.into(), RegisteredKind { kind, factory });
+    }
+}
+
+impl<'e, K: Ord + Clone, C> ConfigLoader<'e, K, C> {
+    /// Builds the Environment described by the given TOML string.
+    pub fn load_toml(
+        &self,
+        config: &str,
+    ) -> Result<Environment<'e, K, C>, Error> {
+        self.load(&Config::from_toml(config)?)
+    }
+
+    /// Builds the Environment described by the given RON string.
+    pub fn load_ron(
+        &self,
+        config: &str,
+    ) -> Result<Environment<'e, K, C>, Error> {
+        self.load(&Config::from_ron(config)?)
+    }
+
+    /// Builds the Environment described by the given Config.
+    ///
+    /// For each Kind, in order, the entities of its patterns are placed first,
+    /// followed by the entities placed randomly according to its density,
+    /// where the random placement depends only on the seed of the Config.
+    /// Kinds with a rule are attached to the Environment as a Rule, and placed
+    /// as its cells rather than as entities.
+    ///
+    /// Returns an error if any of the kinds is not registered, if any of the
+    /// rules cannot be parsed, if a Kind without rule has no factory, or if
+    /// any of the factories fails.
+    pub fn load(
+        &self,
+        config: &Config,
+    ) -> Result<Environment<'e, K, C>, Error> {
+        let mut env = Environment::new(config.dimension);
+        let dimension = env.dimension();
+        let mut rng = SplitMix64(config.seed);
+
+        for kind_config in &config.kinds {
+            let registered =
+                self.kinds.get(&kind_config.name).ok_or_else(|| {
+                    Error::with_message(format!(
+                        "Unknown kind {:?}",
+                        kind_config.name
+                    ))
+                })?;
+
+            let random = (0..dimension.len())
+                .filter(|_| rng.next_f64() < kind_config.density)
+                .map(|index| Location::from_one_dimensional(index, dimension))
+                .collect::<Vec<_>>();
+            let locations = kind_config
+                .patterns
+                .iter()
+                .flat_map(PatternConfig::locations)
+                .chain(random)
+                .map(|mut location| *location.translate((0, 0), dimension));
+
+            if let Some(rule) = &kind_config.rule {
+                let kind = registered.kind.clone();
+                env.attach_rule(Rule::parse(rule)?, kind.clone());
+                for location in locations {
+                    env.set_rule_cell(&kind, location, true);
+                }
+            } else {
+                let factory = registered.factory.as_ref().ok_or_else(|| {
+                    Error::with_message(format!(
+                        "Missing factory of kind {:?}",
+                        kind_config.name
+                    ))
+                })?;
+                for location in locations {
+                    let entity =
+                        factory.create(location, &kind_config.params)?;
+                    env.insert_boxed(entity);
+                }
+            }
+        }
+
+        Ok(env)
+    }
+
+    /// Seeds the given Environment with the placements of the given Seeder,
+    /// by constructing each Entity with the factory registered for its Kind,
+    /// with no parameters.
+    ///
+    /// Returns the number of entities inserted, or an error if any of the
+    /// kinds has no registered factory, or if any of the factories fails.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut loader = ConfigLoader::<char>::new();
+    /// loader.register("tree", 't', |location: Location, _: &Params| {
+    ///     Ok(QuickEntity::new('t').at(location).boxed())
+    /// });
+    ///
+    /// let mut env = Environment::new((10, 10));
+    /// let seeder = Seeder::new(7).scatter('t', 5);
+    /// assert_eq!(loader.seed(&seeder, &mut env).unwrap(), 5);
+    /// assert_eq!(env.count_kind(&'t'), 5);
+    /// ```
+    pub fn seed(
+        &self,
+        seeder: &Seeder<'_, K>,
+        env: &mut Environment<'e, K, C>,
+    ) -> Result<usize, Error> {
+        let params = Params::new();
+        seeder.seed(env, |kind, location| {
+            let factory = self
+                .kinds
+                .values()
+                .filter(|registered| registered.kind == *kind)
+                .find_map(|registered| registered.factory.as_ref())
+                .ok_or_else(|| {
+                    Error::with_message("Missing factory of seeded kind")
+                })?;
+            factory.create(location, &params)
+        })
+    }
+}
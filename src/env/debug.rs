@@ -0,0 +1,285 @@
+//! This module contains the introspection of the Environment, used to diagnose
+//! the logic errors of a simulation, by dumping the state of all its entities,
+//! and by checking the invariants of its data structures.
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use super::*;
+
+impl<'e, K: Ord + fmt::Debug, C> Environment<'e, K, C> {
+    /// Writes a structured (JSON) report of the Environment to the given
+    /// writer, for debugging purposes.
+    ///
+    /// The report includes the dimension and the generation of the
+    /// Environment, the properties of each Entity (its ID, its Kind and State
+    /// formatted via Debug, its location, scope and lifespan), sorted by Kind,
+    /// and the IDs of the entities that occupy each of the non-empty tiles.
+    /// The State of an Entity is only reported if it can be formatted via
+    /// `State::as_debug`, and the lifespan of an immortal Entity is reported
+    /// as `"immortal"`.
+    ///
+    /// Returns an error if writing the report fails.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((4, 4));
+    /// env.insert(QuickEntity::new('a').id(7).at((1, 2)).lifespan(3));
+    ///
+    /// let mut report = Vec::new();
+    /// env.debug_dump(&mut report).unwrap();
+    /// let report = String::from_utf8(report).unwrap();
+    /// assert!(report.contains(r#""id": 7, "kind": "'a'", "location": [1, 2]"#));
+    /// assert!(report.contains(r#"{"location": [1, 2], "entities": [7]}"#));
+    /// ```
+    pub fn debug_dump(&self, mut writer: impl Write) -> Result<(), Error> {
+        let dimension = self.dimension();
+        let mut report = String::new();
+        report.push_str("{\n");
+        report.push_str(&format!(
+            "  \"dimension\": [{}, {}],\n",
+            dimension.x, dimension.y
+        ));
+        report.push_str(&format!("  \"generation\": {},\n", self.generation));
+
+        report.push_str("  \"entities\": [");
+        for (i, entity) in self.entities().enumerate() {
+            let separator = if i == 0 { "\n" } else { ",\n" };
+            let location = entity
+                .location()
+                .map_or("null".to_string(), |l| format!("[{}, {}]", l.x, l.y));
+            let scope = entity
+                .scope()
+                .map_or("null".to_string(), |s| usize::from(s).to_string());
+            let lifespan =
+                entity.lifespan().map_or("null".to_string(), |lifespan| {
+                    match lifespan.length() {
+                        Some(length) => length.to_string(),
+                        None => json_string("immortal"),
+                    }
+                });
+            let state = entity
+                .state()
+                .and_then(|state| state.as_debug())
+                .map_or("null".to_string(), |state| {
+                    json_string(&format!("{:?}", state))
+                });
+            report.push_str(&format!(
+                "{}    {{\"id\": {}, \"kind\": {}, \"location\": {}, \
+                 \"scope\": {}, \"lifespan\": {}, \"state\": {}}}",
+                separator,
+                entity.id(),
+                json_string(&format!("{:?}", entity.kind())),
+                location,
+                scope,
+                lifespan,
+                state,
+            ));
+        }
+        report.push_str("\n  ],\n");
+
+        // the references are grouped by tile, in the order of the tiles
+        let mut tiles: Vec<(Location, Vec<Id>)> = Vec::new();
+        for (location, id, _) in self.tiles.references() {
+            match tiles.last_mut() {
+                Some((last, ids)) if *last == location => ids.push(id),
+                _ => tiles.push((location, vec![id])),
+            }
+        }
+        report.push_str("  \"tiles\": [");
+        for (i, (location, mut ids)) in tiles.into_iter().enumerate() {
+            ids.sort_unstable();
+            let separator = if i == 0 { "\n" } else { ",\n" };
+            let ids: Vec<_> = ids.iter().map(Id::to_string).collect();
+            report.push_str(&format!(
+                "{}    {{\"location\": [{}, {}], \"entities\": [{}]}}",
+                separator,
+                location.x,
+                location.y,
+                ids.join(", ")
+            ));
+        }
+        report.push_str("\n  ]\n}\n");
+
+        writer
+            .write_all(report.as_bytes())
+            .map_err(Error::with_message)
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Checks the invariants of the Environment, that is:
+    /// - The IDs of all the entities are unique.
+    /// - Each Entity is stored with the Kind it currently has.
+    /// - Each Entity with a location is referenced only by the tile at its
+    ///   location.
+    /// - Each reference stored in the tiles belongs to an existing Entity with
+    ///   the same ID, whose location matches the location of the tile.
+    ///
+    /// The invariants can only be broken by logic errors in the user's code,
+    /// such as entities that share the same ID, or that change their Kind, or
+    /// their location outside of the generation steps.
+    ///
+    /// Returns an error that describes all the broken invariants, if any.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((4, 4));
+    /// env.insert(QuickEntity::new('a').id(0).at((1, 2)));
+    /// assert!(env.validate().is_ok());
+    ///
+    /// env.insert(QuickEntity::new('b').id(0).at((3, 3)));
+    /// assert!(env.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut violations = Vec::new();
+
+        let mut ids = HashSet::new();
+        let mut addresses = HashMap::new();
+        for (kind, entities) in &self.entities {
+            for entity in entities {
+                let id = entity.id();
+                if !ids.insert(id) {
+                    violations.push(format!("Duplicate entity ID {}", id));
+                }
+                if entity.kind() != *kind {
+                    violations.push(format!("Entity {} changed its kind", id));
+                }
+                let address = &**entity as *const EntityTrait<'e, K, C>;
+                addresses.insert(address as *const (), (id, entity.location()));
+            }
+        }
+
+        let mut referenced = HashSet::new();
+        for (location, id, address) in self.tiles.references() {
+            match addresses.get(&address) {
+                Some(&(entity_id, entity_location)) => {
+                    referenced.insert(address);
+                    if entity_id != id {
+                        violations.push(format!(
+                            "Tile {:?} references entity {} with ID {}",
+                            location, entity_id, id
+                        ));
+                    }
+                    if entity_location != Some(location) {
+                        violations.push(format!(
+                            "Tile {:?} references entity {} located at {:?}",
+                            location, entity_id, entity_location
+                        ));
+                    }
+                }
+                None => violations.push(format!(
+                    "Tile {:?} references missing entity {}",
+                    location, id
+                )),
+            }
+        }
+        for (address, (id, location)) in &addresses {
+            if location.is_some() && !referenced.contains(address) {
+                violations.push(format!(
+                    "Entity {} is not referenced by any tile",
+                    id
+                ));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::with_message(violations.join("\n")))
+        }
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Checks that the given Entity, that is being inserted into the
+    /// Environment, does not break the contract of the Entity trait, that is,
+    /// it does not have a scope without having a location.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the description of the Entity if the contract is broken.
+    #[cfg(debug_assertions)]
+    pub(super) fn check_entity_contract(entity: &EntityTrait<'e, K, C>) {
+        if entity.location().is_none() && entity.scope().is_some() {
+            panic!(
+                "Entity contract violated: scope without location in {}",
+                describe(entity)
+            );
+        }
+    }
+
+    /// Checks that none of the entities broke the contract of the Entity trait
+    /// during the current generation, that is, none of them:
+    /// - Has a scope without having a location.
+    /// - Had a location and no longer has one, or vice versa.
+    /// - Changed its location outside of the generation steps where the
+    ///   Environment tracks the locations (such as while its Kind is passive,
+    ///   see `Environment::set_passive`).
+    ///
+    /// # Panics
+    ///
+    /// Panics with the description of the first Entity that broke the contract.
+    #[cfg(debug_assertions)]
+    pub(super) fn check_contracts(&self) {
+        let located: HashMap<Id, Location> = self
+            .tiles
+            .references()
+            .map(|(location, id, _)| (id, location))
+            .collect();
+        for entity in self.entities() {
+            Self::check_entity_contract(entity);
+            let violation = match (entity.location(), located.get(&entity.id()))
+            {
+                (Some(_), None) => "location from None to Some",
+                (None, Some(_)) => "location from Some to None",
+                (Some(location), Some(tile)) if location != *tile => {
+                    "location outside of the generation steps"
+                }
+                _ => continue,
+            };
+            panic!(
+                "Entity contract violated: changed {} in {}",
+                violation,
+                describe(entity)
+            );
+        }
+    }
+}
+
+/// Describes the Entity with the properties involved in its contract.
+#[cfg(debug_assertions)]
+fn describe<K, C>(entity: &EntityTrait<'_, K, C>) -> String {
+    format!(
+        "Entity {{ id: {}, location: {:?}, scope: {:?}, lifespan: {:?} }}",
+        entity.id(),
+        entity.location(),
+        entity.scope(),
+        entity.lifespan()
+    )
+}
+
+/// Formats the given string as a JSON string literal.
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                json.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
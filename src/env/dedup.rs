@@ -0,0 +1,172 @@
+use std::collections::hash_map::Entry;
+
+use rustc_hash::FxHashMap;
+
+use super::*;
+
+/// The function that gets the key of an Entity, used to deduplicate the
+/// offspring with `OffspringPolicy::UniqueBy`.
+#[cfg(not(feature = "parallel"))]
+pub type OffspringKey<'e, K, C> =
+    Box<dyn Fn(&EntityTrait<'e, K, C>) -> u64 + 'e>;
+
+/// The function that gets the key of an Entity, used to deduplicate the
+/// offspring with `OffspringPolicy::UniqueBy`.
+#[cfg(feature = "parallel")]
+pub type OffspringKey<'e, K, C> =
+    Box<dyn Fn(&EntityTrait<'e, K, C>) -> u64 + Send + Sync + 'e>;
+
+/// The function that merges an Entity into another one located in the same
+/// tile, used to deduplicate the offspring with `OffspringPolicy::Merge`.
+#[cfg(not(feature = "parallel"))]
+pub type OffspringMerge<'e, K, C> =
+    Box<dyn Fn(&mut EntityTrait<'e, K, C>, Box<EntityTrait<'e, K, C>>) + 'e>;
+
+/// The function that merges an Entity into another one located in the same
+/// tile, used to deduplicate the offspring with `OffspringPolicy::Merge`.
+#[cfg(feature = "parallel")]
+pub type OffspringMerge<'e, K, C> = Box<
+    dyn Fn(&mut EntityTrait<'e, K, C>, Box<EntityTrait<'e, K, C>>)
+        + Send
+        + Sync
+        + 'e,
+>;
+
+/// The policy applied to deduplicate the offspring of the same Kind released
+/// by different entities during the same generation (for example the same
+/// dead cell brought back to life by several live cells), so that this
+/// coordination does not have to be implemented by the entities themselves.
+///
+/// The offspring are visited in the order they are collected (by the Kind of
+/// their parents, and then by the order their parents were inserted in), and
+/// the offspring without a location are never deduplicated.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// // an Entity that gives birth to a child in the tile at its right
+/// struct Parent(Id);
+///
+/// impl<'e> Entity<'e> for Parent {
+///     type Kind = char;
+///
+///     fn id(&self) -> Id {
+///         self.0
+///     }
+///
+///     fn kind(&self) -> char {
+///         'p'
+///     }
+///
+///     fn location(&self) -> Option<Location> {
+///         Some(Location { x: 1, y: 0 })
+///     }
+///
+///     fn offspring(&mut self) -> Option<Offspring<'e, char>> {
+///         let mut offspring = Offspring::default();
+///         let child = QuickEntity::new('c').at((0, 0)).lifespan(1);
+///         offspring.insert_at(child, (1, 0));
+///         Some(offspring)
+///     }
+/// }
+///
+/// let mut env = Environment::new((5, 5));
+/// env.set_offspring_policy('c', OffspringPolicy::Merge(Box::new(|kept, _| {
+///     kept.lifespan_mut().unwrap().lengthen();
+/// })));
+/// // two parents in the same tile give birth in the same tile
+/// env.insert(Parent(0));
+/// env.insert(Parent(1));
+///
+/// env.nextgen().unwrap();
+/// assert_eq!(env.count_kind(&'c'), 1);
+/// let child = env.entities_at((2, 0)).next().unwrap();
+/// assert_eq!(child.lifespan().and_then(Lifespan::length), Some(2));
+/// ```
+pub enum OffspringPolicy<'e, K, C = ()> {
+    /// Only the first of the offspring located in the same tile is inserted.
+    UniqueByLocation,
+    /// Only the first of the offspring with the same key, given by the
+    /// function, is inserted.
+    UniqueBy(OffspringKey<'e, K, C>),
+    /// The offspring located in the same tile are merged into the first one
+    /// with the function, that is given the first Entity and each of the
+    /// following ones.
+    Merge(OffspringMerge<'e, K, C>),
+}
+
+impl<'e, K, C> fmt::Debug for OffspringPolicy<'e, K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UniqueByLocation => f.write_str("UniqueByLocation"),
+            Self::UniqueBy(_) => f.write_str("UniqueBy(..)"),
+            Self::Merge(_) => f.write_str("Merge(..)"),
+        }
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Sets the policy applied to deduplicate the offspring of the given Kind
+    /// released during the same generation, before the limits of the Kind
+    /// are enforced (see `Environment::set_kind_limit`).
+    ///
+    /// The offspring of all the kinds are not deduplicated by default.
+    pub fn set_offspring_policy(
+        &mut self,
+        kind: K,
+        policy: OffspringPolicy<'e, K, C>,
+    ) {
+        self.offspring_policies.insert(kind, policy);
+    }
+
+    /// Removes the policy applied to deduplicate the offspring of the given
+    /// Kind.
+    pub fn remove_offspring_policy(&mut self, kind: &K) {
+        self.offspring_policies.remove(kind);
+    }
+
+    /// Applies the deduplication policy of each Kind to the given offspring,
+    /// and returns the offspring left, in the same order.
+    pub(super) fn dedup_offspring(
+        &self,
+        offspring: Vec<Box<EntityTrait<'e, K, C>>>,
+    ) -> Vec<Box<EntityTrait<'e, K, C>>> {
+        if self.offspring_policies.is_empty() {
+            return offspring;
+        }
+
+        let mut deduped: Vec<Box<EntityTrait<'e, K, C>>> =
+            Vec::with_capacity(offspring.len());
+        // the index of the first of the offspring with the same key, by Kind
+        let mut firsts: BTreeMap<K, FxHashMap<u64, usize>> = BTreeMap::new();
+        let dimension = self.dimension();
+        for entity in offspring {
+            let kind = entity.kind();
+            let Some(policy) = self.offspring_policies.get(&kind) else {
+                deduped.push(entity);
+                continue;
+            };
+            let key = match policy {
+                OffspringPolicy::UniqueBy(key) => Some(key(&*entity)),
+                _ => entity.location().map(|l| l.to_key(dimension)),
+            };
+            let Some(key) = key else {
+                deduped.push(entity);
+                continue;
+            };
+            match firsts.entry(kind).or_default().entry(key) {
+                Entry::Vacant(entry) => {
+                    entry.insert(deduped.len());
+                    deduped.push(entity);
+                }
+                Entry::Occupied(entry) => {
+                    if let OffspringPolicy::Merge(merge) = policy {
+                        merge(&mut *deduped[*entry.get()], entity);
+                    }
+                }
+            }
+        }
+        deduped
+    }
+}
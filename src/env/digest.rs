@@ -0,0 +1,215 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+use super::*;
+
+/// The rolling list of digests of the past generations of the Environment.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Digests {
+    // the digests sorted from the oldest to the most recent generation
+    digests: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl Digests {
+    /// Pushes the digest of the most recent generation, discarding the oldest
+    /// one if the history is full.
+    fn push(&mut self, digest: u64) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.digests.len() == self.capacity {
+            self.digests.pop_front();
+        }
+        self.digests.push_back(digest);
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Sets the number of past generations whose digest is kept by the
+    /// Environment, discarding the oldest digests if needed.
+    ///
+    /// A history of length 0 disables the computation of the digests for each
+    /// generation altogether, which is the default, since computing the digest
+    /// of every generation requires to visit all the entities.
+    pub fn set_digest_history(&mut self, len: usize) {
+        let digests = &mut self.digests.digests;
+        while digests.len() > len {
+            digests.pop_front();
+        }
+        self.digests.capacity = len;
+    }
+
+    /// Computes the digest of the current configuration of the Environment.
+    ///
+    /// The digest depends on the Kind and Location of each Entity, as well as
+    /// on the digest of its state (see `Entity::digest`), but not on the ID of
+    /// the entities, nor on their order. The cells of the attached rules (see
+    /// `Environment::attach_rule`) are also part of the digest.
+    ///
+    /// The kinds are identified by their order among all the kinds of the
+    /// entities ever inserted into the Environment, so that the digests of the
+    /// same configuration are only guaranteed to match within the same
+    /// Environment (or within environments whose kinds were inserted in the
+    /// same way).
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8>::new((5, 5));
+    /// env.insert(QuickEntity::new(0).at((1, 1)));
+    /// let digest = env.digest();
+    ///
+    /// // an Entity of a different Kind in the same location
+    /// env.clear_kind(&0);
+    /// env.insert(QuickEntity::new(1).at((1, 1)));
+    /// assert_ne!(env.digest(), digest);
+    /// ```
+    pub fn digest(&self) -> u64 {
+        let mut digest = 0u64;
+        // the kinds are identified by their ordinal among all the kinds ever
+        // inserted, whose entries are never removed, together with their
+        // number, so that the same ordinal never identifies different kinds
+        let kinds = self.entities.len();
+        for (ordinal, entities) in self.entities.values().enumerate() {
+            for entity in entities {
+                let mut hasher = DefaultHasher::new();
+                (kinds, ordinal).hash(&mut hasher);
+                entity.location().hash(&mut hasher);
+                entity.digest().hash(&mut hasher);
+                // the entities digests are combined with a commutative
+                // operation, so that their order is irrelevant
+                digest = digest.wrapping_add(hasher.finish());
+            }
+        }
+        for (ordinal, cells) in self.rule_boards().enumerate() {
+            let mut hasher = DefaultHasher::new();
+            ordinal.hash(&mut hasher);
+            cells.hash(&mut hasher);
+            digest = digest.wrapping_add(hasher.finish());
+        }
+        digest
+    }
+
+    /// Returns the period of the simulation if it reached a steady state within
+    /// the given window of past generations, or None otherwise.
+    ///
+    /// The simulation is considered steady if the digests of the current and
+    /// of the last `window` generations repeat with the same period, where a
+    /// period of 1 means that the Environment did not change at all (as for
+    /// still lifes), while greater periods identify oscillators. The smallest
+    /// period is returned.
+    ///
+    /// Returns None if the window is empty or longer than the number of
+    /// generations whose digest has been recorded so far (see
+    /// `Environment::set_digest_history`).
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8>::new((10, 10));
+    /// env.set_digest_history(8);
+    /// env.insert(QuickEntity::new(0).at((0, 0)).on_react(|entity, _| {
+    ///     // move back and forth between two tiles
+    ///     let x = 1 - entity.location().unwrap().x;
+    ///     entity.set_location(Location { x, y: 0 });
+    ///     Ok(())
+    /// }));
+    ///
+    /// assert_eq!(env.is_steady_state(2), None);
+    /// for _ in 0..4 {
+    ///     env.nextgen().unwrap();
+    /// }
+    /// assert_eq!(env.is_steady_state(2), Some(2));
+    /// ```
+    pub fn is_steady_state(&self, window: usize) -> Option<usize> {
+        let history = &self.digests.digests;
+        if window == 0 || window > history.len() {
+            return None;
+        }
+
+        let digests: Vec<u64> = history
+            .range(history.len() - window..)
+            .copied()
+            .chain(std::iter::once(self.digest()))
+            .collect();
+        (1..=window).find(|&period| {
+            digests
+                .iter()
+                .zip(&digests[period..])
+                .all(|(prev, next)| prev == next)
+        })
+    }
+
+    /// Returns the period of the cycle entered by the simulation, if the
+    /// current configuration of the Environment was already seen in any of the
+    /// past generations within the given horizon, or None otherwise.
+    ///
+    /// Unlike `Environment::is_steady_state`, a single revisit of a previous
+    /// configuration is enough to detect a cycle, since the evolution of a
+    /// deterministic simulation depends only on its current configuration.
+    /// The period is the number of generations since the most recent revisited
+    /// configuration, and it can be used, for example, to classify the
+    /// oscillators of Life-like automata.
+    ///
+    /// The horizon is limited by the number of generations whose digest has
+    /// been recorded so far (see `Environment::set_digest_history`).
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8>::new((10, 10));
+    /// env.set_digest_history(8);
+    /// env.insert(QuickEntity::new(0).at((0, 0)).on_react(|entity, _| {
+    ///     // move around a cycle of three tiles
+    ///     let x = (entity.location().unwrap().x + 1) % 3;
+    ///     entity.set_location(Location { x, y: 0 });
+    ///     Ok(())
+    /// }));
+    ///
+    /// env.nextgen().unwrap();
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.cycle_period(10), None);
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.cycle_period(10), Some(3));
+    /// assert_eq!(env.cycle_period(2), None);
+    /// ```
+    pub fn cycle_period(&self, horizon: usize) -> Option<usize> {
+        let history = &self.digests.digests;
+        let horizon = horizon.min(history.len());
+        if horizon == 0 {
+            return None;
+        }
+
+        let digest = self.digest();
+        history
+            .iter()
+            .rev()
+            .take(horizon)
+            .position(|&prev| prev == digest)
+            .map(|index| index + 1)
+    }
+
+    /// Computes the digest of the current configuration of the Environment,
+    /// only if the digests of the past generations are being recorded.
+    pub(super) fn pending_digest(&self) -> Option<u64> {
+        (self.digests.capacity > 0).then(|| self.digest())
+    }
+
+    /// Discards the digests of all the past generations.
+    pub(super) fn clear_digests(&mut self) {
+        self.digests.digests.clear();
+    }
+
+    /// Records the digest of the configuration of the Environment of the
+    /// previous generation.
+    pub(super) fn record_digest(&mut self, digest: Option<u64>) {
+        if let Some(digest) = digest {
+            self.digests.push(digest);
+        }
+    }
+}
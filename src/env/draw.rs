@@ -0,0 +1,567 @@
+use super::*;
+
+/// The batch of the DrawCommands of the entities of the same Kind that share
+/// the same mesh, that can be drawn with a single instanced draw call.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DrawBatch {
+    /// The user-defined identifier of the mesh of all the instances.
+    pub mesh: u32,
+    /// The transformation of each instance, as 4x4 column matrix.
+    pub transforms: Vec<[[f32; 4]; 4]>,
+    /// The color of each instance, as RGBA components in the range [0, 1].
+    pub colors: Vec<[f32; 4]>,
+}
+
+impl DrawBatch {
+    /// Constructs a new empty DrawBatch of the given mesh.
+    pub fn new(mesh: u32) -> Self {
+        Self {
+            mesh,
+            ..Self::default()
+        }
+    }
+
+    /// Appends the instance described by the given DrawCommand to the batch,
+    /// regardless of its mesh.
+    pub fn push(&mut self, command: &DrawCommand) {
+        self.transforms.push(command.transform.to_column_matrix4());
+        self.colors.push(command.color);
+    }
+
+    /// Gets the number of instances of the batch.
+    pub fn len(&self) -> usize {
+        self.transforms.len()
+    }
+
+    /// Returns true only if the batch has no instances.
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+}
+
+/// The policy applied when drawing an Entity fails.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorPolicy {
+    /// The drawing stops at the first error, which is returned.
+    #[default]
+    Abort,
+    /// The entities that fail to draw are skipped, and their errors are
+    /// collected, so that the rest of the Environment is still drawn.
+    SkipAndCollect,
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Draws the environment as `Environment::draw`, applying the given
+    /// ErrorPolicy when the draw method of an Entity returns an error, so that
+    /// a single failing Entity (such as one with a corrupted mesh) does not
+    /// prevent the rest of the Environment from being drawn.
+    ///
+    /// Returns the ID of each of the entities that failed to draw together
+    /// with its error, in the order they were drawn, or the first error if the
+    /// policy is to abort.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// struct Mesh(Id, bool);
+    ///
+    /// impl<'e> Entity<'e, u32> for Mesh {
+    ///     type Kind = ();
+    ///
+    ///     fn id(&self) -> Id {
+    ///         self.0
+    ///     }
+    ///
+    ///     fn kind(&self) {}
+    ///
+    ///     fn drawable(&self) -> Option<&dyn Drawable<Context = u32>> {
+    ///         Some(self)
+    ///     }
+    /// }
+    ///
+    /// impl Drawable for Mesh {
+    ///     type Context = u32;
+    ///
+    ///     fn draw(&self, drawn: &mut u32, _: Transform) -> Result<(), Error> {
+    ///         if !self.1 {
+    ///             return Err(Error::with_message("Corrupted mesh"));
+    ///         }
+    ///         *drawn += 1;
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut env = Environment::new((10, 10));
+    /// env.insert(Mesh(0, false));
+    /// env.insert(Mesh(1, true));
+    ///
+    /// let mut drawn = 0;
+    /// assert!(env.draw(&mut drawn, Transform::identity()).is_err());
+    /// assert_eq!(drawn, 0);
+    ///
+    /// let policy = ErrorPolicy::SkipAndCollect;
+    /// let errors = env
+    ///     .draw_with_policy(&mut drawn, Transform::identity(), policy)
+    ///     .unwrap();
+    /// assert_eq!(drawn, 1);
+    /// assert_eq!(errors.len(), 1);
+    /// assert_eq!(errors[0].0, 0);
+    /// ```
+    pub fn draw_with_policy(
+        &self,
+        ctx: &mut C,
+        transform: impl Into<Transform>,
+        policy: ErrorPolicy,
+    ) -> Result<Vec<(Id, Error)>, Error> {
+        let transform = transform.into();
+        let mut errors = Vec::new();
+        for (kind, entities) in &self.entities {
+            let layer = self.layers.get(kind).copied().unwrap_or_default();
+            if !layer.visible {
+                continue;
+            }
+            let draw = DrawContext {
+                transform,
+                alpha: layer.alpha,
+                grid: self.grid,
+                age: 0,
+            };
+            for entity in Self::draw_order(entities) {
+                if let Some(drawable) = entity.drawable() {
+                    let age = self.age(entity.id()).unwrap_or_default();
+                    let result = drawable
+                        .draw_with_context(ctx, DrawContext { age, ..draw });
+                    match (result, policy) {
+                        (Ok(()), _) => (),
+                        (Err(e), ErrorPolicy::Abort) => return Err(e),
+                        (Err(e), ErrorPolicy::SkipAndCollect) => {
+                            errors.push((entity.id(), e))
+                        }
+                    }
+                }
+            }
+        }
+        Ok(errors)
+    }
+
+    /// Prepares the DrawCommand of each of the entities that are Drawable and
+    /// whose Kind is visible, via `Drawable::prepare_draw`, sorted by Kind
+    /// and then by z-order.
+    ///
+    /// With the `parallel` feature the commands are prepared in parallel, and
+    /// can then be submitted to the graphics Context from a single thread, so
+    /// that only the submission of the draw calls is serial. The entities that
+    /// don't prepare any DrawCommand are skipped.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// struct Cell(Location);
+    ///
+    /// impl<'e> Entity<'e> for Cell {
+    ///     type Kind = u8;
+    ///
+    ///     fn id(&self) -> Id {
+    ///         self.0.x as Id
+    ///     }
+    ///
+    ///     fn kind(&self) -> Self::Kind {
+    ///         0
+    ///     }
+    ///
+    ///     fn location(&self) -> Option<Location> {
+    ///         Some(self.0)
+    ///     }
+    ///
+    ///     fn drawable(&self) -> Option<&dyn Drawable<Context = ()>> {
+    ///         Some(self)
+    ///     }
+    /// }
+    ///
+    /// impl Drawable for Cell {
+    ///     type Context = ();
+    ///
+    ///     fn draw(&self, _: &mut (), _: Transform) -> Result<(), Error> {
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn prepare_draw(&self, draw: DrawContext) -> Option<DrawCommand> {
+    ///         let offset = Transform::translate(self.0.to_pixel_coords(1.0));
+    ///         Some(DrawCommand {
+    ///             mesh: 0,
+    ///             transform: draw.transform * offset,
+    ///             color: [1.0, 1.0, 1.0, draw.alpha],
+    ///         })
+    ///     }
+    /// }
+    ///
+    /// let mut env = Environment::new((10, 10));
+    /// env.insert(Cell(Location { x: 1, y: 0 }));
+    /// env.insert(Cell(Location { x: 2, y: 0 }));
+    /// env.set_kind_alpha(0, 0.5);
+    ///
+    /// let commands = env.prepare_draw(Transform::identity());
+    /// assert_eq!(commands.len(), 2);
+    /// assert!(commands.iter().all(|command| command.color[3] == 0.5));
+    /// ```
+    pub fn prepare_draw(
+        &self,
+        transform: impl Into<Transform>,
+    ) -> Vec<DrawCommand> {
+        let transform = transform.into();
+        self.prepare_commands(transform)
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Draws the environment in two passes: first the DrawCommand of each of
+    /// the entities that are Drawable and whose Kind is visible is prepared via
+    /// `Drawable::prepare_draw` (in parallel with the `parallel` feature), then
+    /// each command is given to the `submit` function with the graphics
+    /// Context, sorted by Kind and then by z-order, from the calling thread.
+    ///
+    /// The entities that don't prepare any DrawCommand are instead drawn via
+    /// `Drawable::draw_with_context` during the second pass, in their order.
+    ///
+    /// Returns an error if any of the calls to `submit` or to the draw methods
+    /// returns an error.
+    pub fn draw_prepared(
+        &self,
+        ctx: &mut C,
+        transform: impl Into<Transform>,
+        mut submit: impl FnMut(&mut C, &DrawCommand) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let transform = transform.into();
+        let commands = self.prepare_commands(transform);
+        let entities = self.visible_drawables(transform);
+        for ((_, drawable, draw), command) in entities.zip(commands) {
+            match command {
+                Some(command) => submit(ctx, &command)?,
+                None => drawable.draw_with_context(ctx, draw)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the tiles of the environment one by one, by calling the
+    /// `draw_tile` function for each of them, sorted by row and then by
+    /// column, so that background visualizations (such as grid lines, heatmaps
+    /// of the occupancy of the tiles, or their data) can be drawn without any
+    /// dedicated Entity, usually before drawing the entities with
+    /// `Environment::draw`.
+    ///
+    /// The `draw_tile` function is given the graphics Context, the parameters
+    /// to draw the tile with (whose grid converts the location of the tile to
+    /// pixel coordinates, see `TileGrid::tile_rect`), and a view of the tile
+    /// itself, that gives its location, its entities and its data.
+    ///
+    /// Returns an error if any of the calls to `draw_tile` returns an error.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((3, 2));
+    /// env.set_grid(TileGrid::new(10.0));
+    /// env.insert(QuickEntity::new('a').at((1, 1)));
+    ///
+    /// let mut occupied = Vec::new();
+    /// env.draw_tiles(&mut (), Transform::identity(), |_, draw, tile| {
+    ///     if !tile.is_empty() {
+    ///         occupied.push(draw.grid.tile_rect(tile.location()).origin);
+    ///     }
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// assert_eq!(occupied, vec![Coordinate { x: 10.0, y: 10.0 }]);
+    /// ```
+    pub fn draw_tiles(
+        &self,
+        ctx: &mut C,
+        transform: impl Into<Transform>,
+        mut draw_tile: impl FnMut(
+            &mut C,
+            DrawContext,
+            &TileView<'_, 'e, K, C>,
+        ) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let draw = DrawContext {
+            transform: transform.into(),
+            alpha: 1.0,
+            grid: self.grid,
+            age: 0,
+        };
+        for tile in self.tiles.views() {
+            draw_tile(ctx, draw, &tile)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the environment in aggregate, by grouping the entities whose Kind
+    /// is visible into the squared cells of the screen with the given side in
+    /// pixels, and by calling the `draw_bucket` function once for each of the
+    /// non-empty cells, rather than drawing each Entity, so that very
+    /// zoomed-out views don't need to draw many sub-pixel entities.
+    ///
+    /// Each Entity belongs to the cell that contains the center of its tile,
+    /// as positioned on the screen by the geometry of the grid of tiles (see
+    /// `Environment::set_grid`) and by the given Transform. The `draw_bucket`
+    /// function is given the graphics Context, the number of entities of each
+    /// Kind in the cell (sorted by Kind), and the area of the cell, with the
+    /// cells sorted by row and then by column.
+    ///
+    /// Returns an error if the side of the cells is not positive, or if any of
+    /// the calls to `draw_bucket` returns an error.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((10, 10));
+    /// env.set_grid(TileGrid::new(1.0));
+    /// env.insert(QuickEntity::new('a').at((0, 0)));
+    /// env.insert(QuickEntity::new('a').at((3, 3)));
+    /// env.insert(QuickEntity::new('b').at((2, 1)));
+    /// env.insert(QuickEntity::new('a').at((5, 0)));
+    ///
+    /// let mut buckets = Vec::new();
+    /// env.draw_aggregated(&mut (), Transform::identity(), 4.0, |_, counts, rect| {
+    ///     let counts: Vec<_> = counts.iter().map(|&(&k, n)| (k, n)).collect();
+    ///     buckets.push((rect.origin.x, rect.origin.y, counts));
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// assert_eq!(buckets, vec![
+    ///     (0.0, 0.0, vec![('a', 2), ('b', 1)]),
+    ///     (4.0, 0.0, vec![('a', 1)]),
+    /// ]);
+    /// ```
+    pub fn draw_aggregated(
+        &self,
+        ctx: &mut C,
+        transform: impl Into<Transform>,
+        cell_px: f32,
+        mut draw_bucket: impl FnMut(
+            &mut C,
+            &[(&K, usize)],
+            PixelRect,
+        ) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        if cell_px.is_nan() || cell_px <= 0.0 {
+            return Err(Error::with_message(format!(
+                "Invalid aggregation cell side {}",
+                cell_px
+            )));
+        }
+        let transform = transform.into();
+        let half_tile = self.grid.side / 2.0;
+
+        let mut buckets: BTreeMap<(i64, i64), Vec<(&K, usize)>> =
+            BTreeMap::new();
+        let kinds = self.entities.iter().filter(|(kind, _)| {
+            self.layers.get(*kind).copied().unwrap_or_default().visible
+        });
+        for (kind, entities) in kinds {
+            for location in entities.iter().filter_map(|e| e.location()) {
+                let mut center = self.grid.to_pixel(location);
+                center.x += half_tile;
+                center.y += half_tile;
+                let center = transform * center;
+                let cell = (
+                    (center.y / cell_px).floor() as i64,
+                    (center.x / cell_px).floor() as i64,
+                );
+                let counts = buckets.entry(cell).or_default();
+                // the kinds are visited in order, therefore the count of the
+                // current Kind (if any) is the last one
+                match counts.last_mut() {
+                    Some((last, count)) if *last == kind => *count += 1,
+                    _ => counts.push((kind, 1)),
+                }
+            }
+        }
+
+        for ((row, column), counts) in buckets {
+            let rect = PixelRect {
+                origin: Coordinate {
+                    x: column as f32 * cell_px,
+                    y: row as f32 * cell_px,
+                },
+                size: Size {
+                    width: cell_px,
+                    height: cell_px,
+                },
+            };
+            draw_bucket(ctx, &counts, rect)?;
+        }
+        Ok(())
+    }
+
+    /// Prepares the DrawCommand of each of the entities that are Drawable and
+    /// whose Kind is visible (as `Environment::prepare_draw`), and batches
+    /// them by Kind and by mesh, so that each batch can be drawn with a single
+    /// instanced draw call.
+    ///
+    /// The batches are sorted by Kind, and the batches of the same Kind by
+    /// mesh, while the instances of each batch follow the z-order of the
+    /// entities of their Kind.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// struct Cell(Location, u32);
+    ///
+    /// impl<'e> Entity<'e> for Cell {
+    ///     type Kind = u8;
+    ///
+    ///     fn id(&self) -> Id {
+    ///         self.0.x as Id
+    ///     }
+    ///
+    ///     fn kind(&self) -> Self::Kind {
+    ///         0
+    ///     }
+    ///
+    ///     fn location(&self) -> Option<Location> {
+    ///         Some(self.0)
+    ///     }
+    ///
+    ///     fn drawable(&self) -> Option<&dyn Drawable<Context = ()>> {
+    ///         Some(self)
+    ///     }
+    /// }
+    ///
+    /// impl Drawable for Cell {
+    ///     type Context = ();
+    ///
+    ///     fn draw(&self, _: &mut (), _: Transform) -> Result<(), Error> {
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn prepare_draw(&self, draw: DrawContext) -> Option<DrawCommand> {
+    ///         let offset = Transform::translate(self.0.to_pixel_coords(1.0));
+    ///         Some(DrawCommand {
+    ///             mesh: self.1,
+    ///             transform: draw.transform * offset,
+    ///             color: [1.0, 1.0, 1.0, draw.alpha],
+    ///         })
+    ///     }
+    /// }
+    ///
+    /// let mut env = Environment::new((10, 10));
+    /// for x in 0..5 {
+    ///     env.insert(Cell(Location { x, y: 0 }, x as u32 % 2));
+    /// }
+    ///
+    /// let batches = env.prepare_batches(Transform::identity());
+    /// assert_eq!(batches.len(), 2);
+    /// assert_eq!(batches[0].mesh, 0);
+    /// assert_eq!(batches[0].len(), 3);
+    /// assert_eq!(batches[1].transforms[0][3][0], 1.0);
+    /// ```
+    pub fn prepare_batches(
+        &self,
+        transform: impl Into<Transform>,
+    ) -> Vec<DrawBatch> {
+        let transform = transform.into();
+        let commands = self.prepare_commands(transform);
+        let kinds = self.visible_drawables(transform).map(|(kind, ..)| kind);
+
+        let mut batches = Vec::new();
+        let mut meshes: BTreeMap<u32, DrawBatch> = BTreeMap::new();
+        let mut current = None;
+        for (kind, command) in kinds.zip(commands) {
+            if current != Some(kind) {
+                batches.extend(std::mem::take(&mut meshes).into_values());
+                current = Some(kind);
+            }
+            if let Some(command) = command {
+                meshes
+                    .entry(command.mesh)
+                    .or_insert_with(|| DrawBatch::new(command.mesh))
+                    .push(&command);
+            }
+        }
+        batches.extend(meshes.into_values());
+        batches
+    }
+
+    /// Gets an iterator over the entities whose Kind is visible, sorted by
+    /// Kind, with their Kind and DrawContext.
+    fn visible_entities(
+        &self,
+        transform: Transform,
+    ) -> impl Iterator<Item = (&K, &EntityTrait<'e, K, C>, DrawContext)>
+           + use<'_, 'e, K, C> {
+        self.entities
+            .iter()
+            .filter_map(move |(kind, entities)| {
+                let layer = self.layers.get(kind).copied().unwrap_or_default();
+                let draw = DrawContext {
+                    transform,
+                    alpha: layer.alpha,
+                    grid: self.grid,
+                    age: 0,
+                };
+                layer.visible.then_some((kind, entities, draw))
+            })
+            .flat_map(move |(kind, entities, draw)| {
+                Self::draw_order(entities).into_iter().map(move |entity| {
+                    let age = self.age(entity.id()).unwrap_or_default();
+                    (kind, entity, DrawContext { age, ..draw })
+                })
+            })
+    }
+
+    /// Gets an iterator over the entities that are Drawable and whose Kind is
+    /// visible, sorted by Kind, with their Kind and DrawContext.
+    fn visible_drawables(
+        &self,
+        transform: Transform,
+    ) -> impl Iterator<Item = (&K, &dyn Drawable<Context = C>, DrawContext)>
+           + use<'_, 'e, K, C> {
+        self.visible_entities(transform)
+            .filter_map(|(kind, entity, draw)| {
+                Some((kind, entity.drawable()?, draw))
+            })
+    }
+
+    /// Prepares the DrawCommand (if any) of each of the entities that are
+    /// Drawable and whose Kind is visible, sorted by Kind.
+    #[cfg(not(feature = "parallel"))]
+    fn prepare_commands(
+        &self,
+        transform: Transform,
+    ) -> Vec<Option<DrawCommand>> {
+        self.visible_drawables(transform)
+            .map(|(_, drawable, draw)| drawable.prepare_draw(draw))
+            .collect()
+    }
+
+    /// Prepares the DrawCommand (if any) of each of the entities that are
+    /// Drawable and whose Kind is visible, sorted by Kind.
+    #[cfg(feature = "parallel")]
+    fn prepare_commands(
+        &self,
+        transform: Transform,
+    ) -> Vec<Option<DrawCommand>> {
+        use rayon::prelude::*;
+
+        // the Drawable trait objects are not required to be Sync, therefore
+        // the entities are shared with the tasks rather than their drawables
+        let entities: Vec<_> = self
+            .visible_entities(transform)
+            .map(|(_, entity, draw)| (entity, draw))
+            .collect();
+        entities
+            .into_par_iter()
+            .filter_map(|(entity, draw)| {
+                Some(entity.drawable()?.prepare_draw(draw))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::HashSet;
+
+use super::*;
+
+/// A single edit recorded by the Editor.
+enum Edit<'e, K, C> {
+    Insert(Box<EntityTrait<'e, K, C>>),
+    Kill(Id),
+    Relocate(Id, Location),
+}
+
+/// The batch of edits applied atomically to the Environment by
+/// `Environment::apply`.
+///
+/// The edits are only recorded by the Editor, and they are validated and
+/// applied in the order they were recorded once the batch is complete.
+pub struct Editor<'e, K, C = ()> {
+    edits: Vec<Edit<'e, K, C>>,
+}
+
+impl<'e, K, C> fmt::Debug for Editor<'e, K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Editor")
+            .field("edits", &self.edits.len())
+            .finish()
+    }
+}
+
+impl<'e, K, C> Editor<'e, K, C> {
+    /// Inserts the given Entity into the Environment, whose ID must not belong
+    /// to any other Entity.
+    #[cfg(not(feature = "parallel"))]
+    pub fn insert<E>(&mut self, entity: E)
+    where
+        E: Entity<'e, C, Kind = K> + 'e,
+    {
+        self.edits.push(Edit::Insert(Box::new(entity)));
+    }
+
+    /// Inserts the given Entity into the Environment, whose ID must not belong
+    /// to any other Entity.
+    #[cfg(feature = "parallel")]
+    pub fn insert<E>(&mut self, entity: E)
+    where
+        E: Entity<'e, C, Kind = K> + 'e + Send + Sync,
+    {
+        self.edits.push(Edit::Insert(Box::new(entity)));
+    }
+
+    /// Removes the Entity with the given ID from the Environment.
+    pub fn kill(&mut self, id: Id) {
+        self.edits.push(Edit::Kill(id));
+    }
+
+    /// Moves the Entity with the given ID, that must have a location, to the
+    /// given location (wrapped around the Environment edges).
+    pub fn relocate(&mut self, id: Id, location: impl Into<Location>) {
+        self.edits.push(Edit::Relocate(id, location.into()));
+    }
+
+    /// Gets the number of edits recorded.
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    /// Returns true only if no edit was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Applies the edits recorded by the given function with an Editor as a
+    /// single logical step between two generations, so that interactive
+    /// tools can modify the Environment safely.
+    ///
+    /// The edits are validated in the order they were recorded before any of
+    /// them is applied: an Entity can only be inserted if its ID does not
+    /// belong to any other Entity, if it does not have a scope without a
+    /// location, and if it is not located in a chunk evicted to disk (see
+    /// `Environment::enable_chunk_streaming`), while only an existing Entity
+    /// can be killed or relocated (if it has a location), including the
+    /// entities inserted by the previous edits of the same batch.
+    ///
+    /// Returns the value returned by the given function, or an error if any
+    /// of the edits is not valid, in which case none of the edits is applied
+    /// and the Environment is left unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((10, 10));
+    /// env.insert(QuickEntity::new('a').id(0).at((1, 1)));
+    ///
+    /// env.apply(|editor| {
+    ///     editor.insert(QuickEntity::new('b').id(1).at((2, 2)));
+    ///     editor.kill(0);
+    ///     editor.relocate(1, (3, 3));
+    /// })
+    /// .unwrap();
+    /// assert_eq!(env.count(), 1);
+    /// assert_eq!(env.entities_at((3, 3)).count(), 1);
+    ///
+    /// // the batch is rolled back since the Entity 0 no longer exists
+    /// let result = env.apply(|editor| {
+    ///     editor.kill(1);
+    ///     editor.relocate(0, (4, 4));
+    /// });
+    /// assert!(result.is_err());
+    /// assert_eq!(env.entities_at((3, 3)).count(), 1);
+    ///
+    /// // an Entity with a scope must have a location
+    /// let result = env.apply(|editor| {
+    ///     editor.insert(QuickEntity::new('c').id(2).scope(1));
+    /// });
+    /// assert!(result.is_err());
+    /// assert_eq!(env.count(), 1);
+    ///
+    /// // and the IDs derived for a rejected batch are not consumed
+    /// let mut env = Environment::<char>::new((10, 10));
+    /// let result = env.apply(|editor| {
+    ///     editor.insert(QuickEntity::new('a').at((5, 5)));
+    ///     editor.kill(7);
+    /// });
+    /// assert!(result.is_err());
+    /// env.insert(QuickEntity::new('b').at((6, 6)));
+    /// assert_eq!(env.entities_at((6, 6)).next().map(|e| e.id()), Some(0));
+    /// ```
+    pub fn apply<R>(
+        &mut self,
+        f: impl FnOnce(&mut Editor<'e, K, C>) -> R,
+    ) -> Result<R, Error> {
+        let mut editor = Editor { edits: Vec::new() };
+        let result = f(&mut editor);
+        // the IDs of the inserted entities are needed to validate the edits,
+        // but they are given back if any of the edits is not valid
+        let next_id = self.next_id;
+        for edit in &mut editor.edits {
+            if let Edit::Insert(entity) = edit {
+                self.assign_id(&mut **entity);
+            }
+        }
+        if let Err(error) = self.validate_edits(&editor.edits) {
+            self.next_id = next_id;
+            return Err(error);
+        }
+
+        // the killed entities are removed all at once, unless an Entity with
+        // the same ID is inserted again by a following edit
+        let mut killed = HashSet::new();
+        for edit in editor.edits {
+            match edit {
+                Edit::Insert(entity) => {
+                    if killed.contains(&entity.id()) {
+                        self.kill_all(std::mem::take(&mut killed));
+                    }
+                    self.insert_recorded(entity);
+                }
+                Edit::Kill(id) => {
+                    killed.insert(id);
+                }
+                Edit::Relocate(id, location) => {
+                    self.relocate(id, location);
+                }
+            }
+        }
+        self.kill_all(killed);
+        Ok(result)
+    }
+
+    /// Removes all the entities with the given IDs from the Environment.
+    fn kill_all(&mut self, killed: HashSet<Id>) {
+        if !killed.is_empty() {
+            self.retain(|entity| !killed.contains(&entity.id()));
+        }
+    }
+
+    /// Validates the given edits in order, against the entities of the
+    /// Environment as they would be after each of the previous edits.
+    fn validate_edits(&self, edits: &[Edit<'e, K, C>]) -> Result<(), Error> {
+        // whether each Entity has a location, by ID
+        let mut located: HashMap<Id, bool> = self
+            .entities
+            .values()
+            .flatten()
+            .map(|entity| (entity.id(), entity.location().is_some()))
+            .collect();
+        for edit in edits {
+            match edit {
+                Edit::Insert(entity) => {
+                    let id = entity.id();
+                    if entity.location().is_none() && entity.scope().is_some() {
+                        return Err(Error::with_message(format!(
+                            "Entity {} has a scope without a location",
+                            id
+                        )));
+                    }
+                    if self.is_evicted(&**entity) {
+                        return Err(Error::with_message(format!(
+                            "Entity {} is located in an evicted chunk",
+                            id
+                        )));
+                    }
+                    if located.insert(id, entity.location().is_some()).is_some()
+                    {
+                        return Err(Error::with_message(format!(
+                            "Entity {} already exists",
+                            id
+                        )));
+                    }
+                }
+                Edit::Kill(id) => {
+                    located.remove(id).ok_or_else(|| {
+                        Error::with_message(format!("Entity {} not found", id))
+                    })?;
+                }
+                Edit::Relocate(id, _) => match located.get(id) {
+                    Some(true) => (),
+                    Some(false) => {
+                        return Err(Error::with_message(format!(
+                            "Entity {} has no location",
+                            id
+                        )));
+                    }
+                    None => {
+                        return Err(Error::with_message(format!(
+                            "Entity {} not found",
+                            id
+                        )));
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,348 @@
+use super::*;
+
+/// The default maximum number of commands that can be scheduled in the
+/// Environment at the same time.
+pub const DEFAULT_EVENT_CAPACITY: usize = 4096;
+
+/// The type of the custom actions that can be scheduled in the Environment.
+#[cfg(not(feature = "parallel"))]
+type CommandFn<'e, K, C> =
+    Box<dyn FnOnce(&mut Environment<'e, K, C>) -> Result<(), Error> + 'e>;
+
+/// The type of the custom actions that can be scheduled in the Environment.
+#[cfg(feature = "parallel")]
+type CommandFn<'e, K, C> = Box<
+    dyn FnOnce(&mut Environment<'e, K, C>) -> Result<(), Error>
+        + Send
+        + Sync
+        + 'e,
+>;
+
+/// A command that can be scheduled to be executed by the Environment at the
+/// start of a future generation (see `Environment::schedule`).
+pub enum EntityCommand<'e, K, C = ()> {
+    /// Inserts the given Entity into the Environment.
+    Insert(Box<EntityTrait<'e, K, C>>),
+    /// Removes all the entities with the given ID from the Environment.
+    Remove(Id),
+    /// Moves the Entity with the given ID to the given location (wrapped around
+    /// the Environment edges).
+    Relocate(Id, Location),
+    /// Calls the given function with the Environment.
+    Run(CommandFn<'e, K, C>),
+}
+
+impl<'e, K, C> EntityCommand<'e, K, C> {
+    /// Constructs a new command that inserts the given Entity into the
+    /// Environment.
+    #[cfg(not(feature = "parallel"))]
+    pub fn insert<E>(entity: E) -> Self
+    where
+        E: Entity<'e, C, Kind = K> + 'e,
+    {
+        Self::Insert(Box::new(entity))
+    }
+
+    /// Constructs a new command that inserts the given Entity into the
+    /// Environment.
+    #[cfg(feature = "parallel")]
+    pub fn insert<E>(entity: E) -> Self
+    where
+        E: Entity<'e, C, Kind = K> + 'e + Send + Sync,
+    {
+        Self::Insert(Box::new(entity))
+    }
+
+    /// Constructs a new command that calls the given function with the
+    /// Environment, whose error (if any) aborts the generation.
+    #[cfg(not(feature = "parallel"))]
+    pub fn run(
+        f: impl FnOnce(&mut Environment<'e, K, C>) -> Result<(), Error> + 'e,
+    ) -> Self {
+        Self::Run(Box::new(f))
+    }
+
+    /// Constructs a new command that calls the given function with the
+    /// Environment, whose error (if any) aborts the generation.
+    #[cfg(feature = "parallel")]
+    pub fn run(
+        f: impl FnOnce(&mut Environment<'e, K, C>) -> Result<(), Error>
+            + Send
+            + Sync
+            + 'e,
+    ) -> Self {
+        Self::Run(Box::new(f))
+    }
+}
+
+impl<'e, K: fmt::Debug, C> fmt::Debug for EntityCommand<'e, K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Insert(entity) => {
+                f.debug_tuple("Insert").field(entity).finish()
+            }
+            Self::Remove(id) => f.debug_tuple("Remove").field(id).finish(),
+            Self::Relocate(id, location) => {
+                f.debug_tuple("Relocate").field(id).field(location).finish()
+            }
+            Self::Run(_) => f.write_str("Run(..)"),
+        }
+    }
+}
+
+/// A command scheduled by an Entity, with the generation it is due at.
+pub type ScheduledCommand<'e, K, C = ()> =
+    (Generation, EntityCommand<'e, K, C>);
+
+/// The bounded queue of the commands scheduled in the Environment, sorted by
+/// the generation they are due at.
+pub(crate) struct Events<'e, K, C> {
+    // the commands of each generation, in the order they were scheduled
+    queue: BTreeMap<Generation, Vec<EntityCommand<'e, K, C>>>,
+    // the total number of commands in the queue
+    len: usize,
+    capacity: usize,
+}
+
+impl<'e, K, C> Default for Events<'e, K, C> {
+    fn default() -> Self {
+        Self {
+            queue: BTreeMap::new(),
+            len: 0,
+            capacity: DEFAULT_EVENT_CAPACITY,
+        }
+    }
+}
+
+impl<'e, K, C> Events<'e, K, C> {
+    /// Pushes the given command due at the given generation.
+    ///
+    /// Returns an error if the queue is full.
+    fn push(
+        &mut self,
+        generation: Generation,
+        command: EntityCommand<'e, K, C>,
+    ) -> Result<(), Error> {
+        if self.len >= self.capacity {
+            return Err(Error::with_message(format!(
+                "Cannot schedule more than {} commands",
+                self.capacity
+            )));
+        }
+        self.queue.entry(generation).or_default().push(command);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Takes all the commands due at or before the given generation, sorted by
+    /// generation and then by the order they were scheduled.
+    fn take_due(
+        &mut self,
+        generation: Generation,
+    ) -> Vec<EntityCommand<'e, K, C>> {
+        let later = match generation.get() {
+            u64::MAX => BTreeMap::new(),
+            _ => self.queue.split_off(&generation.next()),
+        };
+        let due = std::mem::replace(&mut self.queue, later);
+        let commands: Vec<_> = due.into_values().flatten().collect();
+        self.len -= commands.len();
+        commands
+    }
+
+    /// Puts back the given commands due at the given generation, before the
+    /// ones already scheduled for it, regardless of the capacity of the queue.
+    fn requeue(
+        &mut self,
+        generation: Generation,
+        commands: impl IntoIterator<Item = EntityCommand<'e, K, C>>,
+    ) {
+        let queue = self.queue.entry(generation).or_default();
+        let len = queue.len();
+        queue.extend(commands);
+        let requeued = queue.len() - len;
+        queue.rotate_right(requeued);
+        self.len += requeued;
+        if queue.is_empty() {
+            self.queue.remove(&generation);
+        }
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Schedules the given command to be executed at the start of the given
+    /// generation, that is, when moving from such generation to the next one,
+    /// before any of the entities observes the Environment.
+    ///
+    /// The commands due at the same generation are executed in the order they
+    /// were scheduled, and the commands due at a generation that already
+    /// started are executed at the start of the next generation.
+    /// The entities can also schedule commands via `Entity::commands`.
+    ///
+    /// Returns an error if the number of scheduled commands already reached the
+    /// capacity set with `Environment::set_event_capacity`.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((4, 4));
+    /// env.insert(QuickEntity::new('a').id(0).at((0, 0)));
+    /// env.schedule(2, EntityCommand::insert(QuickEntity::new('b').id(1)))
+    ///     .unwrap();
+    /// env.schedule(3, EntityCommand::Remove(0)).unwrap();
+    /// assert_eq!(env.pending_commands(), 2);
+    ///
+    /// env.nextgen().unwrap();
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.count(), 1);
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.count(), 2);
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.count(), 1);
+    /// assert_eq!(env.pending_commands(), 0);
+    /// ```
+    pub fn schedule(
+        &mut self,
+        generation: impl Into<Generation>,
+        command: EntityCommand<'e, K, C>,
+    ) -> Result<(), Error> {
+        self.events.push(generation.into(), command)
+    }
+
+    /// Gets the number of commands scheduled and not yet executed.
+    pub fn pending_commands(&self) -> usize {
+        self.events.len
+    }
+
+    /// Sets the maximum number of commands that can be scheduled at the same
+    /// time, without discarding any of the commands already scheduled.
+    ///
+    /// By default, up to `DEFAULT_EVENT_CAPACITY` commands can be scheduled.
+    pub fn set_event_capacity(&mut self, capacity: usize) {
+        self.events.capacity = capacity;
+    }
+
+    /// Executes the commands due at the current generation (or before).
+    ///
+    /// Returns an error if any of the custom commands returns an error, in
+    /// which case the commands executed so far are not undone, while the
+    /// remaining ones are kept, and executed at the start of the next attempt
+    /// to move to the next generation.
+    pub(super) fn run_due_commands(&mut self) -> Result<(), Error> {
+        if self.events.len == 0 {
+            return Ok(());
+        }
+        let mut commands = self.events.take_due(self.generation).into_iter();
+        while let Some(command) = commands.next() {
+            match command {
+                EntityCommand::Insert(entity) => self.insert_boxed(entity),
+                EntityCommand::Remove(id) => {
+                    self.retain_entities(|entity| entity.id() != id)
+                }
+                EntityCommand::Relocate(id, location) => {
+                    self.relocate_entity(id, location);
+                }
+                EntityCommand::Run(f) => {
+                    if let Err(error) = f(self) {
+                        self.events.requeue(self.generation, commands);
+                        return Err(error);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Collects the commands scheduled by the entities during the current
+    /// generation via `Entity::commands`.
+    ///
+    /// Returns an error if the capacity of the queue is exceeded, in which case
+    /// the commands in excess are discarded.
+    pub(super) fn collect_commands(&mut self) -> Result<(), Error> {
+        let commands: Vec<_> = self
+            .entities
+            .values_mut()
+            .flat_map(|e| e.iter_mut())
+            .flat_map(|e| e.commands())
+            .collect();
+        for (generation, command) in commands {
+            self.events.push(generation, command)?;
+        }
+        Ok(())
+    }
+
+    /// Applies the commands issued by the entities to their neighbors during
+    /// the current generation via `Entity::neighbor_commands`, with the
+    /// ordering and conflict resolution described by `NeighborCommand`.
+    pub(super) fn apply_neighbor_commands(&mut self) {
+        // the commands as (target, issuer, command)
+        let mut commands: Vec<_> = self
+            .entities
+            .values_mut()
+            .flat_map(|e| e.iter_mut())
+            .flat_map(|e| {
+                let issuer = e.id();
+                e.neighbor_commands()
+                    .into_iter()
+                    .map(move |(target, command)| (target, issuer, command))
+            })
+            .collect();
+        if commands.is_empty() {
+            return;
+        }
+        // the sort is stable, so that the commands of the same issuer keep
+        // the order they were issued in
+        commands.sort_by_key(|&(target, issuer, _)| (target, issuer));
+        let mut targets: BTreeMap<Id, Vec<NeighborCommand>> = BTreeMap::new();
+        for (target, _, command) in commands {
+            targets.entry(target).or_default().push(command);
+        }
+
+        let dimension = self.dimension();
+        let mut relocations = Vec::new();
+        for entity in self.entities.values_mut().flat_map(|e| e.iter_mut()) {
+            let Some(commands) = targets.remove(&entity.id()) else {
+                continue;
+            };
+            if commands.iter().any(|c| matches!(c, NeighborCommand::Kill)) {
+                if let Some(lifespan) = entity.lifespan_mut() {
+                    lifespan.clear();
+                }
+                continue;
+            }
+            let mut relocated = false;
+            for command in commands {
+                match command {
+                    NeighborCommand::Kill => unreachable!("Killed entity"),
+                    NeighborCommand::Damage(span) => {
+                        if let Some(lifespan) = entity.lifespan_mut() {
+                            lifespan.shorten_by(span);
+                        }
+                    }
+                    NeighborCommand::Heal(span) => {
+                        if let Some(lifespan) = entity.lifespan_mut() {
+                            lifespan.lengthen_by(span);
+                        }
+                    }
+                    NeighborCommand::SetStateAny(value) => {
+                        if let Some(state) = entity.state_mut() {
+                            state.set_any(value);
+                        }
+                    }
+                    NeighborCommand::Relocate(offset) if !relocated => {
+                        relocated = true;
+                        if let Some(mut location) = entity.location() {
+                            location.translate(offset, dimension);
+                            relocations.push((entity.id(), location));
+                        }
+                    }
+                    NeighborCommand::Relocate(_) => (),
+                }
+            }
+        }
+        for (id, location) in relocations {
+            self.relocate_entity(id, location);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::ops::{BitOr, BitOrAssign};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+
+use super::*;
+
+/// The kind of a change of the occupancy of the Environment, that can be
+/// subscribed to with `Environment::subscribe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    /// An Entity was inserted into the Environment.
+    Spawn,
+    /// An Entity was removed from the Environment.
+    Death,
+}
+
+impl EventKind {
+    /// Gets the bit of the EventKind in a set of EventKinds.
+    fn bit(self) -> u8 {
+        match self {
+            Self::Spawn => 1,
+            Self::Death => 1 << 1,
+        }
+    }
+}
+
+impl BitOr for EventKind {
+    type Output = EventKinds;
+
+    fn bitor(self, other: Self) -> EventKinds {
+        EventKinds::from(self) | EventKinds::from(other)
+    }
+}
+
+/// A set of EventKinds, usually built as `EventKind::Spawn | EventKind::Death`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventKinds(u8);
+
+impl EventKinds {
+    /// The set without any EventKind.
+    pub const EMPTY: Self = Self(0);
+
+    /// The set of all the EventKinds.
+    pub const ALL: Self = Self(0b11);
+
+    /// Returns true only if the set contains the given EventKind.
+    pub fn contains(self, kind: EventKind) -> bool {
+        self.0 & kind.bit() != 0
+    }
+
+    /// Returns true only if the set has no EventKinds.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl From<EventKind> for EventKinds {
+    fn from(kind: EventKind) -> Self {
+        Self(kind.bit())
+    }
+}
+
+impl BitOr for EventKinds {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitOr<EventKind> for EventKinds {
+    type Output = Self;
+
+    fn bitor(self, other: EventKind) -> Self {
+        self | Self::from(other)
+    }
+}
+
+impl BitOrAssign for EventKinds {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+/// A change of the occupancy of the Environment, delivered to the subscribers
+/// of its EventKind (see `Environment::subscribe`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OccupancyEvent<K> {
+    /// The kind of the change.
+    pub event: EventKind,
+    /// The generation the change happened in.
+    pub generation: Generation,
+    /// The ID of the Entity that was inserted or removed.
+    pub id: Id,
+    /// The Kind of the Entity that was inserted or removed.
+    pub kind: K,
+    /// The location of the Entity when it was inserted or removed, if any.
+    pub location: Option<Location>,
+}
+
+/// A subscriber to the changes of the occupancy of the Environment.
+struct Subscriber<K> {
+    kinds: EventKinds,
+    sender: SyncSender<OccupancyEvent<K>>,
+    // the events not yet delivered
+    pending: Vec<OccupancyEvent<K>>,
+}
+
+/// The subscribers to the changes of the occupancy of the Environment.
+pub(super) struct Feeds<K> {
+    subscribers: Vec<Subscriber<K>>,
+    // the union of the EventKinds of all the subscribers
+    kinds: EventKinds,
+}
+
+impl<K> Default for Feeds<K> {
+    fn default() -> Self {
+        Self {
+            subscribers: Vec::new(),
+            kinds: EventKinds::EMPTY,
+        }
+    }
+}
+
+impl<K> Feeds<K> {
+    /// Buffers the given change of the given Entity for all the subscribers
+    /// of its EventKind.
+    pub(super) fn record<C>(
+        &mut self,
+        event: EventKind,
+        generation: Generation,
+        entity: &EntityTrait<'_, K, C>,
+    ) {
+        if !self.kinds.contains(event) {
+            return;
+        }
+        for subscriber in &mut self.subscribers {
+            if subscriber.kinds.contains(event) {
+                subscriber.pending.push(OccupancyEvent {
+                    event,
+                    generation,
+                    id: entity.id(),
+                    kind: entity.kind(),
+                    location: entity.location(),
+                });
+            }
+        }
+    }
+
+    /// Sends the buffered events to their subscribers, discarding the ones
+    /// that do not fit in their channel, and drops the subscribers whose
+    /// Receiver was dropped.
+    fn deliver(&mut self) {
+        self.subscribers.retain_mut(|subscriber| {
+            for event in subscriber.pending.drain(..) {
+                match subscriber.sender.try_send(event) {
+                    Ok(()) | Err(TrySendError::Full(_)) => (),
+                    Err(TrySendError::Disconnected(_)) => return false,
+                }
+            }
+            true
+        });
+        self.kinds = self
+            .subscribers
+            .iter()
+            .fold(EventKinds::EMPTY, |kinds, s| kinds | s.kinds);
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Subscribes to the given kinds of changes of the occupancy of the
+    /// Environment, such as the insertion and removal of its entities, so
+    /// that systems like sound effects or network synchronization can react
+    /// to them without owning the game loop.
+    ///
+    /// The changes are buffered as they happen (including the ones outside
+    /// of the generation steps, such as `Environment::insert`), and are sent
+    /// at the end of each generation, in order, through a bounded channel
+    /// that holds up to the given number of events. The events that do not
+    /// fit in the channel are discarded, so that a slow subscriber never
+    /// blocks the simulation, and the subscription ends as soon as the
+    /// returned Receiver is dropped.
+    ///
+    /// # Panics
+    /// Panics if the capacity is zero.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((4, 4));
+    /// let events = env.subscribe(EventKind::Spawn | EventKind::Death, 16);
+    /// env.insert(QuickEntity::new('a').id(0).at((1, 2)));
+    /// env.insert(QuickEntity::new('b').id(1).lifespan(0));
+    /// assert!(events.try_recv().is_err());
+    ///
+    /// env.nextgen().unwrap();
+    /// let events: Vec<_> = events.try_iter().collect();
+    /// assert_eq!(events.len(), 3);
+    /// assert_eq!(events[0].event, EventKind::Spawn);
+    /// assert_eq!(events[0].location, Some(Location { x: 1, y: 2 }));
+    /// assert_eq!((events[2].event, events[2].kind), (EventKind::Death, 'b'));
+    /// ```
+    pub fn subscribe(
+        &mut self,
+        kinds: impl Into<EventKinds>,
+        capacity: usize,
+    ) -> Receiver<OccupancyEvent<K>> {
+        assert!(capacity > 0, "The capacity cannot be zero");
+        let kinds = kinds.into();
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        self.feeds.subscribers.push(Subscriber {
+            kinds,
+            sender,
+            pending: Vec::new(),
+        });
+        self.feeds.kinds |= kinds;
+        receiver
+    }
+
+    /// Sends the changes of the occupancy buffered since the end of the last
+    /// generation to their subscribers.
+    pub(super) fn deliver_events(&mut self) {
+        self.feeds.deliver();
+    }
+}
@@ -0,0 +1,124 @@
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+use super::*;
+
+/// The number of a generation of the Environment, counted from the first
+/// generation 0.
+///
+/// A Generation identifies a point in the life of the Environment, while a
+/// plain `u64` is used for the number of generations between two points (such
+/// as the age of an Entity, or the length of its lifespan). Adding a number of
+/// generations to a Generation gives another Generation, and the difference
+/// between two generations is a number of generations. As for the generation
+/// counter of the Environment, the arithmetic wraps around on overflow.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let generation = Generation::new(10);
+/// assert_eq!(generation + 5, Generation::new(15));
+/// assert_eq!(generation - Generation::new(4), 6);
+/// assert!(generation.is_multiple_of(5));
+/// assert_eq!(generation.to_string(), "10");
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Generation(u64);
+
+impl Generation {
+    /// The first generation of an Environment.
+    pub const ZERO: Self = Self(0);
+
+    /// Constructs a new Generation with the given number.
+    pub const fn new(generation: u64) -> Self {
+        Self(generation)
+    }
+
+    /// Gets the number of the Generation.
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+
+    /// Gets the Generation that follows this one.
+    pub const fn next(self) -> Self {
+        Self(self.0.wrapping_add(1))
+    }
+
+    /// Gets the number of generations elapsed since the given earlier
+    /// Generation, or 0 if it follows this one.
+    pub fn since(self, earlier: Self) -> u64 {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    /// Returns true only if the number of the Generation is a multiple of the
+    /// given number. The only multiple of 0 is the generation 0.
+    pub const fn is_multiple_of(self, n: u64) -> bool {
+        self.0.is_multiple_of(n)
+    }
+}
+
+impl fmt::Display for Generation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<u64> for Generation {
+    fn from(generation: u64) -> Self {
+        Self(generation)
+    }
+}
+
+impl From<Generation> for u64 {
+    fn from(generation: Generation) -> Self {
+        generation.0
+    }
+}
+
+impl PartialEq<u64> for Generation {
+    fn eq(&self, other: &u64) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialOrd<u64> for Generation {
+    fn partial_cmp(&self, other: &u64) -> Option<std::cmp::Ordering> {
+        self.0.partial_cmp(other)
+    }
+}
+
+impl Add<u64> for Generation {
+    type Output = Self;
+
+    fn add(self, generations: u64) -> Self {
+        Self(self.0.wrapping_add(generations))
+    }
+}
+
+impl AddAssign<u64> for Generation {
+    fn add_assign(&mut self, generations: u64) {
+        *self = *self + generations;
+    }
+}
+
+impl Sub<u64> for Generation {
+    type Output = Self;
+
+    fn sub(self, generations: u64) -> Self {
+        Self(self.0.wrapping_sub(generations))
+    }
+}
+
+impl SubAssign<u64> for Generation {
+    fn sub_assign(&mut self, generations: u64) {
+        *self = *self - generations;
+    }
+}
+
+impl Sub for Generation {
+    type Output = u64;
+
+    fn sub(self, earlier: Self) -> u64 {
+        self.0.wrapping_sub(earlier.0)
+    }
+}
@@ -0,0 +1,393 @@
+//! This module contains the optional GPU compute backend, that allows to run
+//! the update of homogeneous automata (such as Life-like automata), where all
+//! the tiles follow the same rule, on the GPU via wgpu compute shaders.
+//!
+//! The state of each tile is encoded as a single `u32`, and the rule that
+//! computes the next state of a tile from the states of the previous
+//! generation is described by a RuleKernel, either written directly in WGSL,
+//! or compiled into a lookup table from a closure.
+
+use std::borrow::Cow;
+
+use super::*;
+
+/// The number of invocations along each axis of a compute workgroup.
+const WORKGROUP_SIDE: u32 = 8;
+
+/// The WGSL code shared by all the kernels, that declares the bindings and
+/// the `cell(x, y)` helper, used to read the state of the tile at the given
+/// location in the previous generation (wrapping around the grid edges).
+const PRELUDE: &str = "
+struct Params {
+    width: u32,
+    height: u32,
+    _pad0: u32,
+    _pad1: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> input: array<u32>;
+@group(0) @binding(2) var<storage, read_write> output: array<u32>;
+
+fn cell(x: i32, y: i32) -> u32 {
+    let w = i32(params.width);
+    let h = i32(params.height);
+    let cx = ((x % w) + w) % w;
+    let cy = ((y % h) + h) % h;
+    return input[u32(cy * w + cx)];
+}
+";
+
+/// The WGSL entry point of all the kernels, that calls the `rule(x, y)`
+/// function for each tile of the grid.
+const ENTRY_POINT: &str = "
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.width || id.y >= params.height) {
+        return;
+    }
+    output[id.y * params.width + id.x] = rule(i32(id.x), i32(id.y));
+}
+";
+
+/// The rule that computes the next state of each tile of a GpuGrid, compiled
+/// into a WGSL compute shader.
+#[derive(Debug, Clone)]
+pub struct RuleKernel {
+    source: String,
+}
+
+impl RuleKernel {
+    /// Constructs a new RuleKernel from the given WGSL code.
+    ///
+    /// The code must define the function `fn rule(x: i32, y: i32) -> u32`,
+    /// that returns the next state of the tile at the given location, and
+    /// that can read the state of any tile in the previous generation via the
+    /// `fn cell(x: i32, y: i32) -> u32` function (out of bounds locations are
+    /// wrapped around the grid edges).
+    pub fn wgsl(rule: &str) -> Self {
+        Self {
+            source: format!("{}\n{}\n{}", PRELUDE, rule, ENTRY_POINT),
+        }
+    }
+
+    /// Constructs a new outer totalistic RuleKernel for automata with the
+    /// given number of states, compiling the given closure into a lookup
+    /// table.
+    ///
+    /// The closure is given the state of a tile and the number of its (Moore)
+    /// neighbors with a state different from 0, and it returns the next state
+    /// of the tile. States greater than or equal to the given number of states
+    /// are clamped to the last state.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// // Conway's Game of Life (B3/S23)
+    /// let life = RuleKernel::lookup(2, |state, neighbors| {
+    ///     (neighbors == 3 || (state == 1 && neighbors == 2)) as u32
+    /// });
+    /// assert!(life.source().contains("fn rule"));
+    /// ```
+    pub fn lookup(states: u32, f: impl Fn(u32, u32) -> u32) -> Self {
+        let states = states.max(1);
+        let table: Vec<String> = (0..states)
+            .flat_map(|state| (0..=8).map(move |n| (state, n)))
+            .map(|(state, neighbors)| format!("{}u", f(state, neighbors)))
+            .collect();
+        let rule = format!(
+            "
+var<private> TABLE: array<u32, {len}> = array<u32, {len}>({table});
+
+fn rule(x: i32, y: i32) -> u32 {{
+    var live = 0u;
+    for (var dy = -1; dy <= 1; dy++) {{
+        for (var dx = -1; dx <= 1; dx++) {{
+            if ((dx != 0 || dy != 0) && cell(x + dx, y + dy) != 0u) {{
+                live += 1u;
+            }}
+        }}
+    }}
+    let state = min(cell(x, y), {last}u);
+    return TABLE[state * 9u + live];
+}}
+",
+            len = table.len(),
+            table = table.join(", "),
+            last = states - 1,
+        );
+        Self::wgsl(&rule)
+    }
+
+    /// Gets the complete WGSL source code of the compute shader.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+/// A grid of tile states stored on the GPU, updated generation after
+/// generation by a RuleKernel.
+///
+/// The states are stored in two buffers used in turn as the input and the
+/// output of the kernel, so that multiple generations can be computed without
+/// any copy between the GPU and the CPU.
+#[derive(Debug)]
+pub struct GpuGrid {
+    dimension: Dimension,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    // the bind groups that read from the buffer with the same index, and write
+    // to the other one
+    bind_groups: [wgpu::BindGroup; 2],
+    cells: [wgpu::Buffer; 2],
+    staging: wgpu::Buffer,
+    // the index of the buffer that contains the current states
+    current: usize,
+}
+
+impl GpuGrid {
+    /// Constructs a new GpuGrid of the given dimension, where all the tiles
+    /// have state 0, that will be updated by the given RuleKernel.
+    ///
+    /// Returns an error if no GPU adapter is available, or if the kernel
+    /// cannot be compiled.
+    pub fn new(
+        dimension: impl Into<Dimension>,
+        kernel: &RuleKernel,
+    ) -> Result<Self, Error> {
+        let dimension = dimension.into();
+        if dimension.is_empty() {
+            return Err(Error::with_message("Invalid GPU grid dimension"));
+        }
+
+        let instance = wgpu::Instance::new(
+            wgpu::InstanceDescriptor::new_without_display_handle(),
+        );
+        let adapter = pollster::block_on(
+            instance.request_adapter(&wgpu::RequestAdapterOptions::default()),
+        )
+        .map_err(Error::with_message)?;
+        let (device, queue) = pollster::block_on(
+            adapter.request_device(&wgpu::DeviceDescriptor::default()),
+        )
+        .map_err(Error::with_message)?;
+
+        let scope = device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module =
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("semeion::RuleKernel"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(
+                    kernel.source(),
+                )),
+            });
+        let pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("semeion::RuleKernel"),
+                layout: None,
+                module: &module,
+                entry_point: Some("main"),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+        if let Some(err) = pollster::block_on(scope.pop()) {
+            return Err(Error::with_message(err));
+        }
+
+        let size = (dimension.len() * std::mem::size_of::<u32>()) as u64;
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("semeion::GpuGrid::params"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let params_bytes: Vec<u8> =
+            [dimension.x as u32, dimension.y as u32, 0, 0]
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect();
+        queue.write_buffer(&params, 0, &params_bytes);
+
+        let cells = [0, 1].map(|_| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("semeion::GpuGrid::cells"),
+                size,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            })
+        });
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("semeion::GpuGrid::staging"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let layout = pipeline.get_bind_group_layout(0);
+        let bind_groups = [0, 1].map(|input| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("semeion::GpuGrid"),
+                layout: &layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: cells[input].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: cells[1 - input].as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        Ok(Self {
+            dimension,
+            device,
+            queue,
+            pipeline,
+            bind_groups,
+            cells,
+            staging,
+            current: 0,
+        })
+    }
+
+    /// Gets the Dimension of the grid.
+    pub fn dimension(&self) -> Dimension {
+        self.dimension
+    }
+
+    /// Writes the given states into the grid, where the states are given for
+    /// each tile from the top-left to the bottom-right corner.
+    ///
+    /// Returns an error if the number of states does not match the number of
+    /// tiles of the grid.
+    pub fn write(&mut self, states: &[u32]) -> Result<(), Error> {
+        if states.len() != self.dimension.len() {
+            return Err(Error::with_message(
+                "Invalid number of GPU grid states",
+            ));
+        }
+        let bytes: Vec<u8> = states
+            .iter()
+            .flat_map(|state| state.to_le_bytes())
+            .collect();
+        self.queue
+            .write_buffer(&self.cells[self.current], 0, &bytes);
+        Ok(())
+    }
+
+    /// Computes the given number of generations on the GPU.
+    pub fn step(&mut self, generations: u32) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        let x = (self.dimension.x as u32).div_ceil(WORKGROUP_SIDE);
+        let y = (self.dimension.y as u32).div_ceil(WORKGROUP_SIDE);
+        for _ in 0..generations {
+            let mut pass = encoder
+                .begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+            pass.dispatch_workgroups(x, y, 1);
+            drop(pass);
+            self.current = 1 - self.current;
+        }
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Reads the current states of the grid, for each tile from the top-left
+    /// to the bottom-right corner.
+    ///
+    /// Returns an error if the states cannot be copied back from the GPU.
+    pub fn read(&self) -> Result<Vec<u32>, Error> {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_buffer_to_buffer(
+            &self.cells[self.current],
+            0,
+            &self.staging,
+            0,
+            None,
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.staging
+            .map_async(wgpu::MapMode::Read, .., move |result| {
+                let _ = sender.send(result);
+            });
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(Error::with_message)?;
+        receiver
+            .recv()
+            .map_err(Error::with_message)?
+            .map_err(Error::with_message)?;
+
+        let states = self
+            .staging
+            .get_mapped_range(..)
+            .chunks_exact(std::mem::size_of::<u32>())
+            .map(|bytes| {
+                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+            })
+            .collect();
+        self.staging.unmap();
+        Ok(states)
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Moves forward to the next generation by running the RuleKernel of the
+    /// given GpuGrid, rather than by dispatching the events to the entities.
+    /// Returns the next generation step number.
+    ///
+    /// The state of each tile is extracted from the entity located in it (or 0
+    /// if the tile is empty), and after the kernel has been run on the GPU,
+    /// the new state of each tile is given back to the entities located in it.
+    /// No entity is inserted or removed from the Environment.
+    ///
+    /// Returns an error if the dimension of the grid does not match the
+    /// dimension of the Environment, or if the GPU fails.
+    pub fn step_gpu(
+        &mut self,
+        grid: &mut GpuGrid,
+        extract: impl Fn(&EntityTrait<'e, K, C>) -> u32,
+        mut apply: impl FnMut(&mut EntityTrait<'e, K, C>, u32),
+    ) -> Result<Generation, Error> {
+        let dimension = self.dimension();
+        if grid.dimension() != dimension {
+            return Err(Error::with_message("Mismatching GPU grid dimension"));
+        }
+
+        let mut states = vec![0; dimension.len()];
+        for entity in self.entities() {
+            if let Some(location) = entity.location() {
+                states[location.one_dimensional(dimension)] = extract(entity);
+            }
+        }
+
+        grid.write(&states)?;
+        grid.step(1);
+        let states = grid.read()?;
+
+        for entity in self.entities_mut() {
+            if let Some(location) = entity.location() {
+                apply(entity, states[location.one_dimensional(dimension)]);
+            }
+        }
+
+        self.generation = self.generation.next();
+        Ok(self.generation)
+    }
+}
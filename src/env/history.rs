@@ -0,0 +1,588 @@
+use std::any::Any;
+use std::collections::{BTreeMap, HashSet};
+use std::marker::PhantomData;
+
+use super::*;
+
+/// The type of the Codec used to record the history of the Environment.
+#[cfg(not(feature = "parallel"))]
+pub(super) type HistoryCodec<'e, K, C> = Box<dyn Codec<'e, K, C> + 'e>;
+
+/// The type of the Codec used to record the history of the Environment.
+#[cfg(feature = "parallel")]
+pub(super) type HistoryCodec<'e, K, C> =
+    Box<dyn Codec<'e, K, C> + Send + Sync + 'e>;
+
+/// The type of the function that evaluates a watch expression on an Entity.
+#[cfg(not(feature = "parallel"))]
+type WatchExpression<'e, K, C> =
+    Box<dyn Fn(&EntityTrait<'e, K, C>) -> AnyValue + 'e>;
+
+/// The type of the function that evaluates a watch expression on an Entity.
+#[cfg(feature = "parallel")]
+type WatchExpression<'e, K, C> =
+    Box<dyn Fn(&EntityTrait<'e, K, C>) -> AnyValue + Send + Sync + 'e>;
+
+/// The handle of an expression watched on an Entity, whose values can be
+/// retrieved with `Environment::watch_series`.
+pub struct WatchId<T> {
+    index: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> fmt::Debug for WatchId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WatchId").field(&self.index).finish()
+    }
+}
+
+impl<T> Clone for WatchId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for WatchId<T> {}
+
+/// An expression watched on an Entity, with its value in each generation.
+struct Watch<'e, K, C> {
+    id: Id,
+    expression: WatchExpression<'e, K, C>,
+    values: BTreeMap<Generation, AnyValue>,
+}
+
+/// The state of the Environment at the beginning of a generation, from which
+/// the Environment can be restored.
+pub(super) struct Checkpoint {
+    // the entities, encoded with the Codec of the history
+    snapshot: Vec<u8>,
+    // the cells of the rules, in the order of their Kind
+    rules: Vec<RuleBoard>,
+    // the generation each Entity was inserted in
+    births: HashMap<Id, Generation>,
+}
+
+/// The history of the Environment, made of the snapshots taken every fixed
+/// number of generations, from which any generation can be reached again by
+/// replaying the generations that followed the closest snapshot.
+pub(crate) struct History<'e, K, C> {
+    codec: HistoryCodec<'e, K, C>,
+    // the number of generations between two consecutive snapshots
+    interval: u64,
+    // the snapshots of the Environment, by generation
+    snapshots: BTreeMap<Generation, Checkpoint>,
+    // the expressions watched on the entities
+    watches: Vec<Watch<'e, K, C>>,
+}
+
+impl<'e, K, C> fmt::Debug for History<'e, K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("History")
+            .field("interval", &self.interval)
+            .field("snapshots", &self.snapshots.len())
+            .field("watches", &self.watches.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Enables the recording of the history of the Environment, taking a
+    /// snapshot of its entities, encoded with the given Codec, every given
+    /// number of generations (starting from the current one), so that any of
+    /// the recorded generations can be reached again with `Environment::seek`.
+    ///
+    /// Any history previously recorded is discarded. An interval of 0 is
+    /// treated as an interval of 1.
+    ///
+    /// Returns an error if the snapshot of the current generation cannot be
+    /// taken, in which case the history is not enabled.
+    #[cfg(not(feature = "parallel"))]
+    pub fn enable_history(
+        &mut self,
+        codec: impl Codec<'e, K, C> + 'e,
+        interval: u64,
+    ) -> Result<(), Error> {
+        self.start_history(Box::new(codec), interval)
+    }
+
+    /// Enables the recording of the history of the Environment, taking a
+    /// snapshot of its entities, encoded with the given Codec, every given
+    /// number of generations (starting from the current one), so that any of
+    /// the recorded generations can be reached again with `Environment::seek`.
+    ///
+    /// Any history previously recorded is discarded. An interval of 0 is
+    /// treated as an interval of 1.
+    ///
+    /// Returns an error if the snapshot of the current generation cannot be
+    /// taken, in which case the history is not enabled.
+    #[cfg(feature = "parallel")]
+    pub fn enable_history(
+        &mut self,
+        codec: impl Codec<'e, K, C> + Send + Sync + 'e,
+        interval: u64,
+    ) -> Result<(), Error> {
+        self.start_history(Box::new(codec), interval)
+    }
+
+    /// Disables the recording of the history of the Environment, discarding
+    /// all the recorded snapshots and watches.
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    /// Gets the range of generations that can be reached with
+    /// `Environment::seek` without simulating new generations, that is from
+    /// the oldest recorded snapshot to the most recent generation reached
+    /// since, or None if the history is not enabled.
+    pub fn history_range(
+        &self,
+    ) -> Option<std::ops::RangeInclusive<Generation>> {
+        let history = self.history.as_ref()?;
+        let (&first, _) = history.snapshots.first_key_value()?;
+        let (&last, _) = history.snapshots.last_key_value()?;
+        Some(first..=last.max(self.generation))
+    }
+
+    /// Moves the Environment to the given generation, and returns it.
+    ///
+    /// If the generation precedes the current one, the Environment is restored
+    /// from the closest recorded snapshot that precedes it, and the following
+    /// generations are replayed with `Environment::nextgen`, so that reaching
+    /// any generation takes at most as many generations as the history
+    /// interval. Moving forwards simply replays the generations in between,
+    /// unless a closer snapshot was recorded.
+    ///
+    /// Only the entities (with the generation they were inserted in) and the
+    /// cells of the rules are restored from the snapshots, while all the other
+    /// properties of the Environment (such as its resources and the data
+    /// attached to its tiles) are left unchanged, and the simulation is
+    /// expected to be deterministic for the replayed generations to match the
+    /// recorded ones. The components and columns of the entities are not
+    /// recorded: they are kept as they are for the restored entities whose ID
+    /// belongs to an Entity of the Environment before the seek, and dropped for
+    /// all the other entities. The entities replaced by the restored ones are
+    /// neither reported as dead nor as born, to the statistics or to the feeds.
+    ///
+    /// Returns an error if the history is not enabled, if the generation
+    /// precedes the oldest recorded snapshot, or if any of the replayed
+    /// generations fails.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// struct Walker(Location);
+    ///
+    /// impl<'e> Entity<'e> for Walker {
+    ///     type Kind = u8;
+    ///
+    ///     fn id(&self) -> Id {
+    ///         0
+    ///     }
+    ///
+    ///     fn kind(&self) -> Self::Kind {
+    ///         0
+    ///     }
+    ///
+    ///     fn location(&self) -> Option<Location> {
+    ///         Some(self.0)
+    ///     }
+    ///
+    ///     fn react(
+    ///         &mut self,
+    ///         _: Option<Neighborhood<'_, 'e, Self::Kind>>,
+    ///     ) -> Result<(), Error> {
+    ///         self.0.x = (self.0.x + 1) % 10;
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// struct WalkerCodec;
+    ///
+    /// impl<'e> Codec<'e, u8> for WalkerCodec {
+    ///     fn encode_kind(&self, kind: &u8) -> Result<Vec<u8>, Error> {
+    ///         Ok(vec![*kind])
+    ///     }
+    ///
+    ///     fn decode_kind(&self, bytes: &[u8]) -> Result<u8, Error> {
+    ///         bytes.first().copied().ok_or(Error::Unknown)
+    ///     }
+    ///
+    ///     fn encode(
+    ///         &self,
+    ///         entity: &EntityTrait<'e, u8>,
+    ///     ) -> Result<Vec<u8>, Error> {
+    ///         Ok(vec![entity.location().ok_or(Error::Unknown)?.x as u8])
+    ///     }
+    ///
+    ///     fn decode(
+    ///         &self,
+    ///         _kind: &u8,
+    ///         blob: &[u8],
+    ///     ) -> Result<Box<EntityTrait<'e, u8>>, Error> {
+    ///         let x = *blob.first().ok_or(Error::Unknown)? as i32;
+    ///         Ok(Box::new(Walker(Location { x, y: 0 })))
+    ///     }
+    /// }
+    ///
+    /// let mut env = Environment::new((10, 1));
+    /// env.insert(Walker(Location::origin()));
+    /// env.attach(0, "walker");
+    /// // a rule whose cells all die after one generation
+    /// env.attach_rule(Rule::parse("B/S").unwrap(), 1);
+    /// env.set_rule_cell(&1, Location::origin(), true);
+    /// env.enable_history(WalkerCodec, 4).unwrap();
+    /// for _ in 0..9 {
+    ///     env.nextgen().unwrap();
+    /// }
+    /// assert_eq!(env.rule_population(&1), 0);
+    ///
+    /// assert_eq!(env.seek(2).unwrap(), 2);
+    /// assert_eq!(env.entities_at((2, 0)).count(), 1);
+    /// assert_eq!(env.seek(7).unwrap(), 7);
+    /// assert_eq!(env.entities_at((7, 0)).count(), 1);
+    /// assert_eq!(env.age(0), Some(7));
+    /// assert_eq!(env.seek(0).unwrap(), 0);
+    /// assert_eq!(env.rule_population(&1), 1);
+    /// // the components of the restored entities are kept by ID
+    /// assert_eq!(env.component::<&str>(0), Some(&"walker"));
+    /// ```
+    pub fn seek(
+        &mut self,
+        generation: impl Into<Generation>,
+    ) -> Result<Generation, Error>
+    where
+        K: ParallelKind,
+    {
+        let generation = generation.into();
+        let history = self
+            .history
+            .as_ref()
+            .ok_or_else(|| Error::with_message("History not enabled"))?;
+        let (&start, checkpoint) = history
+            .snapshots
+            .range(..=generation)
+            .next_back()
+            .ok_or_else(|| {
+                Error::with_message("Generation precedes the history")
+            })?;
+
+        // restore the closest snapshot only if it allows to skip any of the
+        // generations that would be otherwise replayed
+        if generation < self.generation || start > self.generation {
+            let restored = Self::load_from(
+                checkpoint.snapshot.as_slice(),
+                &*history.codec,
+            )?;
+            let rules = checkpoint.rules.clone();
+            let births = checkpoint.births.clone();
+            self.restore(restored);
+            self.restore_rules(rules);
+            self.births = births;
+        }
+        while self.generation < generation {
+            self.nextgen()?;
+        }
+        Ok(self.generation)
+    }
+
+    /// Watches the value of the given expression on the Entity with the given
+    /// ID, recording it for the current generation and for each following one
+    /// (including the ones replayed by `Environment::seek`), so that it is
+    /// possible to trace when exactly the Entity went wrong.
+    ///
+    /// The generations in which the Entity does not exist are not recorded.
+    /// The values are discarded together with the history.
+    ///
+    /// Returns an error if the history is not enabled.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// // an Entity whose lifespan is shortened by one every generation
+    /// fn aging<'e>(lifespan: u64) -> QuickEntity<'e, char, ()> {
+    ///     let entity = QuickEntity::new('a').id(0).at((0, 0));
+    ///     entity.lifespan(lifespan).on_react(|e, _| {
+    ///         e.lifespan_mut().unwrap().shorten();
+    ///         Ok(())
+    ///     })
+    /// }
+    ///
+    /// struct QuickCodec;
+    ///
+    /// impl<'e> Codec<'e, char> for QuickCodec {
+    ///     fn encode_kind(&self, kind: &char) -> Result<Vec<u8>, Error> {
+    ///         Ok(vec![*kind as u8])
+    ///     }
+    ///
+    ///     fn decode_kind(&self, bytes: &[u8]) -> Result<char, Error> {
+    ///         bytes.first().map(|&b| b as char).ok_or(Error::Unknown)
+    ///     }
+    ///
+    ///     fn encode(
+    ///         &self,
+    ///         entity: &EntityTrait<'e, char>,
+    ///     ) -> Result<Vec<u8>, Error> {
+    ///         let lifespan = entity.lifespan().and_then(Lifespan::length);
+    ///         Ok(vec![lifespan.ok_or(Error::Unknown)? as u8])
+    ///     }
+    ///
+    ///     fn decode(
+    ///         &self,
+    ///         _kind: &char,
+    ///         blob: &[u8],
+    ///     ) -> Result<Box<EntityTrait<'e, char>>, Error> {
+    ///         Ok(aging(*blob.first().ok_or(Error::Unknown)? as u64).boxed())
+    ///     }
+    /// }
+    ///
+    /// let mut env = Environment::new((3, 3));
+    /// env.insert(aging(3));
+    /// env.enable_history(QuickCodec, 1).unwrap();
+    /// let watch = env
+    ///     .watch(0, |e| e.lifespan().and_then(Lifespan::length))
+    ///     .unwrap();
+    /// for _ in 0..2 {
+    ///     env.nextgen().unwrap();
+    /// }
+    /// env.seek(1).unwrap();
+    /// env.nextgen().unwrap();
+    ///
+    /// let series: Vec<_> = env
+    ///     .watch_series(watch)
+    ///     .unwrap()
+    ///     .map(|(generation, lifespan)| (generation.get(), *lifespan))
+    ///     .collect();
+    /// assert_eq!(series, [(0, Some(3)), (1, Some(2)), (2, Some(1))]);
+    /// ```
+    #[cfg(not(feature = "parallel"))]
+    pub fn watch<T: Any>(
+        &mut self,
+        id: Id,
+        expression: impl Fn(&EntityTrait<'e, K, C>) -> T + 'e,
+    ) -> Result<WatchId<T>, Error> {
+        self.start_watch(id, Box::new(move |e| Box::new(expression(e))))
+    }
+
+    /// Watches the value of the given expression on the Entity with the given
+    /// ID, recording it for the current generation and for each following one
+    /// (including the ones replayed by `Environment::seek`), so that it is
+    /// possible to trace when exactly the Entity went wrong.
+    ///
+    /// The generations in which the Entity does not exist are not recorded.
+    /// The values are discarded together with the history.
+    ///
+    /// Returns an error if the history is not enabled.
+    #[cfg(feature = "parallel")]
+    pub fn watch<T: Any + Send + Sync>(
+        &mut self,
+        id: Id,
+        expression: impl Fn(&EntityTrait<'e, K, C>) -> T + Send + Sync + 'e,
+    ) -> Result<WatchId<T>, Error> {
+        self.start_watch(id, Box::new(move |e| Box::new(expression(e))))
+    }
+
+    /// Gets an iterator over the values recorded for the given watch, and the
+    /// generations they were recorded in, sorted by generation, or None if the
+    /// history is not enabled (anymore).
+    pub fn watch_series<T: Any>(
+        &self,
+        watch: WatchId<T>,
+    ) -> Option<impl Iterator<Item = (Generation, &T)>> {
+        let watch = self.history.as_ref()?.watches.get(watch.index)?;
+        Some(watch.values.iter().filter_map(|(&generation, value)| {
+            Some((generation, value.downcast_ref()?))
+        }))
+    }
+
+    /// Starts watching the given expression on the Entity with the given ID.
+    fn start_watch<T>(
+        &mut self,
+        id: Id,
+        expression: WatchExpression<'e, K, C>,
+    ) -> Result<WatchId<T>, Error> {
+        let history = self
+            .history
+            .as_mut()
+            .ok_or_else(|| Error::with_message("History not enabled"))?;
+        let index = history.watches.len();
+        history.watches.push(Watch {
+            id,
+            expression,
+            values: BTreeMap::new(),
+        });
+        self.record_watches();
+        Ok(WatchId {
+            index,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Records the value of all the watched expressions in the current
+    /// generation, if the history is enabled.
+    fn record_watches(&mut self) {
+        let Some(history) = self.history.as_mut() else {
+            return;
+        };
+        for watch in &mut history.watches {
+            let entity = self
+                .entities
+                .values()
+                .flatten()
+                .find(|entity| entity.id() == watch.id);
+            if let Some(entity) = entity {
+                let value = (watch.expression)(&**entity);
+                watch.values.insert(self.generation, value);
+            }
+        }
+    }
+
+    /// Takes a checkpoint of the Environment, from which it can be rolled
+    /// back with `Environment::rollback`, or None if the history is not
+    /// enabled.
+    ///
+    /// Returns an error if any of the entities cannot be encoded.
+    pub(super) fn checkpoint(&self) -> Result<Option<Checkpoint>, Error> {
+        match self.history.as_ref() {
+            Some(history) => self.checkpoint_with(&*history.codec).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Takes a checkpoint of the Environment, encoding its entities with the
+    /// given Codec.
+    fn checkpoint_with(
+        &self,
+        codec: &(impl Codec<'e, K, C> + ?Sized),
+    ) -> Result<Checkpoint, Error> {
+        let mut snapshot = Vec::new();
+        self.save_to(&mut snapshot, codec)?;
+        Ok(Checkpoint {
+            snapshot,
+            rules: self.rules.values().cloned().collect(),
+            births: self.births.clone(),
+        })
+    }
+
+    /// Rolls the Environment back to the given checkpoint, by restoring its
+    /// entities and the cells of its rules.
+    ///
+    /// Returns an error if the history is not enabled, or if any of the
+    /// entities cannot be decoded.
+    pub(super) fn rollback(
+        &mut self,
+        checkpoint: Checkpoint,
+    ) -> Result<(), Error> {
+        let history = self
+            .history
+            .as_ref()
+            .ok_or_else(|| Error::with_message("History not enabled"))?;
+        let restored =
+            Self::load_from(checkpoint.snapshot.as_slice(), &*history.codec)?;
+        self.restore(restored);
+        self.restore_rules(checkpoint.rules);
+        self.births = checkpoint.births;
+        Ok(())
+    }
+
+    /// Starts recording the history with the given Codec.
+    fn start_history(
+        &mut self,
+        codec: HistoryCodec<'e, K, C>,
+        interval: u64,
+    ) -> Result<(), Error> {
+        let mut history = History {
+            codec,
+            interval: interval.max(1),
+            snapshots: BTreeMap::new(),
+            watches: Vec::new(),
+        };
+        let checkpoint = self.checkpoint_with(&*history.codec)?;
+        history.snapshots.insert(self.generation, checkpoint);
+        self.history = Some(history);
+        Ok(())
+    }
+
+    /// Records the value of the watched expressions in the current generation,
+    /// and its snapshot if the history is enabled and the generation is a
+    /// multiple of the history interval since the oldest snapshot, and it was
+    /// not already recorded.
+    pub(super) fn record_history(&mut self) -> Result<(), Error> {
+        self.record_watches();
+        let Some(history) = self.history.as_ref() else {
+            return Ok(());
+        };
+        let start = history
+            .snapshots
+            .first_key_value()
+            .map_or(Generation::ZERO, |(&g, _)| g);
+        let elapsed = self.generation - start;
+        if !elapsed.is_multiple_of(history.interval)
+            || history.snapshots.contains_key(&self.generation)
+        {
+            return Ok(());
+        }
+
+        let checkpoint = self.checkpoint_with(&*history.codec)?;
+        if let Some(history) = self.history.as_mut() {
+            history.snapshots.insert(self.generation, checkpoint);
+        }
+        Ok(())
+    }
+
+    /// Replaces the entities and the generation of the Environment with the
+    /// ones of the given Environment.
+    ///
+    /// The entities are replaced without recording their deaths and births,
+    /// and the restored entities are considered inserted in the generation of
+    /// the given Environment. The components and columns are kept by ID for
+    /// the entities that still exist after the restore, and dropped for the
+    /// others.
+    pub(super) fn restore(&mut self, mut restored: Self) {
+        let restored_ids: HashSet<Id> = restored
+            .entities
+            .values()
+            .flatten()
+            .map(|e| e.id())
+            .collect();
+        let removed: Vec<_> = self
+            .entities
+            .values_mut()
+            .flat_map(std::mem::take)
+            .collect();
+        for entity in removed {
+            if let Some(location) = entity.location() {
+                self.tiles.remove(entity.id(), location);
+            }
+            if !restored_ids.contains(&entity.id()) {
+                self.components.remove_entity(entity.id());
+                self.columns.remove_entity(entity.id());
+            }
+        }
+        self.expiring.clear();
+        self.births.clear();
+        self.previous_locations.clear();
+        self.clear_digests();
+        self.generation = restored.generation;
+        self.started = None;
+        for entities in std::mem::take(&mut restored.entities).into_values() {
+            for mut entity in entities {
+                #[cfg(debug_assertions)]
+                Self::check_entity_contract(&*entity);
+                self.tiles.insert(&mut *entity);
+                self.births.insert(entity.id(), self.generation);
+                self.entities.entry(entity.kind()).or_default().push(entity);
+            }
+        }
+        self.stats.clear();
+    }
+
+    /// Replaces the cells of the rules with the given ones, in the order of
+    /// their Kind.
+    fn restore_rules(&mut self, rules: Vec<RuleBoard>) {
+        for (board, rule) in self.rules.values_mut().zip(rules) {
+            *board = rule;
+        }
+    }
+}
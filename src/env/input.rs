@@ -0,0 +1,98 @@
+use super::*;
+
+/// The queue of the external inputs pushed to an Entity, in the order they
+/// were pushed.
+pub(super) type InputQueue = Vec<AnyValue>;
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Pushes the given external input (such as a key pressed by the player)
+    /// to the Entity with the given ID, that will be delivered to the Entity
+    /// via `Entity::on_input` at the start of the next generation, before it
+    /// reacts, rather than mutating the Entity from the game loop.
+    ///
+    /// The inputs pushed to an Entity that does not exist when they are
+    /// delivered are discarded.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// struct Player(Location, Vec<Offset>);
+    ///
+    /// impl<'e> Entity<'e> for Player {
+    ///     type Kind = ();
+    ///
+    ///     fn id(&self) -> Id {
+    ///         0
+    ///     }
+    ///
+    ///     fn kind(&self) {}
+    ///
+    ///     fn location(&self) -> Option<Location> {
+    ///         Some(self.0)
+    ///     }
+    ///
+    ///     fn location_mut(&mut self) -> Option<&mut Location> {
+    ///         Some(&mut self.0)
+    ///     }
+    ///
+    ///     fn on_input(&mut self, input: AnyValue) -> Result<(), Error> {
+    ///         if let Ok(offset) = input.downcast::<Offset>() {
+    ///             self.1.push(*offset);
+    ///         }
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn intent(&mut self) -> Option<MoveIntent> {
+    ///         let offset = self.1.pop()?;
+    ///         Some(MoveIntent::new(self.0 + offset))
+    ///     }
+    /// }
+    ///
+    /// let mut env = Environment::new((5, 5));
+    /// env.insert(Player(Location::origin(), Vec::new()));
+    /// env.push_input(0, Box::new(Offset { x: 1, y: 0 }));
+    /// assert_eq!(env.pending_inputs(0), 1);
+    ///
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.pending_inputs(0), 0);
+    /// assert_eq!(env.entities_at((1, 0)).count(), 1);
+    /// ```
+    pub fn push_input(&mut self, id: Id, input: AnyValue) {
+        self.inputs.entry(id).or_default().push(input);
+    }
+
+    /// Gets the number of inputs pushed to the Entity with the given ID that
+    /// are not yet delivered.
+    pub fn pending_inputs(&self, id: Id) -> usize {
+        self.inputs.get(&id).map_or(0, Vec::len)
+    }
+
+    /// Delivers all the pending inputs to their entities via
+    /// `Entity::on_input`, discarding the ones of entities that do not exist.
+    ///
+    /// Returns an error if any of the entities returns an error, in which case
+    /// the inputs not yet delivered are kept, to be delivered by the next
+    /// attempt to move to the next generation.
+    pub(super) fn deliver_inputs(&mut self) -> Result<(), Error> {
+        if self.inputs.is_empty() {
+            return Ok(());
+        }
+        let mut inputs = std::mem::take(&mut self.inputs);
+        for entity in self.entities.values_mut().flat_map(|e| e.iter_mut()) {
+            let id = entity.id();
+            let mut queue = inputs.remove(&id).into_iter().flatten();
+            while let Some(input) = queue.next() {
+                if let Err(error) = entity.on_input(input) {
+                    let remaining: InputQueue = queue.collect();
+                    if !remaining.is_empty() {
+                        inputs.insert(id, remaining);
+                    }
+                    self.inputs = inputs;
+                    return Err(error);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use super::*;
+
+/// The bounds of the kinds of the entities of an Environment that moves to the
+/// next generation, implemented by all the types.
+///
+/// With the `parallel` feature the kinds are shared between the threads the
+/// entities are dispatched on, and they must therefore be Send and Sync.
+#[cfg(not(feature = "parallel"))]
+pub trait ParallelKind {}
+
+#[cfg(not(feature = "parallel"))]
+impl<T> ParallelKind for T {}
+
+/// The bounds of the kinds of the entities of an Environment that moves to the
+/// next generation, implemented by all the types that are Send and Sync.
+///
+/// With the `parallel` feature the kinds are shared between the threads the
+/// entities are dispatched on, and they must therefore be Send and Sync.
+#[cfg(feature = "parallel")]
+pub trait ParallelKind: Send + Sync {}
+
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync> ParallelKind for T {}
+
+/// A Kind created at runtime, such as the species defined by a plugin, that is
+/// identified by its name interned by the Environment (see
+/// `Environment::register_kind`).
+///
+/// The DynKinds are ordered by the order in which they were registered, so
+/// that the kinds registered first (usually the built-in ones) always come
+/// first, regardless of their names, and the order in which the entities of
+/// different kinds are dispatched is stable across runs as long as the kinds
+/// are registered in the same order.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let mut env = Environment::<DynKind>::new((5, 5));
+/// let grass = env.register_kind("grass");
+/// let sheep = env.register_kind("sheep");
+/// assert!(grass < sheep);
+/// assert_eq!(env.register_kind("grass"), grass);
+///
+/// env.insert(QuickEntity::new(sheep).at((2, 2)));
+/// assert_eq!(env.kind_id("sheep"), Some(sheep));
+/// assert_eq!(env.kind_name(sheep), Some("sheep"));
+/// assert_eq!(env.count_kind(&sheep), 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DynKind(u32);
+
+impl DynKind {
+    /// Gets the index of the DynKind, that is the number of kinds registered
+    /// before it in the KindInterner it belongs to.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl KindIndex for DynKind {
+    fn kind_index(&self) -> usize {
+        self.index()
+    }
+}
+
+impl fmt::Display for DynKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// The interner of the names of the DynKinds of an Environment, that maps each
+/// name to a unique DynKind.
+#[derive(Debug, Clone, Default)]
+pub struct KindInterner {
+    // the names of the kinds, indexed by DynKind
+    names: Vec<Arc<str>>,
+    ids: HashMap<Arc<str>, DynKind>,
+}
+
+impl KindInterner {
+    /// Constructs a new empty KindInterner.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the DynKind with the given name, registering it as a new DynKind
+    /// if no kind with the same name was registered before.
+    ///
+    /// # Panics
+    /// Panics if more than `u32::MAX` kinds are registered.
+    pub fn intern(&mut self, name: impl AsRef<str>) -> DynKind {
+        let name = name.as_ref();
+        if let Some(kind) = self.ids.get(name) {
+            return *kind;
+        }
+        let index =
+            u32::try_from(self.names.len()).expect("Too many kinds registered");
+        let kind = DynKind(index);
+        let name: Arc<str> = name.into();
+        self.names.push(Arc::clone(&name));
+        self.ids.insert(name, kind);
+        kind
+    }
+
+    /// Gets the DynKind with the given name, if registered.
+    pub fn get(&self, name: impl AsRef<str>) -> Option<DynKind> {
+        self.ids.get(name.as_ref()).copied()
+    }
+
+    /// Gets the name of the given DynKind, if registered.
+    pub fn name(&self, kind: DynKind) -> Option<&str> {
+        self.names.get(kind.index()).map(AsRef::as_ref)
+    }
+
+    /// Gets an iterator over all the registered kinds and their names, in the
+    /// order they were registered.
+    pub fn kinds(&self) -> impl Iterator<Item = (DynKind, &str)> {
+        self.names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (DynKind(index as u32), name.as_ref()))
+    }
+
+    /// Gets the number of registered kinds.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns true only if no kind was registered.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+impl<'e, C> Environment<'e, DynKind, C> {
+    /// Registers a new DynKind with the given name, or gets the one already
+    /// registered with the same name, so that kinds created at runtime can
+    /// coexist with the built-in ones.
+    ///
+    /// # Panics
+    /// Panics if more than `u32::MAX` kinds are registered.
+    pub fn register_kind(&mut self, name: impl AsRef<str>) -> DynKind {
+        self.kinds.intern(name)
+    }
+
+    /// Gets the DynKind registered with the given name, if any.
+    pub fn kind_id(&self, name: impl AsRef<str>) -> Option<DynKind> {
+        self.kinds.get(name)
+    }
+
+    /// Gets the name the given DynKind was registered with, if any.
+    pub fn kind_name(&self, kind: DynKind) -> Option<&str> {
+        self.kinds.name(kind)
+    }
+
+    /// Gets a reference to the KindInterner of the Environment, that holds all
+    /// the registered kinds.
+    pub fn kind_interner(&self) -> &KindInterner {
+        &self.kinds
+    }
+}
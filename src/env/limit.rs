@@ -0,0 +1,225 @@
+use std::collections::HashSet;
+
+use super::*;
+
+/// The policy applied when the offspring of a generation would exceed the
+/// maximum number of entities allowed for their Kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EvictionPolicy {
+    /// The offspring that exceed the limit are discarded.
+    Reject,
+    /// The oldest entities of the same Kind are removed from the Environment
+    /// to make room for the offspring.
+    EvictOldest,
+    /// The generation fails with an error.
+    Error,
+}
+
+/// The maximum number of entities of a Kind, and the policy applied when the
+/// limit is exceeded.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct KindLimit {
+    max_count: usize,
+    policy: EvictionPolicy,
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Sets the maximum number of entities of the given Kind, and the policy
+    /// applied when the offspring of a generation would exceed it.
+    ///
+    /// The limit is only enforced when inserting the offspring of the entities
+    /// during `Environment::nextgen`, while `Environment::insert` is never
+    /// restricted. This allows to protect the Environment from runaway
+    /// offspring (for example due to exponential growth bugs).
+    ///
+    /// The entities removed in the same generation are not counted, so that
+    /// the offspring can take the place of the dead entities, such as the ones
+    /// inserted by a dying Entity in `Entity::on_expire`.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// struct Plant<'e>(Id, Lifespan, Offspring<'e, u8>);
+    ///
+    /// impl<'e> Entity<'e> for Plant<'e> {
+    ///     type Kind = u8;
+    ///
+    ///     fn id(&self) -> Id {
+    ///         self.0
+    ///     }
+    ///
+    ///     fn kind(&self) -> Self::Kind {
+    ///         0
+    ///     }
+    ///
+    ///     fn lifespan(&self) -> Option<Lifespan> {
+    ///         Some(self.1)
+    ///     }
+    ///
+    ///     fn on_expire(&mut self) -> Result<(), Error> {
+    ///         // the plant is replaced by its seed
+    ///         let seed = Plant(self.0 + 1, self.1, Offspring::default());
+    ///         self.2.insert(seed);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn offspring(&mut self) -> Option<Offspring<'e, u8, ()>> {
+    ///         Some(self.2.drain())
+    ///     }
+    /// }
+    ///
+    /// let mut env = Environment::new((1, 1));
+    /// env.set_kind_limit(0, 1, EvictionPolicy::Reject);
+    /// env.insert(Plant(0, Lifespan::with_span(0), Offspring::default()));
+    ///
+    /// env.nextgen().unwrap();
+    /// let ids: Vec<_> = env.entities().map(|e| e.id()).collect();
+    /// assert_eq!(ids, [1]);
+    ///
+    /// // the generation fails before any Entity is removed
+    /// env.set_kind_limit(0, 0, EvictionPolicy::Error);
+    /// assert!(env.nextgen().is_err());
+    /// let ids: Vec<_> = env.entities().map(|e| e.id()).collect();
+    /// assert_eq!(ids, [1]);
+    /// ```
+    pub fn set_kind_limit(
+        &mut self,
+        kind: K,
+        max_count: usize,
+        policy: EvictionPolicy,
+    ) {
+        self.limits.insert(kind, KindLimit { max_count, policy });
+    }
+
+    /// Removes the limit on the number of entities of the given Kind.
+    pub fn remove_kind_limit(&mut self, kind: &K) {
+        self.limits.remove(kind);
+    }
+
+    /// Checks the limits of each Kind with the `EvictionPolicy::Error` policy
+    /// against the given offspring, without counting the entities that are
+    /// going to be removed in the same generation.
+    ///
+    /// Returns an error if the offspring exceed any of such limits.
+    pub(super) fn check_offspring_limits(
+        &self,
+        offspring: &[Box<EntityTrait<'e, K, C>>],
+    ) -> Result<(), Error> {
+        if !self
+            .limits
+            .values()
+            .any(|limit| limit.policy == EvictionPolicy::Error)
+        {
+            return Ok(());
+        }
+
+        let mut counts = BTreeMap::new();
+        for entity in offspring {
+            let kind = entity.kind();
+            if self
+                .limits
+                .get(&kind)
+                .is_some_and(|limit| limit.policy == EvictionPolicy::Error)
+            {
+                *counts.entry(kind).or_insert(0) += 1;
+            }
+        }
+        for (kind, count) in counts {
+            let limit = self.limits[&kind];
+            let existing = self.entities.get(&kind).map_or(0, |entities| {
+                entities.iter().filter(|e| !self.is_dead(&***e)).count()
+            });
+            if existing + count > limit.max_count {
+                return Err(Error::with_message(format!(
+                    "Offspring exceed the limit of {} entities per Kind",
+                    limit.max_count
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the limits of each Kind to the given offspring, and returns the
+    /// offspring that can be inserted into the Environment.
+    ///
+    /// The limits with the `EvictionPolicy::Error` policy are expected to be
+    /// already checked with `Environment::check_offspring_limits`.
+    pub(super) fn limit_offspring(
+        &mut self,
+        offspring: Vec<Box<EntityTrait<'e, K, C>>>,
+    ) -> Vec<Box<EntityTrait<'e, K, C>>> {
+        if self.limits.is_empty() {
+            return offspring;
+        }
+
+        // the number of offspring of each limited Kind
+        let mut counts = BTreeMap::new();
+        for entity in &offspring {
+            let kind = entity.kind();
+            if self.limits.contains_key(&kind) {
+                *counts.entry(kind).or_insert(0) += 1;
+            }
+        }
+
+        // the number of offspring of each limited Kind that can still be
+        // inserted, where the most recent offspring are kept when evicting
+        let mut available = BTreeMap::new();
+        for (kind, count) in counts {
+            let limit = self.limits[&kind];
+            let existing = self.count_kind(&kind);
+            let allowed = match limit.policy {
+                EvictionPolicy::Reject => {
+                    limit.max_count.saturating_sub(existing)
+                }
+                EvictionPolicy::EvictOldest => {
+                    let excess = (existing + count)
+                        .saturating_sub(limit.max_count)
+                        .min(existing);
+                    self.evict_oldest(&kind, excess);
+                    limit.max_count.saturating_sub(existing - excess)
+                }
+                EvictionPolicy::Error => count,
+            };
+            // the number of offspring to skip before the allowed ones
+            let skip = match limit.policy {
+                EvictionPolicy::EvictOldest => count.saturating_sub(allowed),
+                _ => 0,
+            };
+            available.insert(kind, (skip, allowed));
+        }
+
+        offspring
+            .into_iter()
+            .filter(|entity| match available.get_mut(&entity.kind()) {
+                Some((skip, _)) if *skip > 0 => {
+                    *skip -= 1;
+                    false
+                }
+                Some((_, allowed)) if *allowed > 0 => {
+                    *allowed -= 1;
+                    true
+                }
+                Some(_) => false,
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Removes the given number of oldest entities of the given Kind from the
+    /// Environment.
+    fn evict_oldest(&mut self, kind: &K, count: usize) {
+        // the entities of the same Kind are stored in insertion order
+        let oldest: HashSet<Id> = self
+            .entities
+            .get(kind)
+            .into_iter()
+            .flatten()
+            .take(count)
+            .map(|entity| entity.id())
+            .collect();
+        if !oldest.is_empty() {
+            self.retain_entities(|entity| !oldest.contains(&entity.id()));
+        }
+    }
+}
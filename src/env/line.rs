@@ -0,0 +1,100 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+/// The history of the rows of a 1-dimensional Environment (see
+/// `Dimension::line`), that keeps the most recent rows up to a given capacity,
+/// so that the evolution of the Environment can be visualized as a 2D image
+/// that scrolls as new generations are recorded.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let mut env = Environment::<char>::new(Dimension::line(3));
+/// env.insert(QuickEntity::new('a').id(0).at((1, 0)));
+///
+/// let mut history = LineHistory::new(2);
+/// for _ in 0..3 {
+///     history.record(&env, |mut entities| entities.next().is_some());
+///     env.nextgen().unwrap();
+/// }
+/// assert_eq!(history.len(), 2);
+/// assert_eq!(history.latest(), Some(&[false, true, false][..]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineHistory<T> {
+    // the recorded rows, from the oldest to the most recent
+    rows: VecDeque<Vec<T>>,
+    capacity: usize,
+}
+
+impl<T> LineHistory<T> {
+    /// Constructs a new empty LineHistory that keeps up to the given number of
+    /// rows.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            rows: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Records the first row of the given Environment, by mapping the entities
+    /// located in each of its tiles (from left to right) with the given
+    /// function, and discarding the oldest row if the capacity was reached.
+    pub fn record<'e, K: Ord, C>(
+        &mut self,
+        env: &Environment<'e, K, C>,
+        mut f: impl FnMut(&mut dyn Iterator<Item = &EntityTrait<'e, K, C>>) -> T,
+    ) {
+        let row = (0..env.dimension().x)
+            .map(|x| f(&mut env.entities_at((x, 0))))
+            .collect();
+        self.push(row);
+    }
+
+    /// Pushes the given row as the most recent one, discarding the oldest row
+    /// if the capacity was reached.
+    pub fn push(&mut self, row: Vec<T>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.rows.len() == self.capacity {
+            self.rows.pop_front();
+        }
+        self.rows.push_back(row);
+    }
+
+    /// Gets an iterator over the recorded rows, from the oldest to the most
+    /// recent, that is, from the top to the bottom of the scrolling view.
+    pub fn rows(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = &[T]> + ExactSizeIterator {
+        self.rows.iter().map(Vec::as_slice)
+    }
+
+    /// Gets the most recent row, if any.
+    pub fn latest(&self) -> Option<&[T]> {
+        self.rows.back().map(Vec::as_slice)
+    }
+
+    /// Gets the number of recorded rows.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns true only if no row was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Gets the maximum number of rows kept.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Removes all the recorded rows.
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+}
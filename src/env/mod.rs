@@ -1,16 +1,120 @@
-use std::collections::{BTreeMap, HashMap};
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
 
 use super::*;
+use arena::*;
+use chunk::*;
+use clock::*;
+use column::*;
+use component::*;
+use digest::*;
+use event::*;
+use feed::*;
+use history::*;
+use input::*;
+use limit::*;
+use notification::*;
+use perception::*;
+use progress::*;
+use region::*;
+#[cfg(feature = "replay")]
+use replay::*;
+use rule::*;
+use tick::*;
 use tile::*;
+use world::*;
 
+mod arena;
+mod branch;
+mod chunk;
+mod clock;
+mod column;
+mod component;
+#[cfg(feature = "config")]
+mod config;
+mod debug;
+mod dedup;
+mod digest;
+mod draw;
+mod editor;
+mod event;
+mod feed;
+mod generation;
+mod history;
+mod input;
+mod kind;
+mod limit;
+mod line;
 mod neighborhood;
+mod notification;
+mod occupancy;
+mod pattern;
+mod perception;
+mod phase;
+mod progress;
+mod region;
+mod render;
+#[cfg(feature = "replay")]
+mod replay;
+mod rule;
+mod seeder;
+mod snapshot;
+mod stack;
+mod stamp;
+mod stats;
+mod sweep;
+#[cfg(feature = "sync")]
+mod sync;
+mod tick;
+mod ticker;
 mod tile;
+mod visitor;
+mod world;
 
+#[cfg(feature = "gpu")]
+mod gpu;
 #[cfg(feature = "parallel")]
 mod scheduler;
 
+pub use clock::DEFAULT_GENERATION_DURATION;
+pub use column::Column;
+#[cfg(feature = "config")]
+pub use config::{
+    Config, ConfigLoader, EntityFactory, KindConfig, Param, Params,
+    PatternConfig,
+};
+pub use dedup::{OffspringKey, OffspringMerge, OffspringPolicy};
+pub use draw::{DrawBatch, ErrorPolicy};
+pub use editor::Editor;
+pub use event::{EntityCommand, ScheduledCommand, DEFAULT_EVENT_CAPACITY};
+pub use feed::{EventKind, EventKinds, OccupancyEvent};
+pub use generation::Generation;
+#[cfg(feature = "gpu")]
+pub use gpu::{GpuGrid, RuleKernel};
+pub use history::WatchId;
+pub use kind::{DynKind, KindInterner, ParallelKind};
+pub use limit::EvictionPolicy;
+pub use line::LineHistory;
 pub use neighborhood::*;
-pub use tile::TileView;
+pub use notification::Notification;
+pub use occupancy::BitVec;
+pub use pattern::Pattern;
+#[cfg(feature = "replay")]
+pub use replay::{Replay, REPLAY_MAGIC, REPLAY_VERSION};
+pub use rule::{BlockRule, Rule};
+pub use seeder::Seeder;
+pub(crate) use seeder::SplitMix64;
+pub use snapshot::*;
+pub use stack::EnvironmentStack;
+pub use stats::KindStats;
+pub use sweep::{Metric, Sweep, SweepRow, SweepTable};
+#[cfg(feature = "sync")]
+pub use sync::{Delta, SyncBaseline, DELTA_MAGIC, DELTA_VERSION};
+pub use ticker::{Ticker, DEFAULT_MAX_CATCHUP};
+pub use tile::{TileOrder, TileView};
+pub use visitor::Visitor;
+pub use world::WorldView;
 
 /// Unordered map of entities identified by their IDs, where all the entities
 /// belongs to the same Kind.
@@ -19,6 +123,21 @@ type Entities<'e, K, C> = Vec<Box<EntityTrait<'e, K, C>>>;
 /// Sorted map of all the entities by Kind.
 type EntitiesKinds<'e, K, C> = BTreeMap<K, Entities<'e, K, C>>;
 
+/// The resolver of the conflicts between the entities that intend to move into
+/// the same destination, given as list of entity IDs and their intents.
+/// Returns the index of the winning intent, if any.
+#[cfg(not(feature = "parallel"))]
+type MoveResolver<'e> =
+    Box<dyn Fn(Location, &[(Id, MoveIntent)]) -> Option<usize> + 'e>;
+
+/// The resolver of the conflicts between the entities that intend to move into
+/// the same destination, given as list of entity IDs and their intents.
+/// Returns the index of the winning intent, if any.
+#[cfg(feature = "parallel")]
+type MoveResolver<'e> = Box<
+    dyn Fn(Location, &[(Id, MoveIntent)]) -> Option<usize> + Send + Sync + 'e,
+>;
+
 /// The Environment is a grid, of squared tiles with the same size, where all
 /// the entities belong.
 ///
@@ -45,8 +164,7 @@ type EntitiesKinds<'e, K, C> = BTreeMap<K, Entities<'e, K, C>>;
 /// bound for the objects (immutable references lifetimes) that implement the
 /// Entity trait, and it allows to propagate the same bound to the entities
 /// Offspring.
-#[derive(Debug)]
-pub struct Environment<'e, K, C> {
+pub struct Environment<'e, K, C = ()> {
     // the list of strong references to the entities
     entities: EntitiesKinds<'e, K, C>,
     // the (1-dimensional) grid of tiles that stores week references to the
@@ -56,11 +174,108 @@ pub struct Environment<'e, K, C> {
     // properties within it at each generation
     snapshots: Vec<Snapshot<K>>,
     // the generation counter
-    generation: u64,
+    generation: Generation,
+    // the generation whose start was already processed by a failed attempt to
+    // move to the next generation, if any
+    started: Option<Generation>,
+    // the number of tiles allocated for the neighborhoods during the previous
+    // generation, used to size the Arena of the next one
+    arena_capacity: usize,
+    // the resolver of the conflicts between move intents
+    move_resolver: Option<MoveResolver<'e>>,
+    // the components attached to the entities
+    components: Components,
+    // the columns of the values of the entities of the kinds in SoA mode
+    columns: Columns<K>,
+    // the names of the kinds registered at runtime, if any
+    kinds: KindInterner,
+    // the drawing parameters of each Kind, if different from the default
+    layers: BTreeMap<K, Layer>,
+    // the kinds whose entities are not dispatched any behavior
+    passive: BTreeSet<K>,
+    // the region outside of which the entities are frozen, if any
+    active_region: Option<Rect>,
+    // the tick groups of the kinds that are not dispatched every generation
+    ticks: TickGroups<K>,
+    // the geometry of the grid of tiles in pixels
+    grid: TileGrid,
+    // the remaining grace period of the entities whose lifespan is over
+    expiring: HashMap<Id, u64>,
+    // the generation each Entity was inserted in
+    births: HashMap<Id, Generation>,
+    // the statistics of each Kind
+    stats: BTreeMap<K, KindStats>,
+    // the locations in the previous generation of the entities that moved
+    previous_locations: HashMap<Id, Location>,
+    // the ordered list of phases dispatched within each generation
+    phases: Vec<String>,
+    // the digests of the past generations
+    digests: Digests,
+    // the maximum number of entities of each Kind
+    limits: BTreeMap<K, KindLimit>,
+    // the policies applied to deduplicate the offspring of each Kind
+    offspring_policies: BTreeMap<K, OffspringPolicy<'e, K, C>>,
+    // the global resources shared by all the entities
+    resources: Resources,
+    // the pacing of the generations
+    clock: Clock,
+    // the source of randomness of the inheritance of the genomes
+    genome_rng: GenomeRng,
+    // the seed of the source of randomness of the perceptions
+    perception_seed: u64,
+    // the render values of the entities of each enabled Kind, by tile
+    render_values: BTreeMap<K, Vec<f32>>,
+    // the cells of the built-in totalistic rules, identified by Kind
+    rules: BTreeMap<K, RuleBoard>,
+    // the snapshots of the past generations, if the history is enabled
+    history: Option<History<'e, K, C>>,
+    // the commands scheduled to be executed at future generations
+    events: Events<'e, K, C>,
+    // the external inputs pushed to the entities, not yet delivered
+    inputs: HashMap<Id, InputQueue>,
+    // the entities and kinds whose notifications are buffered
+    subscriptions: Subscriptions<K>,
+    // the notifications buffered during the last generation
+    notifications: Vec<Notification<K>>,
+    // the subscribers to the changes of the occupancy
+    feeds: Feeds<K>,
+    // the first ID that may be assigned to the entities without one
+    next_id: Id,
+    // the streaming of the cold chunks to disk, if enabled
+    streaming: Option<Streaming<'e, K, C>>,
+    // the interventions recorded to replay the run, if recording
+    #[cfg(feature = "replay")]
+    recording: Option<Recording<'e, K, C>>,
     #[cfg(feature = "parallel")]
     scheduler: scheduler::Scheduler,
 }
 
+impl<'e, K: fmt::Debug, C> fmt::Debug for Environment<'e, K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Environment")
+            .field("entities", &self.entities)
+            .field("dimension", &self.tiles.dimension())
+            .field("generation", &self.generation)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The drawing parameters of all the entities of the same Kind.
+#[derive(Debug, Clone, Copy)]
+struct Layer {
+    visible: bool,
+    alpha: f32,
+}
+
+impl Default for Layer {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            alpha: 1.0,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Snapshot<K> {
     id: Id,
@@ -74,12 +289,71 @@ impl<'e, K: Ord, C> Environment<'e, K, C> {
     /// The dimension represents the size of the grid of squared tiles of same
     /// side length, as number of columns and rows.
     pub fn new(dimension: impl Into<Dimension>) -> Self {
+        Self::with_tile_order(dimension, TileOrder::RowMajor)
+    }
+
+    /// Constructs a new environment with the given dimension, whose tiles are
+    /// stored in memory in the given order.
+    ///
+    /// The order of the tiles does not affect the behavior of the Environment,
+    /// but only the performance of the access to its tiles: the Morton order
+    /// can speed up the construction of the neighborhoods of the entities in
+    /// large Environments, at the cost of an additional lookup per tile.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8>::with_tile_order((10, 10), TileOrder::Morton);
+    /// env.insert(QuickEntity::new(0).at((3, 7)));
+    /// assert_eq!(env.tile_order(), TileOrder::Morton);
+    /// assert_eq!(env.entities_at((3, 7)).count(), 1);
+    /// ```
+    pub fn with_tile_order(
+        dimension: impl Into<Dimension>,
+        order: TileOrder,
+    ) -> Self {
         let dimension = dimension.into();
         Self {
             entities: BTreeMap::new(),
-            tiles: Tiles::new(dimension),
+            tiles: Tiles::with_order(dimension, order),
             snapshots: Vec::default(),
-            generation: 0,
+            generation: Generation::ZERO,
+            started: None,
+            arena_capacity: 0,
+            move_resolver: None,
+            components: Components::default(),
+            columns: Columns::default(),
+            kinds: KindInterner::default(),
+            layers: BTreeMap::new(),
+            passive: BTreeSet::new(),
+            active_region: None,
+            ticks: TickGroups::default(),
+            grid: TileGrid::default(),
+            expiring: HashMap::new(),
+            births: HashMap::new(),
+            stats: BTreeMap::new(),
+            previous_locations: HashMap::new(),
+            phases: Vec::new(),
+            digests: Digests::default(),
+            limits: BTreeMap::new(),
+            offspring_policies: BTreeMap::new(),
+            resources: Resources::default(),
+            clock: Clock::default(),
+            genome_rng: GenomeRng::new(0),
+            perception_seed: 0,
+            render_values: BTreeMap::new(),
+            rules: BTreeMap::new(),
+            history: None,
+            events: Events::default(),
+            inputs: HashMap::new(),
+            subscriptions: Subscriptions::default(),
+            notifications: Vec::new(),
+            feeds: Feeds::default(),
+            next_id: 0,
+            streaming: None,
+            #[cfg(feature = "replay")]
+            recording: None,
             #[cfg(feature = "parallel")]
             scheduler: scheduler::Scheduler::new(
                 dimension,
@@ -93,6 +367,11 @@ impl<'e, K: Ord, C> Environment<'e, K, C> {
         self.tiles.dimension()
     }
 
+    /// Gets the order in which the tiles of the Environment are stored.
+    pub fn tile_order(&self) -> TileOrder {
+        self.tiles.order()
+    }
+
     /// Inserts the given Entity into the Environment.
     ///
     /// This method is usually used to pre-populate the environment with a set
@@ -100,13 +379,23 @@ impl<'e, K: Ord, C> Environment<'e, K, C> {
     /// environment has been pre-populated the set of entities stored in it will
     /// depend on the behavior of the entities itself (such ad lifespan increase
     /// and decrease, or generated offspring).
+    ///
+    /// # Panics
+    /// Panics if the Entity is located in a chunk evicted to disk (see
+    /// `Environment::enable_chunk_streaming`), since its ID cannot be checked
+    /// against the ones of the evicted entities.
     #[cfg(not(feature = "parallel"))]
     pub fn insert<E>(&mut self, entity: E)
     where
         // Trait aliases https://github.com/rust-lang/rust/issues/41517
-        E: Entity<'e, Kind = K, Context = C> + 'e,
+        E: Entity<'e, C, Kind = K> + 'e,
     {
-        self.insert_boxed(Box::new(entity));
+        let entity = Box::new(entity);
+        assert!(
+            !self.is_evicted(&*entity),
+            "Cannot insert an Entity into an evicted chunk"
+        );
+        self.insert_recorded(entity);
     }
 
     /// Inserts the given Entity into the Environment.
@@ -116,41 +405,555 @@ impl<'e, K: Ord, C> Environment<'e, K, C> {
     /// environment has been pre-populated the set of entities stored in it will
     /// depend on the behavior of the entities itself (such ad lifespan increase
     /// and decrease, or generated offspring).
+    ///
+    /// # Panics
+    /// Panics if the Entity is located in a chunk evicted to disk (see
+    /// `Environment::enable_chunk_streaming`), since its ID cannot be checked
+    /// against the ones of the evicted entities.
     #[cfg(feature = "parallel")]
     pub fn insert<E>(&mut self, entity: E)
     where
         // Trait aliases https://github.com/rust-lang/rust/issues/41517
-        E: Entity<'e, Kind = K, Context = C> + 'e + Send + Sync,
+        E: Entity<'e, C, Kind = K> + 'e + Send + Sync,
     {
-        self.insert_boxed(Box::new(entity));
+        let entity = Box::new(entity);
+        assert!(
+            !self.is_evicted(&*entity),
+            "Cannot insert an Entity into an evicted chunk"
+        );
+        self.insert_recorded(entity);
+    }
+
+    /// Inserts the given Entity into the Environment, recording its insertion
+    /// if the run is being recorded.
+    pub(super) fn insert_recorded(
+        &mut self,
+        entity: Box<EntityTrait<'e, K, C>>,
+    ) {
+        self.insert_entity(entity, true);
     }
 
     /// Inserts the given Entity into the Environment.
-    fn insert_boxed(&mut self, mut entity: Box<EntityTrait<'e, K, C>>) {
+    fn insert_boxed(&mut self, entity: Box<EntityTrait<'e, K, C>>) {
+        self.insert_entity(entity, false);
+    }
+
+    /// Inserts the given Entity into the Environment, recording its insertion
+    /// only if requested and if the run is being recorded.
+    fn insert_entity(
+        &mut self,
+        mut entity: Box<EntityTrait<'e, K, C>>,
+        record: bool,
+    ) {
+        self.assign_id(&mut *entity);
+        // the insertion is recorded only once the ID is assigned
+        if record {
+            #[cfg(feature = "replay")]
+            self.record_insert(&*entity);
+        }
+        #[cfg(debug_assertions)]
+        Self::check_entity_contract(&*entity);
         // insert the weak ref in the grid according to the entity location
         self.tiles.insert(&mut *entity);
+        Self::record_birth(
+            &mut self.stats,
+            &mut self.births,
+            &mut self.feeds,
+            self.generation,
+            &*entity,
+        );
         // insert the strong ref in the entities map
         let entities = self.entities.entry(entity.kind()).or_default();
         entities.push(entity);
     }
 
+    /// Assigns an ID that does not belong to any of the entities of the
+    /// Environment to the given Entity, if its ID is meant to be derived by the
+    /// Environment (see `Entity::assign_id`).
+    fn assign_id(&mut self, entity: &mut EntityTrait<'e, K, C>) {
+        while self.births.contains_key(&self.next_id)
+            || self.is_evicted_id(self.next_id)
+        {
+            self.next_id += 1;
+        }
+        if entity.assign_id(self.next_id) {
+            self.next_id += 1;
+        }
+    }
+
+    /// Removes all the entities from the Environment.
+    ///
+    /// All the other properties of the Environment, such as its resources, the
+    /// data attached to its tiles, and its generation, are left unchanged.
+    pub fn clear(&mut self) {
+        self.retain(|_| false);
+    }
+
+    /// Removes all the entities of the given Kind from the Environment.
+    pub fn clear_kind(&mut self, kind: &K) {
+        // the (empty) entry of the Kind is kept, so that the kinds are always
+        // identified in the same way by the digest
+        let Some(entities) = self.entities.get_mut(kind) else {
+            return;
+        };
+        let entities = std::mem::take(entities);
+        #[cfg(feature = "replay")]
+        self.record_removals(entities.iter().map(|entity| entity.id()));
+        for entity in entities {
+            if let Some(location) = entity.location() {
+                self.tiles.remove(entity.id(), location);
+            }
+            self.components.remove_entity(entity.id());
+            self.columns.remove_entity(entity.id());
+            self.expiring.remove(&entity.id());
+            Self::record_death(
+                &mut self.stats,
+                &mut self.births,
+                &mut self.feeds,
+                self.generation,
+                &*entity,
+            );
+        }
+    }
+
+    /// Retains only the entities specified by the given predicate, removing
+    /// from the Environment all the entities for which the predicate returns
+    /// false.
+    ///
+    /// The entities are removed in the order of their Kind and then of their
+    /// ID, regardless of the order they were inserted in, which is also the
+    /// order their removal is notified to the subscribers of the Environment
+    /// (see `Environment::subscribe`).
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8>::new((10, 10));
+    /// for x in 0..4 {
+    ///     env.insert(QuickEntity::new(x as u8 % 2).at((x, 0)));
+    /// }
+    ///
+    /// env.retain(|entity| entity.location().unwrap().x > 0);
+    /// assert_eq!(env.count(), 3);
+    /// assert_eq!(env.entities_at((0, 0)).count(), 0);
+    ///
+    /// env.clear_kind(&1);
+    /// assert_eq!(env.count(), 1);
+    /// env.clear();
+    /// assert!(env.is_empty());
+    /// ```
+    pub fn retain(
+        &mut self,
+        mut f: impl FnMut(&EntityTrait<'e, K, C>) -> bool,
+    ) {
+        #[cfg(feature = "replay")]
+        let mut removed = Vec::new();
+        self.retain_entities(|entity| {
+            let keep = f(entity);
+            #[cfg(feature = "replay")]
+            if !keep {
+                removed.push(entity.id());
+            }
+            keep
+        });
+        #[cfg(feature = "replay")]
+        self.record_removals(removed.into_iter());
+    }
+
+    /// Moves the Entity with the given ID to the given location (wrapped
+    /// around the Environment edges), outside of the generation steps.
+    ///
+    /// Returns false if no such Entity exists, or if it has no location,
+    /// true otherwise.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8>::new((10, 10));
+    /// env.insert(QuickEntity::new(0).id(3).at((1, 1)));
+    ///
+    /// assert!(env.relocate(3, (12, 4)));
+    /// assert_eq!(env.entities_at((2, 4)).count(), 1);
+    /// assert!(!env.relocate(4, (0, 0)));
+    /// ```
+    pub fn relocate(&mut self, id: Id, location: impl Into<Location>) -> bool {
+        let location = location.into();
+        let relocated = self.relocate_entity(id, location);
+        #[cfg(feature = "replay")]
+        if relocated {
+            self.record_relocation(id, location);
+        }
+        relocated
+    }
+
+    /// Replaces the Entity with the given ID with the given Entity, returning
+    /// the replaced one, so that the implementation of an Entity can be swapped
+    /// while the simulation is running.
+    ///
+    /// The new Entity must have the same ID of the replaced one (which is
+    /// assigned to it, if its ID is meant to be derived by the Environment, see
+    /// `Entity::assign_id`), and it takes over its location (if both have
+    /// one, via `Entity::location_mut`), its age, its components and its place
+    /// among the entities of its Kind (if the Kind is the same), while its
+    /// state is left to the new Entity. A change of Kind is recorded as the
+    /// death of the replaced Entity and the birth of the new one (see
+    /// `Environment::stats` and `Environment::subscribe`).
+    ///
+    /// Returns an error if no Entity with the given ID exists, or if the new
+    /// Entity has a different ID, in which case nothing is replaced.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8>::new((10, 10));
+    /// env.insert(QuickEntity::new(0).id(3).at((1, 1)));
+    /// env.nextgen().unwrap();
+    ///
+    /// let upgraded = QuickEntity::new(0).id(3).at((0, 0)).on_react(|e, _| {
+    ///     e.set_location(Location { x: 2, y: 1 });
+    ///     Ok(())
+    /// });
+    /// let old = env.replace_entity(3, Box::new(upgraded)).unwrap();
+    /// assert_eq!(old.location(), Some(Location { x: 1, y: 1 }));
+    /// assert_eq!(env.entities_at((1, 1)).count(), 1);
+    /// assert_eq!(env.age(3), Some(1));
+    ///
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.entities_at((2, 1)).count(), 1);
+    ///
+    /// // a change of Kind is the death of the old Kind and a birth of the new
+    /// let events = env.subscribe(EventKind::Spawn | EventKind::Death, 4);
+    /// env.replace_entity(3, Box::new(QuickEntity::new(1).id(3))).unwrap();
+    /// assert_eq!((env.stats(&0).deaths, env.stats(&1).births), (1, 1));
+    /// assert_eq!(env.age(3), Some(2));
+    ///
+    /// env.nextgen().unwrap();
+    /// let events: Vec<_> =
+    ///     events.try_iter().map(|e| (e.event, e.kind)).collect();
+    /// assert_eq!(events, [(EventKind::Death, 0), (EventKind::Spawn, 1)]);
+    /// ```
+    pub fn replace_entity(
+        &mut self,
+        id: Id,
+        mut entity: Box<EntityTrait<'e, K, C>>,
+    ) -> Result<Box<EntityTrait<'e, K, C>>, Error> {
+        entity.assign_id(id);
+        if entity.id() != id {
+            return Err(Error::with_message(format!(
+                "Cannot replace entity {} with entity {}",
+                id,
+                entity.id()
+            )));
+        }
+        let (kind, index) = self
+            .entities
+            .values()
+            .find_map(|entities| {
+                let index = entities.iter().position(|e| e.id() == id)?;
+                Some((entities[index].kind(), index))
+            })
+            .ok_or_else(|| {
+                Error::with_message(format!("Entity {} not found", id))
+            })?;
+
+        #[cfg(debug_assertions)]
+        Self::check_entity_contract(&*entity);
+        let location = self.entities[&kind][index].location();
+        if let (Some(location), Some(new)) = (location, entity.location_mut()) {
+            *new = location;
+        }
+        // the tiles reference the entities by address, therefore the reference
+        // to the replaced Entity is swapped with the one to the new Entity
+        if let Some(location) = location {
+            self.tiles.remove(id, location);
+        }
+        self.tiles.insert(&mut *entity);
+        #[cfg(feature = "replay")]
+        {
+            self.record_removals(std::iter::once(id));
+            self.record_insert(&*entity);
+        }
+
+        let entities = self.entities.get_mut(&kind).expect("Invalid kind");
+        if entity.kind() == kind {
+            return Ok(std::mem::replace(&mut entities[index], entity));
+        }
+        let old = entities.remove(index);
+        // the new Entity still takes over the age of the replaced one
+        let birth = self.births.get(&id).copied();
+        Self::record_death(
+            &mut self.stats,
+            &mut self.births,
+            &mut self.feeds,
+            self.generation,
+            &*old,
+        );
+        Self::record_birth(
+            &mut self.stats,
+            &mut self.births,
+            &mut self.feeds,
+            self.generation,
+            &*entity,
+        );
+        if let Some(birth) = birth {
+            self.births.insert(id, birth);
+        }
+        self.entities.entry(entity.kind()).or_default().push(entity);
+        Ok(old)
+    }
+
+    /// Moves the Entity with the given ID to the given location (wrapped
+    /// around the Environment edges). Returns false if no such Entity exists.
+    pub(super) fn relocate_entity(
+        &mut self,
+        id: Id,
+        location: Location,
+    ) -> bool {
+        let dimension = self.dimension();
+        let entity = self
+            .entities
+            .values_mut()
+            .flat_map(|entities| entities.iter_mut())
+            .find(|entity| entity.id() == id);
+        let Some(current) = entity.and_then(|e| e.location_mut()) else {
+            return false;
+        };
+        let from = *current;
+        let mut to = location;
+        *current = *to.translate((0, 0), dimension);
+        self.tiles.relocate(id, from, to);
+        true
+    }
+
+    /// Removes the Entity with the given ID from the Environment and returns
+    /// it, without recording its removal. Returns None if no such Entity
+    /// exists.
+    pub(super) fn take_entity(
+        &mut self,
+        id: Id,
+    ) -> Option<Box<EntityTrait<'e, K, C>>> {
+        let entities = self
+            .entities
+            .values_mut()
+            .find(|entities| entities.iter().any(|e| e.id() == id))?;
+        let index = entities.iter().position(|e| e.id() == id)?;
+        let entity = entities.remove(index);
+        if let Some(location) = entity.location() {
+            self.tiles.remove(id, location);
+        }
+        self.components.remove_entity(id);
+        self.columns.remove_entity(id);
+        self.expiring.remove(&id);
+        Self::record_death(
+            &mut self.stats,
+            &mut self.births,
+            &mut self.feeds,
+            self.generation,
+            &*entity,
+        );
+        Some(entity)
+    }
+
+    /// Retains only the entities specified by the given predicate, as
+    /// `Environment::retain`, without recording their removal.
+    pub(super) fn retain_entities(
+        &mut self,
+        mut f: impl FnMut(&EntityTrait<'e, K, C>) -> bool,
+    ) {
+        let tiles = &mut self.tiles;
+        let components = &mut self.components;
+        let columns = &mut self.columns;
+        let expiring = &mut self.expiring;
+        let births = &mut self.births;
+        let feeds = &mut self.feeds;
+        let stats = &mut self.stats;
+        let generation = self.generation;
+        for entities in self.entities.values_mut() {
+            let keep: Vec<_> = entities.iter().map(|e| f(&**e)).collect();
+            if keep.iter().all(|&keep| keep) {
+                continue;
+            }
+            let mut keep = keep.into_iter();
+            let (kept, mut removed): (Vec<_>, Vec<_>) =
+                std::mem::take(entities)
+                    .into_iter()
+                    .partition(|_| keep.next().unwrap_or(true));
+            *entities = kept;
+            // the entities of the same Kind are removed in the order of their
+            // IDs, regardless of how they are stored
+            removed.sort_by_key(|entity| entity.id());
+            for entity in removed {
+                // remove the weak reference to the entity from the grid of
+                // tiles, as well as all its components
+                if let Some(location) = entity.location() {
+                    tiles.remove(entity.id(), location);
+                }
+                components.remove_entity(entity.id());
+                columns.remove_entity(entity.id());
+                expiring.remove(&entity.id());
+                Self::record_death(stats, births, feeds, generation, &*entity);
+            }
+        }
+    }
+
     /// Draws the environment by iterating over each of its entities, sorted by
-    /// kind, and calling the draw method for each one of them.
+    /// kind, and calling the draw method for each one of them that is
+    /// Drawable.
+    ///
+    /// The entities of a Kind that is not visible are skipped, while all the
+    /// others are drawn via `Drawable::draw_with_context`, given the alpha of
+    /// their Kind.
     ///
-    /// Returns an error if any of the draw methods returns an error.
-    /// The order of draw calls for each entity of the same type is arbitrary.
+    /// Returns an error if any of the draw methods returns an error, in which
+    /// case the remaining entities are not drawn (see
+    /// `Environment::draw_with_policy` to skip the failing entities instead).
+    /// The entities of the same Kind are drawn in order of their z-order (see
+    /// `Entity::z_order`), and in the order they are stored otherwise.
     pub fn draw(
         &self,
         ctx: &mut C,
         transform: impl Into<Transform>,
     ) -> Result<(), Error> {
-        let transform = transform.into();
-        for entities in self.entities.values() {
-            for entity in entities {
-                entity.draw(ctx, transform)?;
-            }
+        self.draw_with_policy(ctx, transform, ErrorPolicy::Abort)
+            .map(drop)
+    }
+
+    /// Gets the entities of the same Kind in the order they are drawn, that is
+    /// sorted (stably) by their z-order.
+    fn draw_order<'a>(
+        entities: &'a Entities<'e, K, C>,
+    ) -> Vec<&'a EntityTrait<'e, K, C>> {
+        let mut entities: Vec<_> = entities.iter().map(|e| &**e).collect();
+        entities.sort_by_key(|entity| entity.z_order());
+        entities
+    }
+
+    /// Sets whether the entities of the given Kind are drawn by
+    /// `Environment::draw`.
+    ///
+    /// All the kinds are visible by default.
+    pub fn set_kind_visible(&mut self, kind: K, visible: bool) {
+        self.layers.entry(kind).or_default().visible = visible;
+    }
+
+    /// Returns true only if the entities of the given Kind are drawn by
+    /// `Environment::draw`.
+    pub fn is_kind_visible(&self, kind: &K) -> bool {
+        self.layers.get(kind).is_none_or(|layer| layer.visible)
+    }
+
+    /// Sets the alpha given to the entities of the given Kind when drawn by
+    /// `Environment::draw`, clamped to the range [0, 1].
+    ///
+    /// All the kinds are fully opaque (alpha equal to 1) by default.
+    pub fn set_kind_alpha(&mut self, kind: K, alpha: f32) {
+        self.layers.entry(kind).or_default().alpha = alpha.clamp(0.0, 1.0);
+    }
+
+    /// Gets the alpha given to the entities of the given Kind when drawn by
+    /// `Environment::draw`.
+    pub fn kind_alpha(&self, kind: &K) -> f32 {
+        self.layers.get(kind).map_or(1.0, |layer| layer.alpha)
+    }
+
+    /// Sets whether the entities of the given Kind are passive, that is purely
+    /// decorative entities that are skipped entirely when moving forwards to
+    /// the next generation: they do not observe nor react to their
+    /// Neighborhood, they are not dispatched any phase, their move intents are
+    /// ignored, and their location is not tracked.
+    ///
+    /// Since the location of passive entities is not tracked, they are not
+    /// expected to be moved by other entities (via their Neighborhood) within
+    /// a generation. Their lifespan and offspring are still handled as for
+    /// any other Entity.
+    ///
+    /// All the kinds are not passive by default.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((10, 10));
+    /// for kind in ['a', 'b'] {
+    ///     env.insert(QuickEntity::new(kind).at((1, 1)).on_react(|entity, _| {
+    ///         entity.set_location(Location { x: 2, y: 2 });
+    ///         Ok(())
+    ///     }));
+    /// }
+    ///
+    /// env.set_passive('b', true);
+    /// assert!(env.is_passive(&'b'));
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.entities_at((2, 2)).count(), 1);
+    /// assert_eq!(env.entities_at((1, 1)).count(), 1);
+    /// ```
+    pub fn set_passive(&mut self, kind: K, passive: bool) {
+        if passive {
+            self.passive.insert(kind);
+        } else {
+            self.passive.remove(&kind);
         }
-        Ok(())
+    }
+
+    /// Returns true only if the entities of the given Kind are passive (see
+    /// `Environment::set_passive`).
+    pub fn is_passive(&self, kind: &K) -> bool {
+        self.passive.contains(kind)
+    }
+
+    /// Sets the geometry of the grid of tiles of the Environment in pixels,
+    /// given to the entities when drawn via their DrawContext.
+    ///
+    /// The TileGrid has tiles with a side of 1 pixel by default.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8>::new((10, 10));
+    /// env.set_grid(TileGrid::new(8.0));
+    ///
+    /// assert_eq!(env.to_pixel((2, 3)), Coordinate { x: 16.0, y: 24.0 });
+    /// assert_eq!(env.to_location([17.5, 31.9]), Some(Location { x: 2, y: 3 }));
+    /// assert_eq!(env.to_location([80.0, 0.0]), None);
+    /// assert!(env.tile_rect((2, 3)).contains([23.9, 24.0]));
+    /// ```
+    pub fn set_grid(&mut self, grid: TileGrid) {
+        self.grid = grid;
+    }
+
+    /// Gets the geometry of the grid of tiles of the Environment in pixels.
+    pub fn grid(&self) -> TileGrid {
+        self.grid
+    }
+
+    /// Gets the pixel Coordinate of the top-left corner of the tile at the
+    /// given Location, according to the TileGrid of the Environment.
+    pub fn to_pixel(&self, location: impl Into<Location>) -> Coordinate {
+        self.grid.to_pixel(location)
+    }
+
+    /// Gets the Location of the tile that contains the given pixel Coordinate,
+    /// according to the TileGrid of the Environment, or None if the Coordinate is
+    /// outside of the Environment.
+    ///
+    /// The Coordinate is expected in the space of the Environment, that is
+    /// before applying the transformation given to `Environment::draw`.
+    pub fn to_location(
+        &self,
+        coordinate: impl Into<Coordinate>,
+    ) -> Option<Location> {
+        let location = self.grid.to_location(coordinate);
+        self.dimension().contains(location).then_some(location)
+    }
+
+    /// Gets the area in pixel coordinates covered by the tile at the given
+    /// Location, according to the TileGrid of the Environment.
+    pub fn tile_rect(&self, location: impl Into<Location>) -> PixelRect {
+        self.grid.tile_rect(location)
     }
 
     /// Returns true only if no Entity is currently in the Environment.
@@ -171,8 +974,21 @@ impl<'e, K: Ord, C> Environment<'e, K, C> {
             .unwrap_or(0)
     }
 
+    /// Gets the total number of entities in the Environment whose Kind belongs
+    /// to the given group.
+    pub fn count_group<G: PartialEq>(&self, group: &G) -> usize
+    where
+        K: KindGroup<G>,
+    {
+        self.entities
+            .iter()
+            .filter(|(kind, _)| kind.group() == *group)
+            .map(|(_, entities)| entities.len())
+            .sum()
+    }
+
     /// Gets the current generation step number.
-    pub fn generation(&self) -> u64 {
+    pub fn generation(&self) -> Generation {
         self.generation
     }
 
@@ -180,10 +996,7 @@ impl<'e, K: Ord, C> Environment<'e, K, C> {
     ///
     /// The entities will be returned in an arbitrary order.
     pub fn entities(&self) -> impl Iterator<Item = &EntityTrait<'e, K, C>> {
-        self.entities
-            .values()
-            .map(|e| e.iter().map(|e| &**e))
-            .flatten()
+        self.entities.values().flat_map(|e| e.iter().map(|e| &**e))
     }
 
     /// Gets an iterator over all the (mutable) entities in the Environment.
@@ -194,8 +1007,76 @@ impl<'e, K: Ord, C> Environment<'e, K, C> {
     ) -> impl Iterator<Item = &mut EntityTrait<'e, K, C>> {
         self.entities
             .values_mut()
-            .map(|e| e.iter_mut().map(|e| &mut **e))
-            .flatten()
+            .flat_map(|e| e.iter_mut().map(|e| &mut **e))
+    }
+
+    /// Calls the given function with the location and the State of each of the
+    /// entities of the given Kind, downcast to the given concrete type, so
+    /// that the states of all the entities of a Kind can be updated at once.
+    ///
+    /// The entities without a location, without a State, or whose State is
+    /// not of the given type are skipped.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((4, 4));
+    /// env.insert(QuickEntity::new('a').id(0).at((1, 2)));
+    /// env.insert(QuickEntity::new('a').id(1).at((3, 0)));
+    ///
+    /// // QuickEntity has no State, therefore none of them is visited
+    /// env.for_each_state_mut(&'a', |_, _: &mut u32| unreachable!());
+    /// ```
+    #[cfg(not(feature = "parallel"))]
+    pub fn for_each_state_mut<S: Any>(
+        &mut self,
+        kind: &K,
+        mut f: impl FnMut(Location, &mut S),
+    ) {
+        let Some(entities) = self.entities.get_mut(kind) else {
+            return;
+        };
+        for entity in entities {
+            if let Some((location, state)) = Self::location_state(entity) {
+                f(location, state);
+            }
+        }
+    }
+
+    /// Calls the given function with the location and the State of each of the
+    /// entities of the given Kind, downcast to the given concrete type, so
+    /// that the states of all the entities of a Kind can be updated at once.
+    ///
+    /// The entities are visited in parallel, and the entities without a
+    /// location, without a State, or whose State is not of the given type are
+    /// skipped.
+    #[cfg(feature = "parallel")]
+    pub fn for_each_state_mut<S: Any + Send>(
+        &mut self,
+        kind: &K,
+        f: impl Fn(Location, &mut S) + Send + Sync,
+    ) {
+        use rayon::prelude::*;
+
+        let Some(entities) = self.entities.get_mut(kind) else {
+            return;
+        };
+        entities.par_iter_mut().for_each(|entity| {
+            if let Some((location, state)) = Self::location_state(entity) {
+                f(location, state);
+            }
+        });
+    }
+
+    /// Gets the location and the State of the given Entity, downcast to the
+    /// given concrete type, if any.
+    fn location_state<'a, S: Any>(
+        entity: &'a mut Box<EntityTrait<'e, K, C>>,
+    ) -> Option<(Location, &'a mut S)> {
+        let location = entity.location()?;
+        let state = entity.state_mut()?.as_any_mut().downcast_mut()?;
+        Some((location, state))
     }
 
     /// Gets an iterator over all the entities located at the given location.
@@ -225,45 +1106,710 @@ impl<'e, K: Ord, C> Environment<'e, K, C> {
         self.tiles.entities_at_mut(location)
     }
 
+    /// Gets an iterator over all the entities located in the given Region.
+    ///
+    /// The entities will be returned in an arbitrary order.
+    /// The Environment is seen as a Torus from this method, therefore, the
+    /// portions of the Region that are out of bounds will be translated
+    /// considering that the Environment edges are joined.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((10, 10));
+    /// env.insert(QuickEntity::new('a').at((9, 9)));
+    /// env.insert(QuickEntity::new('b').at((5, 5)));
+    ///
+    /// let disk = Disk::new((0, 0), 2);
+    /// let kinds: Vec<_> = env.entities_in_region(&disk).map(|e| e.kind()).collect();
+    /// assert_eq!(kinds, vec!['a']);
+    /// ```
+    pub fn entities_in_region<'a>(
+        &'a self,
+        region: &impl Region,
+    ) -> impl Iterator<Item = &'a EntityTrait<'e, K, C>> {
+        region
+            .iter(self.dimension())
+            .flat_map(move |location| self.tiles.entities_at(location))
+    }
+
+    /// Calls the given function for each pair of entities made of an Entity of
+    /// the first Kind and an Entity of the second Kind that are located in the
+    /// same tile or in adjacent tiles (including the diagonals, and across the
+    /// Environment edges).
+    ///
+    /// Each pair is visited exactly once: if the two kinds are the same, the
+    /// Entity with the lower ID is given first. The pairs are visited in an
+    /// arbitrary order, and the entities without a location are skipped.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((10, 10));
+    /// env.insert(QuickEntity::new('a').id(0).at((0, 0)));
+    /// env.insert(QuickEntity::new('a').id(1).at((9, 9)));
+    /// env.insert(QuickEntity::new('a').id(2).at((5, 5)));
+    /// env.insert(QuickEntity::new('b').id(3).at((0, 0)));
+    ///
+    /// let mut pairs = Vec::new();
+    /// env.for_each_adjacent_pair(&'a', &'a', |a, b| pairs.push((a.id(), b.id())));
+    /// assert_eq!(pairs, vec![(0, 1)]);
+    ///
+    /// pairs.clear();
+    /// env.for_each_adjacent_pair(&'a', &'b', |a, b| pairs.push((a.id(), b.id())));
+    /// pairs.sort_unstable();
+    /// assert_eq!(pairs, vec![(0, 3), (1, 3)]);
+    /// ```
+    pub fn for_each_adjacent_pair(
+        &self,
+        first: &K,
+        second: &K,
+        mut f: impl FnMut(&EntityTrait<'e, K, C>, &EntityTrait<'e, K, C>),
+    ) {
+        let dimension = self.dimension();
+        let same = first == second;
+        let entities = self.entities.get(first).into_iter().flatten();
+        for entity in entities {
+            let Some(center) = entity.location() else {
+                continue;
+            };
+            // the adjacent tiles may overlap in Environments smaller than 3x3
+            let mut tiles: Vec<_> = Offset::border(1)
+                .into_iter()
+                .chain(std::iter::once(Offset::origin()))
+                .map(|offset| {
+                    let mut location = center;
+                    *location.translate(offset, dimension)
+                })
+                .collect();
+            tiles.sort_unstable_by_key(|l| l.one_dimensional(dimension));
+            tiles.dedup();
+            for location in tiles {
+                let others = self.tiles.entities_at(location);
+                for other in others.filter(|other| other.kind() == *second) {
+                    if !same || other.id() > entity.id() {
+                        f(&**entity, other);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Gets the area of the Environment surrounding the given location (wrapped
+    /// around the Environment edges) with the given scope, as the Neighborhood
+    /// that an Entity with the same location and scope would see, so that the
+    /// surroundings of any tile can be examined without a dedicated Entity.
+    ///
+    /// Since the Neighborhood is not seen by any Entity, all the entities of
+    /// its tiles are visible, including the ones at its center. Returns None
+    /// if the scope forces the Neighborhood to wrap onto itself due to the
+    /// dimension of the Environment being not big enough to contain it.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((10, 10));
+    /// env.insert(QuickEntity::new('a').at((0, 0)));
+    /// env.insert(QuickEntity::new('b').at((9, 9)));
+    ///
+    /// let neighborhood = env.neighborhood_at((0, 0), Scope::with_magnitude(1));
+    /// let neighborhood = neighborhood.unwrap();
+    /// assert_eq!(neighborhood.dimension(), Dimension { x: 3, y: 3 });
+    /// assert!(neighborhood.contains_kind('a'));
+    /// assert!(neighborhood.contains_kind('b'));
+    /// assert_eq!(neighborhood.center().count(), 1);
+    ///
+    /// assert!(env.neighborhood_at((0, 0), Scope::with_magnitude(5)).is_none());
+    /// ```
+    pub fn neighborhood_at(
+        &mut self,
+        location: impl Into<Location>,
+        scope: Scope,
+    ) -> Option<Neighborhood<'_, 'e, K, C>> {
+        // the Neighborhood allows to mutate the entities of its tiles, and it
+        // must therefore borrow the Environment exclusively
+        self.tiles.neighborhood_at(location.into(), scope)
+    }
+
+    /// Calls the given function with the (mutable) Entity with the given ID
+    /// and with its (mutable) Neighborhood, as seen by the Entity during a
+    /// generation, allowing to edit both at once outside of
+    /// `Environment::nextgen`, and returns the result of the function.
+    ///
+    /// The Entity is excluded from the tiles of its Neighborhood at runtime,
+    /// by ID, so that the function can never get two references to it. The
+    /// Neighborhood is None if the Entity has no location or scope, or if its
+    /// scope would make the Neighborhood wrap onto itself (see
+    /// `Entity::observe`). Once the function returns, the tiles are updated
+    /// with the new locations of the Entity and of its neighbors, if moved.
+    ///
+    /// Returns an error if there is no Entity with the given ID.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((5, 5));
+    /// env.insert(QuickEntity::new('a').id(0).at((1, 1)).scope(1).lifespan(5));
+    /// env.insert(QuickEntity::new('b').id(1).at((2, 1)).lifespan(1));
+    ///
+    /// // drain the lifespan of the neighbors, and push them away
+    /// let count = env
+    ///     .with_entity_and_tiles_mut(0, |entity, neighborhood| {
+    ///         let mut count = 0;
+    ///         for tile in neighborhood.unwrap().tiles_mut() {
+    ///             for neighbor in tile.entities_mut() {
+    ///                 neighbor.lifespan_mut().unwrap().shorten();
+    ///                 entity.lifespan_mut().unwrap().lengthen();
+    ///                 neighbor.location_mut().unwrap().x += 1;
+    ///                 count += 1;
+    ///             }
+    ///         }
+    ///         count
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!(count, 1);
+    /// assert_eq!(env.entities_at((2, 1)).count(), 0);
+    /// let neighbor = env.entities_at((3, 1)).next().unwrap();
+    /// assert!(!neighbor.lifespan().unwrap().is_alive());
+    /// let lifespan = env.entities_at((1, 1)).next().unwrap().lifespan();
+    /// assert_eq!(lifespan.and_then(Lifespan::length), Some(6));
+    /// assert!(env.with_entity_and_tiles_mut(2, |_, _| ()).is_err());
+    /// ```
+    pub fn with_entity_and_tiles_mut<R>(
+        &mut self,
+        id: Id,
+        f: impl FnOnce(
+            &mut EntityTrait<'e, K, C>,
+            Option<Neighborhood<'_, 'e, K, C>>,
+        ) -> R,
+    ) -> Result<R, Error> {
+        let entity = self
+            .entities
+            .values_mut()
+            .flat_map(|entities| entities.iter_mut())
+            .find(|entity| entity.id() == id)
+            .ok_or_else(|| {
+                Error::with_message(format!("Entity {} not found", id))
+            })?;
+
+        // the locations of the tiles the function can move entities from
+        let mut area = LocationSet::default();
+        area.extend(entity.location());
+        let arena = Arena::with_capacity(0);
+        let neighborhood = self.tiles.neighborhood(&**entity, &arena);
+        if let Some(neighborhood) = &neighborhood {
+            area.extend(neighborhood.tiles().map(TileView::location));
+        }
+        let result = f(&mut **entity, neighborhood);
+
+        // the entities are referenced by the tiles of their old location until
+        // they are relocated
+        let moved: Vec<_> = area
+            .into_iter()
+            .flat_map(|location| {
+                self.tiles.entities_at(location).filter_map(move |entity| {
+                    let new = entity.location()?;
+                    (new != location).then(|| (entity.id(), location, new))
+                })
+            })
+            .collect();
+        for (id, old, new) in moved {
+            self.tiles.relocate(id, old, new);
+        }
+        Ok(result)
+    }
+
+    /// Gets a reference to the persistent data of the given type attached to
+    /// the tile at the given location, if any.
+    ///
+    /// The Environment is seen as a Torus from this method, therefore, out of
+    /// bounds locations will be translated considering that the Environment
+    /// edges are joined.
+    pub fn tile_data<T: Any>(
+        &self,
+        location: impl Into<Location>,
+    ) -> Option<&T> {
+        self.tiles.data_at(location)
+    }
+
+    /// Gets a mutable reference to the persistent data of the given type
+    /// attached to the tile at the given location, if any.
+    ///
+    /// The Environment is seen as a Torus from this method, therefore, out of
+    /// bounds locations will be translated considering that the Environment
+    /// edges are joined.
+    pub fn tile_data_mut<T: Any>(
+        &mut self,
+        location: impl Into<Location>,
+    ) -> Option<&mut T> {
+        self.tiles.data_at_mut(location)
+    }
+
+    /// Attaches the given persistent data to the tile at the given location,
+    /// replacing and returning the data of the same type previously attached,
+    /// if any.
+    ///
+    /// The data attached to a tile is distinct from the entities that occupy
+    /// it, and it persists across generations. The entities can access it via
+    /// the tiles of their Neighborhood (see `TileView::data`), for example to
+    /// flip the color of a tile rather than creating and removing entities.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Color(u8);
+    ///
+    /// let mut env = Environment::<u8>::new((10, 10));
+    /// env.set_tile_data((1, 2), Color(0));
+    /// if let Some(color) = env.tile_data_mut::<Color>((1, 2)) {
+    ///     color.0 = 255;
+    /// }
+    ///
+    /// assert_eq!(env.tile_data((11, 12)), Some(&Color(255)));
+    /// assert_eq!(env.tile_data::<Color>((2, 2)), None);
+    /// ```
+    #[cfg(not(feature = "parallel"))]
+    pub fn set_tile_data<T: Any>(
+        &mut self,
+        location: impl Into<Location>,
+        data: T,
+    ) -> Option<T> {
+        self.tiles.set_data_at(location, Box::new(data))
+    }
+
+    /// Attaches the given persistent data to the tile at the given location,
+    /// replacing and returning the data of the same type previously attached,
+    /// if any.
+    ///
+    /// The data attached to a tile is distinct from the entities that occupy
+    /// it, and it persists across generations. The entities can access it via
+    /// the tiles of their Neighborhood (see `TileView::data`), for example to
+    /// flip the color of a tile rather than creating and removing entities.
+    #[cfg(feature = "parallel")]
+    pub fn set_tile_data<T: Any + Send + Sync>(
+        &mut self,
+        location: impl Into<Location>,
+        data: T,
+    ) -> Option<T> {
+        self.tiles.set_data_at(location, Box::new(data))
+    }
+
+    /// Detaches the persistent data of the given type from the tile at the
+    /// given location, and returns it.
+    pub fn remove_tile_data<T: Any>(
+        &mut self,
+        location: impl Into<Location>,
+    ) -> Option<T> {
+        self.tiles.remove_data_at(location)
+    }
+
+    /// Gets the age of the Entity with the given ID, that is the number of
+    /// generations since it was inserted into the Environment, or None if no
+    /// such Entity exists.
+    ///
+    /// The offspring inserted during a generation have an age of 0 in the
+    /// generation that follows, while the entities restored from a snapshot
+    /// are considered inserted in the generation of the snapshot. The age of
+    /// each Entity is also given to the entities via `WorldView::age`, and to
+    /// the Drawable objects via `DrawContext::age`.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8>::new((10, 10));
+    /// env.insert(QuickEntity::new(0).id(1).at((0, 0)));
+    /// assert_eq!(env.age(1), Some(0));
+    ///
+    /// env.nextgen().unwrap();
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.age(1), Some(2));
+    /// assert_eq!(env.age(2), None);
+    /// ```
+    pub fn age(&self, id: Id) -> Option<u64> {
+        let birth = self.births.get(&id)?;
+        Some(self.generation.since(*birth))
+    }
+
+    /// Gets the location of the Entity with the given ID in the previous
+    /// generation.
+    ///
+    /// Returns the current location of the Entity if it did not move during the
+    /// last generation (or if it was inserted after it), or None if the Entity
+    /// has no location or it is not part of the Environment.
+    pub fn previous_location(&self, id: Id) -> Option<Location> {
+        self.previous_locations.get(&id).copied().or_else(|| {
+            self.entities()
+                .find(|entity| entity.id() == id)
+                .and_then(|entity| entity.location())
+        })
+    }
+
+    /// Gets an iterator over the ID of each Entity with a location, and its
+    /// position interpolated between its location in the previous generation
+    /// and its current location, according to the given factor in the range
+    /// [0, 1].
+    ///
+    /// This allows to render the movement of the entities smoothly, when the
+    /// frame rate is higher than the rate of the generations. Since the
+    /// Environment is a Torus, the position is interpolated along the shortest
+    /// path between the two locations, and therefore it can lay outside the
+    /// bounds of the Environment when an Entity moves across its edges.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8>::new((10, 10));
+    /// env.insert(QuickEntity::new(0).id(0).at((9, 0)).on_react(|entity, _| {
+    ///     entity.set_location(Location { x: 0, y: 0 });
+    ///     Ok(())
+    /// }));
+    ///
+    /// env.nextgen().unwrap();
+    /// let (id, position) = env.locations_interp(0.25).next().unwrap();
+    /// assert_eq!(id, 0);
+    /// assert_eq!(position, Vector { x: 9.25, y: 0.0 });
+    /// ```
+    pub fn locations_interp(
+        &self,
+        alpha: f32,
+    ) -> impl Iterator<Item = (Id, Vector)> + use<'_, 'e, K, C> {
+        let dimension = self.dimension();
+        self.entities().filter_map(move |entity| {
+            let id = entity.id();
+            let current = entity.location()?;
+            let previous =
+                self.previous_locations.get(&id).copied().unwrap_or(current);
+            // the shortest displacement along a single axis of the Torus
+            let delta = |from: i32, to: i32, side: i32| {
+                let delta = to - from;
+                if delta * 2 > side {
+                    delta - side
+                } else if delta * 2 < -side {
+                    delta + side
+                } else {
+                    delta
+                }
+            };
+            let dx = delta(previous.x, current.x, dimension.x) as f32;
+            let dy = delta(previous.y, current.y, dimension.y) as f32;
+            let position = Vector {
+                x: previous.x as f32 + dx * alpha,
+                y: previous.y as f32 + dy * alpha,
+            };
+            Some((id, position))
+        })
+    }
+
     /// Moves forwards to the next generation.
     /// Returns the next generation step number.
     ///
     /// Moving to the next generation involves the following actions:
-    /// - Calling `Entity::observe(neighborhood)` for each entity with a snapshot
-    ///     of the portion of the environment seen by the entity according to its
-    ///     scope. The order of the entities called is arbitrary.
-    /// - Calling `Entity::react(neighborhood)` for each entity with a snapshot of
-    ///     the portion of the environment seen by the entity according to its
-    ///     scope. The order of the entities called is arbitrary.
-    /// - Inserting the entities offspring in the environment.
-    /// - Removing the entities that reached the end of their lifespan from the
-    ///     environment.
+    /// - Lifting the protection of the lifespans protected until the current
+    ///   generation (see `Lifespan::Protected`).
+    /// - Ticking the cooldowns of the entities via `Entity::cooldowns_mut()`.
+    /// - Executing the commands scheduled for the current generation (see
+    ///   `Environment::schedule`).
+    /// - Delivering the inputs pushed to the entities via
+    ///   `Entity::on_input(input)` (see `Environment::push_input`).
+    /// - Calling `Entity::observe_with(neighborhood, world)` for each entity
+    ///   with a snapshot of the portion of the environment seen by the entity
+    ///   according to its scope, and a view of the global information of the
+    ///   environment. The order of the entities called is arbitrary.
+    /// - Calling `Entity::react_with(neighborhood, world)` for each entity with
+    ///   a snapshot of the portion of the environment seen by the entity
+    ///   according to its scope, and a view of the global information of the
+    ///   environment. The order of the entities called is arbitrary.
+    ///   Only the entities within the region of interest are observed and
+    ///   reacted, if any (see `Environment::set_active_region`), and only the
+    ///   ones whose Kind is due in the current generation according to its
+    ///   tick group (see `Environment::set_tick_group`).
+    /// - Collecting the `Entity::intent()` of each entity, and moving only the
+    ///   entities whose intent wins over the conflicting ones (that is, the
+    ///   intents with the same destination).
+    /// - Calling `Entity::on_phase(phase, neighborhood)` for each entity and
+    ///   for each of the phases set with `Environment::set_phases`, in order,
+    ///   updating the location of the entities after each phase.
+    /// - Applying the commands issued by the entities to their neighbors via
+    ///   `Entity::neighbor_commands()`.
+    /// - Collecting the property changes notified by the entities via
+    ///   `Entity::notifications()`, and buffering the ones of the subscribed
+    ///   entities and kinds (see `Environment::notifications`).
+    /// - Updating the cells of each Rule attached with `Environment::attach_rule`.
+    /// - Calling `Entity::on_expire()` for each entity whose lifespan is over,
+    ///   in the order of their Kind and then of their ID.
+    /// - Collecting the entities offspring, including the ones inserted by the
+    ///   dying entities in `Entity::on_expire()`.
+    /// - Collecting the commands scheduled by the entities via
+    ///   `Entity::commands()`.
+    /// - Removing the entities that reached the end of their lifespan (and of
+    ///   their grace period) from the environment, in the order of their Kind
+    ///   and then of their ID.
+    /// - Inserting the entities offspring in the environment, according to the
+    ///   deduplication policies and the limits set for their kind, so that
+    ///   the offspring can take the place of the entities removed in the same
+    ///   generation. The offspring are inserted in the order of the IDs of
+    ///   their parents, and then in the order they were inserted by each
+    ///   parent, so that the order is deterministic regardless of the number
+    ///   of threads used with the `parallel` feature.
+    /// - Evicting to disk the chunks not needed by the active entities, and
+    ///   reloading the ones needed again, if enabled (see
+    ///   `Environment::enable_chunk_streaming`).
+    /// - Updating the render values of the enabled kinds via
+    ///   `Entity::render_value()` (see `Environment::enable_render_values`).
+    /// - Sending the insertions and removals of the entities to their
+    ///   subscribers (see `Environment::subscribe`).
     ///
     /// This method will return an error if any of the calls to `Entity::observe()`
     /// or `Entity::react()` returns an error, in which case none of the steps that
-    /// involve the update of the environment will take place.
-    pub fn nextgen(&mut self) -> Result<u64, Error> {
+    /// follow them will take place and the generation is not incremented.
+    /// The steps that precede them are not undone though: the scheduled
+    /// commands already executed and the inputs already delivered stay
+    /// applied, and they are not executed nor delivered again when moving to
+    /// the next generation is retried, just like the cooldowns of the entities
+    /// are not ticked again, and their expired protections are not lifted
+    /// again.
+    /// An error is also returned if any of the scheduled commands returns an
+    /// error, in which case the commands not yet executed are kept, to be
+    /// executed by the next attempt, or if any of the calls to
+    /// `Entity::on_input()` returns an error, in which case the inputs not yet
+    /// delivered are kept as well.
+    /// An error is also returned if the offspring exceed the limit of a kind
+    /// with the `EvictionPolicy::Error` policy, in which case the offspring are
+    /// discarded and the generation is not completed (before any Entity is
+    /// removed from the Environment), or if the commands
+    /// scheduled by the entities exceed the capacity set with
+    /// `Environment::set_event_capacity`.
+    /// An error is also returned if the chunk streaming is enabled and any of
+    /// the chunks cannot be evicted or reloaded.
+    /// Finally, an error is returned if the history is enabled (see
+    /// `Environment::enable_history`) and the snapshot of the next generation
+    /// cannot be recorded.
+    ///
+    /// In debug builds, this method panics if any of the entities breaks the
+    /// contract of the Entity trait (such as changing its location from Some
+    /// to None), instead of silently leaving the environment in an
+    /// inconsistent state. The same applies when inserting an Entity with a
+    /// scope but without a location.
+    ///
+    /// With the `parallel` feature, the kinds of the entities are shared
+    /// between the threads the entities are dispatched on, and they must
+    /// therefore be Send and Sync (see `ParallelKind`).
+    pub fn nextgen(&mut self) -> Result<Generation, Error>
+    where
+        K: ParallelKind,
+    {
+        self.nextgen_with_layers(&[], None, &Progress::none())
+    }
+
+    /// Moves forwards to the next generation, where the given layers of the
+    /// EnvironmentStack the Environment belongs to and the given shared
+    /// context are visible to its entities via their WorldView, notifying the
+    /// given Progress.
+    /// Returns the next generation step number.
+    pub(super) fn nextgen_with_layers(
+        &mut self,
+        layers: &[Option<&Environment<'e, K, C>>],
+        shared: Option<&Shared>,
+        progress: &Progress<'_>,
+    ) -> Result<Generation, Error>
+    where
+        K: ParallelKind,
+    {
+        self.reset_generation_stats();
+        // the protections are lifted and the cooldowns are ticked only once
+        // per generation, even if moving to the next generation is retried
+        // after an error
+        if self.started != Some(self.generation) {
+            self.started = Some(self.generation);
+            self.lift_protections();
+            self.tick_cooldowns();
+        }
+        self.run_due_commands()?;
+        self.deliver_inputs()?;
+        let digest = self.pending_digest();
+        self.previous_locations.clear();
         self.record_location();
-        self.observe_and_react()?;
+        self.seed_perceptions();
+        self.observe_and_react(layers, shared, progress)?;
+        let stage = progress.stage("move", 1)?;
+        self.resolve_intents();
         self.update_location();
+        stage.step()?;
+        self.run_phases(progress)?;
+        self.apply_neighbor_commands();
+        self.collect_notifications();
+        self.step_rules(progress)?;
+        #[cfg(debug_assertions)]
+        self.check_contracts();
 
         // take care of newborns entities by inserting them in the environment,
-        // as well as removing entities that reached the end of their lifespan
-        self.populate_with_offspring();
+        // after removing the entities that reached the end of their lifespan,
+        // so that the offspring can take the place of the dead ones
+        let stage = progress.stage("offspring", 1)?;
+        self.expire_dead()?;
+        let offspring = self.take_offspring();
+        let offspring = self.dedup_offspring(offspring);
+        self.check_offspring_limits(&offspring)?;
+        self.collect_commands()?;
         self.depopulate_dead();
+        self.populate_with_offspring(offspring);
+        self.stream_chunks()?;
+        stage.step()?;
+        self.update_render_values();
 
-        self.generation = self.generation.wrapping_add(1);
+        self.record_digest(digest);
+        self.deliver_events();
+        self.generation = self.generation.next();
+        self.record_history()?;
         Ok(self.generation)
     }
 
+    /// Sets the resolver of the conflicts between entities that intend to move
+    /// into the same tile during the same generation.
+    ///
+    /// The resolver is given the (wrapped) destination and the list of the IDs
+    /// of the competing entities with their intents, and returns the index of
+    /// the winning intent, or None if none of the entities should move.
+    /// By default, the intent with the highest priority wins, and ties are
+    /// broken in favor of the Entity with the lowest ID.
+    #[cfg(not(feature = "parallel"))]
+    pub fn set_move_resolver(
+        &mut self,
+        resolver: impl Fn(Location, &[(Id, MoveIntent)]) -> Option<usize> + 'e,
+    ) {
+        self.move_resolver = Some(Box::new(resolver));
+    }
+
+    /// Sets the resolver of the conflicts between entities that intend to move
+    /// into the same tile during the same generation.
+    ///
+    /// The resolver is given the (wrapped) destination and the list of the IDs
+    /// of the competing entities with their intents, and returns the index of
+    /// the winning intent, or None if none of the entities should move.
+    /// By default, the intent with the highest priority wins, and ties are
+    /// broken in favor of the Entity with the lowest ID.
+    #[cfg(feature = "parallel")]
+    pub fn set_move_resolver(
+        &mut self,
+        resolver: impl Fn(Location, &[(Id, MoveIntent)]) -> Option<usize>
+            + Send
+            + Sync
+            + 'e,
+    ) {
+        self.move_resolver = Some(Box::new(resolver));
+    }
+
+    /// Sets the seed of the GenomeRng given to `Genome::crossover` and
+    /// `Genome::mutate` to derive the genomes of the children inserted via
+    /// `Offspring::insert_child_of`, that is 0 by default.
+    ///
+    /// The children are derived in the order their parents are stored, so
+    /// that the same seed gives the same genomes across runs, as long as the
+    /// entities are inserted in the same order.
+    pub fn set_genome_seed(&mut self, seed: u64) {
+        self.genome_rng = GenomeRng::new(seed);
+    }
+
+    /// Collects the move intents of all the entities, resolves the conflicts
+    /// between intents with the same destination, and moves the winning
+    /// entities to their destination.
+    fn resolve_intents(&mut self) {
+        let dimension = self.dimension();
+        let mut intents: LocationMap<Vec<(Id, MoveIntent, &mut _)>> =
+            LocationMap::default();
+        let passive = &self.passive;
+        let (ticks, generation) = (&self.ticks, self.generation);
+        let region = self.active_region;
+        let entities = self
+            .entities
+            .iter_mut()
+            .filter(|(kind, _)| {
+                !passive.contains(kind) && ticks.is_due(kind, generation)
+            })
+            .flat_map(|(_, e)| e.iter_mut())
+            .filter(|e| is_live(&***e, region, dimension));
+        for entity in entities {
+            if let Some(mut intent) = entity.intent() {
+                let mut destination = Location::origin();
+                destination.translate(intent.destination, dimension);
+                intent.destination = destination;
+                let candidates = intents.entry(destination).or_default();
+                candidates.push((entity.id(), intent, entity));
+            }
+        }
+
+        for (destination, mut candidates) in intents {
+            let winner = if let Some(resolver) = &self.move_resolver {
+                let intents: Vec<_> =
+                    candidates.iter().map(|(id, i, _)| (*id, *i)).collect();
+                resolver(destination, &intents)
+            } else {
+                candidates
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(_, (id, intent, _))| {
+                        (intent.priority, std::cmp::Reverse(*id))
+                    })
+                    .map(|(i, _)| i)
+            };
+
+            if let Some(winner) = winner.filter(|&i| i < candidates.len()) {
+                let (_, intent, entity) = candidates.swap_remove(winner);
+                if let Some(location) = entity.location_mut() {
+                    *location = intent.destination;
+                }
+            }
+        }
+    }
+
+    /// Gets the number of entities whose Kind is not passive and is due in the
+    /// current generation, and that are live according to the region of
+    /// interest, if any.
+    fn active_count(&self) -> usize {
+        let entities = self
+            .entities
+            .iter()
+            .filter(|(kind, _)| {
+                !self.passive.contains(kind)
+                    && self.ticks.is_due(kind, self.generation)
+            })
+            .map(|(_, entities)| entities);
+        match self.active_region {
+            None => entities.map(Vec::len).sum(),
+            region => {
+                let dimension = self.dimension();
+                entities
+                    .flatten()
+                    .filter(|e| is_live(&***e, region, dimension))
+                    .count()
+            }
+        }
+    }
+
     /// Takes a snapshot of the environment by storing the entities fields that
     /// are going to be updated before moving forward to the next generation.
     fn record_location(&mut self) {
         self.snapshots.clear();
-        let additional = self.count().saturating_sub(self.snapshots.capacity());
+        let additional = self
+            .active_count()
+            .saturating_sub(self.snapshots.capacity());
         self.snapshots.reserve(additional);
 
-        for entities in self.entities.values() {
+        let passive = &self.passive;
+        for (_, entities) in self
+            .entities
+            .iter()
+            .filter(|(kind, _)| !passive.contains(kind))
+        {
             for (i, entity) in entities.iter().enumerate() {
                 if let Some(location) = entity.location() {
                     self.snapshots.push(Snapshot {
@@ -296,105 +1842,283 @@ impl<'e, K: Ord, C> Environment<'e, K, C> {
             if let Some((id, location)) = find_entity(snapshot) {
                 debug_assert_ne!(location, snapshot.location);
                 self.tiles.relocate(id, snapshot.location, location);
+                // keep the location the entity had at the beginning of the
+                // generation, if it moves more than once
+                self.previous_locations
+                    .entry(id)
+                    .or_insert(snapshot.location);
             }
         }
     }
 
-    /// Collects the offspring of all the entities and insert the new entities
-    /// in the environment.
-    fn populate_with_offspring(&mut self) {
-        // gets a list of all the entities offsprings
-        let offspring: Vec<Box<EntityTrait<'e, K, C>>> = self
+    /// Collects the offspring of all the entities, including the ones that
+    /// are about to be removed, located relative to their parents and with
+    /// their IDs assigned (see `Entity::assign_id`).
+    fn take_offspring(&mut self) -> Vec<Box<EntityTrait<'e, K, C>>> {
+        // gets a list of all the entities offsprings, sorted by the ID of
+        // their parents (the sort is stable, so that the children of the same
+        // parent keep their order), so that the order the offspring are
+        // inserted in does not depend on how the entities are stored
+        let mut parents: Vec<_> = self
             .entities
             .values_mut()
-            .map(|e| e.iter_mut())
-            .flatten()
-            .filter_map(|e| e.offspring())
-            .map(|offspring| offspring.take_entities())
-            .flatten()
+            .flat_map(|e| e.iter_mut())
+            .filter_map(|e| {
+                let parent = (e.id(), e.location());
+                e.offspring().map(|offspring| (parent, offspring))
+            })
             .collect();
+        parents.sort_by_key(|&((id, _), _)| id);
 
-        // collect entities offsprings and insert them in the environment
-        for entity in offspring {
+        // then locate them relative to their parents
+        let dimension = self.dimension();
+        let genome_rng = &mut self.genome_rng;
+        let mut offspring: Vec<_> = parents
+            .into_iter()
+            .flat_map(|((_, parent), offspring)| {
+                offspring.take_entities(parent, dimension, genome_rng)
+            })
+            .collect();
+        // the IDs are assigned before the offspring are deduplicated
+        for entity in &mut offspring {
+            self.assign_id(&mut **entity);
+        }
+        offspring
+    }
+
+    /// Inserts the given (deduplicated) offspring in the environment,
+    /// according to the limits of their Kind.
+    fn populate_with_offspring(
+        &mut self,
+        offspring: Vec<Box<EntityTrait<'e, K, C>>>,
+    ) {
+        for entity in self.limit_offspring(offspring) {
+            let id = entity.id();
             self.insert_boxed(entity);
+            // the offspring are born in the next generation
+            self.births.insert(id, self.generation + 1);
         }
     }
 
-    /// Removes all the entities that reached the end of their lifespan.
-    fn depopulate_dead(&mut self) {
-        for entities in self.entities.values_mut() {
-            // remove the weak reference to the entity from the grid of tiles only
-            // if it has a location and it reached the end of its lifespan
-            for entity in entities.iter() {
-                match (entity.location(), entity.lifespan()) {
-                    (Some(loc), Some(lifespan)) if !lifespan.is_alive() => {
-                        self.tiles.remove(entity.id(), loc);
+    /// Turns the lifespans protected until the current generation (or an
+    /// earlier one) into ephemeral lifespans with the same span.
+    fn lift_protections(&mut self) {
+        let generation = self.generation;
+        for entity in self.entities.values_mut().flat_map(|e| e.iter_mut()) {
+            if let Some(lifespan) = entity.lifespan_mut() {
+                if let Lifespan::Protected { span, until } = *lifespan {
+                    if until <= generation {
+                        *lifespan = Lifespan::Ephemeral(span);
                     }
-                    _ => (),
-                };
+                }
             }
-            // remove the strong reference to the entity if it reached the end
-            // of its lifespan
-            entities.retain(|entity| {
-                if let Some(lifespan) = entity.lifespan() {
-                    lifespan.is_alive()
-                } else {
-                    true
+        }
+    }
+
+    /// Moves the cooldowns of all the entities forward by one generation.
+    ///
+    /// This must be called only once per generation.
+    fn tick_cooldowns(&mut self) {
+        for entity in self.entities.values_mut().flat_map(|e| e.iter_mut()) {
+            if let Some(cooldowns) = entity.cooldowns_mut() {
+                cooldowns.tick();
+            }
+        }
+    }
+
+    /// Calls `Entity::on_expire` for all the entities whose lifespan is over
+    /// since this generation, in the order of their Kind and then of their
+    /// ID, and starts their grace period.
+    ///
+    /// Returns an error if any of the calls to `Entity::on_expire()` returns an
+    /// error.
+    fn expire_dead(&mut self) -> Result<(), Error> {
+        let mut entities: Vec<_> = self
+            .entities
+            .values_mut()
+            .flat_map(|entities| {
+                let mut entities: Vec<_> = entities.iter_mut().collect();
+                entities.sort_by_key(|entity| entity.id());
+                entities
+            })
+            .collect();
+        for entity in &mut entities {
+            let id = entity.id();
+            match entity.lifespan() {
+                Some(lifespan) if !lifespan.is_alive() => {
+                    if self.expiring.contains_key(&id) {
+                        continue;
+                    }
+                    entity.on_expire()?;
+                    // the entity may have restored its own lifespan
+                    if let Some(lifespan) = entity.lifespan() {
+                        if !lifespan.is_alive() {
+                            self.expiring.insert(id, lifespan.grace());
+                        }
+                    }
+                }
+                // entities brought back to life during their grace period
+                _ if !self.expiring.is_empty() => {
+                    self.expiring.remove(&id);
                 }
-            });
+                _ => (),
+            }
         }
+        Ok(())
+    }
+
+    /// Returns true only if the given Entity reached the end of its lifespan,
+    /// and its grace period is over, so that it is going to be removed by
+    /// `Environment::depopulate_dead`.
+    pub(super) fn is_dead(&self, entity: &EntityTrait<'e, K, C>) -> bool {
+        entity
+            .lifespan()
+            .is_some_and(|lifespan| !lifespan.is_alive())
+            && self
+                .expiring
+                .get(&entity.id())
+                .is_none_or(|&grace| grace == 0)
+    }
+
+    /// Removes all the entities that reached the end of their lifespan, and
+    /// whose grace period is over, in the order of their Kind and then of
+    /// their ID.
+    fn depopulate_dead(&mut self) {
+        let mut expiring = std::mem::take(&mut self.expiring);
+        self.retain_entities(|entity| match entity.lifespan() {
+            Some(lifespan) if !lifespan.is_alive() => {
+                match expiring.get_mut(&entity.id()) {
+                    Some(grace) if *grace > 0 => {
+                        *grace -= 1;
+                        true
+                    }
+                    _ => {
+                        expiring.remove(&entity.id());
+                        false
+                    }
+                }
+            }
+            _ => true,
+        });
+        self.expiring = expiring;
     }
 
     /// Iterate over each entity and allow them to:
     /// - Execute the provided custom closure the mutable reference of each
-    ///     entity.
+    ///   entity.
     /// - Manifest their behavior by calling `Entity::observe(neighborhood)`,
-    ///     exposing them to the portion of environment they can see from their
-    ///     current location
+    ///   exposing them to the portion of environment they can see from their
+    ///   current location
     /// - For all the same entities, call `Entity::react(neighborhood)`,
-    ///     allowing each entity to react to the same portion of the environment.
+    ///   allowing each entity to react to the same portion of the environment.
+    ///
     /// Returns an error if any of the calls to `Entity::observe()`,
     /// `Entity::react()`, or the provided closure returns an error.
     #[cfg(not(feature = "parallel"))]
-    fn observe_and_react(&mut self) -> Result<(), Error> {
-        // allow all the entities to observe their neighborhood
-        for entities in self.entities.values_mut() {
-            for entity in entities.iter_mut() {
-                let neighborhood = self.tiles.neighborhood(&**entity);
-                entity.observe(neighborhood)?;
+    fn observe_and_react(
+        &mut self,
+        layers: &[Option<&Environment<'e, K, C>>],
+        shared: Option<&Shared>,
+        progress: &Progress<'_>,
+    ) -> Result<(), Error> {
+        // the tiles of all the neighborhoods of this generation are allocated
+        // in the same arena, released at once at the end of the generation
+        let arena = Arena::with_capacity(self.arena_capacity);
+        let world = Self::world_view(
+            self.generation,
+            self.clock,
+            &self.tiles,
+            &self.entities,
+            &self.births,
+            &self.resources,
+            layers,
+        )
+        .with_shared(shared);
+
+        // allow all the live entities to observe their neighborhood
+        let passive = &self.passive;
+        let (ticks, generation) = (&self.ticks, self.generation);
+        let (region, dimension) = (self.active_region, self.dimension());
+        let stage = progress.stage("observe", self.active_count())?;
+        for (_, entities) in self.entities.iter_mut().filter(|(kind, _)| {
+            !passive.contains(kind) && ticks.is_due(kind, generation)
+        }) {
+            for entity in entities
+                .iter_mut()
+                .filter(|e| is_live(&***e, region, dimension))
+            {
+                let neighborhood = self.tiles.neighborhood(&**entity, &arena);
+                entity.observe_with(neighborhood, &world)?;
+                stage.step()?;
             }
         }
 
         // then allow the same entities to react to the same neighborhoods
-        for entities in self.entities.values_mut() {
-            for entity in entities.iter_mut() {
-                let neighborhood = self.tiles.neighborhood(&**entity);
-                entity.react(neighborhood)?;
+        let stage = progress.stage("react", self.active_count())?;
+        for (_, entities) in self.entities.iter_mut().filter(|(kind, _)| {
+            !passive.contains(kind) && ticks.is_due(kind, generation)
+        }) {
+            for entity in entities
+                .iter_mut()
+                .filter(|e| is_live(&***e, region, dimension))
+            {
+                let neighborhood = self.tiles.neighborhood(&**entity, &arena);
+                entity.react_with(neighborhood, &world)?;
+                stage.step()?;
             }
         }
 
+        self.arena_capacity = arena.len();
         Ok(())
     }
 
     /// Iterate over each entity and allow them to:
     /// - Execute the provided custom closure the mutable reference of each
-    ///     entity.
+    ///   entity.
     /// - Manifest their behavior by calling `Entity::observe(neighborhood)`,
-    ///     exposing them to the portion of environment they can see from their
-    ///     current location
+    ///   exposing them to the portion of environment they can see from their
+    ///   current location
     /// - For all the same entities, call `Entity::react(neighborhood)`,
-    ///     allowing each entity to react to the same portion of the environment.
+    ///   allowing each entity to react to the same portion of the environment.
+    ///
     /// Returns an error if any of the calls to `Entity::observe()`,
     /// `Entity::react()`, or the provided closure returns an error.
     #[cfg(feature = "parallel")]
-    fn observe_and_react(&mut self) -> Result<(), Error> {
+    fn observe_and_react(
+        &mut self,
+        layers: &[Option<&Environment<'e, K, C>>],
+        shared: Option<&Shared>,
+        progress: &Progress<'_>,
+    ) -> Result<(), Error>
+    where
+        K: ParallelKind,
+    {
         use rayon::prelude::*;
 
+        let world = Self::world_view(
+            self.generation,
+            self.clock,
+            &self.tiles,
+            &self.entities,
+            &self.births,
+            &self.resources,
+            layers,
+        )
+        .with_shared(shared);
+        let world = &world;
+        let count = self.active_count();
+
+        let passive = &self.passive;
+        let (ticks, generation) = (&self.ticks, self.generation);
+        let (region, dimension) = (self.active_region, self.dimension());
         let entities = self
             .entities
-            .values_mut()
-            .map(|e| e.iter_mut())
-            .flatten()
+            .iter_mut()
+            .filter(|(kind, _)| {
+                !passive.contains(kind) && ticks.is_due(kind, generation)
+            })
+            .flat_map(|(_, e)| e.iter_mut())
+            .filter(|e| is_live(&***e, region, dimension))
             .map(|e| &mut **e);
 
         let scheduler::Tasks {
@@ -404,34 +2128,59 @@ impl<'e, K: Ord, C> Environment<'e, K, C> {
 
         let tiles = &self.tiles;
 
-        // allow all the entities to observe their neighborhood
-        sync.par_iter_mut().try_for_each(|entities| {
-            for e in entities.iter_mut() {
-                let neighborhood = tiles.neighborhood(*e);
-                e.observe(neighborhood)?;
-            }
-            Ok(())
-        })?;
+        // allow all the entities to observe their neighborhood, where each task
+        // allocates the tiles of its neighborhoods in its own arena
+        let stage = progress.stage("observe", count)?;
+        let stage = &stage;
+        #[cfg(feature = "aliasing-checks")]
+        let log = scheduler::BorrowLog::new("observe");
+        sync.par_iter_mut()
+            .enumerate()
+            .try_for_each(|(_task, entities)| {
+                let arena = Arena::default();
+                for e in entities.iter_mut() {
+                    let neighborhood = tiles.neighborhood(*e, &arena);
+                    #[cfg(feature = "aliasing-checks")]
+                    log.record_neighborhood(_task, *e, neighborhood.as_ref());
+                    e.observe_with(neighborhood, world)?;
+                    stage.step()?;
+                }
+                Ok(())
+            })?;
 
+        let arena = Arena::with_capacity(self.arena_capacity);
         for e in &mut unsync {
-            let neighborhood = self.tiles.neighborhood(*e);
-            e.observe(neighborhood)?;
+            let neighborhood = tiles.neighborhood(*e, &arena);
+            e.observe_with(neighborhood, world)?;
+            stage.step()?;
         }
 
         // finally allow the same entities to react to the same neighborhoods
-        sync.par_iter_mut().try_for_each(|entities| {
-            for e in entities.iter_mut() {
-                let neighborhood = tiles.neighborhood(*e);
-                e.react(neighborhood)?;
-            }
-            Ok(())
-        })?;
+        let stage = progress.stage("react", count)?;
+        let stage = &stage;
+        #[cfg(feature = "aliasing-checks")]
+        let log = scheduler::BorrowLog::new("react");
+        sync.par_iter_mut()
+            .enumerate()
+            .try_for_each(|(_task, entities)| {
+                let arena = Arena::default();
+                for e in entities.iter_mut() {
+                    let neighborhood = tiles.neighborhood(*e, &arena);
+                    #[cfg(feature = "aliasing-checks")]
+                    log.record_neighborhood(_task, *e, neighborhood.as_ref());
+                    e.react_with(neighborhood, world)?;
+                    stage.step()?;
+                }
+                Ok(())
+            })?;
 
         for e in unsync {
-            let neighborhood = self.tiles.neighborhood(e);
-            e.react(neighborhood)?;
+            let neighborhood = tiles.neighborhood(e, &arena);
+            e.react_with(neighborhood, world)?;
+            stage.step()?;
         }
 
+        self.arena_capacity = arena.len();
         Ok(())
     }
 }
@@ -1,20 +1,99 @@
 use std::collections::HashSet;
+use std::ops::{Deref, DerefMut};
 
 use super::*;
 
 /// The neighbor tiles of a specific Entity.
 #[derive(Debug)]
-pub struct Neighborhood<'a, 'e, K, C> {
+pub struct Neighborhood<'a, 'e, K, C = ()> {
+    // the number of columns and rows of the grid of tiles
     dimension: Dimension,
-    tiles: Vec<TileView<'a, 'e, K, C>>,
+    // the position of the center Tile (where the Entity is located) within the
+    // grid of tiles
+    center: Location,
+    // the local frame the offsets given to the Neighborhood are relative to
+    orientation: Orientation,
+    tiles: TileViews<'a, 'e, K, C>,
+}
+
+/// The list of tiles of a Neighborhood, that can either be owned by the
+/// Neighborhood, or borrowed from the Arena of the current generation.
+#[derive(Debug)]
+enum TileViews<'a, 'e, K, C> {
+    Owned(Vec<TileView<'a, 'e, K, C>>),
+    Borrowed(&'a mut [TileView<'a, 'e, K, C>]),
+}
+
+impl<'a, 'e, K, C> Deref for TileViews<'a, 'e, K, C> {
+    type Target = [TileView<'a, 'e, K, C>];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Owned(tiles) => tiles,
+            Self::Borrowed(tiles) => tiles,
+        }
+    }
+}
+
+impl<'a, 'e, K, C> DerefMut for TileViews<'a, 'e, K, C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Owned(tiles) => tiles,
+            Self::Borrowed(tiles) => tiles,
+        }
+    }
 }
 
 impl<'a, 'e, K, C> Neighborhood<'a, 'e, K, C> {
     /// Gets the dimension of this neighborhood.
+    ///
+    /// The dimension of a Neighborhood is not necessarily a square, nor is its
+    /// center Tile necessarily located in the middle of its grid of tiles.
     pub fn dimension(&self) -> Dimension {
         self.dimension
     }
 
+    /// Gets the position of the center Tile of this Neighborhood within its
+    /// grid of tiles, where the top-left Tile is located at the origin.
+    ///
+    /// All the offsets given to the methods of the Neighborhood are relative
+    /// to this position.
+    pub fn center_position(&self) -> Location {
+        self.center
+    }
+
+    /// Views this Neighborhood from the local frame of an Entity with the given
+    /// Orientation, so that all the offsets given to its methods are rotated
+    /// accordingly, and the tile in front of the Entity is always at
+    /// `Offset { x: 0, y: -1 }` regardless of its facing.
+    ///
+    /// Neighborhoods are oriented North by default, where the local frame and
+    /// the grid of the Environment coincide.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((5, 5));
+    /// env.insert(QuickEntity::new('a').id(0).at((3, 2)));
+    ///
+    /// let scope = Scope::with_magnitude(1);
+    /// let neighborhood = env.neighborhood_at((2, 2), scope).unwrap();
+    /// let neighborhood = neighborhood.oriented(Orientation::East);
+    /// assert_eq!(neighborhood.tile((0, -1)).entities().count(), 1);
+    /// assert_eq!(neighborhood.tile((1, 0)).entities().count(), 0);
+    /// ```
+    pub fn oriented(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Gets the Orientation of the local frame of this Neighborhood (see
+    /// `Neighborhood::oriented`).
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
     /// Gets an iterator over all the Tiles that belong to this Neighborhood.
     pub fn tiles(&self) -> impl Iterator<Item = &TileView<'a, 'e, K, C>> {
         self.tiles.iter()
@@ -74,10 +153,10 @@ impl<'a, 'e, K, C> Neighborhood<'a, 'e, K, C> {
         offset: impl Into<Offset>,
         scope: impl Into<Scope>,
     ) -> Option<Vec<&TileView<'a, 'e, K, C>>> {
-        let offset = offset.into();
+        let offset = self.orientation.to_world(offset);
         let scope = scope.into();
         // the location of the tile T relative to the center of the Neighborhood
-        let loc = self.dimension.center() + offset;
+        let loc = self.center + offset;
 
         // iterate over the 4 corners surrounding the tile T to check if
         // the whole border of the tile T is contained within this Neighborhood
@@ -92,7 +171,7 @@ impl<'a, 'e, K, C> Neighborhood<'a, 'e, K, C> {
             Vec::with_capacity(Dimension::perimeter_with_scope(scope));
         for mut delta in Offset::border(scope) {
             let center_offset = *delta.translate(offset, self.dimension);
-            tiles.push(self.tile(center_offset))
+            tiles.push(&self.tiles[self.world_index(center_offset)])
         }
 
         debug_assert_eq!(tiles.capacity(), tiles.len());
@@ -112,15 +191,122 @@ impl<'a, 'e, K, C> Neighborhood<'a, 'e, K, C> {
         self.border(Offset::origin(), scope)
     }
 
+    /// Gets an iterator over the entities of this Neighborhood that form a
+    /// pair with the Entity with the given ID (usually the Entity inspecting
+    /// this Neighborhood), that is, only the entities with a greater ID.
+    ///
+    /// When two entities with the same Scope see each other, only the one with
+    /// the lower ID gets the other from this method, so that each interaction
+    /// between a pair of entities can be handled by one of them, once per
+    /// generation, rather than being discovered by both sides.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((5, 5));
+    /// env.insert(QuickEntity::new('a').id(0).at((2, 2)));
+    /// env.insert(QuickEntity::new('a').id(1).at((3, 2)));
+    /// env.insert(QuickEntity::new('a').id(2).at((2, 3)));
+    ///
+    /// let scope = Scope::with_magnitude(1);
+    /// let neighborhood = env.neighborhood_at((2, 2), scope).unwrap();
+    /// let mut pairs: Vec<_> = neighborhood.pairs_with(1).map(|e| e.id()).collect();
+    /// assert_eq!(pairs, vec![2]);
+    /// pairs = neighborhood.pairs_with(0).map(|e| e.id()).collect();
+    /// pairs.sort_unstable();
+    /// assert_eq!(pairs, vec![1, 2]);
+    /// ```
+    pub fn pairs_with(
+        &self,
+        id: Id,
+    ) -> impl Iterator<Item = &EntityTrait<'e, K, C>> {
+        self.tiles
+            .iter()
+            .flat_map(|tile| tile.entities())
+            .filter(move |entity| entity.id() > id)
+    }
+
+    /// Folds all the entities of this Neighborhood into a single value, by
+    /// applying the given function to the accumulator and to each Entity,
+    /// starting from the given initial value, without considering the Entity
+    /// that is inspecting this Neighborhood.
+    ///
+    /// The entities are visited in arbitrary order.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((5, 5));
+    /// env.insert(QuickEntity::new('a').id(0).at((2, 2)));
+    /// env.insert(QuickEntity::new('b').id(1).at((3, 2)));
+    /// env.insert(QuickEntity::new('b').id(2).at((1, 1)));
+    ///
+    /// let scope = Scope::with_magnitude(1);
+    /// let neighborhood = env.neighborhood_at((2, 2), scope).unwrap();
+    /// let kinds = neighborhood.fold_entities(String::new(), |mut kinds, e| {
+    ///     kinds.push(e.kind());
+    ///     kinds
+    /// });
+    /// assert_eq!(kinds.len(), 3);
+    /// assert_eq!(neighborhood.sum_by(|e| e.id() as u64), 3);
+    /// let max = neighborhood.max_by_key(|e| (e.kind(), e.id())).unwrap();
+    /// assert_eq!(max.id(), 2);
+    /// ```
+    pub fn fold_entities<B>(
+        &self,
+        init: B,
+        f: impl FnMut(B, &EntityTrait<'e, K, C>) -> B,
+    ) -> B {
+        self.neighbors().fold(init, f)
+    }
+
+    /// Gets the sum of the values given by the given function for each Entity
+    /// of this Neighborhood, without considering the Entity that is inspecting
+    /// this Neighborhood, such as the score of the neighbors.
+    pub fn sum_by(&self, f: impl FnMut(&EntityTrait<'e, K, C>) -> u64) -> u64 {
+        self.neighbors().map(f).sum()
+    }
+
+    /// Gets the Entity of this Neighborhood with the maximum value given by the
+    /// given function, without considering the Entity that is inspecting this
+    /// Neighborhood. If several entities are equally maximum, the last one
+    /// visited is returned, in arbitrary order.
+    ///
+    /// Returns None if the Neighborhood contains no other entities.
+    pub fn max_by_key<B: Ord>(
+        &self,
+        mut f: impl FnMut(&EntityTrait<'e, K, C>) -> B,
+    ) -> Option<&EntityTrait<'e, K, C>> {
+        self.neighbors().max_by_key(|entity| f(*entity))
+    }
+
+    /// Gets an iterator over all the entities of this Neighborhood, skipping
+    /// the empty tiles, without considering the Entity that is inspecting this
+    /// Neighborhood.
+    fn neighbors(&self) -> impl Iterator<Item = &EntityTrait<'e, K, C>> {
+        self.tiles
+            .iter()
+            .filter(|tile| !tile.is_empty())
+            .flat_map(|tile| tile.entities())
+    }
+
     /// Gets the index of the Tile located at the given offset from the center
-    /// of this Neighborhood.
+    /// of this Neighborhood, in its local frame.
+    fn index(&self, offset: impl Into<Offset>) -> usize {
+        self.world_index(self.orientation.to_world(offset))
+    }
+
+    /// Gets the index of the Tile located at the given offset from the center
+    /// of this Neighborhood, in the grid of the Environment.
     ///
     /// The Neighborhood is seen as a Torus from this method, therefore, out of
     /// bounds offsets will be translated considering that the Neighborhood
     /// edges are joined.
-    fn index(&self, offset: impl Into<Offset>) -> usize {
+    fn world_index(&self, offset: Offset) -> usize {
         debug_assert!(!self.tiles.is_empty());
-        let mut center = self.dimension.center();
+        let mut center = self.center;
         let index = center
             .translate(offset, self.dimension)
             .one_dimensional(self.dimension);
@@ -128,6 +314,45 @@ impl<'a, 'e, K, C> Neighborhood<'a, 'e, K, C> {
         index
     }
 
+    /// Hides the entities of this Neighborhood that are not perceived with
+    /// the given Perception, where the neighbors are missed according to the
+    /// given seed.
+    pub(crate) fn perceive(&mut self, perception: Perception, seed: u64) {
+        let miss_rate = perception.miss_rate();
+        let (dimension, center) = (self.dimension, self.center);
+        let distance = |index| {
+            let offset =
+                Location::from_one_dimensional(index, dimension) - center;
+            offset.x.pow(2) + offset.y.pow(2)
+        };
+        // the nearest neighbors perceived, up to the maximum count
+        let cutoff = perception.max_count().map(|max_count| {
+            let sense = Sense::new(seed, miss_rate, 0, None);
+            let mut perceived: Vec<_> = self
+                .tiles
+                .iter()
+                .enumerate()
+                .flat_map(|(index, tile)| {
+                    let distance = distance(index);
+                    tile.entities()
+                        .filter(move |e| !sense.misses(e.id()))
+                        .map(move |e| (distance, e.id()))
+                })
+                .collect();
+            match max_count.checked_sub(1) {
+                None => (-1, 0),
+                Some(nth) if nth < perceived.len() => {
+                    *perceived.select_nth_unstable(nth).1
+                }
+                Some(_) => (i32::MAX, Id::MAX),
+            }
+        });
+        for (index, tile) in self.tiles.iter_mut().enumerate() {
+            let sense = Sense::new(seed, miss_rate, distance(index), cutoff);
+            tile.set_sense(sense);
+        }
+    }
+
     /// Returns true only if this Neighborhood contains unique Tiles.
     fn is_unique(&self) -> bool {
         let mut refs = HashSet::with_capacity(self.tiles.len());
@@ -151,10 +376,139 @@ impl<'a, 'e, K: PartialEq, C> Neighborhood<'a, 'e, K, C> {
     pub fn contains_kind(&self, kind: K) -> bool {
         self.tiles
             .iter()
-            .map(|t| t.entities())
-            .flatten()
+            .flat_map(|t| t.entities())
             .any(|e| e.kind() == kind)
     }
+
+    /// Returns true only if any of the Tiles in this Neighborhood contains an
+    /// Entity whose Kind belongs to the given group, without considering the
+    /// Entity that is inspecting this Neighborhood.
+    pub fn contains_group<G: PartialEq>(&self, group: G) -> bool
+    where
+        K: KindGroup<G>,
+    {
+        self.tiles
+            .iter()
+            .flat_map(|t| t.entities())
+            .any(|e| e.kind().group() == group)
+    }
+
+    /// Gets the total number of entities in this Neighborhood whose Kind
+    /// belongs to the given group, without considering the Entity that is
+    /// inspecting this Neighborhood.
+    pub fn count_group<G: PartialEq>(&self, group: G) -> usize
+    where
+        K: KindGroup<G>,
+    {
+        self.tiles
+            .iter()
+            .flat_map(|t| t.entities())
+            .filter(|e| e.kind().group() == group)
+            .count()
+    }
+
+    /// Gets up to the given number of entities of the given Kind that are
+    /// nearest to the center of this Neighborhood, together with their
+    /// Euclidean distance from it (measured across the edges of the
+    /// Environment, where they wrap), sorted by distance and then by ID,
+    /// without considering the Entity that is inspecting this Neighborhood.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((10, 10));
+    /// env.insert(QuickEntity::new('b').id(0).at((0, 2)));
+    /// env.insert(QuickEntity::new('b').id(1).at((3, 1)));
+    /// env.insert(QuickEntity::new('b').id(2).at((9, 0)));
+    /// env.insert(QuickEntity::new('c').id(3).at((1, 0)));
+    ///
+    /// let scope = Scope::with_magnitude(2);
+    /// let neighborhood = env.neighborhood_at((0, 0), scope).unwrap();
+    /// let nearest = neighborhood.nearest_n('b', 2);
+    /// let ids: Vec<_> = nearest.iter().map(|(e, _)| e.id()).collect();
+    /// assert_eq!(ids, [2, 0]);
+    /// assert_eq!(nearest[0].1, 1.0);
+    /// ```
+    pub fn nearest_n(
+        &self,
+        kind: K,
+        n: usize,
+    ) -> Vec<(&EntityTrait<'e, K, C>, f32)> {
+        let mut nearest: Vec<_> = self
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| !tile.is_empty())
+            .flat_map(|(index, tile)| {
+                let position =
+                    Location::from_one_dimensional(index, self.dimension);
+                let offset = position - self.center;
+                let distance = offset.x.pow(2) + offset.y.pow(2);
+                tile.entities()
+                    .filter(|e| e.kind() == kind)
+                    .map(move |e| (e, distance))
+            })
+            .collect();
+        nearest.sort_unstable_by_key(|(e, distance)| (*distance, e.id()));
+        nearest
+            .into_iter()
+            .take(n)
+            .map(|(e, distance)| (e, (distance as f32).sqrt()))
+            .collect()
+    }
+}
+
+impl<'a, 'e, K, C> Neighborhood<'a, 'e, K, C> {
+    /// Constructs a new Neighborhood from a list of tiles allocated in the
+    /// Arena of the current generation.
+    ///
+    /// The list of tiles encodes a grid of the given dimension constructed top
+    /// to bottom and left to right, where the center Tile is located at the
+    /// given position.
+    pub(crate) fn with_tiles(
+        tiles: &'a mut [TileView<'a, 'e, K, C>],
+        dimension: impl Into<Dimension>,
+        center: impl Into<Location>,
+    ) -> Self {
+        Self::with_tile_views(TileViews::Borrowed(tiles), dimension, center)
+    }
+
+    /// Constructs a new Neighborhood from a list of tiles that encodes a grid
+    /// of the given dimension constructed top to bottom and left to right,
+    /// where the center Tile is located at the given position.
+    pub(crate) fn with_dimension(
+        tiles: Vec<TileView<'a, 'e, K, C>>,
+        dimension: impl Into<Dimension>,
+        center: impl Into<Location>,
+    ) -> Self {
+        Self::with_tile_views(TileViews::Owned(tiles), dimension, center)
+    }
+
+    /// Constructs a new Neighborhood from a list of tiles that encodes a grid
+    /// of the given dimension constructed top to bottom and left to right.
+    fn with_tile_views(
+        tiles: TileViews<'a, 'e, K, C>,
+        dimension: impl Into<Dimension>,
+        center: impl Into<Location>,
+    ) -> Self {
+        let dimension = dimension.into();
+        let center = center.into();
+        debug_assert!(!tiles.is_empty());
+        debug_assert_eq!(tiles.len(), dimension.len());
+        debug_assert!(dimension.contains(center));
+
+        let neighborhood = Self {
+            tiles,
+            dimension,
+            center,
+            orientation: Orientation::default(),
+        };
+
+        // NeighborHoods can only contain unique Tiles
+        debug_assert!(neighborhood.is_unique());
+        neighborhood
+    }
 }
 
 impl<'a, 'e, K, C> From<Vec<TileView<'a, 'e, K, C>>>
@@ -163,21 +517,16 @@ impl<'a, 'e, K, C> From<Vec<TileView<'a, 'e, K, C>>>
     /// Constructs a new Neighborhood from a list of tiles.
     ///
     /// The list of tiles encodes a squared grid constructed top to bottom and
-    /// left to right.
+    /// left to right, where the center Tile is located in the middle of the
+    /// grid.
     fn from(tiles: Vec<TileView<'a, 'e, K, C>>) -> Self {
-        debug_assert!(!tiles.is_empty());
         let length = tiles.len() as f64;
-        // NeighborHoods can only be constructed if they represent squares
+        // NeighborHoods constructed from a plain list of tiles can only
+        // represent squares
         debug_assert!(math::is_perfect_square(length));
 
         let side = length.sqrt() as i32;
-        let neighborhood = Self {
-            tiles,
-            dimension: Dimension { x: side, y: side },
-        };
-
-        // NeighborHoods can only contain unique Tiles
-        debug_assert!(neighborhood.is_unique());
-        neighborhood
+        let dimension = Dimension { x: side, y: side };
+        Self::with_dimension(tiles, dimension, dimension.center())
     }
 }
@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+
+use super::*;
+
+/// The change of a property of an Entity notified during a generation (see
+/// `Notifier`).
+pub struct Notification<K> {
+    /// The ID of the Entity whose property changed.
+    pub id: Id,
+    /// The Kind of the Entity whose property changed.
+    pub kind: K,
+    /// The name of the property that changed.
+    pub property: &'static str,
+    /// The new value of the property.
+    pub value: AnyValue,
+}
+
+impl<K: fmt::Debug> fmt::Debug for Notification<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Notification")
+            .field("id", &self.id)
+            .field("kind", &self.kind)
+            .field("property", &self.property)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The entities and kinds whose notifications are buffered.
+#[derive(Debug, Clone)]
+pub(super) struct Subscriptions<K> {
+    ids: HashSet<Id>,
+    kinds: BTreeSet<K>,
+}
+
+impl<K> Default for Subscriptions<K> {
+    fn default() -> Self {
+        Self {
+            ids: HashSet::new(),
+            kinds: BTreeSet::new(),
+        }
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Subscribes to the notifications of the Entity with the given ID, so
+    /// that the changes of its properties notified via
+    /// `Entity::notifications` are buffered by the Environment at each
+    /// generation, and exposed by `Environment::notifications`.
+    pub fn subscribe_entity(&mut self, id: Id) {
+        self.subscriptions.ids.insert(id);
+    }
+
+    /// Unsubscribes from the notifications of the Entity with the given ID.
+    pub fn unsubscribe_entity(&mut self, id: Id) {
+        self.subscriptions.ids.remove(&id);
+    }
+
+    /// Subscribes to the notifications of all the entities of the given Kind,
+    /// so that the changes of their properties notified via
+    /// `Entity::notifications` are buffered by the Environment at each
+    /// generation, and exposed by `Environment::notifications`.
+    pub fn subscribe_kind(&mut self, kind: K) {
+        self.subscriptions.kinds.insert(kind);
+    }
+
+    /// Unsubscribes from the notifications of the entities of the given Kind.
+    pub fn unsubscribe_kind(&mut self, kind: &K) {
+        self.subscriptions.kinds.remove(kind);
+    }
+
+    /// Gets the notifications of the subscribed entities and kinds buffered
+    /// during the last generation, sorted by Entity ID and, for the same
+    /// Entity, in the order they were notified.
+    pub fn notifications(&self) -> &[Notification<K>] {
+        &self.notifications
+    }
+
+    /// Collects the property changes notified by the entities during the
+    /// current generation via `Entity::notifications`, replacing the ones of
+    /// the previous generation, and buffers the ones of the subscribed
+    /// entities and kinds.
+    pub(super) fn collect_notifications(&mut self) {
+        self.notifications.clear();
+        let subscriptions = &self.subscriptions;
+        for entity in self.entities.values_mut().flat_map(|e| e.iter_mut()) {
+            let changes = entity.notifications();
+            if changes.is_empty() {
+                continue;
+            }
+            let id = entity.id();
+            let kind = entity.kind();
+            if !subscriptions.ids.contains(&id)
+                && !subscriptions.kinds.contains(&kind)
+            {
+                continue;
+            }
+            for (property, value) in changes {
+                self.notifications.push(Notification {
+                    id,
+                    kind: entity.kind(),
+                    property,
+                    value,
+                });
+            }
+        }
+        // the sort is stable, so that the notifications of the same Entity
+        // keep the order they were notified in
+        self.notifications
+            .sort_by_key(|notification| notification.id);
+    }
+}
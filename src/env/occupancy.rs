@@ -0,0 +1,251 @@
+use std::ops::BitXor;
+
+use super::*;
+
+/// The number of bits of each word of a BitVec.
+const BITS: usize = u64::BITS as usize;
+
+/// A fixed-length sequence of bits, packed into 64 bits words, where the bit
+/// `i` is stored in the bit `i % 64` of the word `i / 64`.
+///
+/// The unused bits of the last word are always zero, so that two BitVecs with
+/// the same bits have the same words (and hash).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct BitVec {
+    len: usize,
+    words: Vec<u64>,
+}
+
+impl BitVec {
+    /// Constructs a new BitVec of the given length, where all the bits are
+    /// unset.
+    pub fn new(len: usize) -> Self {
+        Self {
+            len,
+            words: vec![0; len.div_ceil(BITS)],
+        }
+    }
+
+    /// Constructs a new BitVec of the given length from its packed words.
+    ///
+    /// Returns an error if the number of words does not match the length, or
+    /// if any of the bits past the length is set.
+    pub fn from_words(words: Vec<u64>, len: usize) -> Result<Self, Error> {
+        if words.len() != len.div_ceil(BITS) {
+            return Err(Error::with_message(format!(
+                "Invalid number of words {} for {} bits",
+                words.len(),
+                len
+            )));
+        }
+        let bitvec = Self { len, words };
+        if bitvec.words.last().copied().unwrap_or(0) & !bitvec.last_word_mask()
+            != 0
+        {
+            return Err(Error::with_message("Bits set past the length"));
+        }
+        Ok(bitvec)
+    }
+
+    /// Gets the number of bits.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true only if the BitVec has no bits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Gets the packed words of the BitVec.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Returns true only if the bit with the given index is set.
+    ///
+    /// # Panics
+    /// Panics if the index is out of bounds.
+    pub fn get(&self, index: usize) -> bool {
+        assert!(index < self.len, "BitVec index out of bounds");
+        self.words[index / BITS] & (1 << (index % BITS)) != 0
+    }
+
+    /// Sets the value of the bit with the given index.
+    ///
+    /// # Panics
+    /// Panics if the index is out of bounds.
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(index < self.len, "BitVec index out of bounds");
+        let mask = 1 << (index % BITS);
+        if value {
+            self.words[index / BITS] |= mask;
+        } else {
+            self.words[index / BITS] &= !mask;
+        }
+    }
+
+    /// Gets the number of set bits.
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Gets an iterator over the indices of the set bits, in ascending order.
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(i * BITS + bit)
+            })
+        })
+    }
+
+    /// Gets the mask of the bits of the last word that are part of the BitVec.
+    fn last_word_mask(&self) -> u64 {
+        match self.len % BITS {
+            0 => u64::MAX,
+            bits => (1 << bits) - 1,
+        }
+    }
+}
+
+impl BitXor for &BitVec {
+    type Output = BitVec;
+
+    /// Gets the bits that differ between two BitVecs of the same length.
+    ///
+    /// # Panics
+    /// Panics if the BitVecs have different lengths.
+    fn bitxor(self, other: Self) -> BitVec {
+        assert_eq!(self.len, other.len, "BitVec lengths differ");
+        BitVec {
+            len: self.len,
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| a ^ b)
+                .collect(),
+        }
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Gets the occupancy of the tiles by the entities of the given Kind, as a
+    /// BitVec with one bit per tile, where each bit is set only if the tile
+    /// contains at least one Entity of such Kind.
+    ///
+    /// The bits follow the order of the tiles by row and then by column, so
+    /// that the bit of the tile at `(x, y)` has the index `y * width + x`.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((4, 4));
+    /// env.insert(QuickEntity::new('a').at((1, 0)));
+    /// env.insert(QuickEntity::new('a').at((2, 3)));
+    /// env.insert(QuickEntity::new('b').at((3, 3)));
+    ///
+    /// let before = env.occupancy_bitmap(&'a');
+    /// assert_eq!(before.len(), 16);
+    /// assert_eq!(before.ones().collect::<Vec<_>>(), vec![1, 14]);
+    ///
+    /// env.clear_kind(&'a');
+    /// env.import_occupancy(&before, |location| {
+    ///     QuickEntity::new('a').at(location)
+    /// })
+    /// .unwrap();
+    /// let after = env.occupancy_bitmap(&'a');
+    /// assert_eq!(before, after);
+    /// assert_eq!((&before ^ &after).count_ones(), 0);
+    /// ```
+    pub fn occupancy_bitmap(&self, kind: &K) -> BitVec {
+        let dimension = self.dimension();
+        let mut bitmap = BitVec::new(dimension.len());
+        let entities = self.entities.get(kind).into_iter().flatten();
+        for location in entities.filter_map(|entity| entity.location()) {
+            bitmap.set(location.one_dimensional(dimension), true);
+        }
+        bitmap
+    }
+
+    /// Inserts into the Environment one new Entity for each of the set bits of
+    /// the given occupancy bitmap (as returned by
+    /// `Environment::occupancy_bitmap`), constructed by the given function
+    /// from the location of its tile.
+    ///
+    /// The entities already in the Environment are left unchanged.
+    ///
+    /// Returns an error if the length of the bitmap does not match the number
+    /// of tiles of the Environment, in which case no Entity is inserted.
+    #[cfg(not(feature = "parallel"))]
+    pub fn import_occupancy<E>(
+        &mut self,
+        bitmap: &BitVec,
+        mut f: impl FnMut(Location) -> E,
+    ) -> Result<(), Error>
+    where
+        E: Entity<'e, C, Kind = K> + 'e,
+    {
+        for location in self.occupied_locations(bitmap)? {
+            self.insert(f(location));
+        }
+        Ok(())
+    }
+
+    /// Inserts into the Environment one new Entity for each of the set bits of
+    /// the given occupancy bitmap (as returned by
+    /// `Environment::occupancy_bitmap`), constructed by the given function
+    /// from the location of its tile.
+    ///
+    /// The entities already in the Environment are left unchanged.
+    ///
+    /// Returns an error if the length of the bitmap does not match the number
+    /// of tiles of the Environment, in which case no Entity is inserted.
+    #[cfg(feature = "parallel")]
+    pub fn import_occupancy<E>(
+        &mut self,
+        bitmap: &BitVec,
+        mut f: impl FnMut(Location) -> E,
+    ) -> Result<(), Error>
+    where
+        E: Entity<'e, C, Kind = K> + 'e + Send + Sync,
+    {
+        for location in self.occupied_locations(bitmap)? {
+            self.insert(f(location));
+        }
+        Ok(())
+    }
+
+    /// Gets the locations of the tiles whose bits are set in the given
+    /// occupancy bitmap.
+    ///
+    /// Returns an error if the length of the bitmap does not match the number
+    /// of tiles of the Environment.
+    fn occupied_locations(
+        &self,
+        bitmap: &BitVec,
+    ) -> Result<Vec<Location>, Error> {
+        let dimension = self.dimension();
+        if bitmap.len() != dimension.len() {
+            return Err(Error::with_message(format!(
+                "Invalid occupancy bitmap length {} for {} tiles",
+                bitmap.len(),
+                dimension.len()
+            )));
+        }
+        Ok(bitmap
+            .ones()
+            .map(|index| Location::from_one_dimensional(index, dimension))
+            .collect())
+    }
+}
@@ -0,0 +1,162 @@
+#[cfg(feature = "image")]
+use std::path::Path;
+
+use super::*;
+
+/// A pattern of entities to seed an Environment with, as a set of placements,
+/// each made of the Location of the Entity relative to the top-left corner of
+/// the pattern, and of the description of the Entity to place there (such as
+/// its Kind).
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let mut glider = Pattern::new((3, 3));
+/// for location in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+///     glider.insert(location, 'a');
+/// }
+///
+/// let mut env = Environment::<char>::new((10, 10));
+/// let count = env.insert_pattern(&glider, (4, 4), |location, &kind| {
+///     Some(QuickEntity::new(kind).at(location).boxed())
+/// });
+/// assert_eq!(count, 5);
+/// assert_eq!(env.entities_at((5, 4)).count(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern<T> {
+    dimension: Dimension,
+    // the placements, in the order they were inserted
+    placements: Vec<(Location, T)>,
+}
+
+impl<T> Pattern<T> {
+    /// Constructs a new empty Pattern with the given Dimension.
+    pub fn new(dimension: impl Into<Dimension>) -> Self {
+        Self {
+            dimension: dimension.into(),
+            placements: Vec::new(),
+        }
+    }
+
+    /// Constructs a new Pattern from the PNG image at the given path, with a
+    /// tile for each pixel, by mapping the color of each pixel (as RGBA
+    /// components) to the description of the Entity to place in its tile, if
+    /// any.
+    ///
+    /// Returns an error if the image cannot be read or decoded.
+    #[cfg(feature = "image")]
+    pub fn from_image(
+        path: impl AsRef<Path>,
+        f: impl FnMut([u8; 4]) -> Option<T>,
+    ) -> Result<Self, Error> {
+        let image = image::open(path).map_err(Error::with_message)?;
+        Ok(Self::from_rgba(&image.to_rgba8(), f))
+    }
+
+    /// Constructs a new Pattern from the given encoded image (such as the
+    /// content of a PNG file), as `Pattern::from_image`.
+    ///
+    /// Returns an error if the image cannot be decoded.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// // a 2x2 image with a single black pixel in the bottom-right corner
+    /// let mut image = image::RgbaImage::from_pixel(2, 2, [255; 4].into());
+    /// image.put_pixel(1, 1, [0, 0, 0, 255].into());
+    /// let mut png = std::io::Cursor::new(Vec::new());
+    /// image.write_to(&mut png, image::ImageFormat::Png).unwrap();
+    ///
+    /// let pattern = Pattern::from_image_bytes(png.get_ref(), |[r, g, b, _]| {
+    ///     (r, g, b).eq(&(0, 0, 0)).then_some('a')
+    /// })
+    /// .unwrap();
+    /// assert_eq!(pattern.dimension(), Dimension { x: 2, y: 2 });
+    /// let placements: Vec<_> = pattern.placements().collect();
+    /// assert_eq!(placements, [(Location { x: 1, y: 1 }, &'a')]);
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn from_image_bytes(
+        bytes: &[u8],
+        f: impl FnMut([u8; 4]) -> Option<T>,
+    ) -> Result<Self, Error> {
+        let image =
+            image::load_from_memory(bytes).map_err(Error::with_message)?;
+        Ok(Self::from_rgba(&image.to_rgba8(), f))
+    }
+
+    /// Constructs a new Pattern from the given decoded image.
+    #[cfg(feature = "image")]
+    fn from_rgba(
+        image: &image::RgbaImage,
+        mut f: impl FnMut([u8; 4]) -> Option<T>,
+    ) -> Self {
+        let mut pattern =
+            Self::new((image.width() as i32, image.height() as i32));
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if let Some(placement) = f(pixel.0) {
+                pattern.insert((x as i32, y as i32), placement);
+            }
+        }
+        pattern
+    }
+
+    /// Gets the Dimension of the Pattern.
+    pub fn dimension(&self) -> Dimension {
+        self.dimension
+    }
+
+    /// Inserts the placement of the Entity described by the given value at the
+    /// given Location, relative to the top-left corner of the Pattern.
+    pub fn insert(&mut self, location: impl Into<Location>, placement: T) {
+        self.placements.push((location.into(), placement));
+    }
+
+    /// Gets an iterator over the placements of the Pattern, in the order they
+    /// were inserted.
+    pub fn placements(&self) -> impl Iterator<Item = (Location, &T)> {
+        self.placements.iter().map(|(location, t)| (*location, t))
+    }
+
+    /// Gets the number of placements of the Pattern.
+    pub fn len(&self) -> usize {
+        self.placements.len()
+    }
+
+    /// Returns true only if the Pattern has no placements.
+    pub fn is_empty(&self) -> bool {
+        self.placements.is_empty()
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Seeds the Environment with the given Pattern, with its top-left corner
+    /// at the given origin, by inserting the Entity constructed by the given
+    /// function for each of its placements, given the location of the
+    /// placement in the Environment (wrapped around its edges).
+    ///
+    /// The function can skip a placement by returning None.
+    /// Returns the number of entities inserted.
+    pub fn insert_pattern<T>(
+        &mut self,
+        pattern: &Pattern<T>,
+        origin: impl Into<Location>,
+        mut f: impl FnMut(Location, &T) -> Option<Box<EntityTrait<'e, K, C>>>,
+    ) -> usize {
+        let origin = origin.into();
+        let dimension = self.dimension();
+        let mut count = 0;
+        for (offset, placement) in pattern.placements() {
+            let mut location = origin;
+            location.translate(offset, dimension);
+            if let Some(entity) = f(location, placement) {
+                self.insert_recorded(entity);
+                count += 1;
+            }
+        }
+        count
+    }
+}
@@ -0,0 +1,99 @@
+use super::*;
+
+/// The perception of the entities of a Tile by the Entity that is seeing it,
+/// according to the Perception of the Entity.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Sense {
+    // the seed of the misses of the Entity that is seeing the Tile
+    seed: u64,
+    miss_rate: f64,
+    // the squared distance of the Tile from the center of the Neighborhood
+    distance: i32,
+    // the distance and ID of the farthest Entity perceived, if limited
+    cutoff: Option<(i32, Id)>,
+}
+
+impl Sense {
+    /// Constructs the Sense of the Tile at the given squared distance from
+    /// the center of the Neighborhood, seen with the given seed and miss rate,
+    /// and where only the entities up to the given cutoff are perceived.
+    pub(crate) fn new(
+        seed: u64,
+        miss_rate: f64,
+        distance: i32,
+        cutoff: Option<(i32, Id)>,
+    ) -> Self {
+        Self {
+            seed,
+            miss_rate,
+            distance,
+            cutoff,
+        }
+    }
+
+    /// Returns true only if the Entity with the given ID is perceived.
+    pub(crate) fn perceives(&self, id: Id) -> bool {
+        self.cutoff
+            .is_none_or(|cutoff| (self.distance, id) <= cutoff)
+            && !self.misses(id)
+    }
+
+    /// Returns true only if the Entity with the given ID is missed, regardless
+    /// of the cutoff.
+    pub(crate) fn misses(&self, id: Id) -> bool {
+        self.miss_rate > 0.0
+            && SplitMix64(self.seed ^ id as u64).next_f64() < self.miss_rate
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Sets the seed of the source of randomness of the entities whose
+    /// Perception misses their neighbors (see `Perception::with_miss_rate`),
+    /// that is 0 by default.
+    ///
+    /// The neighbors missed by an Entity only depend on the seed, on the
+    /// current generation and on the IDs of the entities, so that the same
+    /// seed gives the same perceptions across runs, regardless of the order
+    /// in which the entities are stored or dispatched.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let perceived = |seed| {
+    ///     let mut env = Environment::<char>::new((8, 8));
+    ///     env.set_perception_seed(seed);
+    ///     for x in 0..8 {
+    ///         env.insert(QuickEntity::new('b').id(x as Id).at((x, 0)));
+    ///     }
+    ///     let noisy = Perception::perfect().with_miss_rate(0.5);
+    ///     let observer = QuickEntity::new('a').id(8).at((4, 2)).scope(3);
+    ///     env.insert(observer.perception(noisy));
+    ///     env.with_entity_and_tiles_mut(8, |_, neighborhood| {
+    ///         let neighborhood = neighborhood.unwrap();
+    ///         let seen = neighborhood.tiles().flat_map(|t| t.entities());
+    ///         seen.map(|e| e.id()).collect::<Vec<_>>()
+    ///     })
+    ///     .unwrap()
+    /// };
+    /// assert_eq!(perceived(7), perceived(7));
+    /// assert!(perceived(7).len() < 7);
+    /// ```
+    pub fn set_perception_seed(&mut self, seed: u64) {
+        self.perception_seed = seed;
+        self.seed_perceptions();
+    }
+
+    /// Gets the seed of the source of randomness of the entities whose
+    /// Perception misses their neighbors.
+    pub fn perception_seed(&self) -> u64 {
+        self.perception_seed
+    }
+
+    /// Seeds the perceptions of the entities for the current generation.
+    pub(super) fn seed_perceptions(&mut self) {
+        let generation = self.generation.get();
+        let seed = SplitMix64(self.perception_seed ^ generation).next_u64();
+        self.tiles.set_perception_seed(seed);
+    }
+}
@@ -0,0 +1,170 @@
+use super::*;
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Sets the ordered list of phases dispatched to the entities within each
+    /// generation, via `Entity::on_phase`.
+    ///
+    /// Models that need multiple ordered steps for each generation (such as
+    /// sense, move, eat and reproduce) can split their logic into phases, that
+    /// are dispatched in the given order after all the entities have observed
+    /// and reacted to their Neighborhood. The grid of tiles is updated with the
+    /// new locations of the entities after each phase, so that the Neighborhood
+    /// given to the entities in each phase reflects the changes made by the
+    /// previous one.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// struct Ant(Location);
+    ///
+    /// impl<'e> Entity<'e> for Ant {
+    ///     type Kind = u8;
+    ///
+    ///     fn id(&self) -> Id {
+    ///         0
+    ///     }
+    ///
+    ///     fn kind(&self) -> Self::Kind {
+    ///         0
+    ///     }
+    ///
+    ///     fn location(&self) -> Option<Location> {
+    ///         Some(self.0)
+    ///     }
+    ///
+    ///     fn on_phase(
+    ///         &mut self,
+    ///         phase: &str,
+    ///         _: Option<Neighborhood<'_, 'e, u8>>,
+    ///     ) -> Result<(), Error> {
+    ///         match phase {
+    ///             "move" => self.0.x += 1,
+    ///             "jump" => self.0.y += 2,
+    ///             _ => (),
+    ///         }
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut env = Environment::new((10, 10));
+    /// env.set_phases(&["move", "jump"]);
+    /// env.insert(Ant(Location { x: 0, y: 0 }));
+    ///
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.entities_at((1, 2)).count(), 1);
+    /// ```
+    pub fn set_phases(&mut self, phases: &[&str]) {
+        self.phases = phases.iter().map(|phase| phase.to_string()).collect();
+    }
+
+    /// Gets the ordered list of phases dispatched to the entities within each
+    /// generation.
+    pub fn phases(&self) -> impl Iterator<Item = &str> {
+        self.phases.iter().map(String::as_str)
+    }
+
+    /// Dispatches all the phases to the entities in order, updating the grid
+    /// of tiles after each phase.
+    ///
+    /// Returns an error if any of the calls to `Entity::on_phase()` returns an
+    /// error.
+    pub(super) fn run_phases(
+        &mut self,
+        progress: &Progress<'_>,
+    ) -> Result<(), Error> {
+        // the phases are taken out of self for the duration of the dispatch
+        let phases = std::mem::take(&mut self.phases);
+        let result = phases.iter().try_for_each(|phase| {
+            self.record_location();
+            let result = self.run_phase(phase, progress);
+            self.update_location();
+            result
+        });
+        self.phases = phases;
+        result
+    }
+
+    /// Dispatches the given phase to all the entities.
+    #[cfg(not(feature = "parallel"))]
+    fn run_phase(
+        &mut self,
+        phase: &str,
+        progress: &Progress<'_>,
+    ) -> Result<(), Error> {
+        let arena = Arena::with_capacity(self.arena_capacity);
+        let passive = &self.passive;
+        let (ticks, generation) = (&self.ticks, self.generation);
+        let (region, dimension) = (self.active_region, self.dimension());
+        let stage = progress.stage(phase, self.active_count())?;
+        for (_, entities) in self.entities.iter_mut().filter(|(kind, _)| {
+            !passive.contains(kind) && ticks.is_due(kind, generation)
+        }) {
+            for entity in entities
+                .iter_mut()
+                .filter(|e| is_live(&***e, region, dimension))
+            {
+                let neighborhood = self.tiles.neighborhood(&**entity, &arena);
+                entity.on_phase(phase, neighborhood)?;
+                stage.step()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Dispatches the given phase to all the entities.
+    #[cfg(feature = "parallel")]
+    fn run_phase(
+        &mut self,
+        phase: &str,
+        progress: &Progress<'_>,
+    ) -> Result<(), Error> {
+        use rayon::prelude::*;
+
+        let stage = progress.stage(phase, self.active_count())?;
+        let stage = &stage;
+        let passive = &self.passive;
+        let (ticks, generation) = (&self.ticks, self.generation);
+        let (region, dimension) = (self.active_region, self.dimension());
+        let entities = self
+            .entities
+            .iter_mut()
+            .filter(|(kind, _)| {
+                !passive.contains(kind) && ticks.is_due(kind, generation)
+            })
+            .flat_map(|(_, e)| e.iter_mut())
+            .filter(|e| is_live(&***e, region, dimension))
+            .map(|e| &mut **e);
+
+        let scheduler::Tasks {
+            mut sync,
+            mut unsync,
+        } = self.scheduler.get_tasks(entities);
+
+        let tiles = &self.tiles;
+
+        #[cfg(feature = "aliasing-checks")]
+        let log = scheduler::BorrowLog::new("phase");
+        sync.par_iter_mut()
+            .enumerate()
+            .try_for_each(|(_task, entities)| {
+                let arena = Arena::default();
+                for e in entities.iter_mut() {
+                    let neighborhood = tiles.neighborhood(*e, &arena);
+                    #[cfg(feature = "aliasing-checks")]
+                    log.record_neighborhood(_task, *e, neighborhood.as_ref());
+                    e.on_phase(phase, neighborhood)?;
+                    stage.step()?;
+                }
+                Ok(())
+            })?;
+
+        let arena = Arena::with_capacity(self.arena_capacity);
+        for e in &mut unsync {
+            let neighborhood = tiles.neighborhood(*e, &arena);
+            e.on_phase(phase, neighborhood)?;
+            stage.step()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use super::*;
+
+/// The type of the callback that is notified of the progress of a generation.
+#[cfg(not(feature = "parallel"))]
+type ProgressFn<'p> = dyn FnMut(&str, usize, usize) -> ControlFlow<()> + 'p;
+
+/// The type of the callback that is notified of the progress of a generation.
+#[cfg(feature = "parallel")]
+type ProgressFn<'p> =
+    dyn FnMut(&str, usize, usize) -> ControlFlow<()> + Send + 'p;
+
+/// The progress of a generation, that notifies the user's callback (if any)
+/// and keeps track of whether the generation was cancelled by it.
+pub(crate) struct Progress<'p> {
+    // the callback, shared by all the tasks that update the entities
+    callback: Option<Mutex<&'p mut ProgressFn<'p>>>,
+    // true only if the callback requested to cancel the generation
+    cancelled: AtomicBool,
+}
+
+impl<'p> Progress<'p> {
+    /// Constructs a new Progress that does not notify anyone.
+    pub(super) fn none() -> Self {
+        Self {
+            callback: None,
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    /// Constructs a new Progress that notifies the given callback.
+    fn new(callback: &'p mut ProgressFn<'p>) -> Self {
+        Self {
+            callback: Some(Mutex::new(callback)),
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    /// Starts the stage of the generation with the given name, made of the
+    /// given total number of steps.
+    ///
+    /// Returns an error if the generation is cancelled.
+    pub(super) fn stage<'a>(
+        &'a self,
+        name: &'a str,
+        total: usize,
+    ) -> Result<Stage<'a, 'p>, Error> {
+        self.report(name, 0, total)?;
+        Ok(Stage {
+            progress: self,
+            name,
+            total,
+            done: AtomicUsize::new(0),
+        })
+    }
+
+    /// Notifies the callback of the progress of the given stage.
+    ///
+    /// Returns an error if the generation is cancelled.
+    fn report(
+        &self,
+        name: &str,
+        done: usize,
+        total: usize,
+    ) -> Result<(), Error> {
+        let Some(callback) = &self.callback else {
+            return Ok(());
+        };
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(Error::Cancelled);
+        }
+        let mut callback = callback
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if callback(name, done, total).is_break() {
+            self.cancelled.store(true, Ordering::Relaxed);
+            return Err(Error::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+/// A stage of a generation, whose steps may be completed concurrently.
+pub(crate) struct Stage<'a, 'p> {
+    progress: &'a Progress<'p>,
+    name: &'a str,
+    total: usize,
+    done: AtomicUsize,
+}
+
+impl Stage<'_, '_> {
+    /// Completes one of the steps of the stage.
+    ///
+    /// Returns an error if the generation is cancelled.
+    pub(super) fn step(&self) -> Result<(), Error> {
+        if self.progress.callback.is_none() {
+            return Ok(());
+        }
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        self.progress.report(self.name, done, self.total)
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Moves forwards to the next generation, as `Environment::nextgen`, while
+    /// notifying the given callback of the progress of the generation.
+    /// Returns the next generation step number.
+    ///
+    /// The callback is given the name of the current stage of the generation,
+    /// the number of its steps completed so far, and its total number of steps.
+    /// The stages are, in order:
+    /// - `"observe"` and `"react"`, where each step is an Entity.
+    /// - `"move"`, made of a single step, where the move intents are resolved.
+    /// - Each of the phases set with `Environment::set_phases`, where each step
+    ///   is an Entity.
+    /// - `"rules"`, where each step is one of the attached rules.
+    /// - `"offspring"`, made of a single step, where the offspring are inserted.
+    ///
+    /// Each stage is notified once before any of its steps, and once after
+    /// each step, possibly from different threads when the entities are
+    /// updated in parallel (but never concurrently).
+    ///
+    /// The callback can cancel the generation by returning
+    /// `ControlFlow::Break`, in which case the generation is aborted as soon
+    /// as possible, and `Error::Cancelled` is returned. If the history is
+    /// enabled (see `Environment::enable_history`) the Environment is rolled
+    /// back to the beginning of the generation, by restoring its entities from
+    /// a snapshot taken (with the Codec of the history) before the generation
+    /// started, and the cells of its rules. Otherwise the Environment is left
+    /// as it was when the generation was cancelled, as in the case of any
+    /// other error.
+    ///
+    /// The rollback is only exact for the state of the entities encoded by the
+    /// Codec: the components and columns are kept by ID (with the values they
+    /// had when the generation was cancelled) for the entities that exist
+    /// after the rollback, and dropped for the others, while the data attached
+    /// to the tiles, the resources and the sources of randomness are not
+    /// rolled back, and the scheduled commands and the inputs consumed by the
+    /// cancelled generation are not executed nor delivered again.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    /// use std::ops::ControlFlow;
+    ///
+    /// let mut env = Environment::<u8>::new((10, 10));
+    /// env.insert(QuickEntity::new(0).at((1, 1)).on_react(|entity, _| {
+    ///     entity.set_location(Location { x: 2, y: 1 });
+    ///     Ok(())
+    /// }));
+    ///
+    /// let mut stages = Vec::new();
+    /// env.nextgen_with_progress(|stage, done, total| {
+    ///     stages.push((stage.to_string(), done, total));
+    ///     ControlFlow::Continue(())
+    /// })
+    /// .unwrap();
+    /// assert_eq!(stages[0], ("observe".to_string(), 0, 1));
+    /// assert_eq!(stages.len(), 9);
+    ///
+    /// let result = env.nextgen_with_progress(|stage, _, _| match stage {
+    ///     "move" => ControlFlow::Break(()),
+    ///     _ => ControlFlow::Continue(()),
+    /// });
+    /// assert!(matches!(result, Err(Error::Cancelled)));
+    /// assert_eq!(env.generation(), 1);
+    ///
+    /// // with the history enabled the generation is rolled back, while the
+    /// // components of the entities that still exist are kept
+    /// struct CellCodec;
+    ///
+    /// impl<'e> Codec<'e, u8> for CellCodec {
+    ///     fn encode_kind(&self, kind: &u8) -> Result<Vec<u8>, Error> {
+    ///         Ok(vec![*kind])
+    ///     }
+    ///
+    ///     fn decode_kind(&self, bytes: &[u8]) -> Result<u8, Error> {
+    ///         bytes.first().copied().ok_or(Error::Unknown)
+    ///     }
+    ///
+    ///     fn encode(
+    ///         &self,
+    ///         entity: &EntityTrait<'e, u8>,
+    ///     ) -> Result<Vec<u8>, Error> {
+    ///         let location = entity.location().ok_or(Error::Unknown)?;
+    ///         Ok(vec![entity.id() as u8, location.x as u8, location.y as u8])
+    ///     }
+    ///
+    ///     fn decode(
+    ///         &self,
+    ///         kind: &u8,
+    ///         blob: &[u8],
+    ///     ) -> Result<Box<EntityTrait<'e, u8>>, Error> {
+    ///         let &[id, x, y] = blob else {
+    ///             return Err(Error::Unknown);
+    ///         };
+    ///         let entity = QuickEntity::new(*kind).id(id as Id);
+    ///         Ok(entity.at((x as i32, y as i32)).boxed())
+    ///     }
+    /// }
+    ///
+    /// env.attach(0, 42u32);
+    /// env.enable_history(CellCodec, 1).unwrap();
+    /// let result = env.nextgen_with_progress(|stage, _, _| match stage {
+    ///     "move" => ControlFlow::Break(()),
+    ///     _ => ControlFlow::Continue(()),
+    /// });
+    /// assert!(matches!(result, Err(Error::Cancelled)));
+    /// assert_eq!(env.generation(), 1);
+    /// assert_eq!(env.component::<u32>(0), Some(&42));
+    /// ```
+    #[cfg(not(feature = "parallel"))]
+    pub fn nextgen_with_progress(
+        &mut self,
+        mut progress: impl FnMut(&str, usize, usize) -> ControlFlow<()>,
+    ) -> Result<Generation, Error> {
+        self.nextgen_or_rollback(Progress::new(&mut progress))
+    }
+
+    /// Moves forwards to the next generation, as `Environment::nextgen`, while
+    /// notifying the given callback of the progress of the generation.
+    /// Returns the next generation step number.
+    ///
+    /// The callback is given the name of the current stage of the generation,
+    /// the number of its steps completed so far, and its total number of steps.
+    /// The stages are, in order:
+    /// - `"observe"` and `"react"`, where each step is an Entity.
+    /// - `"move"`, made of a single step, where the move intents are resolved.
+    /// - Each of the phases set with `Environment::set_phases`, where each step
+    ///   is an Entity.
+    /// - `"rules"`, where each step is one of the attached rules.
+    /// - `"offspring"`, made of a single step, where the offspring are inserted.
+    ///
+    /// Each stage is notified once before any of its steps, and once after
+    /// each step, possibly from different threads when the entities are
+    /// updated in parallel (but never concurrently).
+    ///
+    /// The callback can cancel the generation by returning
+    /// `ControlFlow::Break`, in which case the generation is aborted as soon
+    /// as possible, and `Error::Cancelled` is returned. If the history is
+    /// enabled (see `Environment::enable_history`) the Environment is rolled
+    /// back to the beginning of the generation, by restoring its entities from
+    /// a snapshot taken (with the Codec of the history) before the generation
+    /// started, and the cells of its rules. Otherwise the Environment is left
+    /// as it was when the generation was cancelled, as in the case of any
+    /// other error.
+    ///
+    /// The rollback is only exact for the state of the entities encoded by the
+    /// Codec: the components and columns are kept by ID (with the values they
+    /// had when the generation was cancelled) for the entities that exist
+    /// after the rollback, and dropped for the others, while the data attached
+    /// to the tiles, the resources and the sources of randomness are not
+    /// rolled back, and the scheduled commands and the inputs consumed by the
+    /// cancelled generation are not executed nor delivered again.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    /// use std::ops::ControlFlow;
+    ///
+    /// let mut env = Environment::<u8>::new((10, 10));
+    /// env.insert(QuickEntity::new(0).at((1, 1)).on_react(|entity, _| {
+    ///     entity.set_location(Location { x: 2, y: 1 });
+    ///     Ok(())
+    /// }));
+    ///
+    /// let mut stages = Vec::new();
+    /// env.nextgen_with_progress(|stage, done, total| {
+    ///     stages.push((stage.to_string(), done, total));
+    ///     ControlFlow::Continue(())
+    /// })
+    /// .unwrap();
+    /// assert_eq!(stages[0], ("observe".to_string(), 0, 1));
+    /// assert_eq!(stages.len(), 9);
+    ///
+    /// let result = env.nextgen_with_progress(|stage, _, _| match stage {
+    ///     "move" => ControlFlow::Break(()),
+    ///     _ => ControlFlow::Continue(()),
+    /// });
+    /// assert!(matches!(result, Err(Error::Cancelled)));
+    /// assert_eq!(env.generation(), 1);
+    ///
+    /// // with the history enabled the generation is rolled back, while the
+    /// // components of the entities that still exist are kept
+    /// struct CellCodec;
+    ///
+    /// impl<'e> Codec<'e, u8> for CellCodec {
+    ///     fn encode_kind(&self, kind: &u8) -> Result<Vec<u8>, Error> {
+    ///         Ok(vec![*kind])
+    ///     }
+    ///
+    ///     fn decode_kind(&self, bytes: &[u8]) -> Result<u8, Error> {
+    ///         bytes.first().copied().ok_or(Error::Unknown)
+    ///     }
+    ///
+    ///     fn encode(
+    ///         &self,
+    ///         entity: &EntityTrait<'e, u8>,
+    ///     ) -> Result<Vec<u8>, Error> {
+    ///         let location = entity.location().ok_or(Error::Unknown)?;
+    ///         Ok(vec![entity.id() as u8, location.x as u8, location.y as u8])
+    ///     }
+    ///
+    ///     fn decode(
+    ///         &self,
+    ///         kind: &u8,
+    ///         blob: &[u8],
+    ///     ) -> Result<Box<EntityTrait<'e, u8>>, Error> {
+    ///         let &[id, x, y] = blob else {
+    ///             return Err(Error::Unknown);
+    ///         };
+    ///         let entity = QuickEntity::new(*kind).id(id as Id);
+    ///         Ok(entity.at((x as i32, y as i32)).boxed())
+    ///     }
+    /// }
+    ///
+    /// env.attach(0, 42u32);
+    /// env.enable_history(CellCodec, 1).unwrap();
+    /// let result = env.nextgen_with_progress(|stage, _, _| match stage {
+    ///     "move" => ControlFlow::Break(()),
+    ///     _ => ControlFlow::Continue(()),
+    /// });
+    /// assert!(matches!(result, Err(Error::Cancelled)));
+    /// assert_eq!(env.generation(), 1);
+    /// assert_eq!(env.component::<u32>(0), Some(&42));
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn nextgen_with_progress(
+        &mut self,
+        mut progress: impl FnMut(&str, usize, usize) -> ControlFlow<()> + Send,
+    ) -> Result<Generation, Error>
+    where
+        K: ParallelKind,
+    {
+        self.nextgen_or_rollback(Progress::new(&mut progress))
+    }
+
+    /// Moves forwards to the next generation with the given Progress, rolling
+    /// back the Environment if the generation is cancelled and the history is
+    /// enabled.
+    fn nextgen_or_rollback(
+        &mut self,
+        progress: Progress<'_>,
+    ) -> Result<Generation, Error>
+    where
+        K: ParallelKind,
+    {
+        let checkpoint = self.checkpoint()?;
+        match self.nextgen_with_layers(&[], None, &progress) {
+            Err(Error::Cancelled) => {
+                if let Some(checkpoint) = checkpoint {
+                    self.rollback(checkpoint)?;
+                }
+                Err(Error::Cancelled)
+            }
+            result => result,
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use super::*;
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Sets the region of interest of the Environment, so that only the
+    /// entities whose Neighborhood overlaps the given Rect (that is, the
+    /// entities within the Rect extended by the Scope of each Entity) observe,
+    /// react and are dispatched the phases of each generation, while all the
+    /// others are frozen, or removes the region of interest if None, so that
+    /// all the entities are live again.
+    ///
+    /// This allows the simulation of massive worlds where only a portion of
+    /// the Environment (such as the one visible on screen) needs to be live.
+    /// The Rect wraps around the edges of the Environment, as any other
+    /// Region. The entities without a location are always live, while the
+    /// lifespan and offspring of the frozen entities are still handled as for
+    /// any other Entity.
+    ///
+    /// There is no region of interest by default.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((10, 10));
+    /// for x in [1, 3, 8] {
+    ///     env.insert(QuickEntity::new('a').at((x, 0)).on_react(|entity, _| {
+    ///         entity.set_location(entity.location().unwrap() + (0, 1).into());
+    ///         Ok(())
+    ///     }));
+    /// }
+    ///
+    /// env.set_active_region(Some(Rect::new((0, 0), (2, 2))));
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.entities_at((1, 1)).count(), 1);
+    /// assert_eq!(env.entities_at((3, 0)).count(), 1);
+    /// assert_eq!(env.entities_at((8, 0)).count(), 1);
+    ///
+    /// env.set_active_region(None);
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.entities_at((8, 1)).count(), 1);
+    /// ```
+    pub fn set_active_region(&mut self, region: Option<Rect>) {
+        self.active_region = region;
+    }
+
+    /// Gets the region of interest of the Environment, if any (see
+    /// `Environment::set_active_region`).
+    pub fn active_region(&self) -> Option<Rect> {
+        self.active_region
+    }
+
+    /// Returns true only if the given Entity is live according to the region
+    /// of interest of the Environment (see `Environment::set_active_region`).
+    pub fn is_live(&self, entity: &EntityTrait<'e, K, C>) -> bool {
+        is_live(entity, self.active_region, self.dimension())
+    }
+}
+
+/// Returns true only if the given Entity is live according to the given
+/// region of interest, within an Environment of the given Dimension.
+pub(super) fn is_live<'e, K, C>(
+    entity: &EntityTrait<'e, K, C>,
+    region: Option<Rect>,
+    dimension: Dimension,
+) -> bool {
+    let (region, location) = match (region, entity.location()) {
+        (Some(region), Some(location)) => (region, location),
+        _ => return true,
+    };
+    let margin = entity.scope().map_or(0, Scope::magnitude) as i32;
+    // the distance from the origin of the extended region, along each axis,
+    // wrapped around the edges of the Environment
+    let within = |location: i32, origin: i32, side: i32, edge: i32| {
+        let side = side + 2 * margin;
+        side >= edge || (location - origin + margin).rem_euclid(edge) < side
+    };
+    within(location.x, region.origin.x, region.dimension.x, dimension.x)
+        && within(location.y, region.origin.y, region.dimension.y, dimension.y)
+}
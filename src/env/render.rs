@@ -0,0 +1,101 @@
+use super::*;
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Enables the buffer of the render values of the entities of the given
+    /// Kind, that is a contiguous grid of values with one value per tile,
+    /// maintained by the Environment at the end of each generation.
+    ///
+    /// The value of each tile is the sum of the `Entity::render_value` of all
+    /// the entities of the given Kind in the tile, or zero if there are none,
+    /// so that renderers that draw the Environment as an image (such as a
+    /// heatmap) can read a single slice (see `Environment::render_values`)
+    /// instead of iterating over all the entities and downcasting their State.
+    ///
+    /// The buffer is computed right away, and then updated at the end of each
+    /// generation, until disabled.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// struct Heat(Id, Location, f32);
+    ///
+    /// impl<'e> Entity<'e> for Heat {
+    ///     type Kind = ();
+    ///
+    ///     fn id(&self) -> Id {
+    ///         self.0
+    ///     }
+    ///
+    ///     fn kind(&self) {}
+    ///
+    ///     fn location(&self) -> Option<Location> {
+    ///         Some(self.1)
+    ///     }
+    ///
+    ///     fn react(
+    ///         &mut self,
+    ///         _: Option<Neighborhood<'_, 'e, ()>>,
+    ///     ) -> Result<(), Error> {
+    ///         self.2 /= 2.0;
+    ///         Ok(())
+    ///     }
+    ///
+    ///     fn render_value(&self) -> Option<f32> {
+    ///         Some(self.2)
+    ///     }
+    /// }
+    ///
+    /// let mut env = Environment::new((3, 2));
+    /// env.insert(Heat(0, Location { x: 1, y: 0 }, 4.0));
+    /// env.insert(Heat(1, Location { x: 2, y: 1 }, 2.0));
+    /// env.insert(Heat(2, Location { x: 2, y: 1 }, 6.0));
+    ///
+    /// env.enable_render_values(());
+    /// assert_eq!(env.render_values(&()), Some(&[0.0, 4.0, 0.0, 0.0, 0.0, 8.0][..]));
+    ///
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.render_values(&()), Some(&[0.0, 2.0, 0.0, 0.0, 0.0, 4.0][..]));
+    /// ```
+    pub fn enable_render_values(&mut self, kind: K) {
+        let values = self.compute_render_values(&kind);
+        self.render_values.insert(kind, values);
+    }
+
+    /// Disables the buffer of the render values of the entities of the given
+    /// Kind, and drops it.
+    pub fn disable_render_values(&mut self, kind: &K) {
+        self.render_values.remove(kind);
+    }
+
+    /// Gets the render values of the entities of the given Kind, with one
+    /// value per tile in row-major order (see `Location::one_dimensional`), as
+    /// they were at the end of the last generation, or None if the buffer of
+    /// the Kind is not enabled (see `Environment::enable_render_values`).
+    pub fn render_values(&self, kind: &K) -> Option<&[f32]> {
+        self.render_values.get(kind).map(Vec::as_slice)
+    }
+
+    /// Updates the buffers of the render values of all the enabled kinds.
+    pub(super) fn update_render_values(&mut self) {
+        let mut buffers = std::mem::take(&mut self.render_values);
+        for (kind, values) in &mut buffers {
+            *values = self.compute_render_values(kind);
+        }
+        self.render_values = buffers;
+    }
+
+    /// Computes the render values of the entities of the given Kind.
+    fn compute_render_values(&self, kind: &K) -> Vec<f32> {
+        let dimension = self.dimension();
+        let mut values = vec![0.0; dimension.len()];
+        for entity in self.entities.get(kind).into_iter().flatten() {
+            if let (Some(location), Some(value)) =
+                (entity.location(), entity.render_value())
+            {
+                values[location.one_dimensional(dimension)] += value;
+            }
+        }
+        values
+    }
+}
@@ -0,0 +1,495 @@
+//! This module contains the event log used to replay a run of an Environment.
+//!
+//! A recording is made of the seed of the run, the snapshot of the Environment
+//! at the beginning of the recording, and the list of the interventions made
+//! on the Environment from outside of its generations (the entities inserted,
+//! removed and relocated), each stamped with the generation it was made in.
+//!
+//! A replay starts with a fixed header, made of the magic bytes and the version
+//! of its format, followed by:
+//!
+//! | Field      | Content                                                 |
+//! |------------|---------------------------------------------------------|
+//! | seed       | the seed of the run, chosen by the user                 |
+//! | generation | the generation the recording was stopped at             |
+//! | snapshot   | the snapshot of the Environment, prefixed by its length |
+//! | events     | the number of interventions, followed by each of them   |
+//!
+//! Where each intervention is made of its tag, its generation, and its
+//! payload: the encoded Kind and Entity of an insertion, the ID of a removal,
+//! or the ID and the new location of a relocation.
+
+use std::io::{Read, Write};
+use std::sync::{Mutex, PoisonError};
+
+use super::*;
+use snapshot::{push_blob, push_len, read_blob, read_u32, read_u64};
+
+/// The magic bytes that identify a replay of an Environment.
+pub const REPLAY_MAGIC: [u8; 4] = *b"SMNR";
+
+/// The version of the replay format written by this library.
+pub const REPLAY_VERSION: u32 = 1;
+
+const INSERT_TAG: u8 = 0;
+const REMOVE_TAG: u8 = 1;
+const RELOCATE_TAG: u8 = 2;
+
+/// An intervention made on the Environment from outside of its generations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Event {
+    /// An Entity, encoded with its Kind, was inserted.
+    Insert { kind: Vec<u8>, blob: Vec<u8> },
+    /// The Entity with the given ID was removed.
+    Remove(Id),
+    /// The Entity with the given ID was moved to the given location.
+    Relocate(Id, Location),
+}
+
+/// The recording of a run of an Environment, that can be replayed to reach the
+/// same generation, with the same entities, of the recorded Environment.
+///
+/// The replay of a run only reproduces the recorded interventions, while the
+/// generations in between are simulated again, therefore the simulation is
+/// expected to be deterministic, given the seed of the run, for the replayed
+/// generations to match the recorded ones.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// struct Cell(Id, Location);
+///
+/// impl<'e> Entity<'e> for Cell {
+///     type Kind = u8;
+///
+///     fn id(&self) -> Id {
+///         self.0
+///     }
+///
+///     fn kind(&self) -> Self::Kind {
+///         0
+///     }
+///
+///     fn location(&self) -> Option<Location> {
+///         Some(self.1)
+///     }
+///
+///     fn location_mut(&mut self) -> Option<&mut Location> {
+///         Some(&mut self.1)
+///     }
+/// }
+///
+/// struct CellCodec;
+///
+/// impl<'e> Codec<'e, u8> for CellCodec {
+///     fn encode_kind(&self, kind: &u8) -> Result<Vec<u8>, Error> {
+///         Ok(vec![*kind])
+///     }
+///
+///     fn decode_kind(&self, bytes: &[u8]) -> Result<u8, Error> {
+///         bytes.first().copied().ok_or(Error::Unknown)
+///     }
+///
+///     fn encode(
+///         &self,
+///         entity: &EntityTrait<'e, u8>,
+///     ) -> Result<Vec<u8>, Error> {
+///         let location = entity.location().ok_or(Error::Unknown)?;
+///         Ok(vec![entity.id() as u8, location.x as u8, location.y as u8])
+///     }
+///
+///     fn decode(
+///         &self,
+///         _kind: &u8,
+///         blob: &[u8],
+///     ) -> Result<Box<EntityTrait<'e, u8>>, Error> {
+///         let &[id, x, y] = blob else {
+///             return Err(Error::Unknown);
+///         };
+///         let location = Location { x: x as i32, y: y as i32 };
+///         Ok(Box::new(Cell(id as Id, location)))
+///     }
+/// }
+///
+/// let mut env = Environment::new((10, 10));
+/// env.insert(Cell(0, Location { x: 1, y: 1 }));
+/// env.start_recording(CellCodec, 42).unwrap();
+///
+/// env.nextgen().unwrap();
+/// env.insert(Cell(1, Location { x: 2, y: 2 }));
+/// env.nextgen().unwrap();
+/// assert!(env.relocate(0, (5, 5)));
+/// env.retain(|entity| entity.id() != 1);
+///
+/// let mut log = Vec::new();
+/// env.stop_recording().unwrap().write_to(&mut log).unwrap();
+///
+/// let replay = Replay::read_from(log.as_slice()).unwrap();
+/// assert_eq!(replay.seed(), 42);
+/// assert_eq!(replay.len(), 3);
+///
+/// let replayed = replay.run(&CellCodec).unwrap();
+/// assert_eq!(replayed.generation(), 2);
+/// assert_eq!(replayed.count(), 1);
+/// assert_eq!(replayed.entities_at((5, 5)).count(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    seed: u64,
+    // the generation the recording was stopped at
+    generation: Generation,
+    // the snapshot of the Environment when the recording started
+    snapshot: Vec<u8>,
+    // the interventions, by generation, in the order they were made
+    events: Vec<(Generation, Event)>,
+}
+
+/// The recording in progress of the interventions on an Environment.
+pub(crate) struct Recording<'e, K, C> {
+    codec: HistoryCodec<'e, K, C>,
+    replay: Replay,
+    // the first error that occurred while encoding an inserted Entity, behind
+    // a Mutex since the Error is not Sync
+    error: Mutex<Option<Error>>,
+}
+
+impl<'e, K, C> fmt::Debug for Recording<'e, K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recording")
+            .field("replay", &self.replay)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Replay {
+    /// Gets the seed of the recorded run.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Gets the generation the recording was stopped at, that is the
+    /// generation reached by replaying it.
+    pub fn generation(&self) -> Generation {
+        self.generation
+    }
+
+    /// Gets the number of recorded interventions.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns true only if no intervention was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Writes the replay into the given writer.
+    ///
+    /// Returns an error if writing to the given writer fails.
+    pub fn write_to(&self, mut writer: impl Write) -> Result<(), Error> {
+        let mut bytes = REPLAY_MAGIC.to_vec();
+        bytes.extend_from_slice(&REPLAY_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.generation.get().to_le_bytes());
+        push_blob(&mut bytes, &self.snapshot);
+
+        push_len(&mut bytes, self.events.len());
+        for (generation, event) in &self.events {
+            let tag = match event {
+                Event::Insert { .. } => INSERT_TAG,
+                Event::Remove(_) => REMOVE_TAG,
+                Event::Relocate(..) => RELOCATE_TAG,
+            };
+            bytes.push(tag);
+            bytes.extend_from_slice(&generation.get().to_le_bytes());
+            match event {
+                Event::Insert { kind, blob } => {
+                    push_blob(&mut bytes, kind);
+                    push_blob(&mut bytes, blob);
+                }
+                Event::Remove(id) => {
+                    bytes.extend_from_slice(&(*id as u64).to_le_bytes());
+                }
+                Event::Relocate(id, location) => {
+                    bytes.extend_from_slice(&(*id as u64).to_le_bytes());
+                    bytes.extend_from_slice(&location.x.to_le_bytes());
+                    bytes.extend_from_slice(&location.y.to_le_bytes());
+                }
+            }
+        }
+
+        writer.write_all(&bytes).map_err(Error::with_message)
+    }
+
+    /// Reads a replay from the given reader.
+    ///
+    /// Returns an error if the replay is malformed, or if it was written by a
+    /// more recent version of the library.
+    pub fn read_from(mut reader: impl Read) -> Result<Self, Error> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic).map_err(Error::with_message)?;
+        if magic != REPLAY_MAGIC {
+            return Err(Error::with_message("Invalid replay magic bytes"));
+        }
+        let version = read_u32(&mut reader)?;
+        if version > REPLAY_VERSION {
+            return Err(Error::with_message(format!(
+                "Unsupported replay version {}",
+                version
+            )));
+        }
+
+        let seed = read_u64(&mut reader)?;
+        let generation = Generation::from(read_u64(&mut reader)?);
+        let snapshot = read_blob(&mut reader)?;
+
+        // the count is not trusted to pre-allocate the events, since the
+        // recording may be corrupted
+        let count = read_u32(&mut reader)?;
+        let mut events = Vec::new();
+        for _ in 0..count {
+            let mut tag = [0; 1];
+            reader.read_exact(&mut tag).map_err(Error::with_message)?;
+            let generation = Generation::from(read_u64(&mut reader)?);
+            let event = match tag[0] {
+                INSERT_TAG => Event::Insert {
+                    kind: read_blob(&mut reader)?,
+                    blob: read_blob(&mut reader)?,
+                },
+                REMOVE_TAG => Event::Remove(read_u64(&mut reader)? as Id),
+                RELOCATE_TAG => {
+                    let id = read_u64(&mut reader)? as Id;
+                    let x = read_u32(&mut reader)? as i32;
+                    let y = read_u32(&mut reader)? as i32;
+                    Event::Relocate(id, Location { x, y })
+                }
+                tag => {
+                    return Err(Error::with_message(format!(
+                        "Invalid replay event tag {}",
+                        tag
+                    )))
+                }
+            };
+            events.push((generation, event));
+        }
+
+        Ok(Self {
+            seed,
+            generation,
+            snapshot,
+            events,
+        })
+    }
+
+    /// Replays the recorded run, by loading the Environment from the recorded
+    /// snapshot with the given Codec, and by moving it forwards to the
+    /// generation the recording was stopped at, while applying each recorded
+    /// intervention at the generation it was made in.
+    ///
+    /// Only the entities are recorded, therefore the Environment is loaded
+    /// with the default value of all its other properties (such as its rules
+    /// and phases). Use `Replay::run_in` to replay the run in an Environment
+    /// configured as the recorded one.
+    ///
+    /// Returns an error if the snapshot or any of the inserted entities cannot
+    /// be decoded, or if any of the replayed generations fails.
+    pub fn run<'e, K: Ord + ParallelKind, C>(
+        &self,
+        codec: &(impl Codec<'e, K, C> + ?Sized),
+    ) -> Result<Environment<'e, K, C>, Error> {
+        let mut env = Environment::load_from(self.snapshot.as_slice(), codec)?;
+        self.apply(&mut env, codec)?;
+        Ok(env)
+    }
+
+    /// Replays the recorded run in the given Environment, as `Replay::run`,
+    /// by replacing its entities and its generation with the ones of the
+    /// recorded snapshot, while leaving all its other properties unchanged.
+    /// Returns the generation reached.
+    ///
+    /// Returns an error if the snapshot or any of the inserted entities cannot
+    /// be decoded, or if any of the replayed generations fails.
+    pub fn run_in<'e, K: Ord + ParallelKind, C>(
+        &self,
+        env: &mut Environment<'e, K, C>,
+        codec: &(impl Codec<'e, K, C> + ?Sized),
+    ) -> Result<Generation, Error> {
+        let restored = Environment::load_from(self.snapshot.as_slice(), codec)?;
+        env.restore(restored);
+        self.apply(env, codec)?;
+        Ok(env.generation)
+    }
+
+    /// Applies the recorded interventions to the given Environment, moving it
+    /// forwards to the generation of each of them.
+    fn apply<'e, K: Ord + ParallelKind, C>(
+        &self,
+        env: &mut Environment<'e, K, C>,
+        codec: &(impl Codec<'e, K, C> + ?Sized),
+    ) -> Result<(), Error> {
+        for (generation, event) in &self.events {
+            if *generation < env.generation {
+                return Err(Error::with_message(format!(
+                    "Replay event of past generation {}",
+                    generation
+                )));
+            }
+            while env.generation < *generation {
+                env.nextgen()?;
+            }
+            match event {
+                Event::Insert { kind, blob } => {
+                    let kind = codec.decode_kind(kind)?;
+                    env.insert_boxed(codec.decode(&kind, blob)?);
+                }
+                Event::Remove(id) => env.retain_entities(|e| e.id() != *id),
+                Event::Relocate(id, location) => {
+                    env.relocate_entity(*id, *location);
+                }
+            }
+        }
+        while env.generation < self.generation {
+            env.nextgen()?;
+        }
+        Ok(())
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Starts recording the interventions made on the Environment from outside
+    /// of its generations, that is the entities inserted via
+    /// `Environment::insert`, removed via `Environment::retain` (or any of the
+    /// clear methods) and moved via `Environment::relocate`, so that the run
+    /// can be replayed later, starting from a snapshot of the current
+    /// generation encoded with the given Codec.
+    ///
+    /// The seed is not used by the Environment, and it is only stored to be
+    /// given back by `Replay::seed`, to seed again the sources of randomness of
+    /// the simulation. The changes made to the entities via any of the mutable
+    /// accessors of the Environment are not recorded, and any recording
+    /// previously in progress is discarded.
+    ///
+    /// Returns an error if the snapshot of the current generation cannot be
+    /// taken, in which case the recording is not started.
+    #[cfg(not(feature = "parallel"))]
+    pub fn start_recording(
+        &mut self,
+        codec: impl Codec<'e, K, C> + 'e,
+        seed: u64,
+    ) -> Result<(), Error> {
+        self.start_replay(Box::new(codec), seed)
+    }
+
+    /// Starts recording the interventions made on the Environment from outside
+    /// of its generations, that is the entities inserted via
+    /// `Environment::insert`, removed via `Environment::retain` (or any of the
+    /// clear methods) and moved via `Environment::relocate`, so that the run
+    /// can be replayed later, starting from a snapshot of the current
+    /// generation encoded with the given Codec.
+    ///
+    /// The seed is not used by the Environment, and it is only stored to be
+    /// given back by `Replay::seed`, to seed again the sources of randomness of
+    /// the simulation. The changes made to the entities via any of the mutable
+    /// accessors of the Environment are not recorded, and any recording
+    /// previously in progress is discarded.
+    ///
+    /// Returns an error if the snapshot of the current generation cannot be
+    /// taken, in which case the recording is not started.
+    #[cfg(feature = "parallel")]
+    pub fn start_recording(
+        &mut self,
+        codec: impl Codec<'e, K, C> + Send + Sync + 'e,
+        seed: u64,
+    ) -> Result<(), Error> {
+        self.start_replay(Box::new(codec), seed)
+    }
+
+    /// Stops the recording in progress, and returns the Replay of the run
+    /// recorded so far, up to the current generation.
+    ///
+    /// Returns an error if no recording is in progress, or if any of the
+    /// inserted entities could not be encoded.
+    pub fn stop_recording(&mut self) -> Result<Replay, Error> {
+        let recording = self
+            .recording
+            .take()
+            .ok_or_else(|| Error::with_message("Recording not started"))?;
+        let error = recording.error.into_inner();
+        if let Some(error) = error.unwrap_or_else(PoisonError::into_inner) {
+            return Err(error);
+        }
+        let mut replay = recording.replay;
+        replay.generation = self.generation;
+        Ok(replay)
+    }
+
+    /// Returns true only if the interventions on the Environment are being
+    /// recorded.
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Starts recording with the given Codec.
+    fn start_replay(
+        &mut self,
+        codec: HistoryCodec<'e, K, C>,
+        seed: u64,
+    ) -> Result<(), Error> {
+        let mut snapshot = Vec::new();
+        self.save_to(&mut snapshot, &*codec)?;
+        self.recording = Some(Recording {
+            codec,
+            replay: Replay {
+                seed,
+                generation: self.generation,
+                snapshot,
+                events: Vec::new(),
+            },
+            error: Mutex::new(None),
+        });
+        Ok(())
+    }
+
+    /// Records the insertion of the given Entity, if recording.
+    pub(super) fn record_insert(&mut self, entity: &EntityTrait<'e, K, C>) {
+        let generation = self.generation;
+        let Some(recording) = self.recording.as_mut() else {
+            return;
+        };
+        let encoded = recording
+            .codec
+            .encode_kind(&entity.kind())
+            .and_then(|kind| Ok((kind, recording.codec.encode(entity)?)));
+        match encoded {
+            Ok((kind, blob)) => recording
+                .replay
+                .events
+                .push((generation, Event::Insert { kind, blob })),
+            Err(error) => {
+                let first = recording.error.get_mut();
+                first
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .get_or_insert(error);
+            }
+        }
+    }
+
+    /// Records the removal of the entities with the given IDs, if recording.
+    pub(super) fn record_removals(&mut self, ids: impl Iterator<Item = Id>) {
+        let generation = self.generation;
+        if let Some(recording) = self.recording.as_mut() {
+            let events = ids.map(|id| (generation, Event::Remove(id)));
+            recording.replay.events.extend(events);
+        }
+    }
+
+    /// Records the relocation of the Entity with the given ID, if recording.
+    pub(super) fn record_relocation(&mut self, id: Id, location: Location) {
+        let generation = self.generation;
+        if let Some(recording) = self.recording.as_mut() {
+            let event = Event::Relocate(id, location);
+            recording.replay.events.push((generation, event));
+        }
+    }
+}
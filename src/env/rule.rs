@@ -0,0 +1,676 @@
+//! This module contains the built-in engine of Life-like totalistic automata,
+//! whose cells live directly on the tiles of the Environment, without the need
+//! to implement any Entity.
+//!
+//! The cells of each rule are stored as a bitboard, one bit per tile, and the
+//! number of live neighbors of all the cells of a row are counted in parallel
+//! with bitwise adders.
+//!
+//! Besides totalistic rules, block rules partition the tiles into 2x2 blocks
+//! (the Margolus neighborhood), whose alignment alternates at each generation,
+//! and update all the cells of each block at once.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::*;
+
+/// The number of bits of each word of a bitboard.
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A Life-like totalistic rule, that determines the state of a cell in the next
+/// generation according to its current state and to the number of its live
+/// (Moore) neighbors.
+///
+/// A dead cell becomes alive if its number of live neighbors is one of the
+/// birth counts, and a live cell stays alive if its number of live neighbors
+/// is one of the survival counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rule {
+    // the bitmasks of the neighbors counts, where bit `n` is set if `n` is one
+    // of the birth (or survival) counts
+    birth: u16,
+    survival: u16,
+}
+
+impl Rule {
+    /// Conway's Game of Life (B3/S23).
+    pub const LIFE: Self = Self::new(&[3], &[2, 3]);
+    /// HighLife (B36/S23), where replicators exist.
+    pub const HIGH_LIFE: Self = Self::new(&[3, 6], &[2, 3]);
+    /// Day & Night (B3678/S34678), where live and dead cells are symmetric.
+    pub const DAY_AND_NIGHT: Self = Self::new(&[3, 6, 7, 8], &[3, 4, 6, 7, 8]);
+
+    /// Constructs a new Rule from the given birth and survival counts of live
+    /// neighbors. Counts greater than 8 are ignored.
+    pub const fn new(birth: &[u8], survival: &[u8]) -> Self {
+        Self {
+            birth: mask(birth),
+            survival: mask(survival),
+        }
+    }
+
+    /// Parses a Rule written in the B/S notation, such as "B3/S23".
+    ///
+    /// The birth and survival parts can be given in any order, the letters are
+    /// case insensitive, and either part can be empty (as in "B2/S").
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let rule = Rule::parse("B36/S23").unwrap();
+    /// assert_eq!(rule, Rule::HIGH_LIFE);
+    /// assert!(rule.is_born(6));
+    /// assert!(!rule.survives(6));
+    /// assert_eq!(rule.to_string(), "B36/S23");
+    /// assert!(Rule::parse("B39/S23").is_err());
+    /// ```
+    pub fn parse(rule: &str) -> Result<Self, Error> {
+        let invalid =
+            || Error::with_message(format!("Invalid rule {:?}", rule));
+
+        let mut birth = None;
+        let mut survival = None;
+        for part in rule.trim().split('/') {
+            let mut chars = part.chars();
+            let counts = match chars.next() {
+                Some('B' | 'b') => &mut birth,
+                Some('S' | 's') => &mut survival,
+                _ => return Err(invalid()),
+            };
+            if counts.is_some() {
+                return Err(invalid());
+            }
+            let mut bits = 0;
+            for c in chars {
+                match c.to_digit(10) {
+                    Some(n) if n <= 8 => bits |= 1 << n,
+                    _ => return Err(invalid()),
+                }
+            }
+            *counts = Some(bits);
+        }
+
+        match (birth, survival) {
+            (Some(birth), Some(survival)) => Ok(Self { birth, survival }),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Returns true if a dead cell with the given number of live neighbors
+    /// becomes alive in the next generation.
+    pub fn is_born(&self, neighbors: u8) -> bool {
+        neighbors <= 8 && self.birth & (1 << neighbors) != 0
+    }
+
+    /// Returns true if a live cell with the given number of live neighbors
+    /// stays alive in the next generation.
+    pub fn survives(&self, neighbors: u8) -> bool {
+        neighbors <= 8 && self.survival & (1 << neighbors) != 0
+    }
+}
+
+impl FromStr for Rule {
+    type Err = Error;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        Self::parse(rule)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "B")?;
+        for n in (0..=8).filter(|&n| self.is_born(n)) {
+            write!(f, "{}", n)?;
+        }
+        write!(f, "/S")?;
+        for n in (0..=8).filter(|&n| self.survives(n)) {
+            write!(f, "{}", n)?;
+        }
+        Ok(())
+    }
+}
+
+/// Gets the bitmask of the given neighbors counts.
+const fn mask(counts: &[u8]) -> u16 {
+    let mut bits = 0;
+    let mut i = 0;
+    while i < counts.len() {
+        if counts[i] <= 8 {
+            bits |= 1 << counts[i];
+        }
+        i += 1;
+    }
+    bits
+}
+
+/// A block rule, that partitions the tiles into 2x2 blocks (the Margolus
+/// neighborhood) and determines the next state of the cells of each block
+/// according to their current states.
+///
+/// The blocks are aligned to the even rows and columns of the Environment in
+/// even generations, and to the odd ones in odd generations, so that the
+/// information can propagate across blocks. The state of a block is encoded as
+/// 4 bits, where the bits from 0 to 3 represent respectively the top-left, the
+/// top-right, the bottom-left and the bottom-right cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockRule {
+    // the next state of the block for each of its possible states
+    table: [u8; 16],
+}
+
+impl BlockRule {
+    /// The Critters rule, a reversible automaton where blocks with exactly two
+    /// live cells are unchanged, while all the other blocks are complemented,
+    /// and also rotated by 180 degrees if they had exactly three live cells.
+    pub const CRITTERS: Self = Self::new(critters());
+    /// The Tron rule, where blocks whose cells all have the same state are
+    /// complemented, while all the other blocks are unchanged.
+    pub const TRON: Self = Self::new(tron());
+
+    /// Constructs a new BlockRule from the next state of the block for each of
+    /// its possible states. Only the lowest 4 bits of each state are used.
+    pub const fn new(table: [u8; 16]) -> Self {
+        let mut masked = [0; 16];
+        let mut i = 0;
+        while i < 16 {
+            masked[i] = table[i] & 0xF;
+            i += 1;
+        }
+        Self { table: masked }
+    }
+
+    /// Constructs a new BlockRule from the given function, that computes the
+    /// next state of the block from its current state.
+    pub fn from_fn(f: impl Fn(u8) -> u8) -> Self {
+        let mut table = [0; 16];
+        for (state, next) in table.iter_mut().enumerate() {
+            *next = f(state as u8);
+        }
+        Self::new(table)
+    }
+
+    /// Gets the next state of the block with the given (4 bits) state.
+    pub fn next(&self, state: u8) -> u8 {
+        self.table[state as usize & 0xF]
+    }
+
+    /// Returns true if the rule is reversible, that is if each state of the
+    /// block is the next state of exactly one state.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// assert!(BlockRule::CRITTERS.is_reversible());
+    /// assert!(!BlockRule::from_fn(|_| 0).is_reversible());
+    /// ```
+    pub fn is_reversible(&self) -> bool {
+        let mut seen = 0u16;
+        for &next in &self.table {
+            seen |= 1 << next;
+        }
+        seen == u16::MAX
+    }
+}
+
+/// Gets the table of the Critters block rule.
+const fn critters() -> [u8; 16] {
+    let mut table = [0; 16];
+    let mut state = 0;
+    while state < 16 {
+        table[state] = match (state as u8).count_ones() {
+            2 => state as u8,
+            // the 180 degrees rotation swaps the top-left with the
+            // bottom-right cell, and the top-right with the bottom-left cell,
+            // that is reverses the order of the 4 bits
+            3 => (!state as u8).reverse_bits() >> 4,
+            _ => !state as u8 & 0xF,
+        };
+        state += 1;
+    }
+    table
+}
+
+/// Gets the table of the Tron block rule.
+const fn tron() -> [u8; 16] {
+    let mut table = [0; 16];
+    let mut state = 0;
+    while state < 16 {
+        table[state] = match state {
+            0 => 0xF,
+            0xF => 0,
+            _ => state as u8,
+        };
+        state += 1;
+    }
+    table
+}
+
+/// Gets the locations of the cells of the Margolus block that contains the
+/// given Location in the given generation, sorted as top-left, top-right,
+/// bottom-left and bottom-right (wrapped around the Environment edges).
+pub(crate) fn margolus_block(
+    location: Location,
+    generation: Generation,
+    dimension: Dimension,
+) -> [Location; 4] {
+    let parity = i32::from(!generation.is_multiple_of(2));
+    let origin = Location {
+        x: (location.x - parity).div_euclid(2) * 2 + parity,
+        y: (location.y - parity).div_euclid(2) * 2 + parity,
+    };
+    [(0, 0), (1, 0), (0, 1), (1, 1)].map(|offset| {
+        let mut cell = origin;
+        *cell.translate(offset, dimension)
+    })
+}
+
+/// The update applied to the cells of a RuleBoard at each generation.
+#[derive(Debug, Clone, Copy)]
+enum Update {
+    Totalistic(Rule),
+    Block(BlockRule),
+}
+
+/// The cells of a Rule, stored as a bitboard where each row of tiles is packed
+/// into a sequence of words.
+#[derive(Debug, Clone)]
+pub(crate) struct RuleBoard {
+    update: Update,
+    dimension: Dimension,
+    // the number of words used to store each row
+    row_len: usize,
+    cells: Vec<u64>,
+}
+
+impl RuleBoard {
+    /// Constructs a new RuleBoard of the given dimension, where all the cells
+    /// are dead.
+    fn new(update: Update, dimension: Dimension) -> Self {
+        let row_len = (dimension.x.max(0) as usize).div_ceil(WORD_BITS);
+        Self {
+            update,
+            dimension,
+            row_len,
+            cells: vec![0; row_len * dimension.y.max(0) as usize],
+        }
+    }
+
+    /// Gets the index of the word and the mask of the bit of the cell at the
+    /// given (wrapped) location.
+    fn bit(&self, mut location: Location) -> (usize, u64) {
+        let location = *location.translate(Offset::origin(), self.dimension);
+        let x = location.x as usize;
+        let index = location.y as usize * self.row_len + x / WORD_BITS;
+        (index, 1 << (x % WORD_BITS))
+    }
+
+    /// Gets the mask of the bits of the last word of each row that represent
+    /// actual tiles.
+    fn last_word_mask(&self) -> u64 {
+        match self.dimension.x as usize % WORD_BITS {
+            0 => u64::MAX,
+            bits => (1 << bits) - 1,
+        }
+    }
+
+    /// Gets the row with the given (wrapped) index.
+    fn row(&self, y: i32) -> &[u64] {
+        let y = y.rem_euclid(self.dimension.y) as usize;
+        &self.cells[y * self.row_len..(y + 1) * self.row_len]
+    }
+
+    /// Gets the row whose bit `x` is the bit `x - 1` of the given row, that is
+    /// the west neighbor of each cell (wrapping around the row).
+    fn west(&self, row: &[u64]) -> Vec<u64> {
+        let last = self.dimension.x as usize - 1;
+        let wrapped = (row[last / WORD_BITS] >> (last % WORD_BITS)) & 1;
+        let mut shifted: Vec<u64> = (0..row.len())
+            .map(|i| {
+                let carry = if i > 0 { row[i - 1] >> 63 } else { wrapped };
+                (row[i] << 1) | carry
+            })
+            .collect();
+        shifted[self.row_len - 1] &= self.last_word_mask();
+        shifted
+    }
+
+    /// Gets the row whose bit `x` is the bit `x + 1` of the given row, that is
+    /// the east neighbor of each cell (wrapping around the row).
+    fn east(&self, row: &[u64]) -> Vec<u64> {
+        let last = self.dimension.x as usize - 1;
+        let mut shifted: Vec<u64> = (0..row.len())
+            .map(|i| {
+                let carry = row.get(i + 1).map_or(0, |word| word << 63);
+                (row[i] >> 1) | carry
+            })
+            .collect();
+        shifted[last / WORD_BITS] |= (row[0] & 1) << (last % WORD_BITS);
+        shifted
+    }
+
+    /// Gets the state of the cell at the given (wrapped) location.
+    fn get(&self, location: Location) -> bool {
+        let (index, bit) = self.bit(location);
+        self.cells[index] & bit != 0
+    }
+
+    /// Sets the state of the cell at the given (wrapped) location.
+    fn set(&mut self, location: Location, alive: bool) {
+        let (index, bit) = self.bit(location);
+        if alive {
+            self.cells[index] |= bit;
+        } else {
+            self.cells[index] &= !bit;
+        }
+    }
+
+    /// Computes the next generation of the cells, where the given generation is
+    /// the current one.
+    fn step(&mut self, generation: Generation) {
+        if self.cells.is_empty() {
+            return;
+        }
+        match self.update {
+            Update::Totalistic(rule) => self.step_totalistic(rule),
+            Update::Block(rule) => self.step_block(rule, generation),
+        }
+    }
+
+    /// Computes the next generation of the cells of a block rule, where the
+    /// given generation determines the alignment of the blocks.
+    fn step_block(&mut self, rule: BlockRule, generation: Generation) {
+        let parity = i32::from(!generation.is_multiple_of(2));
+        for y in (parity..self.dimension.y + parity).step_by(2) {
+            for x in (parity..self.dimension.x + parity).step_by(2) {
+                let block =
+                    margolus_block((x, y).into(), generation, self.dimension);
+                let state = block
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &cell)| self.get(cell))
+                    .fold(0, |state, (bit, _)| state | 1 << bit);
+                let next = rule.next(state);
+                for (bit, &cell) in block.iter().enumerate() {
+                    self.set(cell, next >> bit & 1 == 1);
+                }
+            }
+        }
+    }
+
+    /// Computes the next generation of the cells of a totalistic rule.
+    fn step_totalistic(&mut self, rule: Rule) {
+        let mut next = Vec::with_capacity(self.cells.len());
+        for y in 0..self.dimension.y {
+            let (north, row, south) =
+                (self.row(y - 1), self.row(y), self.row(y + 1));
+            let neighbors = [
+                self.west(north),
+                north.to_vec(),
+                self.east(north),
+                self.west(row),
+                self.east(row),
+                self.west(south),
+                south.to_vec(),
+                self.east(south),
+            ];
+
+            for (i, &alive) in row.iter().enumerate() {
+                // the number of live neighbors of each cell, as 4 bit-planes
+                let mut count = [0u64; 4];
+                for neighbor in &neighbors {
+                    let mut carry = neighbor[i];
+                    for plane in &mut count {
+                        let sum = *plane ^ carry;
+                        carry &= *plane;
+                        *plane = sum;
+                    }
+                }
+
+                let mut cells = 0;
+                for n in 0..=8 {
+                    let (born, survives) = (rule.is_born(n), rule.survives(n));
+                    if !born && !survives {
+                        continue;
+                    }
+                    let matching =
+                        count
+                            .iter()
+                            .enumerate()
+                            .map(|(bit, plane)| {
+                                if n >> bit & 1 == 1 {
+                                    *plane
+                                } else {
+                                    !plane
+                                }
+                            })
+                            .fold(u64::MAX, |acc, plane| acc & plane);
+                    if born {
+                        cells |= matching & !alive;
+                    }
+                    if survives {
+                        cells |= matching & alive;
+                    }
+                }
+                if i == self.row_len - 1 {
+                    cells &= self.last_word_mask();
+                }
+                next.push(cells);
+            }
+        }
+        self.cells = next;
+    }
+
+    /// Gets an iterator over the locations of the live cells.
+    fn live(&self) -> impl Iterator<Item = Location> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .flat_map(move |(index, &word)| {
+                let y = (index / self.row_len) as i32;
+                let x = (index % self.row_len * WORD_BITS) as i32;
+                (0..WORD_BITS as i32)
+                    .filter(move |bit| word >> bit & 1 == 1)
+                    .map(move |bit| Location { x: x + bit, y })
+            })
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Attaches the given Rule to the Environment, identified by the given Kind,
+    /// replacing any Rule previously attached with the same Kind.
+    ///
+    /// The cells of the Rule live directly on the tiles of the Environment,
+    /// independently of its entities, and they are all initially dead. At each
+    /// generation, after the phases of the entities have been dispatched, the
+    /// cells of each Rule are updated according to the number of their live
+    /// neighbors.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8>::new((5, 5));
+    /// env.attach_rule(Rule::parse("B3/S23").unwrap(), 0);
+    /// for y in 1..=3 {
+    ///     env.set_rule_cell(&0, Location { x: 2, y }, true);
+    /// }
+    ///
+    /// // the blinker oscillates between the vertical and horizontal phases
+    /// env.nextgen().unwrap();
+    /// let mut cells: Vec<_> = env.rule_cells(&0).collect();
+    /// cells.sort_by_key(|location| location.x);
+    /// assert_eq!(cells, [(1, 2).into(), (2, 2).into(), (3, 2).into()]);
+    /// ```
+    pub fn attach_rule(&mut self, rule: Rule, kind: K) {
+        let board = RuleBoard::new(Update::Totalistic(rule), self.dimension());
+        self.rules.insert(kind, board);
+    }
+
+    /// Attaches the given BlockRule to the Environment, identified by the given
+    /// Kind, replacing any rule previously attached with the same Kind.
+    ///
+    /// The cells of the BlockRule are accessed and updated in the same way as
+    /// the ones of the rules attached with `Environment::attach_rule`, with the
+    /// alignment of the blocks depending on the current generation (see
+    /// `WorldView::margolus_block`).
+    ///
+    /// Returns an error if the Environment has an odd number of columns or rows,
+    /// since it cannot be partitioned into 2x2 blocks.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8>::new((4, 4));
+    /// env.attach_block_rule(BlockRule::TRON, 0).unwrap();
+    ///
+    /// // all the blocks are empty, and therefore complemented
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.rule_population(&0), 16);
+    /// assert!(env.attach_block_rule(BlockRule::TRON, 1).is_ok());
+    /// assert!(Environment::<u8>::new((3, 4))
+    ///     .attach_block_rule(BlockRule::TRON, 0)
+    ///     .is_err());
+    /// ```
+    pub fn attach_block_rule(
+        &mut self,
+        rule: BlockRule,
+        kind: K,
+    ) -> Result<(), Error> {
+        let dimension = self.dimension();
+        if dimension.x % 2 != 0 || dimension.y % 2 != 0 {
+            return Err(Error::with_message(
+                "Block rules require an even number of columns and rows",
+            ));
+        }
+        let board = RuleBoard::new(Update::Block(rule), dimension);
+        self.rules.insert(kind, board);
+        Ok(())
+    }
+
+    /// Detaches the rule identified by the given Kind, removing all its cells.
+    /// Returns true if such rule was attached, false otherwise.
+    pub fn detach_rule(&mut self, kind: &K) -> bool {
+        self.rules.remove(kind).is_some()
+    }
+
+    /// Gets the Rule identified by the given Kind, if attached.
+    pub fn rule(&self, kind: &K) -> Option<Rule> {
+        match self.rules.get(kind)?.update {
+            Update::Totalistic(rule) => Some(rule),
+            Update::Block(_) => None,
+        }
+    }
+
+    /// Gets the BlockRule identified by the given Kind, if attached.
+    pub fn block_rule(&self, kind: &K) -> Option<BlockRule> {
+        match self.rules.get(kind)?.update {
+            Update::Block(rule) => Some(rule),
+            Update::Totalistic(_) => None,
+        }
+    }
+
+    /// Sets the state of the cell at the given Location (wrapped around the
+    /// Environment edges) of the Rule identified by the given Kind.
+    ///
+    /// Returns false if no such Rule is attached, true otherwise.
+    pub fn set_rule_cell(
+        &mut self,
+        kind: &K,
+        location: impl Into<Location>,
+        alive: bool,
+    ) -> bool {
+        let Some(board) = self.rules.get_mut(kind) else {
+            return false;
+        };
+        board.set(location.into(), alive);
+        true
+    }
+
+    /// Sets the state of all the cells in the given Region (wrapped around the
+    /// Environment edges) of the Rule identified by the given Kind.
+    ///
+    /// Returns false if no such Rule is attached, true otherwise.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<u8>::new((10, 10));
+    /// env.attach_rule(Rule::parse("B3/S23").unwrap(), 0);
+    /// assert!(env.set_rule_region(&0, &Ring::new((0, 0), 1, 1), true));
+    /// assert_eq!(env.rule_population(&0), 4);
+    /// assert!(env.is_rule_cell_alive(&0, (9, 0)));
+    /// ```
+    pub fn set_rule_region(
+        &mut self,
+        kind: &K,
+        region: &impl Region,
+        alive: bool,
+    ) -> bool {
+        let Some(board) = self.rules.get_mut(kind) else {
+            return false;
+        };
+        for location in region.iter(self.tiles.dimension()) {
+            board.set(location, alive);
+        }
+        true
+    }
+
+    /// Returns true if the cell at the given Location (wrapped around the
+    /// Environment edges) of the Rule identified by the given Kind is alive.
+    pub fn is_rule_cell_alive(
+        &self,
+        kind: &K,
+        location: impl Into<Location>,
+    ) -> bool {
+        let location = location.into();
+        self.rules
+            .get(kind)
+            .is_some_and(|board| board.get(location))
+    }
+
+    /// Gets an iterator over the locations of the live cells of the Rule
+    /// identified by the given Kind, sorted by row and then by column.
+    pub fn rule_cells(
+        &self,
+        kind: &K,
+    ) -> impl Iterator<Item = Location> + use<'_, 'e, K, C> {
+        self.rules.get(kind).into_iter().flat_map(RuleBoard::live)
+    }
+
+    /// Gets the number of live cells of the Rule identified by the given Kind.
+    pub fn rule_population(&self, kind: &K) -> usize {
+        self.rules.get(kind).map_or(0, |board| {
+            board
+                .cells
+                .iter()
+                .map(|word| word.count_ones() as usize)
+                .sum()
+        })
+    }
+
+    /// Computes the next generation of the cells of all the attached rules,
+    /// notifying the given Progress.
+    pub(super) fn step_rules(
+        &mut self,
+        progress: &Progress<'_>,
+    ) -> Result<(), Error> {
+        let stage = progress.stage("rules", self.rules.len())?;
+        for board in self.rules.values_mut() {
+            board.step(self.generation);
+            stage.step()?;
+        }
+        Ok(())
+    }
+
+    /// Gets the bitboards of the cells of all the attached rules, sorted by
+    /// Kind.
+    pub(super) fn rule_boards(&self) -> impl Iterator<Item = &[u64]> {
+        self.rules.values().map(|board| board.cells.as_slice())
+    }
+}
@@ -33,8 +33,16 @@
 //! synchronized with any other entity, and therefore, these entities operations
 //! must be run on the same thread only after all the N previous tiles entities
 //! operations are completed.
+//! The entities that declare to access only themselves (see `Access`) are
+//! never given a neighborhood, and are therefore assigned to the tile that
+//! contains their location regardless of their scope, while the entities that
+//! declare to access the global state are always assigned to the special tile.
 
 use std::collections::BTreeMap;
+#[cfg(feature = "aliasing-checks")]
+use std::collections::HashMap;
+#[cfg(feature = "aliasing-checks")]
+use std::sync::Mutex;
 
 use super::*;
 
@@ -103,8 +111,18 @@ impl Scheduler {
 
         // assign each entity to its own task
         for e in entities {
-            if let Some(location) = e.location() {
-                let scope = e.scope().unwrap_or_else(Scope::empty);
+            let access = Access::of(&*e);
+            if access == Access::Global {
+                // the entities that access the global state are always run
+                // after all the others, one at a time
+                unsync.push(e);
+            } else if let Some(location) = e.location() {
+                // the entities that access only themselves are assigned to the
+                // tile that contains their location, regardless of their scope
+                let scope = match access {
+                    Access::SelfOnly => Scope::empty(),
+                    _ => e.scope().unwrap_or_else(Scope::empty),
+                };
                 // each entity must be assigned to its own tile, if the tile
                 // cannot be found it's an unrecoverable internal error
                 let tile =
@@ -130,6 +148,83 @@ impl Scheduler {
     }
 }
 
+/// The log of all the entities that can be borrowed mutably by each of the
+/// tasks run in parallel during a single phase (observe or react) of a
+/// generation.
+///
+/// Each task can borrow mutably its own entities, as well as all the entities
+/// located in their neighborhoods. If the same Entity is ever recorded by two
+/// different tasks, the Scheduler handed out aliasing mutable borrows, and the
+/// log panics with a diagnostic of the conflict.
+#[cfg(feature = "aliasing-checks")]
+#[derive(Debug)]
+pub struct BorrowLog {
+    phase: &'static str,
+    // the map of entity addresses to the index of the task and the ID of the
+    // Entity that borrowed them first
+    borrows: Mutex<HashMap<usize, (usize, Id)>>,
+}
+
+#[cfg(feature = "aliasing-checks")]
+impl BorrowLog {
+    /// Constructs a new empty log for the given phase of the generation.
+    pub fn new(phase: &'static str) -> Self {
+        Self {
+            phase,
+            borrows: Mutex::default(),
+        }
+    }
+
+    /// Records that the given Entity can be borrowed mutably by the task with
+    /// the given index, on behalf of the Entity with the given ID.
+    ///
+    /// Panics if the Entity was already recorded by a different task.
+    pub fn record<K, C>(
+        &self,
+        task: usize,
+        owner: Id,
+        entity: &EntityTrait<'_, K, C>,
+    ) {
+        let address =
+            entity as *const EntityTrait<'_, K, C> as *const () as usize;
+        let mut borrows = self.borrows.lock().expect("Poisoned borrow log");
+        let (other_task, other_owner) =
+            *borrows.entry(address).or_insert((task, owner));
+        if other_task != task {
+            panic!(
+                "Aliasing mutable borrow during {}: Entity {} is reachable \
+                 from task {} (via Entity {}) and task {} (via Entity {})",
+                self.phase,
+                entity.id(),
+                other_task,
+                other_owner,
+                task,
+                owner,
+            );
+        }
+    }
+
+    /// Records that the given Entity, and all the entities located in its
+    /// Neighborhood, can be borrowed mutably by the task with the given index.
+    ///
+    /// Panics if any of these entities was already recorded by a different
+    /// task.
+    pub fn record_neighborhood<K, C>(
+        &self,
+        task: usize,
+        entity: &EntityTrait<'_, K, C>,
+        neighborhood: Option<&Neighborhood<'_, '_, K, C>>,
+    ) {
+        let owner = entity.id();
+        self.record(task, owner, entity);
+        for tile in neighborhood.into_iter().flat_map(|n| n.tiles()) {
+            for neighbor in tile.entities() {
+                self.record(task, owner, neighbor);
+            }
+        }
+    }
+}
+
 /// The coordinate in space of a 2-dimensional Location (Point), that could
 /// either represents its abscissa or ordinate.
 type Coordinate = i32;
@@ -163,6 +258,8 @@ enum Tile {
 #[derive(Debug)]
 struct Grid {
     dimension: Dimension,
+    // whether the Grid splits a 1-dimensional Environment
+    line: bool,
     vertical: EdgesMap,
     horizontal: EdgesMap,
 }
@@ -176,11 +273,17 @@ impl Grid {
     /// mapped dimension (B) of the new Grid would be equal to 2x2, where each
     /// tile of the new Grid, would have to have dimension of 2x2 in order to
     /// fill up the dimension (A).
+    ///
+    /// A 1-dimensional Environment is only split along its length.
     fn new(dimension: impl Into<Dimension>, count: usize) -> Self {
-        let grid_dimension = Dimension::with_eq_rectangles(count);
+        let dimension = dimension.into();
+        let line = dimension.is_line();
+        let grid_dimension = match line {
+            true => Dimension::line(count as i32),
+            false => Dimension::with_eq_rectangles(count),
+        };
         debug_assert!(!grid_dimension.is_empty());
 
-        let dimension = dimension.into();
         let tile_dimension = grid_dimension.scale(dimension);
 
         let step = tile_dimension.x as usize;
@@ -207,6 +310,7 @@ impl Grid {
 
         Self {
             dimension: grid_dimension,
+            line,
             vertical,
             horizontal,
         }
@@ -233,12 +337,15 @@ impl Grid {
         let (y, (top, bottom)) = tile_edges(&self.horizontal, location.y)?;
         let index = Location { x, y }.one_dimensional(self.dimension);
 
+        // the right and bottom edges are excluded from the tile
         let scope = scope.magnitude() as i32;
-        if left > location.x - scope || right < location.x + scope {
+        if left > location.x - scope || right <= location.x + scope {
             // the scope goes beyond the tile that contains the given location
             return Some(Tile::Unsync);
         }
-        if top > location.y - scope || bottom < location.y + scope {
+        if !self.line
+            && (top > location.y - scope || bottom <= location.y + scope)
+        {
             // the scope goes beyond the tile that contains the given location
             return Some(Tile::Unsync);
         }
@@ -0,0 +1,226 @@
+use super::*;
+
+/// The type of the predicate over the locations of the tiles to fill.
+type LocationPredicate<'s> = Box<dyn Fn(Location) -> bool + 's>;
+
+/// A step of a Seeder, that places the entities of a Kind.
+enum Step<'s, K> {
+    Scatter {
+        kind: K,
+        count: usize,
+    },
+    Cluster {
+        kind: K,
+        count: usize,
+        radius: Scope,
+    },
+    FillWhere {
+        kind: K,
+        predicate: LocationPredicate<'s>,
+    },
+}
+
+/// The builder of the random initial population of an Environment, made of a
+/// list of steps that place the entities of a Kind each, and whose random
+/// placement depends only on the given seed.
+///
+/// The Seeder only decides where the entities are placed: the entities
+/// themselves are constructed by the factory given to `Seeder::seed` (or by
+/// the factories registered in a ConfigLoader, with the `config` feature).
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let seeder = Seeder::new(42)
+///     .scatter('t', 10)
+///     .cluster('w', 3, 1)
+///     .fill_where('~', |location| location.y == 0);
+///
+/// let mut env = Environment::<char>::new((8, 8));
+/// let count = seeder
+///     .seed(&mut env, |&kind, location| {
+///         Ok(QuickEntity::new(kind).at(location).boxed())
+///     })
+///     .unwrap();
+/// assert_eq!(count, 21);
+/// assert_eq!(env.count_kind(&'t'), 10);
+/// assert_eq!(env.count_kind(&'w'), 3);
+/// assert_eq!(env.entities_at((5, 0)).filter(|e| e.kind() == '~').count(), 1);
+/// ```
+pub struct Seeder<'s, K> {
+    seed: u64,
+    steps: Vec<Step<'s, K>>,
+}
+
+impl<'s, K: fmt::Debug> fmt::Debug for Seeder<'s, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Seeder")
+            .field("seed", &self.seed)
+            .field("steps", &self.steps.len())
+            .finish()
+    }
+}
+
+impl<'s, K> Seeder<'s, K> {
+    /// Constructs a new Seeder with the given seed, and without any step.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Places the given number of entities of the given Kind in distinct tiles
+    /// chosen at random across the whole Environment (or in all its tiles, if
+    /// the number exceeds the number of tiles).
+    pub fn scatter(mut self, kind: K, count: usize) -> Self {
+        self.steps.push(Step::Scatter { kind, count });
+        self
+    }
+
+    /// Places the given number of entities of the given Kind in distinct tiles
+    /// chosen at random within the given Scope from a random center, so that
+    /// they form a single cluster.
+    pub fn cluster(
+        mut self,
+        kind: K,
+        count: usize,
+        radius: impl Into<Scope>,
+    ) -> Self {
+        let radius = radius.into();
+        self.steps.push(Step::Cluster {
+            kind,
+            count,
+            radius,
+        });
+        self
+    }
+
+    /// Places an Entity of the given Kind in each tile of the Environment whose
+    /// Location satisfies the given predicate (such as a noise function under
+    /// a threshold).
+    pub fn fill_where(
+        mut self,
+        kind: K,
+        predicate: impl Fn(Location) -> bool + 's,
+    ) -> Self {
+        let predicate = Box::new(predicate);
+        self.steps.push(Step::FillWhere { kind, predicate });
+        self
+    }
+
+    /// Gets the locations of the entities of each step, in order, for the
+    /// given Dimension of the Environment.
+    fn placements(
+        &self,
+        dimension: Dimension,
+    ) -> impl Iterator<Item = (&K, Location)> {
+        let mut rng = SplitMix64(self.seed);
+        self.steps.iter().flat_map(move |step| {
+            let (kind, locations) = match step {
+                Step::Scatter { kind, count } => {
+                    let tiles = (0..dimension.len())
+                        .map(|i| Location::from_one_dimensional(i, dimension))
+                        .collect();
+                    (kind, rng.choose(tiles, *count))
+                }
+                Step::Cluster {
+                    kind,
+                    count,
+                    radius,
+                } => {
+                    let index = rng.next_below(dimension.len());
+                    let center =
+                        Location::from_one_dimensional(index, dimension);
+                    let side = radius.magnitude() as i32;
+                    let mut tiles = Vec::new();
+                    for y in -side..=side {
+                        for x in -side..=side {
+                            let mut location = center;
+                            location.translate((x, y), dimension);
+                            if !tiles.contains(&location) {
+                                tiles.push(location);
+                            }
+                        }
+                    }
+                    (kind, rng.choose(tiles, *count))
+                }
+                Step::FillWhere { kind, predicate } => {
+                    let tiles = (0..dimension.len())
+                        .map(|i| Location::from_one_dimensional(i, dimension))
+                        .filter(|&location| predicate(location))
+                        .collect();
+                    (kind, tiles)
+                }
+            };
+            locations.into_iter().map(move |location| (kind, location))
+        })
+    }
+}
+
+impl<'s, K: Ord> Seeder<'s, K> {
+    /// Seeds the given Environment, by inserting the Entity constructed by the
+    /// given factory for each of the placements of the steps of the Seeder, in
+    /// order, given its Kind and Location.
+    ///
+    /// Returns the number of entities inserted, or the first error returned by
+    /// the factory, in which case the entities inserted until then are left in
+    /// the Environment.
+    pub fn seed<'e, C, F>(
+        &self,
+        env: &mut Environment<'e, K, C>,
+        mut factory: F,
+    ) -> Result<usize, Error>
+    where
+        F: FnMut(&K, Location) -> Result<Box<EntityTrait<'e, K, C>>, Error>,
+    {
+        let mut count = 0;
+        for (kind, location) in self.placements(env.dimension()) {
+            let entity = factory(kind, location)?;
+            env.insert_recorded(entity);
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// The SplitMix64 pseudorandom number generator, used for the reproducible
+/// random placement of the entities, and for the inheritance of their genomes.
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    /// Gets the next random number.
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Gets the next random number in [0, 1).
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Gets the next random number in [0, n), where n must be positive.
+    pub(crate) fn next_below(&mut self, n: usize) -> usize {
+        debug_assert!(n > 0);
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Chooses the given number of distinct items at random from the given
+    /// list, or all of them if the number exceeds its length.
+    fn choose<T>(&mut self, mut items: Vec<T>, count: usize) -> Vec<T> {
+        let count = count.min(items.len());
+        // partial Fisher-Yates shuffle of the first count items
+        for i in 0..count {
+            let j = i + self.next_below(items.len() - i);
+            items.swap(i, j);
+        }
+        items.truncate(count);
+        items
+    }
+}
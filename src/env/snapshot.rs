@@ -0,0 +1,576 @@
+//! This module contains the versioned binary container used to save the state
+//! of an Environment to any writer, and to load it back from any reader.
+//!
+//! A snapshot starts with a fixed header, made of the magic bytes, the version
+//! of the container format and the version of the user's Codec, followed by a
+//! list of tagged sections, each prefixed by its length in bytes:
+//!
+//! | Section | Content                                                   |
+//! |---------|-----------------------------------------------------------|
+//! | `DIMS`  | the Environment dimension and generation                  |
+//! | `KIND`  | the table of the encoded kinds                            |
+//! | `ENTS`  | the entity blobs, each referencing its kind in the table  |
+//! | `DATA`  | the data attached to the tiles, if the Codec encodes any  |
+//! | `END.`  | the end of the snapshot                                   |
+//!
+//! Snapshots written with a more recent version of the container format are
+//! rejected, while sections with an unknown tag are skipped when loading a
+//! snapshot, so that optional sections can be added by newer versions of the
+//! library without changing the container version.
+//! The entity blobs encoded with an older version of the Codec are migrated,
+//! one version at a time, by the `Codec::migrate` hook before being decoded.
+
+use std::any::{Any, TypeId};
+use std::io::{Read, Write};
+
+use super::*;
+
+/// The magic bytes that identify a snapshot of an Environment.
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"SMNS";
+
+/// The version of the snapshot container format written by this library.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// The tag of a section of a snapshot.
+type Tag = [u8; 4];
+
+const DIMENSION_TAG: Tag = *b"DIMS";
+const KINDS_TAG: Tag = *b"KIND";
+const ENTITIES_TAG: Tag = *b"ENTS";
+const DATA_TAG: Tag = *b"DATA";
+const END_TAG: Tag = *b"END.";
+
+/// The maximum number of tiles of an Environment loaded from a snapshot, so
+/// that a malformed dimension cannot allocate an arbitrary amount of memory.
+const MAX_SNAPSHOT_TILES: i32 = 1 << 28;
+
+/// The trait that describes how the kinds and the entities of an Environment
+/// are encoded to, and decoded from, the binary blobs of a snapshot.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// struct Cell(Id, Location);
+///
+/// impl<'e> Entity<'e> for Cell {
+///     type Kind = u8;
+///
+///     fn id(&self) -> Id {
+///         self.0
+///     }
+///
+///     fn kind(&self) -> Self::Kind {
+///         0
+///     }
+///
+///     fn location(&self) -> Option<Location> {
+///         Some(self.1)
+///     }
+/// }
+///
+/// struct CellCodec;
+///
+/// impl<'e> Codec<'e, u8> for CellCodec {
+///     fn encode_kind(&self, kind: &u8) -> Result<Vec<u8>, Error> {
+///         Ok(vec![*kind])
+///     }
+///
+///     fn decode_kind(&self, bytes: &[u8]) -> Result<u8, Error> {
+///         bytes.first().copied().ok_or(Error::Unknown)
+///     }
+///
+///     fn encode(
+///         &self,
+///         entity: &EntityTrait<'e, u8>,
+///     ) -> Result<Vec<u8>, Error> {
+///         let location = entity.location().ok_or(Error::Unknown)?;
+///         let mut blob = (entity.id() as u32).to_le_bytes().to_vec();
+///         blob.extend_from_slice(&location.x.to_le_bytes());
+///         blob.extend_from_slice(&location.y.to_le_bytes());
+///         Ok(blob)
+///     }
+///
+///     fn decode(
+///         &self,
+///         _kind: &u8,
+///         blob: &[u8],
+///     ) -> Result<Box<EntityTrait<'e, u8>>, Error> {
+///         let field = |i: usize| {
+///             let mut bytes = [0; 4];
+///             bytes.copy_from_slice(&blob[i * 4..(i + 1) * 4]);
+///             bytes
+///         };
+///         let id = u32::from_le_bytes(field(0)) as Id;
+///         let x = i32::from_le_bytes(field(1));
+///         let y = i32::from_le_bytes(field(2));
+///         Ok(Box::new(Cell(id, Location { x, y })))
+///     }
+/// }
+///
+/// let mut env = Environment::new((10, 10));
+/// env.insert(Cell(0, Location { x: 3, y: 4 }));
+///
+/// let mut bytes = Vec::new();
+/// env.save_to(&mut bytes, &CellCodec).unwrap();
+///
+/// let env = Environment::load_from(bytes.as_slice(), &CellCodec).unwrap();
+/// assert_eq!(env.count(), 1);
+/// assert_eq!(env.entities_at((3, 4)).count(), 1);
+///
+/// // snapshots written with a newer container format are rejected
+/// let mut newer = bytes.clone();
+/// newer[4..8].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+/// assert!(Environment::load_from(newer.as_slice(), &CellCodec).is_err());
+///
+/// // as well as sections that claim to be longer than the snapshot
+/// let mut truncated = bytes[..12].to_vec();
+/// truncated.extend_from_slice(b"DIMS");
+/// truncated.extend_from_slice(&u32::MAX.to_le_bytes());
+/// assert!(Environment::load_from(truncated.as_slice(), &CellCodec).is_err());
+///
+/// // or whose dimension is not positive, or is too big to be allocated
+/// let big = 1 << 15;
+/// for (x, y) in [(0, 10), (10, u32::MAX), (big, big), (2 * big, 2 * big)] {
+///     let mut malformed = bytes[..12].to_vec();
+///     malformed.extend_from_slice(b"DIMS");
+///     malformed.extend_from_slice(&16u32.to_le_bytes());
+///     malformed.extend_from_slice(&x.to_le_bytes());
+///     malformed.extend_from_slice(&y.to_le_bytes());
+///     malformed.extend_from_slice(&0u64.to_le_bytes());
+///     let loaded = Environment::load_from(malformed.as_slice(), &CellCodec);
+///     assert!(loaded.is_err());
+/// }
+/// ```
+pub trait Codec<'e, K, C = ()> {
+    /// Gets the version of the encoding used by this Codec for the entity
+    /// blobs.
+    ///
+    /// Every time the encoding of the entities changes in a way that is not
+    /// backward compatible, this version should be increased, and the
+    /// `Codec::migrate` hook should be updated accordingly.
+    fn version(&self) -> u32 {
+        0
+    }
+
+    /// Encodes the given Kind into a binary blob.
+    fn encode_kind(&self, kind: &K) -> Result<Vec<u8>, Error>;
+
+    /// Decodes the Kind from the given binary blob.
+    fn decode_kind(&self, bytes: &[u8]) -> Result<K, Error>;
+
+    /// Encodes the given Entity into a binary blob.
+    fn encode(&self, entity: &EntityTrait<'e, K, C>) -> Result<Vec<u8>, Error>;
+
+    /// Decodes a new Entity of the given Kind from the given binary blob.
+    fn decode(
+        &self,
+        kind: &K,
+        blob: &[u8],
+    ) -> Result<Box<EntityTrait<'e, K, C>>, Error>;
+
+    /// Migrates the given Entity blob, encoded with the given version of this
+    /// Codec, to the next version.
+    ///
+    /// When loading a snapshot written with an older version of the Codec, this
+    /// method is called once for each version between the one stored in the
+    /// snapshot and the current one. By default the blob is left unchanged.
+    fn migrate(&self, _version: u32, blob: Vec<u8>) -> Result<Vec<u8>, Error> {
+        Ok(blob)
+    }
+
+    /// Gets the codecs of the data attached to the tiles of the Environment
+    /// (see `Environment::set_tile_data`) that is saved together with the
+    /// entities, if any.
+    ///
+    /// By default no tile data is saved.
+    fn tile_data(&self) -> Option<&TileDataCodecs> {
+        None
+    }
+}
+
+/// The type of the function that encodes the data attached to a tile.
+type DataEncoder =
+    Box<dyn Fn(&dyn Any) -> Result<Vec<u8>, Error> + Send + Sync>;
+
+/// The type of the function that decodes the data attached to a tile.
+type DataDecoder = Box<dyn Fn(&[u8]) -> Result<Datum, Error> + Send + Sync>;
+
+/// The codec of the data of a specific type attached to the tiles.
+struct TileDataCodec {
+    name: String,
+    type_id: TypeId,
+    encode: DataEncoder,
+    decode: DataDecoder,
+}
+
+/// The codecs of the data attached to the tiles of an Environment, one for
+/// each type of data, each identified in the snapshot by a unique name, so
+/// that the state of the tiles (such as pheromones or terrain) round-trips
+/// through the snapshots together with the entities.
+///
+/// The data of the types without a codec is not saved, and the data saved
+/// with a name without a codec is skipped when loading a snapshot.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Pheromone(u8);
+///
+/// struct PheromoneCodec(TileDataCodecs);
+///
+/// impl<'e> Codec<'e, u8> for PheromoneCodec {
+///     fn encode_kind(&self, kind: &u8) -> Result<Vec<u8>, Error> {
+///         Ok(vec![*kind])
+///     }
+///
+///     fn decode_kind(&self, bytes: &[u8]) -> Result<u8, Error> {
+///         bytes.first().copied().ok_or(Error::Unknown)
+///     }
+///
+///     fn encode(&self, _: &EntityTrait<'e, u8>) -> Result<Vec<u8>, Error> {
+///         Err(Error::Unknown)
+///     }
+///
+///     fn decode(
+///         &self,
+///         _: &u8,
+///         _: &[u8],
+///     ) -> Result<Box<EntityTrait<'e, u8>>, Error> {
+///         Err(Error::Unknown)
+///     }
+///
+///     fn tile_data(&self) -> Option<&TileDataCodecs> {
+///         Some(&self.0)
+///     }
+/// }
+///
+/// let mut codecs = TileDataCodecs::new();
+/// codecs.register(
+///     "pheromone",
+///     |pheromone: &Pheromone| Ok(vec![pheromone.0]),
+///     |bytes| Ok(Pheromone(*bytes.first().ok_or(Error::Unknown)?)),
+/// );
+/// let codec = PheromoneCodec(codecs);
+///
+/// let mut env = Environment::new((4, 4));
+/// env.set_tile_data((1, 2), Pheromone(7));
+/// let mut bytes = Vec::new();
+/// env.save_to(&mut bytes, &codec).unwrap();
+///
+/// let env = Environment::load_from(bytes.as_slice(), &codec).unwrap();
+/// assert_eq!(env.tile_data((1, 2)), Some(&Pheromone(7)));
+/// assert_eq!(env.tile_data::<Pheromone>((2, 1)), None);
+/// ```
+#[derive(Default)]
+pub struct TileDataCodecs {
+    codecs: Vec<TileDataCodec>,
+}
+
+impl fmt::Debug for TileDataCodecs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.codecs.iter().map(|codec| &codec.name))
+            .finish()
+    }
+}
+
+impl TileDataCodecs {
+    /// Constructs a new empty list of codecs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the given functions to encode and decode the data of type T
+    /// attached to the tiles, identified by the given name in the snapshots,
+    /// replacing the codec previously registered for the same type or name.
+    #[cfg(not(feature = "parallel"))]
+    pub fn register<T: Any>(
+        &mut self,
+        name: impl Into<String>,
+        encode: impl Fn(&T) -> Result<Vec<u8>, Error> + Send + Sync + 'static,
+        decode: impl Fn(&[u8]) -> Result<T, Error> + Send + Sync + 'static,
+    ) -> &mut Self {
+        let decode = move |bytes: &[u8]| Ok(Box::new(decode(bytes)?) as Datum);
+        self.insert::<T>(name.into(), encode, Box::new(decode))
+    }
+
+    /// Registers the given functions to encode and decode the data of type T
+    /// attached to the tiles, identified by the given name in the snapshots,
+    /// replacing the codec previously registered for the same type or name.
+    #[cfg(feature = "parallel")]
+    pub fn register<T: Any + Send + Sync>(
+        &mut self,
+        name: impl Into<String>,
+        encode: impl Fn(&T) -> Result<Vec<u8>, Error> + Send + Sync + 'static,
+        decode: impl Fn(&[u8]) -> Result<T, Error> + Send + Sync + 'static,
+    ) -> &mut Self {
+        let decode = move |bytes: &[u8]| Ok(Box::new(decode(bytes)?) as Datum);
+        self.insert::<T>(name.into(), encode, Box::new(decode))
+    }
+
+    /// Inserts the codec of the data of type T.
+    fn insert<T: Any>(
+        &mut self,
+        name: String,
+        encode: impl Fn(&T) -> Result<Vec<u8>, Error> + Send + Sync + 'static,
+        decode: DataDecoder,
+    ) -> &mut Self {
+        let type_id = TypeId::of::<T>();
+        self.codecs
+            .retain(|codec| codec.type_id != type_id && codec.name != name);
+        let encode = move |data: &dyn Any| match data.downcast_ref() {
+            Some(data) => encode(data),
+            None => Err(Error::with_message("Invalid tile data type")),
+        };
+        self.codecs.push(TileDataCodec {
+            name,
+            type_id,
+            encode: Box::new(encode),
+            decode,
+        });
+        self
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Saves a snapshot of the Environment into the given writer, encoding its
+    /// kinds and entities with the given Codec.
+    ///
+    /// Returns an error if any of the kinds or entities cannot be encoded, or
+    /// if writing to the given writer fails.
+    pub fn save_to(
+        &self,
+        mut writer: impl Write,
+        codec: &(impl Codec<'e, K, C> + ?Sized),
+    ) -> Result<(), Error> {
+        writer
+            .write_all(&SNAPSHOT_MAGIC)
+            .map_err(Error::with_message)?;
+        write_u32(&mut writer, SNAPSHOT_VERSION)?;
+        write_u32(&mut writer, codec.version())?;
+
+        let mut section = Vec::new();
+        let dimension = self.dimension();
+        section.extend_from_slice(&dimension.x.to_le_bytes());
+        section.extend_from_slice(&dimension.y.to_le_bytes());
+        section.extend_from_slice(&self.generation.get().to_le_bytes());
+        write_section(&mut writer, DIMENSION_TAG, &section)?;
+
+        section.clear();
+        push_len(&mut section, self.entities.len());
+        for kind in self.entities.keys() {
+            push_blob(&mut section, &codec.encode_kind(kind)?);
+        }
+        write_section(&mut writer, KINDS_TAG, &section)?;
+
+        section.clear();
+        push_len(&mut section, self.count());
+        for (index, entities) in self.entities.values().enumerate() {
+            for entity in entities {
+                push_len(&mut section, index);
+                push_blob(&mut section, &codec.encode(&**entity)?);
+            }
+        }
+        write_section(&mut writer, ENTITIES_TAG, &section)?;
+
+        if let Some(codecs) = codec.tile_data() {
+            section.clear();
+            let mut count = 0;
+            let mut entries = Vec::new();
+            for data_codec in &codecs.codecs {
+                for (location, data) in self.tiles.data_of(data_codec.type_id) {
+                    push_blob(&mut entries, data_codec.name.as_bytes());
+                    entries.extend_from_slice(&location.x.to_le_bytes());
+                    entries.extend_from_slice(&location.y.to_le_bytes());
+                    push_blob(&mut entries, &(data_codec.encode)(data)?);
+                    count += 1;
+                }
+            }
+            push_len(&mut section, count);
+            section.extend_from_slice(&entries);
+            write_section(&mut writer, DATA_TAG, &section)?;
+        }
+
+        write_section(&mut writer, END_TAG, &[])
+    }
+
+    /// Loads an Environment from a snapshot read from the given reader,
+    /// decoding its kinds and entities with the given Codec.
+    ///
+    /// Returns an error if the snapshot is malformed, if it was written with a
+    /// more recent version of the container format or of the Codec, or if any
+    /// of the kinds or entities cannot be decoded. The dimension of the
+    /// snapshot must be positive and made of at most 2^28 tiles.
+    pub fn load_from(
+        mut reader: impl Read,
+        codec: &(impl Codec<'e, K, C> + ?Sized),
+    ) -> Result<Self, Error> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic).map_err(Error::with_message)?;
+        if magic != SNAPSHOT_MAGIC {
+            return Err(Error::with_message("Invalid snapshot magic bytes"));
+        }
+        let version = read_u32(&mut reader)?;
+        if version > SNAPSHOT_VERSION {
+            return Err(Error::with_message(format!(
+                "Unsupported snapshot version {}",
+                version
+            )));
+        }
+        let version = read_u32(&mut reader)?;
+        if version > codec.version() {
+            return Err(Error::with_message(format!(
+                "Unsupported snapshot codec version {}",
+                version
+            )));
+        }
+
+        let mut env = None;
+        let mut kinds = Vec::new();
+        loop {
+            let mut tag = [0; 4];
+            reader.read_exact(&mut tag).map_err(Error::with_message)?;
+            // the section is allocated as it is read (see `read_blob`)
+            let section = read_blob(&mut reader)?;
+            let mut section = section.as_slice();
+
+            match tag {
+                DIMENSION_TAG => {
+                    let x = read_u32(&mut section)? as i32;
+                    let y = read_u32(&mut section)? as i32;
+                    let tiles = x.checked_mul(y).filter(|&tiles| {
+                        x > 0 && y > 0 && tiles <= MAX_SNAPSHOT_TILES
+                    });
+                    if tiles.is_none() {
+                        return Err(Error::with_message(format!(
+                            "Invalid snapshot dimension {}x{}",
+                            x, y
+                        )));
+                    }
+                    let mut new = Self::new(Dimension { x, y });
+                    new.generation = read_u64(&mut section)?.into();
+                    env = Some(new);
+                }
+                KINDS_TAG => {
+                    let count = read_u32(&mut section)?;
+                    for _ in 0..count {
+                        let blob = read_blob(&mut section)?;
+                        kinds.push(codec.decode_kind(&blob)?);
+                    }
+                }
+                ENTITIES_TAG => {
+                    let env = env.as_mut().ok_or_else(|| {
+                        Error::with_message("Missing snapshot dimension")
+                    })?;
+                    let count = read_u32(&mut section)?;
+                    for _ in 0..count {
+                        let index = read_u32(&mut section)? as usize;
+                        let kind = kinds.get(index).ok_or_else(|| {
+                            Error::with_message("Invalid snapshot kind index")
+                        })?;
+                        let mut blob = read_blob(&mut section)?;
+                        for v in version..codec.version() {
+                            blob = codec.migrate(v, blob)?;
+                        }
+                        env.insert_boxed(codec.decode(kind, &blob)?);
+                    }
+                }
+                DATA_TAG => {
+                    let env = env.as_mut().ok_or_else(|| {
+                        Error::with_message("Missing snapshot dimension")
+                    })?;
+                    let count = read_u32(&mut section)?;
+                    for _ in 0..count {
+                        let name = read_blob(&mut section)?;
+                        let x = read_u32(&mut section)? as i32;
+                        let y = read_u32(&mut section)? as i32;
+                        let blob = read_blob(&mut section)?;
+                        // skip the data whose type has no codec
+                        let data_codec = codec.tile_data().and_then(|codecs| {
+                            codecs
+                                .codecs
+                                .iter()
+                                .find(|c| c.name.as_bytes() == name)
+                        });
+                        if let Some(data_codec) = data_codec {
+                            let data = (data_codec.decode)(&blob)?;
+                            env.tiles.insert_datum(
+                                Location { x, y },
+                                data_codec.type_id,
+                                data,
+                            );
+                        }
+                    }
+                }
+                END_TAG => break,
+                // skip sections written by newer versions of the library
+                _ => (),
+            }
+        }
+
+        env.ok_or_else(|| Error::with_message("Missing snapshot dimension"))
+    }
+}
+
+/// Writes a section with the given tag and content.
+fn write_section(
+    writer: &mut impl Write,
+    tag: Tag,
+    section: &[u8],
+) -> Result<(), Error> {
+    writer.write_all(&tag).map_err(Error::with_message)?;
+    write_u32(writer, section.len() as u32)?;
+    writer.write_all(section).map_err(Error::with_message)
+}
+
+/// Writes the given value in little endian.
+fn write_u32(writer: &mut impl Write, value: u32) -> Result<(), Error> {
+    writer
+        .write_all(&value.to_le_bytes())
+        .map_err(Error::with_message)
+}
+
+/// Pushes the given length as a 32 bits value in little endian.
+pub(super) fn push_len(buffer: &mut Vec<u8>, len: usize) {
+    buffer.extend_from_slice(&(len as u32).to_le_bytes());
+}
+
+/// Pushes the given blob prefixed by its length.
+pub(super) fn push_blob(buffer: &mut Vec<u8>, blob: &[u8]) {
+    push_len(buffer, blob.len());
+    buffer.extend_from_slice(blob);
+}
+
+/// Reads a 32 bits value in little endian.
+pub(super) fn read_u32(reader: &mut impl Read) -> Result<u32, Error> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes).map_err(Error::with_message)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Reads a 64 bits value in little endian.
+pub(super) fn read_u64(reader: &mut impl Read) -> Result<u64, Error> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes).map_err(Error::with_message)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Reads a blob prefixed by its length.
+///
+/// The blob is only allocated as it is read, so that a corrupted or malicious
+/// length cannot exhaust the memory before the reader runs out of bytes.
+pub(super) fn read_blob(reader: &mut impl Read) -> Result<Vec<u8>, Error> {
+    let len = u64::from(read_u32(reader)?);
+    let mut blob = Vec::new();
+    reader
+        .take(len)
+        .read_to_end(&mut blob)
+        .map_err(Error::with_message)?;
+    if blob.len() as u64 != len {
+        return Err(Error::with_message("Truncated blob"));
+    }
+    Ok(blob)
+}
@@ -0,0 +1,263 @@
+use super::*;
+
+/// A stack of environments of the same Dimension, whose layers are moved to
+/// the next generation together, in order, and whose entities can see the
+/// other layers of the stack.
+///
+/// Each layer is an independent Environment (for example a vegetation layer,
+/// and an animals layer), but its entities can query a read-only view of any
+/// other layer via `WorldView::layer` when they observe and react to their
+/// Neighborhood.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let mut stack = EnvironmentStack::<&str>::new((5, 5));
+/// let mut vegetation = Environment::new((5, 5));
+/// vegetation.insert(QuickEntity::new("grass").at((2, 2)));
+/// let vegetation = stack.push(vegetation).unwrap();
+///
+/// let mut animals = Environment::new((5, 5));
+/// animals.insert(QuickEntity::new("sheep").at((2, 2)).scope(0));
+/// let animals = stack.push(animals).unwrap();
+/// assert!(stack.push(Environment::new((4, 4))).is_err());
+///
+/// stack.nextgen().unwrap();
+/// assert_eq!(stack.generation(), 1);
+/// assert_eq!(stack.layer(vegetation).unwrap().generation(), 1);
+/// assert_eq!(stack.layer(animals).unwrap().count(), 1);
+/// ```
+pub struct EnvironmentStack<'e, K, C = ()> {
+    dimension: Dimension,
+    // the layers, sorted in the order they are moved to the next generation
+    layers: Vec<Environment<'e, K, C>>,
+    // the generation counter of the whole stack
+    generation: Generation,
+}
+
+impl<'e, K: fmt::Debug, C> fmt::Debug for EnvironmentStack<'e, K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnvironmentStack")
+            .field("dimension", &self.dimension)
+            .field("layers", &self.layers)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+impl<'e, K: Ord, C> EnvironmentStack<'e, K, C> {
+    /// Constructs a new empty stack, whose layers must all have the given
+    /// dimension.
+    pub fn new(dimension: impl Into<Dimension>) -> Self {
+        Self {
+            dimension: dimension.into(),
+            layers: Vec::new(),
+            generation: Generation::ZERO,
+        }
+    }
+
+    /// Gets the Dimension of the layers of the stack.
+    pub fn dimension(&self) -> Dimension {
+        self.dimension
+    }
+
+    /// Pushes the given Environment on top of the stack, and returns the index
+    /// of its layer.
+    ///
+    /// Returns an error if the Environment dimension is different from the
+    /// dimension of the stack.
+    pub fn push(&mut self, env: Environment<'e, K, C>) -> Result<usize, Error> {
+        if env.dimension() != self.dimension {
+            return Err(Error::with_message("Mismatching layer dimension"));
+        }
+        self.layers.push(env);
+        Ok(self.layers.len() - 1)
+    }
+
+    /// Gets the number of layers of the stack.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns true only if the stack has no layers.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Gets the current generation step number of the stack.
+    pub fn generation(&self) -> Generation {
+        self.generation
+    }
+
+    /// Gets a reference to the layer with the given index, if any.
+    pub fn layer(&self, index: usize) -> Option<&Environment<'e, K, C>> {
+        self.layers.get(index)
+    }
+
+    /// Gets a mutable reference to the layer with the given index, if any.
+    pub fn layer_mut(
+        &mut self,
+        index: usize,
+    ) -> Option<&mut Environment<'e, K, C>> {
+        self.layers.get_mut(index)
+    }
+
+    /// Gets an iterator over all the layers of the stack, from the bottom to
+    /// the top of the stack.
+    pub fn layers(&self) -> impl Iterator<Item = &Environment<'e, K, C>> {
+        self.layers.iter()
+    }
+
+    /// Gets an iterator over all the entities located at the given location of
+    /// the given layer, that is empty if the layer does not exist.
+    ///
+    /// The entities will be returned in an arbitrary order.
+    pub fn entities_at(
+        &self,
+        location: impl Into<Location3>,
+    ) -> impl Iterator<Item = &EntityTrait<'e, K, C>> {
+        let location = location.into();
+        self.layers
+            .get(location.layer)
+            .into_iter()
+            .flat_map(move |layer| layer.entities_at(location.location()))
+    }
+
+    /// Gets an iterator over all the entities located at the given location of
+    /// any layer, from the bottom to the top of the stack, each with the index
+    /// of its layer.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut stack = EnvironmentStack::<&str>::new((5, 5));
+    /// let mut surface = Environment::new((5, 5));
+    /// surface.insert(QuickEntity::new("rabbit").id(0).at((2, 2)));
+    /// let surface = stack.push(surface).unwrap();
+    /// let mut underground = Environment::new((5, 5));
+    /// underground.insert(QuickEntity::new("mole").id(1).at((2, 2)));
+    /// let underground = stack.push(underground).unwrap();
+    ///
+    /// let column: Vec<_> = stack
+    ///     .column((2, 2))
+    ///     .map(|(layer, entity)| (layer, entity.kind()))
+    ///     .collect();
+    /// assert_eq!(column, [(surface, "rabbit"), (underground, "mole")]);
+    ///
+    /// stack.move_to_layer(1, (3, 3, surface)).unwrap();
+    /// assert_eq!(stack.locate(1), Some(Location3::from((3, 3, surface))));
+    /// assert_eq!(stack.entities_at((3, 3, surface)).count(), 1);
+    /// assert!(stack.layer(underground).unwrap().is_empty());
+    /// ```
+    pub fn column(
+        &self,
+        location: impl Into<Location>,
+    ) -> impl Iterator<Item = (usize, &EntityTrait<'e, K, C>)> {
+        let location = location.into();
+        self.layers
+            .iter()
+            .enumerate()
+            .flat_map(move |(index, layer)| {
+                layer.entities_at(location).map(move |e| (index, e))
+            })
+    }
+
+    /// Gets the Location3 of the Entity with the given ID, searching the
+    /// layers from the bottom to the top of the stack, or None if no such
+    /// Entity exists or if it has no location.
+    pub fn locate(&self, id: Id) -> Option<Location3> {
+        self.layers.iter().enumerate().find_map(|(index, layer)| {
+            let entity = layer.entities().find(|e| e.id() == id)?;
+            Some(Location3::new(entity.location()?, index))
+        })
+    }
+
+    /// Moves the Entity with the given ID to the given location (wrapped around
+    /// the edges of the layers) of the given layer, that can be the same layer
+    /// where the Entity currently is.
+    ///
+    /// When the Entity changes layer, it is removed from its current layer and
+    /// inserted into the destination one, and its age starts over.
+    ///
+    /// Returns an error if the destination layer does not exist, or if no
+    /// Entity with the given ID and with a location that can be changed (via
+    /// `Entity::location_mut`) exists, in which case the Entity is not moved.
+    pub fn move_to_layer(
+        &mut self,
+        id: Id,
+        destination: impl Into<Location3>,
+    ) -> Result<(), Error> {
+        let destination = destination.into();
+        if destination.layer >= self.layers.len() {
+            return Err(Error::with_message(format!(
+                "Layer {} not found",
+                destination.layer
+            )));
+        }
+        let source = self
+            .layers
+            .iter_mut()
+            .position(|layer| {
+                layer
+                    .entities_mut()
+                    .find(|e| e.id() == id)
+                    .and_then(|e| e.location_mut())
+                    .is_some()
+            })
+            .ok_or_else(|| {
+                Error::with_message(format!("Entity {} cannot be moved", id))
+            })?;
+        if source == destination.layer {
+            self.layers[source].relocate(id, destination.location());
+            return Ok(());
+        }
+
+        let mut entity = self.layers[source]
+            .take_entity(id)
+            .expect("Entity not found");
+        #[cfg(feature = "replay")]
+        self.layers[source].record_removals(std::iter::once(id));
+        let mut location = destination.location();
+        location.translate((0, 0), self.dimension);
+        *entity.location_mut().expect("Entity without location") = location;
+        let layer = &mut self.layers[destination.layer];
+        #[cfg(feature = "replay")]
+        layer.record_insert(&*entity);
+        layer.insert_boxed(entity);
+        Ok(())
+    }
+
+    /// Moves all the layers of the stack forwards to the next generation.
+    /// Returns the next generation step number of the stack.
+    ///
+    /// The layers are moved to the next generation one at a time, from the
+    /// bottom to the top of the stack, and while the entities of a layer are
+    /// being updated all the other layers are visible to them via
+    /// `WorldView::layer`. The layers below have therefore already reached the
+    /// next generation, while the layers above are still in the current one.
+    ///
+    /// Returns an error as soon as any of the layers fails to move to the next
+    /// generation, in which case the layers above it are not moved, and the
+    /// generation of the stack is not incremented.
+    pub fn nextgen(&mut self) -> Result<Generation, Error>
+    where
+        K: ParallelKind,
+    {
+        for index in 0..self.layers.len() {
+            let (below, rest) = self.layers.split_at_mut(index);
+            let (layer, above) =
+                rest.split_first_mut().expect("Invalid layer index");
+            let layers: Vec<_> = below
+                .iter()
+                .map(Some)
+                .chain(std::iter::once(None))
+                .chain(above.iter().map(Some))
+                .collect();
+            layer.nextgen_with_layers(&layers, None, &Progress::none())?;
+        }
+        self.generation = self.generation.next();
+        Ok(self.generation)
+    }
+}
@@ -0,0 +1,65 @@
+use super::*;
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Stamps the given Region onto the Environment, by inserting the Entity
+    /// constructed by the given function for each Location of the Region
+    /// (wrapped around the edges of the Environment), such as to draw the
+    /// wires of a circuit along a Line, or to fill a Disk or a Flood.
+    ///
+    /// The function can skip a Location by returning None.
+    /// Returns the number of entities inserted.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((10, 10));
+    /// let wire = Line::new((1, 0), (8, 2));
+    /// let count = env.stamp(&wire, |location| {
+    ///     Some(QuickEntity::new('w').at(location).boxed())
+    /// });
+    /// assert_eq!(count, 8);
+    ///
+    /// // fill the inside of a circle with a different Kind
+    /// env.stamp(&Circle::new((5, 5), 2), |location| {
+    ///     Some(QuickEntity::new('c').at(location).boxed())
+    /// });
+    /// let inside = env.flood((5, 5), |mut entities| entities.next().is_none());
+    /// let count = env.stamp(&inside, |location| {
+    ///     Some(QuickEntity::new('i').at(location).boxed())
+    /// });
+    /// assert_eq!(count, 9);
+    /// assert_eq!(env.count_kind(&'i'), 9);
+    /// ```
+    pub fn stamp(
+        &mut self,
+        region: &dyn Region,
+        mut f: impl FnMut(Location) -> Option<Box<EntityTrait<'e, K, C>>>,
+    ) -> usize {
+        let mut count = 0;
+        for location in region.iter(self.dimension()) {
+            if let Some(entity) = f(location) {
+                self.insert_recorded(entity);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Gets the Flood of all the tiles reachable from the given starting
+    /// Location by moving horizontally and vertically only across the tiles
+    /// whose entities satisfy the given predicate (such as the empty tiles),
+    /// so that the region enclosed by other entities can be stamped (see
+    /// `Environment::stamp`).
+    pub fn flood(
+        &self,
+        start: impl Into<Location>,
+        mut predicate: impl FnMut(
+            &mut dyn Iterator<Item = &EntityTrait<'e, K, C>>,
+        ) -> bool,
+    ) -> Flood {
+        Flood::new(start, self.dimension(), |location| {
+            predicate(&mut self.entities_at(location))
+        })
+    }
+}
@@ -0,0 +1,112 @@
+use super::*;
+
+/// The statistics of the entities of a Kind, kept up to date by the
+/// Environment as the entities are inserted and removed.
+///
+/// The births and deaths are counted since the start of the current generation
+/// (that is, since the start of the last call to `Environment::nextgen`), and
+/// include the entities inserted and removed outside of the generation steps.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct KindStats {
+    /// The number of entities of the Kind currently in the Environment.
+    pub count: usize,
+    /// The number of entities of the Kind inserted during the current
+    /// generation.
+    pub births: usize,
+    /// The number of entities of the Kind removed during the current
+    /// generation.
+    pub deaths: usize,
+    /// The total number of entities of the Kind removed from the Environment.
+    pub total_deaths: u64,
+    /// The sum of the ages of all the entities of the Kind removed from the
+    /// Environment, at the time of their removal.
+    pub total_age: u64,
+}
+
+impl KindStats {
+    /// Gets the average number of generations the removed entities of the
+    /// Kind spent in the Environment, or None if none was removed.
+    pub fn average_lifespan(&self) -> Option<f64> {
+        (self.total_deaths > 0)
+            .then(|| self.total_age as f64 / self.total_deaths as f64)
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Gets the statistics of the entities of the given Kind.
+    ///
+    /// The statistics are updated incrementally, so that this method does not
+    /// need to scan the entities. They are reset when the Environment is
+    /// restored from its history (see `Environment::seek`).
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((4, 4));
+    /// env.insert(QuickEntity::new('a').id(0).at((0, 0)));
+    /// env.insert(QuickEntity::new('a').id(1).at((1, 0)));
+    /// assert_eq!(env.stats(&'a').births, 2);
+    ///
+    /// env.nextgen().unwrap();
+    /// env.nextgen().unwrap();
+    /// assert_eq!(env.stats(&'a').births, 0);
+    /// env.retain(|entity| entity.id() != 0);
+    /// let stats = env.stats(&'a');
+    /// assert_eq!((stats.count, stats.deaths), (1, 1));
+    ///
+    /// env.nextgen().unwrap();
+    /// env.nextgen().unwrap();
+    /// env.clear();
+    /// let stats = env.stats(&'a');
+    /// assert_eq!((stats.count, stats.total_deaths), (0, 2));
+    /// assert_eq!(stats.average_lifespan(), Some(3.0));
+    /// ```
+    pub fn stats(&self, kind: &K) -> KindStats {
+        KindStats {
+            count: self.count_kind(kind),
+            ..self.stats.get(kind).copied().unwrap_or_default()
+        }
+    }
+
+    /// Resets the births and deaths counted during the current generation.
+    pub(super) fn reset_generation_stats(&mut self) {
+        for stats in self.stats.values_mut() {
+            stats.births = 0;
+            stats.deaths = 0;
+        }
+    }
+
+    /// Records the birth of the given Entity in the statistics of its Kind,
+    /// and buffers it for the subscribers to its insertion.
+    pub(super) fn record_birth(
+        stats: &mut BTreeMap<K, KindStats>,
+        births: &mut HashMap<Id, Generation>,
+        feeds: &mut Feeds<K>,
+        generation: Generation,
+        entity: &EntityTrait<'e, K, C>,
+    ) {
+        feeds.record(EventKind::Spawn, generation, entity);
+        births.insert(entity.id(), generation);
+        stats.entry(entity.kind()).or_default().births += 1;
+    }
+
+    /// Records the death of the given Entity in the statistics of its Kind,
+    /// forgetting its birth.
+    pub(super) fn record_death(
+        stats: &mut BTreeMap<K, KindStats>,
+        births: &mut HashMap<Id, Generation>,
+        feeds: &mut Feeds<K>,
+        generation: Generation,
+        entity: &EntityTrait<'e, K, C>,
+    ) {
+        feeds.record(EventKind::Death, generation, entity);
+        let age = births
+            .remove(&entity.id())
+            .map_or(0, |birth| generation.since(birth));
+        let stats = stats.entry(entity.kind()).or_default();
+        stats.deaths += 1;
+        stats.total_deaths += 1;
+        stats.total_age += age;
+    }
+}
@@ -0,0 +1,289 @@
+use std::io;
+
+use super::*;
+
+/// The type of the functions that measure a metric of an Environment at the
+/// end of each run of a Sweep.
+#[cfg(not(feature = "parallel"))]
+pub type Metric<'s, K, C> = Box<dyn Fn(&Environment<'_, K, C>) -> f64 + 's>;
+
+/// The type of the functions that measure a metric of an Environment at the
+/// end of each run of a Sweep.
+#[cfg(feature = "parallel")]
+pub type Metric<'s, K, C> =
+    Box<dyn Fn(&Environment<'_, K, C>) -> f64 + Send + Sync + 's>;
+
+/// A parameter sweep, that runs a number of simulations for each point of a
+/// grid of parameter values, each for the same number of generations, and
+/// collects the final value of its metrics into a SweepTable.
+///
+/// Each simulation is run on its own Environment, built from the parameters of
+/// its point and the index of its run (that can be used to seed the random
+/// placements, such as via `Config::seed` or `Seeder::new`). With the
+/// `parallel` feature, the simulations are run in parallel across the rayon
+/// thread pool.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let table = Sweep::new(["B3/S23", "B3/S"])
+///     .runs(2)
+///     .generations(4)
+///     .metric("population", |env| env.rule_population(&0) as f64)
+///     .run(|rule, run| {
+///         let mut env = Environment::<u8>::new((8, 8));
+///         env.attach_rule(Rule::parse(rule)?, 0);
+///         // a blinker, translated by the index of the run
+///         let blinker = Rect {
+///             origin: Location { x: run as i32, y: 2 },
+///             dimension: Dimension { x: 3, y: 1 },
+///         };
+///         env.set_rule_region(&0, &blinker, true);
+///         Ok(env)
+///     })
+///     .unwrap();
+///
+/// assert_eq!(table.rows().len(), 4);
+/// assert_eq!(table.mean(0, "population"), Some(3.0));
+/// assert_eq!(table.mean(1, "population"), Some(0.0));
+///
+/// let mut csv = Vec::new();
+/// table.write_csv(&mut csv).unwrap();
+/// assert!(csv.starts_with(b"point,run,population\n0,0,3\n0,1,3\n1,0,0\n"));
+/// ```
+pub struct Sweep<'s, P, K, C = ()> {
+    points: Vec<P>,
+    runs: usize,
+    generations: u64,
+    metrics: Vec<(String, Metric<'s, K, C>)>,
+}
+
+impl<'s, P: fmt::Debug, K, C> fmt::Debug for Sweep<'s, P, K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metrics: Vec<_> =
+            self.metrics.iter().map(|(name, _)| name).collect();
+        f.debug_struct("Sweep")
+            .field("points", &self.points)
+            .field("runs", &self.runs)
+            .field("generations", &self.generations)
+            .field("metrics", &metrics)
+            .finish()
+    }
+}
+
+impl<'s, P, K, C> Sweep<'s, P, K, C> {
+    /// Constructs a new Sweep over the given grid of parameter values, with a
+    /// single run for each point, of zero generations, and without any metric.
+    pub fn new(points: impl IntoIterator<Item = P>) -> Self {
+        Self {
+            points: points.into_iter().collect(),
+            runs: 1,
+            generations: 0,
+            metrics: Vec::new(),
+        }
+    }
+
+    /// Sets the number of simulations run for each point of the grid.
+    pub fn runs(mut self, runs: usize) -> Self {
+        self.runs = runs;
+        self
+    }
+
+    /// Sets the number of generations each simulation is run for.
+    pub fn generations(mut self, generations: u64) -> Self {
+        self.generations = generations;
+        self
+    }
+
+    /// Adds a metric with the given name, measured by the given function on
+    /// the Environment of each simulation once its generations are over.
+    #[cfg(not(feature = "parallel"))]
+    pub fn metric(
+        mut self,
+        name: impl Into<String>,
+        f: impl Fn(&Environment<'_, K, C>) -> f64 + 's,
+    ) -> Self {
+        self.metrics.push((name.into(), Box::new(f)));
+        self
+    }
+
+    /// Adds a metric with the given name, measured by the given function on
+    /// the Environment of each simulation once its generations are over.
+    #[cfg(feature = "parallel")]
+    pub fn metric(
+        mut self,
+        name: impl Into<String>,
+        f: impl Fn(&Environment<'_, K, C>) -> f64 + Send + Sync + 's,
+    ) -> Self {
+        self.metrics.push((name.into(), Box::new(f)));
+        self
+    }
+
+    /// Gets the grid of parameter values of the Sweep, indexed by point.
+    pub fn points(&self) -> &[P] {
+        &self.points
+    }
+
+    /// Gets the point and run index of each simulation, in order.
+    fn jobs(&self) -> Vec<(usize, usize)> {
+        (0..self.points.len())
+            .flat_map(|point| (0..self.runs).map(move |run| (point, run)))
+            .collect()
+    }
+
+    /// Gets the names of the metrics of the Sweep, in order.
+    fn columns(&self) -> Vec<String> {
+        self.metrics.iter().map(|(name, _)| name.clone()).collect()
+    }
+}
+
+impl<'s, P, K: Ord, C> Sweep<'s, P, K, C> {
+    /// Runs the simulation of the given point and run index, with the
+    /// Environment built by the given function.
+    fn simulate<'e>(
+        &self,
+        point: usize,
+        run: usize,
+        build: impl Fn(&P, usize) -> Result<Environment<'e, K, C>, Error>,
+    ) -> Result<SweepRow, Error>
+    where
+        K: ParallelKind,
+    {
+        let mut env = build(&self.points[point], run)?;
+        for _ in 0..self.generations {
+            env.nextgen()?;
+        }
+        let values = self.metrics.iter().map(|(_, f)| f(&env)).collect();
+        Ok(SweepRow { point, run, values })
+    }
+
+    /// Runs all the simulations of the Sweep, with the Environment of each
+    /// built by the given function from the parameters of its point and the
+    /// index of its run, and collects their metrics into a SweepTable.
+    ///
+    /// Returns the first error returned by the function or by any of the
+    /// generations of a simulation.
+    #[cfg(not(feature = "parallel"))]
+    pub fn run<'e>(
+        &self,
+        build: impl Fn(&P, usize) -> Result<Environment<'e, K, C>, Error>,
+    ) -> Result<SweepTable, Error> {
+        let rows = self
+            .jobs()
+            .into_iter()
+            .map(|(point, run)| self.simulate(point, run, &build))
+            .collect::<Result<_, _>>()?;
+        Ok(SweepTable {
+            columns: self.columns(),
+            rows,
+        })
+    }
+
+    /// Runs all the simulations of the Sweep in parallel, with the Environment
+    /// of each built by the given function from the parameters of its point
+    /// and the index of its run, and collects their metrics into a SweepTable.
+    ///
+    /// Returns an error returned by the function or by any of the generations
+    /// of a simulation, if any.
+    #[cfg(feature = "parallel")]
+    pub fn run<'e>(
+        &self,
+        build: impl Fn(&P, usize) -> Result<Environment<'e, K, C>, Error>
+            + Send
+            + Sync,
+    ) -> Result<SweepTable, Error>
+    where
+        P: Sync,
+        K: ParallelKind,
+    {
+        use rayon::prelude::*;
+
+        let rows = self
+            .jobs()
+            .into_par_iter()
+            .map(|(point, run)| self.simulate(point, run, &build))
+            .collect::<Result<_, _>>()?;
+        Ok(SweepTable {
+            columns: self.columns(),
+            rows,
+        })
+    }
+}
+
+/// The final metrics of a simulation of a Sweep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepRow {
+    /// The index of the point of the grid the simulation was run for.
+    pub point: usize,
+    /// The index of the run of the simulation, among the ones of its point.
+    pub run: usize,
+    /// The value of each metric, in the order of the columns of the table.
+    pub values: Vec<f64>,
+}
+
+/// The results of a Sweep, with a row for each of its simulations, ordered by
+/// point and run, and a column for each of its metrics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepTable {
+    columns: Vec<String>,
+    rows: Vec<SweepRow>,
+}
+
+impl SweepTable {
+    /// Gets the names of the metrics, in order.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Gets the rows of the table, ordered by point and run.
+    pub fn rows(&self) -> &[SweepRow] {
+        &self.rows
+    }
+
+    /// Gets an iterator over the values of the metric with the given name for
+    /// all the runs of the given point, or None if there is no such metric.
+    pub fn values(
+        &self,
+        point: usize,
+        metric: &str,
+    ) -> Option<impl Iterator<Item = f64> + '_> {
+        let column = self.columns.iter().position(|name| name == metric)?;
+        Some(
+            self.rows
+                .iter()
+                .filter(move |row| row.point == point)
+                .map(move |row| row.values[column]),
+        )
+    }
+
+    /// Gets the mean of the values of the metric with the given name across
+    /// all the runs of the given point, or None if there is no such metric or
+    /// the point has no runs.
+    pub fn mean(&self, point: usize, metric: &str) -> Option<f64> {
+        let (count, sum) = self
+            .values(point, metric)?
+            .fold((0, 0.0), |(count, sum), value| (count + 1, sum + value));
+        (count > 0).then(|| sum / count as f64)
+    }
+
+    /// Writes the table in CSV format to the given writer, with a header made
+    /// of the point and run columns followed by the names of the metrics.
+    ///
+    /// Returns an error if the writer fails.
+    pub fn write_csv(&self, mut writer: impl io::Write) -> Result<(), Error> {
+        let mut header = vec!["point", "run"];
+        header.extend(self.columns.iter().map(String::as_str));
+        writeln!(writer, "{}", header.join(","))
+            .map_err(Error::with_message)?;
+        for row in &self.rows {
+            write!(writer, "{},{}", row.point, row.run)
+                .map_err(Error::with_message)?;
+            for value in &row.values {
+                write!(writer, ",{}", value).map_err(Error::with_message)?;
+            }
+            writeln!(writer).map_err(Error::with_message)?;
+        }
+        Ok(())
+    }
+}
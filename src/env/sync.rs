@@ -0,0 +1,555 @@
+//! This module contains the delta encoding used to mirror an Environment.
+//!
+//! A delta starts with a fixed header, made of the magic bytes and the version
+//! of its format, followed by:
+//!
+//! | Field      | Content                                                 |
+//! |------------|---------------------------------------------------------|
+//! | keyframe   | 1 if the delta replaces all the entities, 0 otherwise   |
+//! | base       | the generation the delta applies to (0 for a keyframe)  |
+//! | generation | the generation reached once the delta is applied        |
+//! | dimension  | the columns and rows of the Environment (keyframe only) |
+//! | changes    | the number of changes, followed by each of them         |
+//!
+//! Where each change is made of its tag and its payload: the encoded Kind,
+//! Entity and location of a spawn or of a state change, the ID of a death, or
+//! the ID and the new location of a move.
+
+use std::io::{Read, Write};
+
+use super::*;
+use snapshot::{push_blob, push_len, read_blob, read_u32, read_u64};
+
+/// The magic bytes that identify a Delta of an Environment.
+pub const DELTA_MAGIC: [u8; 4] = *b"SMND";
+
+/// The version of the delta format written by this library.
+pub const DELTA_VERSION: u32 = 1;
+
+const SPAWN_TAG: u8 = 0;
+const DEATH_TAG: u8 = 1;
+const MOVE_TAG: u8 = 2;
+const STATE_TAG: u8 = 3;
+
+/// The change of an Entity between two generations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Change {
+    /// An Entity, encoded with its Kind, was inserted at the given location.
+    Spawn(Encoded),
+    /// The Entity with the given ID was removed.
+    Death(Id),
+    /// The Entity with the given ID was moved to the given location.
+    Move(Id, Location),
+    /// The encoding of an Entity changed, and replaces the mirrored one.
+    State(Encoded),
+}
+
+/// An Entity encoded with its Kind and its location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Encoded {
+    kind: Vec<u8>,
+    blob: Vec<u8>,
+    location: Option<Location>,
+}
+
+/// The changes of the entities of an Environment between two generations,
+/// computed by an authoritative Environment with `Environment::delta`, and
+/// applied to its mirrors with `Environment::apply_delta`.
+///
+/// A Delta can be sent over any transport, via `Delta::write_to` and
+/// `Delta::read_from`.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// struct CellCodec;
+///
+/// impl<'e> Codec<'e, char> for CellCodec {
+///     fn encode_kind(&self, kind: &char) -> Result<Vec<u8>, Error> {
+///         Ok(vec![*kind as u8])
+///     }
+///
+///     fn decode_kind(&self, bytes: &[u8]) -> Result<char, Error> {
+///         bytes.first().map(|&b| b as char).ok_or(Error::Unknown)
+///     }
+///
+///     fn encode(
+///         &self,
+///         entity: &EntityTrait<'e, char>,
+///     ) -> Result<Vec<u8>, Error> {
+///         Ok(vec![entity.id() as u8])
+///     }
+///
+///     fn decode(
+///         &self,
+///         kind: &char,
+///         blob: &[u8],
+///     ) -> Result<Box<EntityTrait<'e, char>>, Error> {
+///         // the location is mirrored on its own
+///         let id = *blob.first().ok_or(Error::Unknown)? as Id;
+///         Ok(QuickEntity::new(*kind).id(id).at((0, 0)).boxed())
+///     }
+/// }
+///
+/// let mut server = Environment::<char>::new((8, 8));
+/// server.insert(QuickEntity::new('a').id(0).at((0, 0)).on_react(|e, _| {
+///     e.set_location(e.location().unwrap() + (1, 0).into());
+///     Ok(())
+/// }));
+/// let mut baseline = SyncBaseline::new();
+/// let mut client = Environment::<char>::new((8, 8));
+///
+/// // the first delta is a keyframe with all the entities
+/// let delta = server.delta(&mut baseline, &CellCodec).unwrap();
+/// assert!(delta.is_keyframe());
+/// client.apply_delta(&delta, &CellCodec).unwrap();
+/// assert_eq!(client.entities_at((0, 0)).count(), 1);
+///
+/// server.nextgen().unwrap();
+/// server.insert(QuickEntity::new('b').id(1).at((4, 4)));
+/// let mut bytes = Vec::new();
+/// let delta = server.delta(&mut baseline, &CellCodec).unwrap();
+/// delta.write_to(&mut bytes).unwrap();
+///
+/// let delta = Delta::read_from(bytes.as_slice()).unwrap();
+/// assert_eq!(delta.len(), 2);
+/// assert_eq!(client.apply_delta(&delta, &CellCodec).unwrap(), 1);
+/// assert_eq!(client.entities_at((1, 0)).count(), 1);
+/// assert_eq!(client.entities_at((4, 4)).count(), 1);
+///
+/// // a spawned Entity cannot take the ID of an existing one
+/// client.insert(QuickEntity::new('c').id(2).at((2, 2)));
+/// server.nextgen().unwrap();
+/// server.insert(QuickEntity::new('c').id(2).at((5, 5)));
+/// let delta = server.delta(&mut baseline, &CellCodec).unwrap();
+/// assert!(client.apply_delta(&delta, &CellCodec).is_err());
+///
+/// // a keyframe cannot be applied to a mirror of a different dimension
+/// baseline.reset();
+/// let keyframe = server.delta(&mut baseline, &CellCodec).unwrap();
+/// let mut small = Environment::<char>::new((4, 4));
+/// assert!(small.apply_delta(&keyframe, &CellCodec).is_err());
+/// assert!(client.apply_delta(&keyframe, &CellCodec).is_ok());
+///
+/// // and an Entity cannot be spawned outside the mirror
+/// let mut bytes = DELTA_MAGIC.to_vec();
+/// bytes.extend_from_slice(&DELTA_VERSION.to_le_bytes());
+/// bytes.push(1);
+/// bytes.extend_from_slice(&[0; 16]);
+/// bytes.extend_from_slice(&[8, 0, 0, 0, 8, 0, 0, 0]); // 8x8
+/// bytes.extend_from_slice(&1u32.to_le_bytes()); // a single change
+/// bytes.extend_from_slice(&[0, 1, 0, 0, 0, b'd', 1, 0, 0, 0, 3, 1]);
+/// bytes.extend_from_slice(&[8, 0, 0, 0, 0, 0, 0, 0]); // at (8, 0)
+/// let delta = Delta::read_from(bytes.as_slice()).unwrap();
+/// assert!(client.apply_delta(&delta, &CellCodec).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delta {
+    // the generation the delta applies to, or None for a keyframe
+    base: Option<Generation>,
+    // the generation reached once the delta is applied
+    generation: Generation,
+    // the dimension of the Environment, only for a keyframe
+    dimension: Option<Dimension>,
+    // the deaths, sorted by ID, followed by the other changes in the order
+    // the entities are stored
+    changes: Vec<Change>,
+}
+
+impl Delta {
+    /// Gets the generation of the mirror the Delta can be applied to, or None
+    /// if the Delta is a keyframe that can be applied to any mirror.
+    pub fn base(&self) -> Option<Generation> {
+        self.base
+    }
+
+    /// Gets the generation reached by applying the Delta.
+    pub fn generation(&self) -> Generation {
+        self.generation
+    }
+
+    /// Returns true only if the Delta replaces all the entities of the mirror
+    /// it is applied to.
+    pub fn is_keyframe(&self) -> bool {
+        self.base.is_none()
+    }
+
+    /// Gets the number of changes of the entities.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Returns true only if no Entity changed.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Writes the Delta into the given writer.
+    ///
+    /// Returns an error if writing to the given writer fails.
+    pub fn write_to(&self, mut writer: impl Write) -> Result<(), Error> {
+        let mut bytes = DELTA_MAGIC.to_vec();
+        bytes.extend_from_slice(&DELTA_VERSION.to_le_bytes());
+        bytes.push(self.is_keyframe().into());
+        let base = self.base.map_or(0, Generation::get);
+        bytes.extend_from_slice(&base.to_le_bytes());
+        bytes.extend_from_slice(&self.generation.get().to_le_bytes());
+        if let Some(dimension) = self.dimension {
+            bytes.extend_from_slice(&dimension.x.to_le_bytes());
+            bytes.extend_from_slice(&dimension.y.to_le_bytes());
+        }
+
+        push_len(&mut bytes, self.changes.len());
+        for change in &self.changes {
+            match change {
+                Change::Spawn(encoded) => {
+                    bytes.push(SPAWN_TAG);
+                    push_encoded(&mut bytes, encoded);
+                }
+                Change::Death(id) => {
+                    bytes.push(DEATH_TAG);
+                    bytes.extend_from_slice(&(*id as u64).to_le_bytes());
+                }
+                Change::Move(id, location) => {
+                    bytes.push(MOVE_TAG);
+                    bytes.extend_from_slice(&(*id as u64).to_le_bytes());
+                    push_location(&mut bytes, *location);
+                }
+                Change::State(encoded) => {
+                    bytes.push(STATE_TAG);
+                    push_encoded(&mut bytes, encoded);
+                }
+            }
+        }
+
+        writer.write_all(&bytes).map_err(Error::with_message)
+    }
+
+    /// Reads a Delta from the given reader.
+    ///
+    /// Returns an error if the Delta is malformed, or if it was written by a
+    /// more recent version of the library.
+    ///
+    /// The lengths read are never trusted to allocate memory upfront, so that
+    /// a malformed Delta received from the network is rejected as soon as the
+    /// reader runs out of bytes.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// // a keyframe that claims to be made of u32::MAX changes
+    /// let mut bytes = DELTA_MAGIC.to_vec();
+    /// bytes.extend_from_slice(&DELTA_VERSION.to_le_bytes());
+    /// bytes.push(1);
+    /// bytes.extend_from_slice(&[0; 16]);
+    /// bytes.extend_from_slice(&8u32.to_le_bytes());
+    /// bytes.extend_from_slice(&8u32.to_le_bytes());
+    /// bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    /// assert!(Delta::read_from(bytes.as_slice()).is_err());
+    ///
+    /// // and a spawned Entity whose kind claims to be u32::MAX bytes long
+    /// bytes.truncate(bytes.len() - 4);
+    /// bytes.extend_from_slice(&1u32.to_le_bytes());
+    /// bytes.push(0);
+    /// bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    /// assert!(Delta::read_from(bytes.as_slice()).is_err());
+    /// ```
+    pub fn read_from(mut reader: impl Read) -> Result<Self, Error> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic).map_err(Error::with_message)?;
+        if magic != DELTA_MAGIC {
+            return Err(Error::with_message("Invalid delta magic bytes"));
+        }
+        let version = read_u32(&mut reader)?;
+        if version > DELTA_VERSION {
+            return Err(Error::with_message(format!(
+                "Unsupported delta version {}",
+                version
+            )));
+        }
+
+        let keyframe = read_u8(&mut reader)? != 0;
+        let base = Generation::from(read_u64(&mut reader)?);
+        let base = (!keyframe).then_some(base);
+        let generation = Generation::from(read_u64(&mut reader)?);
+        let dimension = if keyframe {
+            let x = read_u32(&mut reader)? as i32;
+            let y = read_u32(&mut reader)? as i32;
+            Some(Dimension { x, y })
+        } else {
+            None
+        };
+
+        // the count is not trusted to pre-allocate the changes, since the
+        // delta may come from the network
+        let count = read_u32(&mut reader)?;
+        let mut changes = Vec::new();
+        for _ in 0..count {
+            let change = match read_u8(&mut reader)? {
+                SPAWN_TAG => Change::Spawn(read_encoded(&mut reader)?),
+                DEATH_TAG => Change::Death(read_u64(&mut reader)? as Id),
+                MOVE_TAG => {
+                    let id = read_u64(&mut reader)? as Id;
+                    Change::Move(id, read_location(&mut reader)?)
+                }
+                STATE_TAG => Change::State(read_encoded(&mut reader)?),
+                tag => {
+                    return Err(Error::with_message(format!(
+                        "Invalid delta change tag {}",
+                        tag
+                    )))
+                }
+            };
+            changes.push(change);
+        }
+
+        Ok(Self {
+            base,
+            generation,
+            dimension,
+            changes,
+        })
+    }
+}
+
+/// The state of the entities last sent to the mirrors of an authoritative
+/// Environment, that `Environment::delta` compares with the current one to
+/// encode only the entities that changed since then.
+///
+/// A new SyncBaseline (or one that was reset) gives a keyframe, that holds all
+/// the entities of the Environment, such as for a client that just joined.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncBaseline {
+    // the generation of the last delta, if any
+    generation: Option<Generation>,
+    // the blob and location of each Entity sent with the last delta
+    entities: BTreeMap<Id, (Vec<u8>, Option<Location>)>,
+}
+
+impl SyncBaseline {
+    /// Constructs a new empty SyncBaseline, whose first Delta is a keyframe.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the generation of the last Delta computed with the SyncBaseline,
+    /// if any.
+    pub fn generation(&self) -> Option<Generation> {
+        self.generation
+    }
+
+    /// Resets the SyncBaseline, so that its next Delta is a keyframe.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Computes the changes of the entities of the Environment since the
+    /// given SyncBaseline, that is the entities inserted, removed, moved, or
+    /// whose encoding with the given Codec changed, and updates the
+    /// SyncBaseline to the current generation.
+    ///
+    /// The location of the entities is mirrored on its own, so that it does
+    /// not need to be encoded by the Codec. The IDs of the entities are
+    /// expected to be unique.
+    ///
+    /// Returns an error if any of the entities cannot be encoded, in which case
+    /// the SyncBaseline is left unchanged.
+    pub fn delta(
+        &self,
+        baseline: &mut SyncBaseline,
+        codec: &(impl Codec<'e, K, C> + ?Sized),
+    ) -> Result<Delta, Error> {
+        let mut entities = BTreeMap::new();
+        let mut changes = Vec::new();
+        for entity in self.entities.values().flatten() {
+            let (id, location) = (entity.id(), entity.location());
+            let blob = codec.encode(&**entity)?;
+            let encode = |blob: &Vec<u8>| -> Result<Encoded, Error> {
+                Ok(Encoded {
+                    kind: codec.encode_kind(&entity.kind())?,
+                    blob: blob.clone(),
+                    location,
+                })
+            };
+            match baseline.entities.get(&id) {
+                None => changes.push(Change::Spawn(encode(&blob)?)),
+                Some((previous, _)) if *previous != blob => {
+                    changes.push(Change::State(encode(&blob)?))
+                }
+                Some((_, previous)) if *previous != location => {
+                    if let Some(location) = location {
+                        changes.push(Change::Move(id, location));
+                    } else {
+                        changes.push(Change::State(encode(&blob)?))
+                    }
+                }
+                Some(_) => (),
+            }
+            entities.insert(id, (blob, location));
+        }
+
+        // the deaths come first, so that the IDs of the removed entities can
+        // be reused by the inserted ones
+        let deaths = baseline
+            .entities
+            .keys()
+            .filter(|id| !entities.contains_key(id))
+            .map(|&id| Change::Death(id));
+        let changes = deaths.chain(changes).collect();
+
+        let delta = Delta {
+            base: baseline.generation,
+            generation: self.generation,
+            dimension: baseline.generation.is_none().then(|| self.dimension()),
+            changes,
+        };
+        baseline.generation = Some(self.generation);
+        baseline.entities = entities;
+        Ok(delta)
+    }
+
+    /// Applies the given Delta of an authoritative Environment, so that this
+    /// Environment mirrors its entities and its generation, by decoding the
+    /// changed entities with the given Codec. Returns the generation reached.
+    ///
+    /// The entities of a keyframe replace all the entities of the
+    /// Environment, while the entities whose state changed replace the ones
+    /// with the same ID (see `Environment::replace_entity`). The inserted and
+    /// removed entities are notified to the subscribers of the Environment as
+    /// any other insertion and removal (see `Environment::subscribe`).
+    ///
+    /// Returns an error if the Delta is not a keyframe and the generation of
+    /// the Environment is not the one the Delta applies to, or if it is a
+    /// keyframe of an Environment with a different dimension. Returns an error
+    /// as well if any of the entities cannot be decoded, or if a spawned
+    /// Entity is located outside the Environment or has the ID of an Entity
+    /// that already exists, in which case the changes applied until then are
+    /// left in the Environment.
+    pub fn apply_delta(
+        &mut self,
+        delta: &Delta,
+        codec: &(impl Codec<'e, K, C> + ?Sized),
+    ) -> Result<Generation, Error> {
+        match delta.base {
+            Some(base) if base != self.generation => {
+                return Err(Error::with_message(format!(
+                    "Cannot apply the delta of generation {} to generation {}",
+                    base, self.generation
+                )));
+            }
+            Some(_) => (),
+            None => {
+                if delta.dimension != Some(self.dimension()) {
+                    return Err(Error::with_message(format!(
+                        "Cannot apply the keyframe of dimension {:?} to \
+                         dimension {:?}",
+                        delta.dimension,
+                        self.dimension()
+                    )));
+                }
+                self.retain_entities(|_| false);
+            }
+        }
+        let decode = |encoded: &Encoded| {
+            let kind = codec.decode_kind(&encoded.kind)?;
+            let mut entity = codec.decode(&kind, &encoded.blob)?;
+            if let (Some(location), Some(current)) =
+                (encoded.location, entity.location_mut())
+            {
+                *current = location;
+            }
+            Ok::<_, Error>(entity)
+        };
+
+        for change in &delta.changes {
+            match change {
+                Change::Spawn(encoded) => {
+                    let entity = decode(encoded)?;
+                    if let Some(location) = entity.location() {
+                        if !self.dimension().contains(location) {
+                            return Err(Error::with_message(format!(
+                                "Cannot spawn the entity {} at {:?}",
+                                entity.id(),
+                                location
+                            )));
+                        }
+                    }
+                    if self.births.contains_key(&entity.id()) {
+                        return Err(Error::with_message(format!(
+                            "Cannot spawn the entity {} twice",
+                            entity.id()
+                        )));
+                    }
+                    self.insert_boxed(entity);
+                }
+                Change::Death(id) => self.retain_entities(|e| e.id() != *id),
+                Change::Move(id, location) => {
+                    self.relocate_entity(*id, *location);
+                }
+                Change::State(encoded) => {
+                    let entity = decode(encoded)?;
+                    let (id, location) = (entity.id(), entity.location());
+                    self.replace_entity(id, entity)?;
+                    if let Some(location) = location {
+                        self.relocate_entity(id, location);
+                    }
+                }
+            }
+        }
+        self.generation = delta.generation;
+        self.started = None;
+        Ok(self.generation)
+    }
+}
+
+/// Pushes the given encoded Entity.
+fn push_encoded(buffer: &mut Vec<u8>, encoded: &Encoded) {
+    push_blob(buffer, &encoded.kind);
+    push_blob(buffer, &encoded.blob);
+    match encoded.location {
+        Some(location) => {
+            buffer.push(1);
+            push_location(buffer, location);
+        }
+        None => buffer.push(0),
+    }
+}
+
+/// Pushes the given location.
+fn push_location(buffer: &mut Vec<u8>, location: Location) {
+    buffer.extend_from_slice(&location.x.to_le_bytes());
+    buffer.extend_from_slice(&location.y.to_le_bytes());
+}
+
+/// Reads an encoded Entity.
+fn read_encoded(reader: &mut impl Read) -> Result<Encoded, Error> {
+    let kind = read_blob(reader)?;
+    let blob = read_blob(reader)?;
+    let location = match read_u8(reader)? {
+        0 => None,
+        _ => Some(read_location(reader)?),
+    };
+    Ok(Encoded {
+        kind,
+        blob,
+        location,
+    })
+}
+
+/// Reads a location.
+fn read_location(reader: &mut impl Read) -> Result<Location, Error> {
+    let x = read_u32(reader)? as i32;
+    let y = read_u32(reader)? as i32;
+    Ok(Location { x, y })
+}
+
+/// Reads a single byte.
+fn read_u8(reader: &mut impl Read) -> Result<u8, Error> {
+    let mut byte = [0; 1];
+    reader.read_exact(&mut byte).map_err(Error::with_message)?;
+    Ok(byte[0])
+}
@@ -0,0 +1,127 @@
+use super::*;
+
+/// The schedule of a tick group, whose kinds are dispatched once every period
+/// generations, starting from the generation equal to the phase offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Schedule {
+    phase_offset: u64,
+    period: u64,
+}
+
+/// The tick groups of the kinds of the Environment, and their schedules.
+#[derive(Debug, Clone)]
+pub(crate) struct TickGroups<K> {
+    schedules: BTreeMap<String, Schedule>,
+    groups: BTreeMap<K, String>,
+}
+
+impl<K> Default for TickGroups<K> {
+    fn default() -> Self {
+        Self {
+            schedules: BTreeMap::new(),
+            groups: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Ord> TickGroups<K> {
+    /// Returns true only if the entities of the given Kind are dispatched in
+    /// the given generation, that is if the Kind belongs to no tick group, or
+    /// if the generation is one of the ticks of its group.
+    pub fn is_due(&self, kind: &K, generation: Generation) -> bool {
+        if self.groups.is_empty() {
+            return true;
+        }
+        self.groups
+            .get(kind)
+            .and_then(|group| self.schedules.get(group))
+            .is_none_or(|schedule| {
+                generation.get() % schedule.period
+                    == schedule.phase_offset % schedule.period
+            })
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Assigns the given Kind to the given tick group, whose entities are only
+    /// dispatched (that is, they observe, react, move and are dispatched the
+    /// phases of the generation) once every given period of generations,
+    /// starting from the generation equal to the given phase offset, so that
+    /// populations that evolve at different rates (such as vegetation and
+    /// animals) can be interleaved within the same Environment.
+    ///
+    /// The schedule of the group is replaced by the given one, for all the
+    /// kinds already assigned to it. The kinds that belong to no tick group
+    /// are dispatched every generation, while the lifespan and offspring of
+    /// the entities are handled every generation regardless of their group,
+    /// so that the generation counting stays consistent across all the kinds.
+    ///
+    /// # Panics
+    /// Panics if the period is zero.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let mut env = Environment::<char>::new((30, 1));
+    /// for kind in ['v', 'a'] {
+    ///     env.insert(QuickEntity::new(kind).at((0, 0)).on_react(|entity, _| {
+    ///         entity.set_location(entity.location().unwrap() + (1, 0).into());
+    ///         Ok(())
+    ///     }));
+    /// }
+    ///
+    /// // the vegetation updates every 10 generations, offset by 3
+    /// env.set_tick_group('v', "vegetation", 3, 10);
+    /// let mut due = Vec::new();
+    /// for _ in 0..25 {
+    ///     if env.is_kind_due(&'v') {
+    ///         due.push(env.generation().get());
+    ///     }
+    ///     env.nextgen().unwrap();
+    /// }
+    /// assert_eq!(due, [3, 13, 23]);
+    /// assert_eq!(env.entities_at((3, 0)).count(), 1);
+    /// assert_eq!(env.entities_at((25, 0)).count(), 1);
+    /// ```
+    pub fn set_tick_group(
+        &mut self,
+        kind: K,
+        group: impl Into<String>,
+        phase_offset: u64,
+        period: u64,
+    ) {
+        assert!(period > 0, "The period of a tick group cannot be zero");
+        let group = group.into();
+        let schedule = Schedule {
+            phase_offset,
+            period,
+        };
+        self.ticks.schedules.insert(group.clone(), schedule);
+        self.ticks.groups.insert(kind, group);
+    }
+
+    /// Removes the given Kind from its tick group, if any, so that its
+    /// entities are dispatched every generation again.
+    pub fn clear_tick_group(&mut self, kind: &K) {
+        if let Some(group) = self.ticks.groups.remove(kind) {
+            // drop the schedule of the groups left without kinds
+            if !self.ticks.groups.values().any(|g| *g == group) {
+                self.ticks.schedules.remove(&group);
+            }
+        }
+    }
+
+    /// Gets the name of the tick group the given Kind belongs to, if any (see
+    /// `Environment::set_tick_group`).
+    pub fn tick_group(&self, kind: &K) -> Option<&str> {
+        self.ticks.groups.get(kind).map(String::as_str)
+    }
+
+    /// Returns true only if the entities of the given Kind are dispatched in
+    /// the current generation, according to its tick group (see
+    /// `Environment::set_tick_group`).
+    pub fn is_kind_due(&self, kind: &K) -> bool {
+        self.ticks.is_due(kind, self.generation)
+    }
+}
@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+/// The default maximum number of ticks a Ticker catches up with at once.
+pub const DEFAULT_MAX_CATCHUP: u32 = 5;
+
+/// A fixed timestep accumulator, that converts the elapsed time of each frame
+/// into a number of ticks of the same duration (such as the generations of an
+/// Environment), so that the simulation advances at the same rate regardless
+/// of the frame rate.
+///
+/// The time accumulated is capped to a maximum number of ticks, so that after
+/// a long pause (or a slow frame) the simulation does not try to catch up
+/// with all the ticks missed at once, and the time in excess is dropped.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+/// use std::time::Duration;
+///
+/// let mut env = Environment::<char>::new((4, 4));
+/// // 10 generations per second
+/// let mut ticker = Ticker::with_rate(10).max_catchup(3);
+///
+/// // a frame of 250ms is worth 2 generations, with 50ms left over
+/// let dt = Duration::from_millis(250);
+/// while ticker.should_tick(dt) {
+///     env.nextgen().unwrap();
+/// }
+/// assert_eq!(env.generation().get(), 2);
+/// assert_eq!(ticker.until_next_tick(), Duration::from_millis(50));
+///
+/// // a frame of 2s is capped to 3 generations
+/// assert_eq!(ticker.ticks(Duration::from_secs(2)), 3);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ticker {
+    timestep: Duration,
+    max_catchup: u32,
+    // the time accumulated and not yet consumed by the ticks
+    accumulator: Duration,
+    // true while the ticks of the current frame are being consumed
+    draining: bool,
+}
+
+impl Ticker {
+    /// Constructs a new Ticker that ticks once every given timestep, with the
+    /// default maximum catch-up.
+    ///
+    /// # Panics
+    /// Panics if the timestep is zero.
+    pub fn new(timestep: Duration) -> Self {
+        assert!(!timestep.is_zero(), "The timestep cannot be zero");
+        Self {
+            timestep,
+            max_catchup: DEFAULT_MAX_CATCHUP,
+            accumulator: Duration::ZERO,
+            draining: false,
+        }
+    }
+
+    /// Constructs a new Ticker that ticks the given number of times per second,
+    /// with the default maximum catch-up.
+    ///
+    /// # Panics
+    /// Panics if the rate is zero.
+    pub fn with_rate(ticks_per_second: u32) -> Self {
+        assert!(ticks_per_second > 0, "The rate cannot be zero");
+        Self::new(Duration::from_secs(1) / ticks_per_second)
+    }
+
+    /// Sets the maximum number of ticks the Ticker catches up with after a
+    /// single frame (at least one).
+    pub fn max_catchup(mut self, max_catchup: u32) -> Self {
+        self.max_catchup = max_catchup.max(1);
+        self
+    }
+
+    /// Gets the duration of each tick.
+    pub fn timestep(&self) -> Duration {
+        self.timestep
+    }
+
+    /// Returns true if a tick is due, and should therefore be called in a loop
+    /// until it returns false, with the same time elapsed since the previous
+    /// frame, that is accumulated only by the first call of each frame.
+    pub fn should_tick(&mut self, dt: Duration) -> bool {
+        if !self.draining {
+            self.accumulate(dt);
+            self.draining = true;
+        }
+        if self.accumulator >= self.timestep {
+            self.accumulator -= self.timestep;
+            true
+        } else {
+            self.draining = false;
+            false
+        }
+    }
+
+    /// Accumulates the given time elapsed since the previous frame, and gets
+    /// the number of ticks that are due.
+    pub fn ticks(&mut self, dt: Duration) -> u32 {
+        let mut ticks = 0;
+        while self.should_tick(dt) {
+            ticks += 1;
+        }
+        ticks
+    }
+
+    /// Gets the fraction of the timestep accumulated towards the next tick, in
+    /// [0, 1), that can be used to interpolate the drawing between two ticks.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.timestep.as_secs_f32()
+    }
+
+    /// Gets the time left until the next tick is due, that headless runs can
+    /// sleep for to be rate-limited.
+    pub fn until_next_tick(&self) -> Duration {
+        self.timestep.saturating_sub(self.accumulator)
+    }
+
+    /// Resets the time accumulated, as after a pause.
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::ZERO;
+        self.draining = false;
+    }
+
+    /// Accumulates the given time, capped to the maximum catch-up.
+    fn accumulate(&mut self, dt: Duration) {
+        let max = self.timestep * self.max_catchup;
+        self.accumulator = (self.accumulator + dt).min(max);
+    }
+}
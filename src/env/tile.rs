@@ -1,4 +1,31 @@
+use std::any::{Any, TypeId};
+use std::cell::UnsafeCell;
+use std::mem::size_of;
+
 use super::*;
+use rustc_hash::FxHashMap;
+
+/// The type of the persistent data attached to a Tile.
+#[cfg(not(feature = "parallel"))]
+pub(super) type Datum = Box<dyn Any>;
+
+/// The type of the persistent data attached to a Tile.
+#[cfg(feature = "parallel")]
+pub(super) type Datum = Box<dyn Any + Send + Sync>;
+
+/// The order in which the tiles of the Environment are stored in memory.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TileOrder {
+    /// The tiles are stored row by row, from the top-left to the bottom-right
+    /// corner of the Environment.
+    #[default]
+    RowMajor,
+    /// The tiles are stored following a Z-order (Morton) curve, so that the
+    /// tiles that are close to each other in the Environment are likely to be
+    /// close to each other in memory as well, improving the cache locality of
+    /// the neighborhoods of large Environments.
+    Morton,
+}
 
 /// A 1-dimensional list of tiles that represents a grid of given dimension with
 /// squared tiles of the same side length.
@@ -8,19 +35,61 @@ use super::*;
 pub struct Tiles<'e, K, C> {
     dimension: Dimension,
     tiles: Vec<Tile<'e, K, C>>,
+    layout: Layout,
+    // the seed of the perceptions of the entities in the current generation
+    perception_seed: u64,
+}
+
+/// The mapping between the locations of the tiles and their index in the list
+/// of tiles.
+#[derive(Debug)]
+enum Layout {
+    RowMajor,
+    // the Morton order of a square grid whose side is a power of two, where
+    // the index of each tile is its position along the curve
+    Morton,
+    // the Morton order of any other grid, where the index of each tile is
+    // looked up by its row-major index
+    MortonTable(Vec<u32>),
 }
 
 impl<'e, K, C> Tiles<'e, K, C> {
     /// Constructs a new list of tiles of the given dimension with no entities
-    /// assigned to it.
-    pub fn new(dimension: impl Into<Dimension>) -> Self {
+    /// assigned to it, stored in the given order.
+    pub fn with_order(
+        dimension: impl Into<Dimension>,
+        order: TileOrder,
+    ) -> Self {
         let dimension = dimension.into();
-        let mut tiles = Vec::with_capacity(dimension.len());
-        for i in 0..dimension.len() {
-            tiles.push(Tile::new(Location::from_one_dimensional(i, dimension)));
-        }
+        let mut locations: Vec<_> = (0..dimension.len())
+            .map(|i| Location::from_one_dimensional(i, dimension))
+            .collect();
+        let layout = match order {
+            TileOrder::RowMajor => Layout::RowMajor,
+            TileOrder::Morton => {
+                locations.sort_unstable_by_key(|&location| morton(location));
+                if dimension.x == dimension.y
+                    && (dimension.x as u32).is_power_of_two()
+                {
+                    Layout::Morton
+                } else {
+                    let mut indices = vec![0; dimension.len()];
+                    for (index, location) in locations.iter().enumerate() {
+                        indices[location.one_dimensional(dimension)] =
+                            index as u32;
+                    }
+                    Layout::MortonTable(indices)
+                }
+            }
+        };
+        let tiles = locations.into_iter().map(Tile::new).collect();
 
-        Self { dimension, tiles }
+        Self {
+            dimension,
+            tiles,
+            layout,
+            perception_seed: 0,
+        }
     }
 
     /// Gets the Dimension of the Environment.
@@ -28,13 +97,39 @@ impl<'e, K, C> Tiles<'e, K, C> {
         self.dimension
     }
 
+    /// Sets the seed of the perceptions of the entities whose neighborhoods
+    /// are constructed from now on.
+    pub fn set_perception_seed(&mut self, seed: u64) {
+        self.perception_seed = seed;
+    }
+
+    /// Gets the order in which the tiles are stored.
+    pub fn order(&self) -> TileOrder {
+        match self.layout {
+            Layout::RowMajor => TileOrder::RowMajor,
+            Layout::Morton | Layout::MortonTable(_) => TileOrder::Morton,
+        }
+    }
+
+    /// Gets the index in the list of the tile at the given (wrapped) location.
+    fn index(&self, location: Location) -> usize {
+        let index = match &self.layout {
+            Layout::RowMajor => location.one_dimensional(self.dimension),
+            Layout::Morton => morton(location) as usize,
+            Layout::MortonTable(indices) => {
+                indices[location.one_dimensional(self.dimension)] as usize
+            }
+        };
+        debug_assert!(index < self.tiles.len());
+        index
+    }
+
     /// Inserts the given Entity in the grid according to its location. If the
     /// Entity has not location it will not be inserted.
     /// Returns whether the Entity was inserted or not.
     pub fn insert(&mut self, entity: &mut EntityTrait<'e, K, C>) -> bool {
         if let Some(location) = entity.location() {
-            let index = location.one_dimensional(self.dimension);
-            debug_assert!(index < self.tiles.len());
+            let index = self.index(location);
             let tile = &mut self.tiles[index];
             tile.entities
                 .insert(entity.id(), entity as *mut EntityTrait<'e, K, C>);
@@ -47,9 +142,7 @@ impl<'e, K, C> Tiles<'e, K, C> {
     /// Remove the Entity with the given ID from the given location.
     /// Returns whether the Entity was removed or not.
     pub fn remove(&mut self, id: Id, location: impl Into<Location>) -> bool {
-        let location = location.into();
-        let index = location.one_dimensional(self.dimension);
-        debug_assert!(index < self.tiles.len());
+        let index = self.index(location.into());
         let tile = &mut self.tiles[index];
         tile.entities.remove(&id).is_some()
     }
@@ -61,14 +154,11 @@ impl<'e, K, C> Tiles<'e, K, C> {
         from: impl Into<Location>,
         to: impl Into<Location>,
     ) {
-        let from = from.into();
-        let index = from.one_dimensional(self.dimension);
-        debug_assert!(index < self.tiles.len());
+        let index = self.index(from.into());
         let tile = &mut self.tiles[index];
 
         if let Some(e) = tile.entities.remove(&id) {
-            let to = to.into();
-            let index = to.one_dimensional(self.dimension);
+            let index = self.index(to.into());
             let tile = &mut self.tiles[index];
             tile.entities.insert(id, e);
         }
@@ -99,9 +189,126 @@ impl<'e, K, C> Tiles<'e, K, C> {
         self.tile_at_mut(location.into()).entities_mut()
     }
 
+    /// Gets an iterator over the references to the entities stored in each
+    /// tile, as the location of the tile, the ID of the Entity and the address
+    /// of the Entity, without dereferencing them.
+    pub fn references(
+        &self,
+    ) -> impl Iterator<Item = (Location, Id, *const ())> + use<'_, 'e, K, C>
+    {
+        self.tiles.iter().flat_map(|tile| {
+            tile.entities
+                .iter()
+                .map(|(id, e)| (tile.location, id, e as *const ()))
+        })
+    }
+
+    /// Gets a reference to the data of the given type attached to the tile at
+    /// the given location.
+    pub fn data_at<T: Any>(&self, location: impl Into<Location>) -> Option<&T> {
+        let location = self.wrap(location.into());
+        self.tile_at(location).data()
+    }
+
+    /// Gets a mutable reference to the data of the given type attached to the
+    /// tile at the given location.
+    pub fn data_at_mut<T: Any>(
+        &mut self,
+        location: impl Into<Location>,
+    ) -> Option<&mut T> {
+        let location = self.wrap(location.into());
+        self.tile_at_mut(location)
+            .data
+            .get_mut()
+            .get_mut(&TypeId::of::<T>())?
+            .downcast_mut()
+    }
+
+    /// Attaches the given data to the tile at the given location, replacing
+    /// and returning the data of the same type previously attached, if any.
+    pub fn set_data_at<T: Any>(
+        &mut self,
+        location: impl Into<Location>,
+        data: Datum,
+    ) -> Option<T> {
+        let location = self.wrap(location.into());
+        self.tile_at_mut(location)
+            .data
+            .get_mut()
+            .insert(TypeId::of::<T>(), data)
+            .and_then(|data| data.downcast().ok())
+            .map(|data| *data)
+    }
+
+    /// Detaches the data of the given type from the tile at the given location,
+    /// and returns it.
+    pub fn remove_data_at<T: Any>(
+        &mut self,
+        location: impl Into<Location>,
+    ) -> Option<T> {
+        let location = self.wrap(location.into());
+        self.tile_at_mut(location)
+            .data
+            .get_mut()
+            .remove(&TypeId::of::<T>())?
+            .downcast()
+            .ok()
+            .map(|data| *data)
+    }
+
+    /// Gets an iterator over the views of all the tiles, not seen by any
+    /// Entity, sorted by row and then by column.
+    pub fn views(&self) -> impl Iterator<Item = TileView<'_, 'e, K, C>> {
+        (0..self.dimension.len()).map(|index| {
+            let location =
+                Location::from_one_dimensional(index, self.dimension);
+            TileView {
+                id: None,
+                tile: self.tile_at(location),
+                sense: None,
+            }
+        })
+    }
+
+    /// Gets an iterator over the data of the type with the given ID attached
+    /// to the tiles, with the location of their tile.
+    pub fn data_of(
+        &self,
+        type_id: TypeId,
+    ) -> impl Iterator<Item = (Location, &dyn Any)> + use<'_, 'e, K, C> {
+        self.tiles.iter().filter_map(move |tile| {
+            // Dereferencing the data is safe because the Environment is
+            // borrowed immutably, so that no Entity can change it
+            let data = unsafe { &*tile.data.get() };
+            let datum: &dyn Any = &**data.get(&type_id)?;
+            Some((tile.location, datum))
+        })
+    }
+
+    /// Attaches the given data of the type with the given ID to the tile at
+    /// the given location, replacing the data of the same type previously
+    /// attached, if any.
+    pub fn insert_datum(
+        &mut self,
+        location: impl Into<Location>,
+        type_id: TypeId,
+        datum: Datum,
+    ) {
+        let location = self.wrap(location.into());
+        self.tile_at_mut(location)
+            .data
+            .get_mut()
+            .insert(type_id, datum);
+    }
+
+    /// Wraps the given location around the edges of the grid.
+    fn wrap(&self, mut location: Location) -> Location {
+        *location.translate(Offset::origin(), self.dimension)
+    }
+
     /// Gets the tile at the given location.
     fn tile_at(&self, location: Location) -> &Tile<'e, K, C> {
-        let index = self.tile_index_at(location);
+        let index = self.index(location);
         let tile = &self.tiles[index];
         debug_assert_eq!(tile.location, location);
         tile
@@ -109,71 +316,289 @@ impl<'e, K, C> Tiles<'e, K, C> {
 
     /// Gets the (mutable) tile at the given location.
     fn tile_at_mut(&mut self, location: Location) -> &mut Tile<'e, K, C> {
-        let index = self.tile_index_at(location);
+        let index = self.index(location);
         let tile = &mut self.tiles[index];
         debug_assert_eq!(tile.location, location);
         tile
     }
 
-    /// Gets the tile index at the given location.
-    fn tile_index_at(&self, location: Location) -> usize {
-        let index = location.one_dimensional(self.dimension);
-        debug_assert!(index < self.tiles.len());
-        index
-    }
-
-    /// Gets the area of the environment surrounding the given Entity.
+    /// Gets the area of the environment surrounding the given Entity, whose
+    /// tiles are allocated in the given Arena.
     /// Returns None if the Entity has no location or scope, or if the scope of
     /// the Entity forces its neighborhood to wrap onto itself due to the
     /// dimensions of the Environment being not big enough to contain it.
-    pub fn neighborhood(
-        &self,
+    ///
+    /// The neighborhood of an Entity that does not wrap (see `Entity::wraps`)
+    /// is instead clipped to the edges of the Environment, and it is never
+    /// None as long as the Entity has both a location and a scope.
+    pub fn neighborhood<'a>(
+        &'a self,
         entity: &EntityTrait<'e, K, C>,
+        arena: &'a Arena<TileView<'a, 'e, K, C>>,
+    ) -> Option<Neighborhood<'a, 'e, K, C>> {
+        // only entities that have both a scope and a location can interact
+        // with the surrounding environment, unless they access only themselves
+        if Access::of(entity) == Access::SelfOnly {
+            return None;
+        }
+        let center = entity.location()?;
+        let scope = entity.scope()?;
+        let owner = Some(entity.id());
+        let (tiles, dimension, center) =
+            self.neighborhood_tiles(owner, center, scope, entity.wraps())?;
+        let tiles = arena.alloc_extend(tiles);
+        let mut neighborhood =
+            Neighborhood::with_tiles(tiles, dimension, center);
+        let perception = entity.perception();
+        if !perception.is_perfect() {
+            let mut rng = SplitMix64(self.perception_seed ^ entity.id() as u64);
+            neighborhood.perceive(perception, rng.next_u64());
+        }
+        Some(neighborhood)
+    }
+
+    /// Gets the area of the environment surrounding the given location, that
+    /// is not seen by any Entity.
+    /// Returns None if the scope forces the neighborhood to wrap onto itself
+    /// due to the dimensions of the Environment being not big enough to
+    /// contain it.
+    pub fn neighborhood_at(
+        &self,
+        center: Location,
+        scope: Scope,
     ) -> Option<Neighborhood<'_, 'e, K, C>> {
-        match (entity.location(), entity.scope()) {
-            // only entities that have both a scope and a location can interact
-            // with the surrounding environment
-            (Some(center), Some(scope)) => {
-                if scope.overflows(self.dimension) {
-                    // the dimension of the environment are not big enough to
-                    // construct a valid neighborhood given this entity scope
-                    return None;
+        let center = self.wrap(center);
+        let (tiles, dimension, center) =
+            self.neighborhood_tiles(None, center, scope, true)?;
+        Some(Neighborhood::with_dimension(
+            tiles.collect(),
+            dimension,
+            center,
+        ))
+    }
+
+    /// Gets the tiles of the area of the environment surrounding the given
+    /// center, as seen by the given owner, top to bottom and left to right,
+    /// with the dimension of the area and the position of its center.
+    /// Returns None if the area would wrap onto itself.
+    fn neighborhood_tiles<'a>(
+        &'a self,
+        owner: Option<Id>,
+        center: Location,
+        scope: Scope,
+        wraps: bool,
+    ) -> Option<(
+        impl ExactSizeIterator<Item = TileView<'a, 'e, K, C>> + 'a,
+        Dimension,
+        Location,
+    )> {
+        if wraps && scope.overflows(self.dimension) {
+            // the dimension of the environment are not big enough to
+            // construct a valid neighborhood given this scope
+            return None;
+        }
+
+        let line = self.dimension.is_line();
+        let len = match line {
+            true => scope.magnitude() * 2 + 1,
+            false => Dimension::len_with_scope(scope),
+        };
+        let scope = scope.magnitude() as i32;
+        // the neighborhoods of a line only extend to the left and right
+        let vertical = if line { 0 } else { scope };
+        // the offsets of the top-left and bottom-right corners of the
+        // neighborhood from its center, clipped to the edges of the
+        // environment if the neighborhood does not wrap
+        let (top_left, bottom_right) = if wraps {
+            (
+                Offset {
+                    x: -scope,
+                    y: -vertical,
+                },
+                Offset {
+                    x: scope,
+                    y: vertical,
+                },
+            )
+        } else {
+            let top_left = Offset {
+                x: -scope.min(center.x),
+                y: -vertical.min(center.y),
+            };
+            let bottom_right = Offset {
+                x: scope.min(self.dimension.x - 1 - center.x),
+                y: vertical.min(self.dimension.y - 1 - center.y),
+            };
+            (top_left, bottom_right)
+        };
+        let dimension = Dimension {
+            x: bottom_right.x - top_left.x + 1,
+            y: bottom_right.y - top_left.y + 1,
+        };
+        debug_assert!(!wraps || dimension.len() == len);
+
+        // build the portion of the environment seen from the center tile by
+        // tile from the top-left corner to the bottom-down corner
+        let tiles = (0..dimension.len() as i32).map(move |i| {
+            let offset = Offset {
+                x: i % dimension.x + top_left.x,
+                y: i / dimension.x + top_left.y,
+            };
+            let mut location = center;
+            location.translate(offset, self.dimension);
+            TileView {
+                id: owner,
+                tile: &self.tiles[self.index(location)],
+                sense: None,
+            }
+        });
+
+        let center = Location {
+            x: -top_left.x,
+            y: -top_left.y,
+        };
+        Some((tiles, dimension, center))
+    }
+}
+
+/// Gets the position of the given location along the Z-order (Morton) curve,
+/// by interleaving the bits of its coordinates.
+fn morton(location: Location) -> u64 {
+    // spreads the bits of the given value to the even bits of the result
+    fn spread(value: i32) -> u64 {
+        let mut value = u64::from(value as u32);
+        value = (value | (value << 16)) & 0x0000_ffff_0000_ffff;
+        value = (value | (value << 8)) & 0x00ff_00ff_00ff_00ff;
+        value = (value | (value << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+        value = (value | (value << 2)) & 0x3333_3333_3333_3333;
+        (value | (value << 1)) & 0x5555_5555_5555_5555
+    }
+    spread(location.x) | spread(location.y) << 1
+}
+
+/// The maximum number of entities that occupy a Tile that are stored inline,
+/// without allocating a map.
+const INLINE_OCCUPANTS: usize = 2;
+
+/// A *weak* reference to an Entity that occupies a Tile, with its ID.
+type Occupant<'e, K, C> = (Id, *mut EntityTrait<'e, K, C>);
+
+/// The *weak* references to the entities that occupy a Tile, by ID.
+///
+/// The references are stored inline, and moved to a map only when the Tile
+/// gets crowded, so that no allocation is needed for the tiles occupied by at
+/// most `INLINE_OCCUPANTS` entities.
+///
+/// This makes an empty Tile a single word larger than a map of its entities
+/// (7 words rather than 6), while sparing the allocation of the buckets of the
+/// map (at least 4 buckets of 3 words each) of every occupied Tile.
+#[derive(Debug)]
+enum Occupants<'e, K, C> {
+    // the occupied slots always precede the empty ones
+    Inline([Option<Occupant<'e, K, C>>; INLINE_OCCUPANTS]),
+    // the hasher is not randomly seeded, so that the order of the entities of
+    // crowded tiles is the same across runs
+    Map(FxHashMap<Id, *mut EntityTrait<'e, K, C>>),
+}
+
+// the inline occupants cost at most a word for each Tile (see `Occupants`)
+const _: () = assert!(
+    size_of::<Occupants<'static, (), ()>>()
+        <= size_of::<HashMap<Id, *mut EntityTrait<'static, (), ()>>>()
+            + size_of::<usize>()
+);
+
+impl<'e, K, C> Default for Occupants<'e, K, C> {
+    fn default() -> Self {
+        Self::Inline([None; INLINE_OCCUPANTS])
+    }
+}
+
+impl<'e, K, C> Occupants<'e, K, C> {
+    /// Inserts the reference to the Entity with the given ID, replacing the
+    /// reference to the Entity with the same ID, if any.
+    fn insert(&mut self, id: Id, entity: *mut EntityTrait<'e, K, C>) {
+        match self {
+            Self::Inline(slots) => {
+                let slot = slots.iter_mut().find(|slot| match slot {
+                    Some((other, _)) => *other == id,
+                    None => true,
+                });
+                match slot {
+                    Some(slot) => *slot = Some((id, entity)),
+                    None => {
+                        let mut map: FxHashMap<_, _> =
+                            slots.iter().flatten().copied().collect();
+                        map.insert(id, entity);
+                        *self = Self::Map(map);
+                    }
                 }
+            }
+            Self::Map(map) => {
+                map.insert(id, entity);
+            }
+        }
+    }
 
-                let mut neighborhood =
-                    Vec::with_capacity(Dimension::len_with_scope(scope));
-                let scope = scope.magnitude() as i32;
-
-                // build the portion of the environment seen by the entity tile
-                // by tile from the top-left corner to the bottom-down corner
-                for y in -scope..=scope {
-                    for x in -scope..=scope {
-                        let mut location = center;
-                        location.translate(Offset { x, y }, self.dimension);
-                        let index = location.one_dimensional(self.dimension);
-                        debug_assert!(index < self.tiles.len());
-
-                        let tile = &self.tiles[index];
-                        neighborhood
-                            .push(TileView::with_owner(entity.id(), tile));
+    /// Removes the reference to the Entity with the given ID, and returns it.
+    fn remove(&mut self, id: &Id) -> Option<*mut EntityTrait<'e, K, C>> {
+        match self {
+            Self::Inline(slots) => {
+                let index = slots.iter().position(|slot| {
+                    slot.is_some_and(|(other, _)| other == *id)
+                })?;
+                let (_, entity) = slots[index].take()?;
+                // keeps the occupied slots before the empty ones
+                slots[index..].rotate_left(1);
+                Some(entity)
+            }
+            Self::Map(map) => {
+                let entity = map.remove(id)?;
+                // the map is dropped only once the Tile is far from crowded,
+                // to avoid allocating it again as soon as an Entity enters
+                if map.len() < INLINE_OCCUPANTS {
+                    let mut slots = [None; INLINE_OCCUPANTS];
+                    for (slot, occupant) in slots.iter_mut().zip(map.drain()) {
+                        *slot = Some(occupant);
                     }
+                    *self = Self::Inline(slots);
                 }
+                Some(entity)
+            }
+        }
+    }
+
+    /// Gets an iterator over the references to the entities, with their IDs,
+    /// in arbitrary order.
+    fn iter(&self) -> impl Iterator<Item = Occupant<'e, K, C>> + '_ {
+        let (slots, map) = match self {
+            Self::Inline(slots) => (Some(slots.iter().map_while(|s| *s)), None),
+            Self::Map(map) => (None, Some(map.iter().map(|(&id, &e)| (id, e)))),
+        };
+        slots.into_iter().flatten().chain(map.into_iter().flatten())
+    }
 
-                Some(neighborhood.into())
+    /// Gets the number of entities.
+    fn len(&self) -> usize {
+        match self {
+            Self::Inline(slots) => {
+                slots.iter().take_while(|s| s.is_some()).count()
             }
-            _ => None,
+            Self::Map(map) => map.len(),
         }
     }
 }
 
-/// A single tile of the environment. This data structure contains a map of
-/// *weak* references to the entities.
+/// A single tile of the environment. This data structure contains the *weak*
+/// references to the entities.
 #[derive(Debug)]
 pub struct Tile<'e, K, C> {
     // the location of the Tile in the Environment
     location: Location,
     // the entities that currently occupy this Tile
-    entities: HashMap<Id, *mut EntityTrait<'e, K, C>>,
+    entities: Occupants<'e, K, C>,
+    // the persistent data attached to this Tile, by type
+    data: UnsafeCell<HashMap<TypeId, Datum>>,
 }
 
 impl<'e, K, C> Tile<'e, K, C> {
@@ -181,14 +606,35 @@ impl<'e, K, C> Tile<'e, K, C> {
     fn new(location: impl Into<Location>) -> Self {
         Self {
             location: location.into(),
-            entities: HashMap::default(),
+            entities: Occupants::default(),
+            data: UnsafeCell::default(),
         }
     }
 
+    /// Gets a reference to the data of the given type attached to this Tile.
+    fn data<T: Any>(&self) -> Option<&T> {
+        // Dereferencing the data is safe because the Environment guarantees
+        // that the data of a Tile can only be changed by a single Entity at a
+        // time (the same guarantee given for the entities of the Tile), and
+        // never while a reference to it is alive.
+        let data = unsafe { &*self.data.get() };
+        data.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Gets the map of all the data attached to this Tile.
+    #[allow(clippy::mut_from_ref)]
+    fn data_map_mut(&self) -> &mut HashMap<TypeId, Datum> {
+        // Mutably dereferencing the data is safe for the same reasons that
+        // allow to get mutable references to the entities of the Tile, that is
+        // the Environment guarantees that each Tile can be seen (mutably) only
+        // by a single Entity at a time.
+        unsafe { &mut *self.data.get() }
+    }
+
     /// Gets an iterator over all the entities located in this Tile.
     /// The entities are returned in arbitrary order.
     pub fn entities(&self) -> impl Iterator<Item = &EntityTrait<'e, K, C>> {
-        self.entities.iter().filter_map(move |(_id, e)| {
+        self.entities.iter().filter_map(|(_, e)| {
             // Dereferencing the Entity pointer to return its reference
             // is safe because the Environment guarantees that this
             // method can only be called while the Entity pointed by this
@@ -204,7 +650,7 @@ impl<'e, K, C> Tile<'e, K, C> {
     pub fn entities_mut(
         &self,
     ) -> impl Iterator<Item = &mut EntityTrait<'e, K, C>> {
-        self.entities.iter().filter_map(move |(_id, e)| {
+        self.entities.iter().filter_map(|(_, e)| {
             // Dereferencing the Entity pointer to return its reference
             // is safe because the Environment guarantees that this
             // method can only be called while the Entity pointed by this
@@ -218,12 +664,15 @@ impl<'e, K, C> Tile<'e, K, C> {
 
 /// A single Environment tile as seen by a single Entity.
 #[derive(Debug)]
-pub struct TileView<'a, 'e, K, C> {
+pub struct TileView<'a, 'e, K, C = ()> {
     // the ID of the Entity that is seeing this tile
     id: Option<Id>,
     // the reference to the Tile in the Environment, where the *weak* references
     // to the entities are stored
     tile: &'a Tile<'e, K, C>,
+    // the perception of the entities by the Entity that is seeing this tile,
+    // if not perfect
+    sense: Option<Sense>,
 }
 
 impl<'a, 'e, K, C> TileView<'a, 'e, K, C> {
@@ -233,32 +682,107 @@ impl<'a, 'e, K, C> TileView<'a, 'e, K, C> {
     }
 
     /// Gets an iterator over all the entities located in this Tile that does not
-    /// include the Entity that is seeing the tile.
+    /// include the Entity that is seeing the tile, nor the entities it does not
+    /// perceive (see `Entity::perception`).
     ///
     /// The entities are returned in arbitrary order.
     pub fn entities(&self) -> impl Iterator<Item = &EntityTrait<'e, K, C>> {
-        self.tile.entities().filter(move |e| {
-            !matches!(&self.id, Some(entity_id) if entity_id == &e.id())
-        })
+        self.tile
+            .entities()
+            .filter(move |e| self.is_visible(e.id()))
     }
 
     /// Gets an iterator over all the mutable entities located in this Tile that
-    /// does not include the Entity that is seeing the tile.
+    /// does not include the Entity that is seeing the tile, nor the entities it
+    /// does not perceive (see `Entity::perception`).
     ///
     /// The entities are returned in arbitrary order.
     pub fn entities_mut(
         &mut self,
     ) -> impl Iterator<Item = &mut EntityTrait<'e, K, C>> {
-        let entity_id = self.id;
+        let (entity_id, sense) = (self.id, self.sense);
         self.tile.entities_mut().filter(move |e| {
             !matches!(&entity_id, Some(entity_id) if entity_id == &e.id())
+                && sense.is_none_or(|sense| sense.perceives(e.id()))
         })
     }
 
+    /// Returns true only if the Entity with the given ID is not the one that
+    /// is seeing the tile, and it is perceived by it.
+    fn is_visible(&self, id: Id) -> bool {
+        !matches!(&self.id, Some(entity_id) if entity_id == &id)
+            && self.sense.is_none_or(|sense| sense.perceives(id))
+    }
+
+    /// Gets a reference to the persistent data of the given type attached to
+    /// this Tile, if any.
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.tile.data()
+    }
+
+    /// Gets a mutable reference to the persistent data of the given type
+    /// attached to this Tile, if any.
+    pub fn data_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.tile
+            .data_map_mut()
+            .get_mut(&TypeId::of::<T>())?
+            .downcast_mut()
+    }
+
+    /// Attaches the given persistent data to this Tile, replacing and
+    /// returning the data of the same type previously attached, if any.
+    ///
+    /// The data attached to a Tile outlives the entities that occupy it, and
+    /// it can be used to store the state of the Tile itself (such as its
+    /// color), rather than creating dedicated entities.
+    #[cfg(not(feature = "parallel"))]
+    pub fn set_data<T: Any>(&mut self, data: T) -> Option<T> {
+        self.insert_data(Box::new(data))
+    }
+
+    /// Attaches the given persistent data to this Tile, replacing and
+    /// returning the data of the same type previously attached, if any.
+    ///
+    /// The data attached to a Tile outlives the entities that occupy it, and
+    /// it can be used to store the state of the Tile itself (such as its
+    /// color), rather than creating dedicated entities.
+    #[cfg(feature = "parallel")]
+    pub fn set_data<T: Any + Send + Sync>(&mut self, data: T) -> Option<T> {
+        self.insert_data(Box::new(data))
+    }
+
+    /// Detaches the persistent data of the given type from this Tile, and
+    /// returns it.
+    pub fn remove_data<T: Any>(&mut self) -> Option<T> {
+        self.tile
+            .data_map_mut()
+            .remove(&TypeId::of::<T>())?
+            .downcast()
+            .ok()
+            .map(|data| *data)
+    }
+
+    /// Inserts the given data into the Tile.
+    fn insert_data<T: Any>(&mut self, data: Datum) -> Option<T> {
+        self.tile
+            .data_map_mut()
+            .insert(TypeId::of::<T>(), data)
+            .and_then(|data| data.downcast().ok())
+            .map(|data| *data)
+    }
+
     /// Gets the total number of entities located in this Tile, including the
-    /// Entity that is seeing the tile.
+    /// Entity that is seeing the tile, but not the entities it does not
+    /// perceive (see `Entity::perception`).
     pub fn count(&self) -> usize {
-        self.tile.entities.len()
+        match self.sense {
+            None => self.tile.entities.len(),
+            Some(sense) => self
+                .tile
+                .entities()
+                .filter(|e| Some(e.id()) == self.id || sense.perceives(e.id()))
+                .count(),
+        }
     }
 
     /// Returns true only if there are no entities located in this tile.
@@ -279,16 +803,50 @@ impl<'a, 'e, K: PartialEq, C> TileView<'a, 'e, K, C> {
     pub fn count_kind(&self, kind: K) -> usize {
         self.entities().filter(|e| e.kind() == kind).count()
     }
+
+    /// Returns true only if this Tile contains an Entity whose Kind belongs to
+    /// the given group, without considering the Entity that is seeing the tile.
+    pub fn contains_group<G: PartialEq>(&self, group: G) -> bool
+    where
+        K: KindGroup<G>,
+    {
+        self.entities().any(|e| e.kind().group() == group)
+    }
+
+    /// Gets the total number of entities in this Tile whose Kind belongs to the
+    /// given group, without considering the Entity that is seeing the tile.
+    pub fn count_group<G: PartialEq>(&self, group: G) -> usize
+    where
+        K: KindGroup<G>,
+    {
+        self.entities()
+            .filter(|e| e.kind().group() == group)
+            .count()
+    }
 }
 
-impl<'a, 'e, K, C> TileView<'a, 'e, K, C> {
-    /// Constructs a new TileView with a specific Entity as owner.
-    pub(crate) fn with_owner(id: Id, tile: &'a Tile<'e, K, C>) -> Self {
-        Self { id: Some(id), tile }
+impl<'a, 'e, K: KindIndex, C> TileView<'a, 'e, K, C> {
+    /// Gets the KindMask of the kinds of the entities in this Tile, without
+    /// considering the Entity that is seeing the tile, so that the kinds of
+    /// the tiles of a Neighborhood can be tested with bitwise operations.
+    pub fn kind_mask(&self) -> KindMask {
+        let mut mask = KindMask::EMPTY;
+        for entity in self.entities() {
+            mask.insert(&entity.kind());
+        }
+        mask
     }
+}
 
+impl<'a, 'e, K, C> TileView<'a, 'e, K, C> {
     /// Gets a reference to the inner Tile.
     pub(crate) fn inner(&self) -> &Tile<'e, K, C> {
         self.tile
     }
+
+    /// Sets the perception of the entities by the Entity that is seeing this
+    /// tile.
+    pub(crate) fn set_sense(&mut self, sense: Sense) {
+        self.sense = Some(sense);
+    }
 }
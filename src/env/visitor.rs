@@ -0,0 +1,169 @@
+use std::any::{Any, TypeId};
+
+use super::*;
+
+/// The type of the handlers of a Visitor, that downcast the State of each
+/// Entity to their concrete type.
+type Handler<'v> = Box<dyn FnMut(&mut dyn Any) + 'v>;
+
+/// A set of handlers of the states of the entities, one for each concrete
+/// State type, that allows cross-cutting passes over all the entities of an
+/// Environment (such as healing all the wolves, or recoloring all the cells)
+/// without downcasting the State of each Entity by hand (see
+/// `Environment::visit_all`).
+///
+/// # Example
+/// ```
+/// use semeion::*;
+/// use std::any::Any;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Health(u32);
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Color(u8);
+///
+/// macro_rules! state {
+///     ($state:ty) => {
+///         impl State for $state {
+///             fn as_any(&self) -> &dyn Any {
+///                 self
+///             }
+///
+///             fn as_any_mut(&mut self) -> &mut dyn Any {
+///                 self
+///             }
+///         }
+///     };
+/// }
+/// state!(Health);
+/// state!(Color);
+///
+/// struct Thing<S>(Id, S);
+///
+/// impl<'e, S: State + Send + Sync + 'static> Entity<'e> for Thing<S> {
+///     type Kind = ();
+///
+///     fn id(&self) -> Id {
+///         self.0
+///     }
+///
+///     fn kind(&self) {}
+///
+///     fn state(&self) -> Option<&dyn State> {
+///         Some(&self.1)
+///     }
+///
+///     fn state_mut(&mut self) -> Option<&mut dyn State> {
+///         Some(&mut self.1)
+///     }
+/// }
+///
+/// let mut env = Environment::new((3, 3));
+/// env.insert(Thing(0, Health(5)));
+/// env.insert(Thing(1, Health(8)));
+/// env.insert(Thing(2, Color(1)));
+///
+/// let mut healed = 0;
+/// let mut visitor = Visitor::new()
+///     .on(|health: &mut Health| {
+///         health.0 = 10;
+///         healed += 1;
+///     })
+///     .on(|color: &mut Color| color.0 = 255);
+/// assert_eq!(env.visit_all(&mut visitor), 3);
+/// drop(visitor);
+/// assert_eq!(healed, 2);
+///
+/// let color = env.entities().find(|e| e.id() == 2).unwrap();
+/// let color = color.state().unwrap().as_any().downcast_ref::<Color>();
+/// assert_eq!(color, Some(&Color(255)));
+/// ```
+#[derive(Default)]
+pub struct Visitor<'v> {
+    handlers: HashMap<TypeId, Handler<'v>>,
+}
+
+impl<'v> fmt::Debug for Visitor<'v> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Visitor")
+            .field("handlers", &self.handlers.len())
+            .finish()
+    }
+}
+
+impl<'v> Visitor<'v> {
+    /// Constructs a new Visitor without any handler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the handler of the states of the given concrete type, replacing
+    /// the handler of the same type previously set, if any.
+    pub fn on<S: Any>(mut self, mut f: impl FnMut(&mut S) + 'v) -> Self {
+        let handler: Handler<'v> = Box::new(move |state| {
+            if let Some(state) = state.downcast_mut() {
+                f(state);
+            }
+        });
+        self.handlers.insert(TypeId::of::<S>(), handler);
+        self
+    }
+
+    /// Gets the number of handlers of the Visitor.
+    pub fn len(&self) -> usize {
+        self.handlers.len()
+    }
+
+    /// Returns true only if the Visitor has no handlers.
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+
+    /// Calls the handler of the type of the State of the given Entity, if
+    /// any, and returns true only if it was called.
+    fn visit<K, C>(&mut self, entity: &mut EntityTrait<'_, K, C>) -> bool {
+        let Some(state) = entity.state_mut() else {
+            return false;
+        };
+        let state = state.as_any_mut();
+        match self.handlers.get_mut(&(*state).type_id()) {
+            Some(handler) => {
+                handler(state);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Calls the handler of the given Visitor that matches the concrete type
+    /// of the State of each Entity of the Environment, if any, in the same
+    /// order of `Environment::entities`.
+    ///
+    /// Returns the number of entities visited, that is the number of entities
+    /// whose State matched one of the handlers.
+    pub fn visit_all(&mut self, visitor: &mut Visitor<'_>) -> usize {
+        self.entities_mut()
+            .map(|entity| visitor.visit(entity))
+            .filter(|&visited| visited)
+            .count()
+    }
+
+    /// Calls the handler of the given Visitor that matches the concrete type
+    /// of the State of each Entity of the given Kind, if any.
+    ///
+    /// Returns the number of entities visited, that is the number of entities
+    /// whose State matched one of the handlers.
+    pub fn visit_kind(&mut self, kind: &K, visitor: &mut Visitor<'_>) -> usize {
+        let Some(entities) = self.entities.get_mut(kind) else {
+            return 0;
+        };
+        entities
+            .iter_mut()
+            .map(|entity| visitor.visit(&mut **entity))
+            .filter(|&visited| visited)
+            .count()
+    }
+}
@@ -0,0 +1,420 @@
+use std::any::{Any, TypeId};
+use std::time::Duration;
+
+use super::*;
+
+/// The type of the global resources stored in the Environment.
+#[cfg(not(feature = "parallel"))]
+type Resource = Box<dyn Any>;
+
+/// The type of the global resources stored in the Environment.
+#[cfg(feature = "parallel")]
+type Resource = Box<dyn Any + Send + Sync>;
+
+/// The type of the shared context given to the entities for a generation.
+#[cfg(not(feature = "parallel"))]
+pub(crate) type Shared = dyn Any;
+
+/// The type of the shared context given to the entities for a generation.
+#[cfg(feature = "parallel")]
+pub(crate) type Shared = dyn Any + Send + Sync;
+
+/// The map of global resources of the Environment, where the Environment can
+/// store at most one resource of each type.
+#[derive(Debug, Default)]
+pub(crate) struct Resources {
+    resources: HashMap<TypeId, Resource>,
+}
+
+impl Resources {
+    /// Gets a reference to the resource of the given type.
+    fn get<T: Any>(&self) -> Option<&T> {
+        self.resources.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+}
+
+// the layers of the EnvironmentStack are shared by reference with the entities
+// dispatched on different threads, which is only sound as long as the
+// Environment is Send and Sync by itself (given kinds that are Send and Sync)
+#[cfg(feature = "parallel")]
+#[allow(dead_code)]
+fn assert_send_sync<'a, K: Send + Sync, C>(
+    env: &'a Environment<'_, K, C>,
+) -> &'a (dyn Send + Sync + 'a) {
+    env
+}
+
+/// A read-only view of the global information of the Environment, given to
+/// the entities when they observe and react to their Neighborhood.
+///
+/// The WorldView allows the entities to make decisions based on the state of
+/// the whole Environment (such as the total number of entities of a specific
+/// Kind), as it was at the beginning of the current generation.
+pub struct WorldView<'a, 'e, K, C = ()> {
+    generation: Generation,
+    dimension: Dimension,
+    clock: Clock,
+    counts: BTreeMap<K, usize>,
+    births: &'a HashMap<Id, Generation>,
+    resources: &'a Resources,
+    // the context shared with the entities for the current generation, if any
+    shared: Option<&'a Shared>,
+    // the layers of the EnvironmentStack the Environment belongs to, if any,
+    // where the layer of the Environment itself is None
+    layers: &'a [Option<&'a Environment<'e, K, C>>],
+}
+
+impl<'a, 'e, K: fmt::Debug, C> fmt::Debug for WorldView<'a, 'e, K, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorldView")
+            .field("generation", &self.generation)
+            .field("dimension", &self.dimension)
+            .field("counts", &self.counts)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, 'e, K: Ord, C> WorldView<'a, 'e, K, C> {
+    /// Gets the current generation step number.
+    pub fn generation(&self) -> Generation {
+        self.generation
+    }
+
+    /// Gets the Dimension of the Environment.
+    pub fn dimension(&self) -> Dimension {
+        self.dimension
+    }
+
+    /// Gets the wall-clock duration of the current generation, scaled by the
+    /// time scale of the Environment (see `Environment::set_time_scale`).
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    /// use std::time::Duration;
+    ///
+    /// // an Entity that dies once its cooldown (in seconds) is over
+    /// struct Spark(Lifespan, Duration);
+    ///
+    /// impl<'e> Entity<'e> for Spark {
+    ///     type Kind = ();
+    ///
+    ///     fn id(&self) -> Id {
+    ///         0
+    ///     }
+    ///
+    ///     fn kind(&self) {}
+    ///
+    ///     fn lifespan(&self) -> Option<Lifespan> {
+    ///         Some(self.0)
+    ///     }
+    ///
+    ///     fn react_with(
+    ///         &mut self,
+    ///         _: Option<Neighborhood<'_, 'e, ()>>,
+    ///         world: &WorldView<'_, 'e, ()>,
+    ///     ) -> Result<(), Error> {
+    ///         self.1 = self.1.saturating_sub(world.dt());
+    ///         if self.1.is_zero() {
+    ///             self.0.clear();
+    ///         }
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut env = Environment::new((1, 1));
+    /// env.set_generation_duration(Duration::from_millis(100));
+    /// env.insert(Spark(Lifespan::Immortal, Duration::from_millis(400)));
+    ///
+    /// env.nextgen().unwrap();
+    /// env.set_time_scale(3.0);
+    /// env.nextgen().unwrap();
+    /// assert!(env.is_empty());
+    /// ```
+    pub fn dt(&self) -> Duration {
+        self.clock.dt()
+    }
+
+    /// Gets the factor the duration of each generation is scaled by.
+    pub fn time_scale(&self) -> f32 {
+        self.clock.scale()
+    }
+
+    /// Gets the total number of entities in the Environment.
+    pub fn count(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    /// Gets the total number of entities in the Environment of the given Kind.
+    pub fn count_kind(&self, kind: &K) -> usize {
+        self.counts.get(kind).copied().unwrap_or(0)
+    }
+
+    /// Gets the total number of entities in the Environment whose Kind belongs
+    /// to the given group.
+    pub fn count_group<G: PartialEq>(&self, group: &G) -> usize
+    where
+        K: KindGroup<G>,
+    {
+        self.counts
+            .iter()
+            .filter(|(kind, _)| kind.group() == *group)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Gets the age of the Entity with the given ID, that is the number of
+    /// generations since it was inserted into the Environment (see
+    /// `Environment::age`), or None if no such Entity exists.
+    pub fn age(&self, id: Id) -> Option<u64> {
+        let birth = self.births.get(&id)?;
+        Some(self.generation.since(*birth))
+    }
+
+    /// Gets a reference to the global resource of the given type, if any.
+    pub fn resource<T: Any>(&self) -> Option<&T> {
+        self.resources.get()
+    }
+
+    /// Gets a reference to the context shared with all the entities for the
+    /// current generation (see `Environment::nextgen_with`), if it is of the
+    /// given type.
+    pub fn shared<S: Any>(&self) -> Option<&'a S> {
+        self.shared?.downcast_ref()
+    }
+
+    /// Sets the context shared with all the entities for the current
+    /// generation.
+    pub(super) fn with_shared(mut self, shared: Option<&'a Shared>) -> Self {
+        self.shared = shared;
+        self
+    }
+
+    /// Gets the locations of the tiles of the 2x2 block (Margolus
+    /// neighborhood) that contains the given Location in the current
+    /// generation, sorted as top-left, top-right, bottom-left and bottom-right
+    /// (wrapped around the Environment edges).
+    ///
+    /// The blocks are aligned to the even rows and columns in even generations,
+    /// and to the odd ones in odd generations, as for the rules attached with
+    /// `Environment::attach_block_rule`. Entities that implement block
+    /// automata can observe their block with a scope of 1, and all the entities
+    /// of the same block compute the same update.
+    pub fn margolus_block(
+        &self,
+        location: impl Into<Location>,
+    ) -> [Location; 4] {
+        margolus_block(location.into(), self.generation, self.dimension)
+    }
+
+    /// Gets a read-only reference to the layer with the given index of the
+    /// EnvironmentStack the Environment belongs to.
+    ///
+    /// Returns None if the Environment does not belong to any stack, if the
+    /// index is out of bounds, or if the index is the one of the layer of the
+    /// Environment itself (whose entities are being updated).
+    ///
+    /// The layers that precede the Environment in the stack have already been
+    /// moved to the current generation, while the layers that follow it are
+    /// still in the previous one (see `EnvironmentStack::nextgen`).
+    ///
+    /// With the `parallel` feature, the same layers are read by all the
+    /// threads the entities are dispatched on, and their resources, components,
+    /// columns and tile data are therefore all Send and Sync.
+    pub fn layer(&self, index: usize) -> Option<&'a Environment<'e, K, C>> {
+        self.layers.get(index).copied().flatten()
+    }
+
+    /// Gets the index of the layer of the Environment in the EnvironmentStack
+    /// it belongs to, or None if it does not belong to any stack.
+    pub fn layer_index(&self) -> Option<usize> {
+        self.layers.iter().position(Option::is_none)
+    }
+
+    /// Gets a read-only reference to the layer at the given vertical offset
+    /// from the layer of the Environment in the EnvironmentStack it belongs
+    /// to, where positive offsets move towards the top of the stack (see
+    /// `WorldView::layer`).
+    ///
+    /// Returns None if the Environment does not belong to any stack, if there
+    /// is no layer at such offset, or if the offset is 0.
+    pub fn layer_at_offset(
+        &self,
+        offset: isize,
+    ) -> Option<&'a Environment<'e, K, C>> {
+        let index = self.layer_index()?.checked_add_signed(offset)?;
+        self.layer(index)
+    }
+}
+
+impl<'e, K: Ord, C> Environment<'e, K, C> {
+    /// Inserts the given global resource into the Environment, replacing and
+    /// returning the resource of the same type previously inserted, if any.
+    ///
+    /// Global resources (such as the amount of food available, or the current
+    /// season) are shared by all the entities, that can read them from the
+    /// WorldView given to `Entity::observe_with` and `Entity::react_with`.
+    #[cfg(not(feature = "parallel"))]
+    pub fn insert_resource<T: Any>(&mut self, resource: T) -> Option<T> {
+        self.resources
+            .resources
+            .insert(TypeId::of::<T>(), Box::new(resource))
+            .and_then(|r| r.downcast().ok())
+            .map(|r| *r)
+    }
+
+    /// Inserts the given global resource into the Environment, replacing and
+    /// returning the resource of the same type previously inserted, if any.
+    ///
+    /// Global resources (such as the amount of food available, or the current
+    /// season) are shared by all the entities, that can read them from the
+    /// WorldView given to `Entity::observe_with` and `Entity::react_with`.
+    #[cfg(feature = "parallel")]
+    pub fn insert_resource<T: Any + Send + Sync>(
+        &mut self,
+        resource: T,
+    ) -> Option<T> {
+        self.resources
+            .resources
+            .insert(TypeId::of::<T>(), Box::new(resource))
+            .and_then(|r| r.downcast().ok())
+            .map(|r| *r)
+    }
+
+    /// Moves forwards to the next generation as `Environment::nextgen`, where
+    /// the given context is shared by reference with all the entities, that
+    /// can read it from the WorldView given to `Entity::observe_with` and
+    /// `Entity::react_with` (see `WorldView::shared`).
+    ///
+    /// The shared context is meant for the read-only configuration of the
+    /// simulation (such as its rules or palettes), that the entities would
+    /// otherwise need to store each (such as via an Rc), and unlike the global
+    /// resources it is borrowed rather than owned by the Environment, for a
+    /// single generation.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// struct Rules {
+    ///     max_energy: u32,
+    /// }
+    ///
+    /// struct Cell(u32);
+    ///
+    /// impl<'e> Entity<'e> for Cell {
+    ///     type Kind = ();
+    ///
+    ///     fn id(&self) -> Id {
+    ///         0
+    ///     }
+    ///
+    ///     fn kind(&self) {}
+    ///
+    ///     fn react_with(
+    ///         &mut self,
+    ///         _: Option<Neighborhood<'_, 'e, ()>>,
+    ///         world: &WorldView<'_, 'e, ()>,
+    ///     ) -> Result<(), Error> {
+    ///         let rules = world
+    ///             .shared::<Rules>()
+    ///             .ok_or_else(|| Error::with_message("Missing rules"))?;
+    ///         self.0 = (self.0 + 1).min(rules.max_energy);
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut env = Environment::new((3, 3));
+    /// env.insert(Cell(0));
+    /// let rules = Rules { max_energy: 2 };
+    /// for _ in 0..5 {
+    ///     env.nextgen_with(&rules).unwrap();
+    /// }
+    /// assert!(env.nextgen().is_err());
+    /// ```
+    #[cfg(not(feature = "parallel"))]
+    pub fn nextgen_with<S: Any>(
+        &mut self,
+        shared: &S,
+    ) -> Result<Generation, Error>
+    where
+        K: ParallelKind,
+    {
+        self.nextgen_with_layers(&[], Some(shared), &Progress::none())
+    }
+
+    /// Moves forwards to the next generation as `Environment::nextgen`, where
+    /// the given context is shared by reference with all the entities, that
+    /// can read it from the WorldView given to `Entity::observe_with` and
+    /// `Entity::react_with` (see `WorldView::shared`).
+    ///
+    /// The shared context is meant for the read-only configuration of the
+    /// simulation (such as its rules or palettes), that the entities would
+    /// otherwise need to store each (such as via an Arc), and unlike the
+    /// global resources it is borrowed rather than owned by the Environment,
+    /// for a single generation.
+    #[cfg(feature = "parallel")]
+    pub fn nextgen_with<S: Any + Send + Sync>(
+        &mut self,
+        shared: &S,
+    ) -> Result<Generation, Error>
+    where
+        K: ParallelKind,
+    {
+        self.nextgen_with_layers(&[], Some(shared), &Progress::none())
+    }
+
+    /// Removes the global resource of the given type from the Environment, and
+    /// returns it.
+    pub fn remove_resource<T: Any>(&mut self) -> Option<T> {
+        self.resources
+            .resources
+            .remove(&TypeId::of::<T>())?
+            .downcast()
+            .ok()
+            .map(|r| *r)
+    }
+
+    /// Gets a reference to the global resource of the given type, if any.
+    pub fn resource<T: Any>(&self) -> Option<&T> {
+        self.resources.get()
+    }
+
+    /// Gets a mutable reference to the global resource of the given type, if
+    /// any.
+    pub fn resource_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.resources
+            .resources
+            .get_mut(&TypeId::of::<T>())?
+            .downcast_mut()
+    }
+
+    /// Constructs the WorldView of the current generation of the Environment.
+    ///
+    /// The view is built from the individual fields of the Environment, so that
+    /// its entities can be borrowed mutably while the view is alive.
+    pub(super) fn world_view<'a>(
+        generation: Generation,
+        clock: Clock,
+        tiles: &Tiles<'e, K, C>,
+        entities: &EntitiesKinds<'e, K, C>,
+        births: &'a HashMap<Id, Generation>,
+        resources: &'a Resources,
+        layers: &'a [Option<&'a Environment<'e, K, C>>],
+    ) -> WorldView<'a, 'e, K, C> {
+        let counts = entities
+            .values()
+            .filter_map(|e| e.first().map(|first| (first.kind(), e.len())))
+            .collect();
+        WorldView {
+            generation,
+            dimension: tiles.dimension(),
+            clock,
+            counts,
+            births,
+            resources,
+            shared: None,
+            layers,
+        }
+    }
+}
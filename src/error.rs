@@ -20,6 +20,10 @@ pub enum Error {
     Code(i32),
     /// The Message variant allows to encode the error as a string.
     Message(String),
+    /// The Cancelled variant is raised when an operation is cancelled by the
+    /// user before its completion, such as a generation cancelled via the
+    /// callback given to `Environment::nextgen_with_progress`.
+    Cancelled,
     /// The Any variant allows to encode any type of error with performance costs
     /// due to the heap allocations, and type erasure.
     ///
@@ -65,6 +69,7 @@ impl fmt::Display for Error {
             Self::Unknown => write!(f, "Error unknown"),
             Self::Code(code) => write!(f, "{}", code),
             Self::Message(message) => write!(f, "{}", message),
+            Self::Cancelled => write!(f, "Operation cancelled"),
             Self::Any(err) => write!(f, "{}", err),
         }
     }
@@ -37,9 +37,17 @@ pub use env::*;
 pub use error::*;
 pub use math::*;
 pub use space::*;
+pub use spatial::*;
+
+#[cfg(feature = "scripting")]
+pub use rhai;
+#[cfg(feature = "derive")]
+pub use semeion_derive::Entity;
 
 pub mod entity;
 pub mod env;
 pub mod error;
 pub mod math;
 pub mod space;
+pub mod spatial;
+pub mod testing;
@@ -0,0 +1,207 @@
+use super::*;
+
+/// The default minimum zoom of a Camera.
+pub const DEFAULT_MIN_ZOOM: f32 = 0.1;
+
+/// The default maximum zoom of a Camera.
+pub const DEFAULT_MAX_ZOOM: f32 = 10.0;
+
+/// A 2D camera that looks at the world (such as an Environment drawn in pixel
+/// coordinates) through a viewport, with a zoom, a pan and a rotation, and
+/// that produces the Transform given to `Environment::draw`.
+///
+/// The center of the Camera is the world Coordinate shown at the center of
+/// the viewport, and the zoom is always clamped within the zoom limits of the
+/// Camera.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let viewport = Size { width: 200.0, height: 100.0 };
+/// let mut camera = Camera::new(viewport);
+/// assert_eq!(camera.transform(), Transform::identity());
+///
+/// // zoom in around the center of the viewport
+/// camera.set_zoom(2.0);
+/// let point = Coordinate { x: 110.0, y: 50.0 };
+/// assert_eq!(camera.world_to_screen(point), Coordinate { x: 120.0, y: 50.0 });
+/// assert_eq!(camera.screen_to_world([120.0, 50.0]), point);
+///
+/// // fit an Environment of 10x10 tiles of 20 pixels into the viewport
+/// camera.fit((10, 10), TileGrid::new(20.0));
+/// assert_eq!(camera.zoom(), 0.5);
+/// assert_eq!(camera.center(), Coordinate { x: 100.0, y: 100.0 });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    center: Coordinate,
+    zoom: f32,
+    rotation: f32,
+    viewport: Size,
+    min_zoom: f32,
+    max_zoom: f32,
+}
+
+impl Camera {
+    /// Constructs a new Camera for the given viewport, centered on the center
+    /// of the viewport, without zoom nor rotation, so that its transformation
+    /// is the identity.
+    pub fn new(viewport: impl Into<Size>) -> Self {
+        let viewport = viewport.into();
+        Self {
+            center: viewport.center(),
+            zoom: 1.0,
+            rotation: 0.0,
+            viewport,
+            min_zoom: DEFAULT_MIN_ZOOM,
+            max_zoom: DEFAULT_MAX_ZOOM,
+        }
+    }
+
+    /// Sets the minimum and maximum zoom of the Camera, clamping its current
+    /// zoom accordingly.
+    ///
+    /// # Panics
+    /// Panics if the minimum zoom is not positive, or if it is greater than
+    /// the maximum zoom.
+    pub fn zoom_limits(mut self, min: f32, max: f32) -> Self {
+        assert!(min > 0.0, "The minimum zoom must be positive");
+        assert!(min <= max, "The minimum zoom cannot exceed the maximum");
+        self.min_zoom = min;
+        self.max_zoom = max;
+        self.set_zoom(self.zoom);
+        self
+    }
+
+    /// Gets the world Coordinate shown at the center of the viewport.
+    pub fn center(&self) -> Coordinate {
+        self.center
+    }
+
+    /// Sets the world Coordinate shown at the center of the viewport.
+    pub fn set_center(&mut self, center: impl Into<Coordinate>) {
+        self.center = center.into();
+    }
+
+    /// Pans the Camera by the given displacement in screen pixels, so that
+    /// the world moves along with it, regardless of the zoom and rotation.
+    pub fn pan(&mut self, delta: impl Into<Vector>) {
+        let delta = Transform::rotate(-self.rotation) * delta.into();
+        self.center = self.center - delta / self.zoom;
+    }
+
+    /// Gets the zoom of the Camera, where values greater than 1 zoom in.
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Sets the zoom of the Camera, clamped within its zoom limits.
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(self.min_zoom, self.max_zoom);
+    }
+
+    /// Multiplies the zoom of the Camera by the given factor, keeping the world
+    /// Coordinate under the given screen Coordinate (such as the cursor) in
+    /// place.
+    pub fn zoom_around(&mut self, factor: f32, screen: impl Into<Coordinate>) {
+        let screen = screen.into();
+        let anchor = self.screen_to_world(screen);
+        self.set_zoom(self.zoom * factor);
+        // move the center so that the anchor is back under the screen point
+        self.center = self.center + anchor - self.screen_to_world(screen);
+    }
+
+    /// Gets the rotation of the Camera in degrees.
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    /// Sets the rotation of the Camera in degrees, around the center of the
+    /// viewport.
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation % 360.0;
+    }
+
+    /// Gets the Size of the viewport of the Camera.
+    pub fn viewport(&self) -> Size {
+        self.viewport
+    }
+
+    /// Sets the Size of the viewport of the Camera, such as after the window
+    /// is resized, keeping the same world Coordinate at its center.
+    pub fn set_viewport(&mut self, viewport: impl Into<Size>) {
+        self.viewport = viewport.into();
+    }
+
+    /// Centers the Camera on the grid of tiles of the given Dimension and
+    /// geometry, with the highest zoom (within the zoom limits) that shows all
+    /// of it in the viewport.
+    pub fn fit(&mut self, dimension: impl Into<Dimension>, grid: TileGrid) {
+        let dimension = dimension.into();
+        let size = Size {
+            width: dimension.x as f32 * grid.side,
+            height: dimension.y as f32 * grid.side,
+        };
+        self.center = size.center();
+        let zoom = (self.viewport.width / size.width)
+            .min(self.viewport.height / size.height);
+        self.set_zoom(zoom);
+    }
+
+    /// Moves the center of the Camera towards the given world Coordinate by
+    /// the given fraction of their distance, in [0, 1], where 1 moves the
+    /// Camera right onto the target, so that calling it once per frame
+    /// follows the target smoothly.
+    pub fn follow(&mut self, target: impl Into<Coordinate>, smoothing: f32) {
+        let target = target.into();
+        let smoothing = smoothing.clamp(0.0, 1.0);
+        self.center = self.center + (target - self.center) * smoothing;
+    }
+
+    /// Moves the center of the Camera towards the center of the tile of the
+    /// Entity with the given ID (see `Camera::follow`), according to the
+    /// TileGrid of the given Environment.
+    ///
+    /// Returns false if there is no such Entity, or if it has no location.
+    pub fn follow_entity<K: Ord, C>(
+        &mut self,
+        env: &Environment<'_, K, C>,
+        id: Id,
+        smoothing: f32,
+    ) -> bool {
+        let location = env
+            .entities()
+            .find(|entity| entity.id() == id)
+            .and_then(|entity| entity.location());
+        match location {
+            Some(location) => {
+                let target = env.grid().tile_rect(location).center();
+                self.follow(target, smoothing);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Gets the transformation from world to screen coordinates.
+    pub fn transform(&self) -> Transform {
+        Transform::translate(self.viewport.center())
+            * Transform::rotate(self.rotation)
+            * Transform::scale([self.zoom, self.zoom])
+            * Transform::translate([-self.center.x, -self.center.y])
+    }
+
+    /// Converts the given world Coordinate to screen coordinates.
+    pub fn world_to_screen(&self, world: impl Into<Coordinate>) -> Coordinate {
+        self.transform() * world.into()
+    }
+
+    /// Converts the given screen Coordinate (such as the cursor) to world
+    /// coordinates.
+    pub fn screen_to_world(&self, screen: impl Into<Coordinate>) -> Coordinate {
+        let offset = screen.into() - self.viewport.center();
+        let offset = Transform::rotate(-self.rotation) * offset;
+        self.center + offset / self.zoom
+    }
+}
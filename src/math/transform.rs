@@ -125,6 +125,76 @@ impl Transform {
         }
     }
 
+    /// Constructs a transformation that scales by the given factors, then
+    /// rotates by the given angle in degrees, and finally translates by the
+    /// given displacements, all relative to the origin.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let transform = Transform::from_srt([2.0, 2.0], 90.0, [1.0, 0.0]);
+    /// let expected = Transform::translate([1.0, 0.0])
+    ///     * Transform::rotate(90.0)
+    ///     * Transform::scale([2.0, 2.0]);
+    /// assert_eq!(transform, expected);
+    ///
+    /// let point = transform * Vector { x: 1.0, y: 0.0 };
+    /// assert!((point.x - 1.0).abs() < 1e-6 && (point.y - 2.0).abs() < 1e-6);
+    /// ```
+    pub fn from_srt(
+        scale: impl Into<Vector>,
+        rotation: f32,
+        translation: impl Into<Vector>,
+    ) -> Self {
+        Self::translate(translation)
+            * Self::rotate(rotation)
+            * Self::scale(scale)
+    }
+
+    /// Constructs the transformation from the local frame of an Entity to the
+    /// pixel coordinates of the Environment, for an Entity located at the
+    /// given Location of a grid of tiles with the given side, and rotated by
+    /// the given angle in degrees clockwise around the center of its tile
+    /// (such as the `Orientation::degrees` of the Entity).
+    ///
+    /// The given anchor is the position of the origin of the local frame
+    /// relative to the top-left corner of the (unrotated) tile, such as the
+    /// origin for shapes drawn from the top-left corner of the tile, or half
+    /// the side for shapes centered on the origin.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let location = Location { x: 1, y: 2 };
+    /// let t = Transform::for_entity(location, 10.0, 0.0, [0.0, 0.0]);
+    /// assert_eq!(t * Coordinate { x: 0.0, y: 0.0 }, Coordinate { x: 10.0, y: 20.0 });
+    ///
+    /// // the top-left corner of the tile is rotated around its center
+    /// let angle = Orientation::East.degrees();
+    /// let t = Transform::for_entity(location, 10.0, angle, [0.0, 0.0]);
+    /// let corner = t * Coordinate { x: 0.0, y: 0.0 };
+    /// assert!((corner.x - 20.0).abs() < 1e-4 && (corner.y - 20.0).abs() < 1e-4);
+    ///
+    /// // shapes centered on the origin are drawn in the center of the tile
+    /// let t = Transform::for_entity(location, 10.0, angle, [5.0, 5.0]);
+    /// let center = t * Coordinate { x: 0.0, y: 0.0 };
+    /// assert!((center.x - 15.0).abs() < 1e-4 && (center.y - 25.0).abs() < 1e-4);
+    /// ```
+    pub fn for_entity(
+        location: impl Into<Location>,
+        side: f32,
+        angle: f32,
+        anchor: impl Into<Vector>,
+    ) -> Self {
+        let origin = location.into().to_pixel_coords(side);
+        let half = side / 2f32;
+        Self::translate(origin)
+            * Self::rotate_around(angle, [half, half])
+            * Self::translate(anchor)
+    }
+
     /// Gets the rotation angle in degrees.
     pub fn rotation(self) -> f32 {
         // compute the x skew angle
@@ -322,3 +392,106 @@ impl SubAssign<Transform> for Transform {
         *self = *self - other;
     }
 }
+
+/// A stack of transformations, where each transformation pushed is composed
+/// with the one below it, such as a camera transformation at the bottom of
+/// the stack, followed by the local transformations of the entities drawn.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let camera = Transform::scale([2.0, 2.0]);
+/// let mut stack = TransformStack::with_base(camera);
+///
+/// stack.push(Transform::translate([10.0, 0.0]));
+/// let origin = stack.current() * Vector { x: 0.0, y: 0.0 };
+/// assert_eq!(origin, Vector { x: 20.0, y: 0.0 });
+///
+/// let point = stack.scoped(Transform::translate([0.0, 5.0]), |stack| {
+///     stack.current() * Vector { x: 0.0, y: 0.0 }
+/// });
+/// assert_eq!(point, Vector { x: 20.0, y: 10.0 });
+///
+/// assert!(stack.pop().is_some());
+/// assert!(stack.pop().is_none());
+/// assert_eq!(stack.current(), camera);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformStack {
+    // the composed transformations, where the first is the base and it is
+    // never popped
+    transforms: Vec<Transform>,
+}
+
+impl TransformStack {
+    /// Constructs a new TransformStack with the identity as base.
+    pub fn new() -> Self {
+        Self::with_base(Transform::identity())
+    }
+
+    /// Constructs a new TransformStack with the given transformation as base,
+    /// that is composed with all the transformations pushed.
+    pub fn with_base(base: Transform) -> Self {
+        Self {
+            transforms: vec![base],
+        }
+    }
+
+    /// Gets the current transformation, that is the composition of the base
+    /// with all the transformations pushed, in order.
+    pub fn current(&self) -> Transform {
+        *self.transforms.last().expect("Empty TransformStack")
+    }
+
+    /// Gets the composition of the current transformation with the given one,
+    /// without pushing it.
+    pub fn compose(&self, transform: Transform) -> Transform {
+        self.current() * transform
+    }
+
+    /// Pushes the given transformation, composed with the current one, and
+    /// returns the new current transformation.
+    pub fn push(&mut self, transform: Transform) -> Transform {
+        let current = self.compose(transform);
+        self.transforms.push(current);
+        current
+    }
+
+    /// Pops the last transformation pushed, and returns it composed with the
+    /// ones below it, or None if only the base is left.
+    pub fn pop(&mut self) -> Option<Transform> {
+        if self.transforms.len() > 1 {
+            self.transforms.pop()
+        } else {
+            None
+        }
+    }
+
+    /// Pushes the given transformation, calls the given function with the
+    /// stack, and pops the transformation before returning the result of the
+    /// function, so that pushes and pops are always balanced.
+    pub fn scoped<R>(
+        &mut self,
+        transform: Transform,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let depth = self.depth();
+        self.push(transform);
+        let result = f(self);
+        self.transforms.truncate(depth + 1);
+        result
+    }
+
+    /// Gets the number of transformations pushed on top of the base.
+    pub fn depth(&self) -> usize {
+        self.transforms.len() - 1
+    }
+}
+
+impl Default for TransformStack {
+    /// Returns a TransformStack with the identity as base.
+    fn default() -> Self {
+        Self::new()
+    }
+}
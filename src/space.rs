@@ -1,5 +1,8 @@
+use std::collections::HashSet;
 use std::ops::{Add, Sub};
 
+use rustc_hash::{FxHashMap, FxHashSet};
+
 /// A Point in 2D space.
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Point<T> {
@@ -14,6 +17,14 @@ pub struct Dimension {
     pub y: i32,
 }
 
+/// A rectangular area of a grid, identified by the Location of its top-left
+/// tile and by its Dimension.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub origin: Location,
+    pub dimension: Dimension,
+}
+
 /// The size of a graphic element represented as number of pixels.
 #[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub struct Size {
@@ -21,6 +32,35 @@ pub struct Size {
     pub height: f32,
 }
 
+/// The scale factor between the logical pixels used to draw, and the physical
+/// pixels of a display, as the number of physical pixels per logical pixel.
+///
+/// On HiDPI displays the windowing systems usually report the size of the
+/// windows and the position of the cursor in physical pixels, while the
+/// drawing happens in logical pixels, so that the former need to be scaled
+/// before being mapped to the tiles of an Environment.
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct PixelScale(f32);
+
+/// The geometry of the grid of tiles of an Environment in pixels, used to
+/// convert Locations to pixel Coordinates and vice versa, so that drawing and
+/// picking share the same source of truth.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TileGrid {
+    /// The length in (logical) pixels of the side of each squared tile.
+    pub side: f32,
+    /// The scale factor of the display the grid is drawn on.
+    pub scale: PixelScale,
+}
+
+/// A rectangular area expressed in pixel coordinates, identified by the
+/// Coordinate of its top-left corner and by its Size.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct PixelRect {
+    pub origin: Coordinate,
+    pub size: Size,
+}
+
 /// Represents the location of an entity within the environment as pair of
 /// coordinate that identify the environment grid tile.
 pub type Location = Point<i32>;
@@ -28,6 +68,24 @@ pub type Location = Point<i32>;
 /// Represents an offset from an Entity location within the environment.
 pub type Offset = Point<i32>;
 
+/// A hash map keyed by Location, that uses a hasher faster than the default
+/// one, suited to the hot paths such as the caches of the visited tiles.
+pub type LocationMap<T> = FxHashMap<Location, T>;
+
+/// A hash set of locations, that uses a hasher faster than the default one,
+/// suited to the hot paths such as the sets of the tiles of a pattern.
+pub type LocationSet = FxHashSet<Location>;
+
+/// Represents the location of an entity within a layer of an
+/// EnvironmentStack, as the coordinates of the tile of the layer, and the
+/// index of the layer in the stack (from the bottom to the top).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Location3 {
+    pub x: i32,
+    pub y: i32,
+    pub layer: usize,
+}
+
 /// Represents the location of an entity within the environment expressed in
 /// pixel coordinates.
 pub type Coordinate = Point<f32>;
@@ -95,6 +153,17 @@ impl Location {
         }
     }
 
+    /// Converts the Point into a point expressed as physical pixel coordinates,
+    /// according to the length of each grid square side in logical pixels, and
+    /// to the given scale factor.
+    pub fn to_physical_coords(
+        self,
+        side: f32,
+        scale: PixelScale,
+    ) -> Coordinate {
+        scale.to_physical(self.to_pixel_coords(side))
+    }
+
     /// Maps a 2-dimensional coordinate in a Torus of the given dimension, to a
     /// 1-dimensional index.
     pub fn one_dimensional(self, dimension: impl Into<Dimension>) -> usize {
@@ -121,6 +190,38 @@ impl Location {
         }
     }
 
+    /// Gets the compact key of the Location in a Torus of the given dimension,
+    /// that is the 1-dimensional index of the Location once wrapped around the
+    /// Torus edges, so that all the locations that wrap to the same tile have
+    /// the same key.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let dimension = Dimension { x: 10, y: 5 };
+    /// let location = Location { x: 3, y: 2 };
+    /// assert_eq!(location.to_key(dimension), 23);
+    /// assert_eq!(Location { x: -7, y: 7 }.to_key(dimension), 23);
+    /// assert_eq!(Location::from_key(23, dimension), location);
+    ///
+    /// let mut visited = LocationSet::default();
+    /// assert!(visited.insert(location));
+    /// assert!(!visited.insert(Location::from_key(23, dimension)));
+    /// ```
+    pub fn to_key(self, dimension: impl Into<Dimension>) -> u64 {
+        let dimension = dimension.into();
+        let mut location = self;
+        location.translate(Offset::origin(), dimension);
+        location.one_dimensional(dimension) as u64
+    }
+
+    /// Gets the Location with the given compact key in a Torus of the given
+    /// dimension (see `Location::to_key`).
+    pub fn from_key(key: u64, dimension: impl Into<Dimension>) -> Self {
+        Self::from_one_dimensional(key as usize, dimension)
+    }
+
     /// Translates the Location coordinates by the given Offset, while keeping the
     /// final Location within a Torus with the given dimension.
     ///
@@ -166,6 +267,56 @@ impl Location {
     }
 }
 
+impl Location3 {
+    /// Constructs a new Location3 from the given Location within the layer
+    /// with the given index.
+    pub const fn new(location: Location, layer: usize) -> Self {
+        Self {
+            x: location.x,
+            y: location.y,
+            layer,
+        }
+    }
+
+    /// Gets the Location of the tile within its layer.
+    pub const fn location(self) -> Location {
+        Location {
+            x: self.x,
+            y: self.y,
+        }
+    }
+
+    /// Gets the Location3 of the same tile in the layer at the given vertical
+    /// offset from this one, where positive offsets move towards the top of
+    /// the stack, or None if the layer index would be negative.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let location = Location3::from((1, 2, 1));
+    /// assert_eq!(location.translate_layer(1), Some((1, 2, 2).into()));
+    /// assert_eq!(location.translate_layer(-1), Some((1, 2, 0).into()));
+    /// assert_eq!(location.translate_layer(-2), None);
+    /// ```
+    pub fn translate_layer(self, offset: isize) -> Option<Self> {
+        let layer = self.layer.checked_add_signed(offset)?;
+        Some(Self { layer, ..self })
+    }
+}
+
+impl From<(i32, i32, usize)> for Location3 {
+    fn from((x, y, layer): (i32, i32, usize)) -> Self {
+        Self { x, y, layer }
+    }
+}
+
+impl From<(Location, usize)> for Location3 {
+    fn from((location, layer): (Location, usize)) -> Self {
+        Self::new(location, layer)
+    }
+}
+
 impl From<(i32, i32)> for Location {
     fn from((x, y): (i32, i32)) -> Self {
         Self { x, y }
@@ -178,6 +329,34 @@ impl From<Location> for (i32, i32) {
     }
 }
 
+/// The facing of an Entity, as one of the four cardinal directions, where
+/// North points towards the top of the grid (negative y).
+///
+/// The Orientation maps the offsets in the local frame of an Entity, where the
+/// tile in front of it is always at `Offset { x: 0, y: -1 }` and the one on its
+/// right at `Offset { x: 1, y: 0 }`, to the offsets of the grid, and vice
+/// versa.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let orientation = Orientation::East;
+/// assert_eq!(orientation.forward(), Offset { x: 1, y: 0 });
+/// assert_eq!(orientation.to_world((1, 0)), Offset { x: 0, y: 1 });
+/// assert_eq!(orientation.to_local((0, 1)), Offset { x: 1, y: 0 });
+/// assert_eq!(orientation.turn_right(), Orientation::South);
+/// assert_eq!(Orientation::from_degrees(-80.0), Orientation::West);
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Orientation {
+    #[default]
+    North,
+    East,
+    South,
+    West,
+}
+
 impl Offset {
     /// Gets a list of offsets from a central location in a grid, to all the tiles
     /// located in its border, according to the given distance between the tile
@@ -225,6 +404,74 @@ impl Offset {
     }
 }
 
+impl Orientation {
+    /// Gets the Orientation closest to the given angle in degrees, measured
+    /// clockwise from North.
+    pub fn from_degrees(degrees: f32) -> Self {
+        Self::North.rotate((degrees / 90.0).round().rem_euclid(4.0) as u8)
+    }
+
+    /// Gets the angle of the Orientation in degrees, measured clockwise from
+    /// North.
+    pub fn degrees(self) -> f32 {
+        self.quarter_turns() as f32 * 90.0
+    }
+
+    /// Gets the Orientation after a quarter turn clockwise.
+    pub fn turn_right(self) -> Self {
+        self.rotate(1)
+    }
+
+    /// Gets the Orientation after a quarter turn counterclockwise.
+    pub fn turn_left(self) -> Self {
+        self.rotate(3)
+    }
+
+    /// Gets the opposite Orientation.
+    pub fn opposite(self) -> Self {
+        self.rotate(2)
+    }
+
+    /// Gets the Offset of the tile in front of an Entity with this
+    /// Orientation.
+    pub fn forward(self) -> Offset {
+        self.to_world((0, -1))
+    }
+
+    /// Maps the given Offset from the local frame of an Entity with this
+    /// Orientation to the grid.
+    pub fn to_world(self, local: impl Into<Offset>) -> Offset {
+        let Offset { x, y } = local.into();
+        match self {
+            Self::North => Offset { x, y },
+            Self::East => Offset { x: -y, y: x },
+            Self::South => Offset { x: -x, y: -y },
+            Self::West => Offset { x: y, y: -x },
+        }
+    }
+
+    /// Maps the given Offset from the grid to the local frame of an Entity
+    /// with this Orientation.
+    pub fn to_local(self, world: impl Into<Offset>) -> Offset {
+        Self::North.rotate(4 - self.quarter_turns()).to_world(world)
+    }
+
+    /// Gets the number of clockwise quarter turns from North.
+    fn quarter_turns(self) -> u8 {
+        self as u8
+    }
+
+    /// Gets the Orientation after the given number of clockwise quarter turns.
+    fn rotate(self, turns: u8) -> Self {
+        match (self.quarter_turns() + turns) % 4 {
+            0 => Self::North,
+            1 => Self::East,
+            2 => Self::South,
+            _ => Self::West,
+        }
+    }
+}
+
 impl Size {
     /// Gets the Coordinate of the center of this Size.
     pub fn center(self) -> Coordinate {
@@ -241,6 +488,201 @@ impl Size {
             y: (self.height / side) as i32,
         }
     }
+
+    /// Converts the Size in physical pixels (such as the size of a window
+    /// reported by the windowing system) to a Dimension, according to the
+    /// given side length in logical pixels and the given scale factor.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let window = Size { width: 1600.0, height: 800.0 };
+    /// let dimension = window.to_dimension_scaled(10.0, PixelScale::new(2.0));
+    /// assert_eq!(dimension, Dimension { x: 80, y: 40 });
+    /// ```
+    pub fn to_dimension_scaled(
+        self,
+        side: f32,
+        scale: PixelScale,
+    ) -> Dimension {
+        scale.to_logical_size(self).to_dimension(side)
+    }
+}
+
+impl Default for PixelScale {
+    /// Gets the PixelScale of displays where the logical pixels are the same
+    /// as the physical pixels.
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl PixelScale {
+    /// The PixelScale where the logical pixels are the same as the physical
+    /// pixels.
+    pub const IDENTITY: Self = Self(1.0);
+
+    /// Constructs a new PixelScale with the given number of physical pixels per
+    /// logical pixel (such as the scale factor reported by the windowing
+    /// system), that is 1 if the factor is not finite and positive.
+    pub fn new(factor: f32) -> Self {
+        if factor.is_finite() && factor > 0.0 {
+            Self(factor)
+        } else {
+            Self::IDENTITY
+        }
+    }
+
+    /// Gets the number of physical pixels per logical pixel.
+    pub fn factor(self) -> f32 {
+        self.0
+    }
+
+    /// Converts the given Coordinate in logical pixels to physical pixels.
+    pub fn to_physical(self, coordinate: impl Into<Coordinate>) -> Coordinate {
+        let coordinate = coordinate.into();
+        Coordinate {
+            x: coordinate.x * self.0,
+            y: coordinate.y * self.0,
+        }
+    }
+
+    /// Converts the given Coordinate in physical pixels to logical pixels.
+    pub fn to_logical(self, coordinate: impl Into<Coordinate>) -> Coordinate {
+        let coordinate = coordinate.into();
+        Coordinate {
+            x: coordinate.x / self.0,
+            y: coordinate.y / self.0,
+        }
+    }
+
+    /// Converts the given Size in logical pixels to physical pixels.
+    pub fn to_physical_size(self, size: impl Into<Size>) -> Size {
+        let size = size.into();
+        Size {
+            width: size.width * self.0,
+            height: size.height * self.0,
+        }
+    }
+
+    /// Converts the given Size in physical pixels to logical pixels.
+    pub fn to_logical_size(self, size: impl Into<Size>) -> Size {
+        let size = size.into();
+        Size {
+            width: size.width / self.0,
+            height: size.height / self.0,
+        }
+    }
+}
+
+impl Default for TileGrid {
+    /// Gets the TileGrid whose tiles have a side of 1 pixel, with no scaling.
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl TileGrid {
+    /// Constructs a new TileGrid whose tiles have the given side length in
+    /// pixels, with no scaling.
+    pub const fn new(side: f32) -> Self {
+        Self::with_scale(side, PixelScale::IDENTITY)
+    }
+
+    /// Constructs a new TileGrid whose tiles have the given side length in
+    /// logical pixels, drawn on a display with the given scale factor.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let grid = TileGrid::with_scale(10.0, PixelScale::new(2.0));
+    /// assert_eq!(grid.to_pixel((1, 2)), Coordinate { x: 10.0, y: 20.0 });
+    /// assert_eq!(grid.to_physical((1, 2)), Coordinate { x: 20.0, y: 40.0 });
+    /// let cursor = Coordinate { x: 45.0, y: 19.0 };
+    /// assert_eq!(grid.physical_to_location(cursor), Location { x: 2, y: 0 });
+    /// ```
+    pub const fn with_scale(side: f32, scale: PixelScale) -> Self {
+        Self { side, scale }
+    }
+
+    /// Gets the pixel Coordinate of the top-left corner of the tile at the
+    /// given Location.
+    pub fn to_pixel(self, location: impl Into<Location>) -> Coordinate {
+        location.into().to_pixel_coords(self.side)
+    }
+
+    /// Gets the Location of the tile that contains the given pixel Coordinate.
+    ///
+    /// The Location is not wrapped around the edges of any Environment, and it
+    /// is therefore negative for negative coordinates.
+    pub fn to_location(self, coordinate: impl Into<Coordinate>) -> Location {
+        let coordinate = coordinate.into();
+        Location {
+            x: (coordinate.x / self.side).floor() as i32,
+            y: (coordinate.y / self.side).floor() as i32,
+        }
+    }
+
+    /// Gets the area in pixel coordinates covered by the tile at the given
+    /// Location.
+    pub fn tile_rect(self, location: impl Into<Location>) -> PixelRect {
+        PixelRect {
+            origin: self.to_pixel(location),
+            size: Size {
+                width: self.side,
+                height: self.side,
+            },
+        }
+    }
+
+    /// Gets the Dimension of the grid of tiles that fits in the given Size.
+    pub fn dimension(self, size: impl Into<Size>) -> Dimension {
+        size.into().to_dimension(self.side)
+    }
+
+    /// Gets the physical pixel Coordinate of the top-left corner of the tile
+    /// at the given Location.
+    pub fn to_physical(self, location: impl Into<Location>) -> Coordinate {
+        location.into().to_physical_coords(self.side, self.scale)
+    }
+
+    /// Gets the Location of the tile that contains the given physical pixel
+    /// Coordinate (such as the position of the cursor reported by the
+    /// windowing system), as `TileGrid::to_location`.
+    pub fn physical_to_location(
+        self,
+        coordinate: impl Into<Coordinate>,
+    ) -> Location {
+        self.to_location(self.scale.to_logical(coordinate))
+    }
+
+    /// Gets the Dimension of the grid of tiles that fits in the given Size in
+    /// physical pixels (such as the size of a window reported by the windowing
+    /// system).
+    pub fn physical_dimension(self, size: impl Into<Size>) -> Dimension {
+        size.into().to_dimension_scaled(self.side, self.scale)
+    }
+}
+
+impl PixelRect {
+    /// Gets the Coordinate of the center of this PixelRect.
+    pub fn center(self) -> Coordinate {
+        Coordinate {
+            x: self.origin.x + self.size.width / 2.0,
+            y: self.origin.y + self.size.height / 2.0,
+        }
+    }
+
+    /// Returns true only if the given Coordinate is within this PixelRect.
+    pub fn contains(self, coordinate: impl Into<Coordinate>) -> bool {
+        let coordinate = coordinate.into();
+        coordinate.x >= self.origin.x
+            && coordinate.y >= self.origin.y
+            && coordinate.x < self.origin.x + self.size.width
+            && coordinate.y < self.origin.y + self.size.height
+    }
 }
 
 impl From<(f32, f32)> for Size {
@@ -280,6 +722,38 @@ impl Dimension {
         self.x.saturating_mul(self.y) as usize
     }
 
+    /// Constructs a new Dimension of a single row of the given number of tiles,
+    /// that can be used to construct a 1-dimensional Environment.
+    ///
+    /// The neighborhoods of the entities of a 1-dimensional Environment only
+    /// extend to the left and to the right of their location, so that the
+    /// Scope of an Entity is the number of tiles it can see on each side.
+    ///
+    /// # Example
+    /// ```
+    /// use semeion::*;
+    ///
+    /// let dimension = Dimension::line(8);
+    /// assert_eq!(dimension, Dimension { x: 8, y: 1 });
+    /// assert!(dimension.is_line());
+    ///
+    /// let mut env = Environment::<char>::new(dimension);
+    /// env.insert(QuickEntity::new('a').at((7, 0)));
+    /// let neighborhood = env.neighborhood_at((0, 0), Scope::with_magnitude(2));
+    /// let neighborhood = neighborhood.unwrap();
+    /// assert_eq!(neighborhood.dimension(), Dimension::line(5));
+    /// assert_eq!(neighborhood.tile(Offset { x: -1, y: 0 }).count(), 1);
+    /// ```
+    pub const fn line(len: i32) -> Self {
+        Self { x: len, y: 1 }
+    }
+
+    /// Returns true only if this Dimension is made of a single row of tiles,
+    /// and therefore it represents a 1-dimensional space.
+    pub const fn is_line(self) -> bool {
+        self.y == 1
+    }
+
     /// Returns true only if the number of tiles in the grid is 0.
     pub fn is_empty(self) -> bool {
         self.len() == 0
@@ -325,6 +799,82 @@ impl Dimension {
         }
     }
 
+    /// Splits self into the lowest number of rectangles of (roughly) equal
+    /// size that is greater or equal than the given `count`, aligned so that
+    /// the number of rows and columns are equal (see
+    /// `Dimension::with_eq_rectangles`).
+    ///
+    /// The rectangles in the last row and column extend to the edges of self,
+    /// so that the whole area is covered even if self cannot be divided
+    /// exactly. The rectangles are returned from the top-left to the
+    /// bottom-right corner.
+    pub fn split(self, count: usize) -> Vec<Rect> {
+        let grid = Self::with_eq_rectangles(count);
+        let tile = grid.scale(self);
+        let mut rects = Vec::with_capacity(grid.len());
+        for j in 0..grid.y {
+            for i in 0..grid.x {
+                let origin = Location {
+                    x: i * tile.x,
+                    y: j * tile.y,
+                };
+                let end = Location {
+                    x: if i == grid.x - 1 {
+                        self.x
+                    } else {
+                        origin.x + tile.x
+                    },
+                    y: if j == grid.y - 1 {
+                        self.y
+                    } else {
+                        origin.y + tile.y
+                    },
+                };
+                let dimension = Dimension {
+                    x: end.x - origin.x,
+                    y: end.y - origin.y,
+                };
+                rects.push(Rect::new(origin, dimension));
+            }
+        }
+        rects
+    }
+
+    /// Tiles self with rectangles of the given Dimension, starting from the
+    /// origin.
+    ///
+    /// The rectangles in the last row and column are clipped to the edges of
+    /// self if self cannot be divided exactly. The rectangles are returned
+    /// from the top-left to the bottom-right corner.
+    pub fn sub_rects(self, tile: impl Into<Self>) -> Vec<Rect> {
+        let tile = tile.into();
+        debug_assert!(tile.x.is_positive() && tile.y.is_positive());
+        let mut rects = Vec::new();
+        for y in (0..self.y).step_by(tile.y as usize) {
+            for x in (0..self.x).step_by(tile.x as usize) {
+                let dimension = Dimension {
+                    x: tile.x.min(self.x - x),
+                    y: tile.y.min(self.y - y),
+                };
+                rects.push(Rect::new((x, y), dimension));
+            }
+        }
+        rects
+    }
+
+    /// Returns true only if the given Rect is entirely within this Dimension.
+    pub fn contains_rect(self, rect: impl Into<Rect>) -> bool {
+        let rect = rect.into();
+        if rect.is_empty() {
+            return self.contains(rect.origin);
+        }
+        let last = Location {
+            x: rect.origin.x + rect.dimension.x - 1,
+            y: rect.origin.y + rect.dimension.y - 1,
+        };
+        self.contains(rect.origin) && self.contains(last)
+    }
+
     /// Gets the length of the side of a squared grid (where the number of rows
     /// is equal to the number of columns), given a specific scope (maximum
     /// distance from the center tile of the grid to the farthest).
@@ -358,6 +908,41 @@ impl Dimension {
     }
 }
 
+impl Rect {
+    /// Constructs a new Rect with the given origin and Dimension.
+    pub fn new(
+        origin: impl Into<Location>,
+        dimension: impl Into<Dimension>,
+    ) -> Self {
+        Self {
+            origin: origin.into(),
+            dimension: dimension.into(),
+        }
+    }
+
+    /// Gets the number of tiles covered by this Rect.
+    pub fn len(self) -> usize {
+        self.dimension.len()
+    }
+
+    /// Returns true only if this Rect does not cover any tile.
+    pub fn is_empty(self) -> bool {
+        self.dimension.is_empty()
+    }
+
+    /// Returns true only if the given Location is within this Rect.
+    pub fn contains(self, location: impl Into<Location>) -> bool {
+        self.dimension.contains(location.into() - self.origin)
+    }
+}
+
+impl From<Dimension> for Rect {
+    /// Constructs a Rect with origin in (0, 0) and the given Dimension.
+    fn from(dimension: Dimension) -> Self {
+        Self::new(Location::origin(), dimension)
+    }
+}
+
 impl From<(i32, i32)> for Dimension {
     fn from((x, y): (i32, i32)) -> Self {
         Self { x, y }
@@ -400,14 +985,479 @@ impl Scope {
 
     /// Returns true only if the area covered by the neighborhood of an Entity
     /// with such Scope, would be bigger (in the x or y dimension) of the given
-    /// Dimension.
+    /// Dimension. The neighborhoods of a line only extend horizontally.
     pub(crate) fn overflows(self, dimension: impl Into<Dimension>) -> bool {
-        let side = Dimension::side_with_scope(self) as i32;
+        let side = self.magnitude() as i32 * 2 + 1;
+        let dimension = dimension.into();
+        side > dimension.x || (side > dimension.y && !dimension.is_line())
+    }
+}
+
+/// A set of locations of the grid of an Environment, that can be used to
+/// query and update the portion of the Environment it covers.
+///
+/// The locations of a Region are defined on an unbounded plane, and they are
+/// wrapped around the edges of the Torus of the Environment only when the
+/// Region is iterated, so that a Region that crosses the edges of the
+/// Environment covers the tiles on the opposite side.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let dimension = Dimension { x: 10, y: 10 };
+/// let disk = Disk::new((0, 0), 1);
+/// assert!(disk.contains(Location { x: -1, y: 0 }));
+/// assert!(!disk.contains(Location { x: 1, y: 1 }));
+///
+/// let locations: Vec<_> = disk.iter(dimension).collect();
+/// assert_eq!(locations.len(), 5);
+/// assert!(locations.contains(&Location { x: 9, y: 0 }));
+///
+/// let union = Union::new().with(disk).with(Rect::new((5, 5), (2, 2)));
+/// assert_eq!(union.iter(dimension).count(), 9);
+///
+/// let line = Line::new((0, 0), (4, 2));
+/// assert!(line.contains(Location { x: 2, y: 1 }));
+/// assert_eq!(line.iter(dimension).count(), 5);
+/// assert_eq!(Circle::new((5, 5), 3).iter(dimension).count(), 16);
+/// ```
+pub trait Region {
+    /// Returns true only if the given Location, on the unbounded plane (that
+    /// is without wrapping it around the edges of any Torus), belongs to the
+    /// Region.
+    fn contains(&self, location: Location) -> bool;
+
+    /// Gets an iterator over all the locations of the Region, wrapped around
+    /// the edges of the Torus of the given Dimension, where each Location is
+    /// returned only once even if the Region is bigger than the Torus.
+    fn iter(&self, dimension: Dimension) -> Box<dyn Iterator<Item = Location>>;
+
+    /// Gets a random Location of the Region, wrapped around the edges of the
+    /// Torus of the given Dimension, given a source of uniformly distributed
+    /// random numbers, or None if the Region is empty.
+    fn random_in(
+        &self,
+        dimension: Dimension,
+        rng: &mut dyn FnMut() -> u64,
+    ) -> Option<Location> {
+        let locations: Vec<_> = self.iter(dimension).collect();
+        if locations.is_empty() {
+            None
+        } else {
+            Some(locations[(rng() % locations.len() as u64) as usize])
+        }
+    }
+}
+
+/// A circular Region, made of all the locations whose Euclidean distance from
+/// its center is not greater than its radius.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Disk {
+    pub center: Location,
+    pub radius: u32,
+}
+
+/// An annular Region, made of all the locations whose Euclidean distance from
+/// its center is between its inner and outer radius (inclusive).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Ring {
+    pub center: Location,
+    pub inner: u32,
+    pub outer: u32,
+}
+
+/// A straight segment Region between two locations (both inclusive), made of
+/// the locations rasterized by Bresenham's line algorithm, so that each pair
+/// of consecutive locations are adjacent (possibly diagonally).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Line {
+    pub from: Location,
+    pub to: Location,
+}
+
+/// The outline of a circle, made of the locations rasterized by the midpoint
+/// circle algorithm for its center and radius (see Disk for the filled
+/// circle).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Circle {
+    pub center: Location,
+    pub radius: u32,
+}
+
+/// The Region made of all the locations reachable from a starting Location by
+/// moving horizontally and vertically (wrapping around the edges of the Torus
+/// of a given Dimension) only across the locations that satisfy a predicate,
+/// such as the empty tiles enclosed by a wall of entities.
+///
+/// Unlike the other regions, the locations of a Flood are computed once, and
+/// they are already wrapped around the edges of the Torus, so that it only
+/// contains locations within the bounds of its Dimension.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let dimension = Dimension { x: 5, y: 5 };
+/// // a wall around the center of a square of side 3
+/// let square = Rect::new((1, 1), (3, 3));
+/// let center = Location { x: 2, y: 2 };
+/// let wall = |location| square.contains(location) && location != center;
+///
+/// let inside = Flood::new(center, dimension, |location| !wall(location));
+/// assert_eq!(inside.len(), 1);
+///
+/// // the outside of the wall wraps around the edges
+/// let outside = Flood::new((0, 0), dimension, |location| !wall(location));
+/// assert_eq!(outside.len(), 16);
+/// assert!(!outside.contains(center));
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Flood {
+    // the locations, sorted by row and then by column
+    locations: Vec<Location>,
+}
+
+/// The union of any number of regions.
+#[derive(Default)]
+pub struct Union {
+    regions: Vec<Box<dyn Region>>,
+}
+
+impl Disk {
+    /// Constructs a new Disk with the given center and radius.
+    pub fn new(center: impl Into<Location>, radius: u32) -> Self {
+        Self {
+            center: center.into(),
+            radius,
+        }
+    }
+}
+
+impl Ring {
+    /// Constructs a new Ring with the given center, and inner and outer radius.
+    pub fn new(center: impl Into<Location>, inner: u32, outer: u32) -> Self {
+        Self {
+            center: center.into(),
+            inner,
+            outer,
+        }
+    }
+}
+
+impl Line {
+    /// Constructs a new Line between the given locations.
+    pub fn new(from: impl Into<Location>, to: impl Into<Location>) -> Self {
+        Self {
+            from: from.into(),
+            to: to.into(),
+        }
+    }
+
+    /// Gets the locations of this Line, on the unbounded plane, from its start
+    /// to its end.
+    fn locations(self) -> impl Iterator<Item = Location> {
+        let dx = (self.to.x - self.from.x).abs();
+        let dy = -(self.to.y - self.from.y).abs();
+        let step_x = if self.from.x < self.to.x { 1 } else { -1 };
+        let step_y = if self.from.y < self.to.y { 1 } else { -1 };
+        let (to, mut error) = (self.to, dx + dy);
+        let mut next = Some(self.from);
+        std::iter::from_fn(move || {
+            let location = next?;
+            next = (location != to).then(|| {
+                let (mut next, double) = (location, 2 * error);
+                if double >= dy {
+                    error += dy;
+                    next.x += step_x;
+                }
+                if double <= dx {
+                    error += dx;
+                    next.y += step_y;
+                }
+                next
+            });
+            Some(location)
+        })
+    }
+}
+
+impl Circle {
+    /// Constructs a new Circle with the given center and radius.
+    pub fn new(center: impl Into<Location>, radius: u32) -> Self {
+        Self {
+            center: center.into(),
+            radius,
+        }
+    }
+
+    /// Gets the locations of this Circle, on the unbounded plane, sorted by
+    /// row and then by column.
+    fn locations(self) -> Vec<Location> {
+        let mut locations = Vec::new();
+        let (mut x, mut y) = (self.radius as i32, 0);
+        let mut error = 1 - x;
+        // compute an octant, and mirror it into the other seven
+        while x >= y {
+            for (x, y) in [(x, y), (y, x)] {
+                for offset in [(x, y), (-x, y), (x, -y), (-x, -y)] {
+                    locations.push(self.center + Offset::from(offset));
+                }
+            }
+            y += 1;
+            if error < 0 {
+                error += 2 * y + 1;
+            } else {
+                x -= 1;
+                error += 2 * (y - x) + 1;
+            }
+        }
+        locations.sort_unstable_by_key(|l| (l.y, l.x));
+        locations.dedup();
+        locations
+    }
+}
+
+impl Flood {
+    /// Constructs a new Flood from the given starting Location, across the
+    /// locations that satisfy the given predicate, within the Torus of the
+    /// given Dimension.
+    ///
+    /// The Flood is empty if the starting Location does not satisfy the
+    /// predicate.
+    pub fn new(
+        start: impl Into<Location>,
+        dimension: impl Into<Dimension>,
+        mut predicate: impl FnMut(Location) -> bool,
+    ) -> Self {
         let dimension = dimension.into();
-        side > dimension.x || side > dimension.y
+        let mut start = start.into();
+        start.translate((0, 0), dimension);
+        let mut seen = HashSet::new();
+        let mut stack = vec![start];
+        let mut locations = Vec::new();
+        seen.insert(start);
+        while let Some(location) = stack.pop() {
+            if !predicate(location) {
+                continue;
+            }
+            locations.push(location);
+            for offset in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let mut neighbor = location;
+                neighbor.translate(offset, dimension);
+                if seen.insert(neighbor) {
+                    stack.push(neighbor);
+                }
+            }
+        }
+        locations.sort_unstable_by_key(|l| (l.y, l.x));
+        Self { locations }
+    }
+
+    /// Gets the number of locations of the Flood.
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    /// Returns true only if the Flood has no locations.
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+}
+
+impl Union {
+    /// Constructs a new empty Union.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the given Region to the Union.
+    pub fn with(mut self, region: impl Region + 'static) -> Self {
+        self.regions.push(Box::new(region));
+        self
+    }
+
+    /// Gets the number of regions of the Union.
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Returns true only if the Union has no regions.
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+}
+
+impl std::fmt::Debug for Union {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Union")
+            .field("regions", &self.regions.len())
+            .finish()
+    }
+}
+
+impl Region for Rect {
+    fn contains(&self, location: Location) -> bool {
+        Rect::contains(*self, location)
+    }
+
+    fn iter(&self, dimension: Dimension) -> Box<dyn Iterator<Item = Location>> {
+        // the rows and columns of a Rect bigger than the Torus would only
+        // cover the same tiles again
+        let origin = self.origin;
+        let columns = self.dimension.x.clamp(0, dimension.x);
+        let rows = self.dimension.y.clamp(0, dimension.y);
+        Box::new((0..rows).flat_map(move |y| {
+            (0..columns).map(move |x| {
+                let mut location = origin;
+                *location.translate(Offset { x, y }, dimension)
+            })
+        }))
+    }
+
+    fn random_in(
+        &self,
+        dimension: Dimension,
+        rng: &mut dyn FnMut() -> u64,
+    ) -> Option<Location> {
+        if self.is_empty() {
+            return None;
+        }
+        let offset = Offset {
+            x: (rng() % self.dimension.x as u64) as i32,
+            y: (rng() % self.dimension.y as u64) as i32,
+        };
+        let mut location = self.origin;
+        Some(*location.translate(offset, dimension))
     }
 }
 
+impl Region for Disk {
+    fn contains(&self, location: Location) -> bool {
+        squared_distance(self.center, location) <= (self.radius as i64).pow(2)
+    }
+
+    fn iter(&self, dimension: Dimension) -> Box<dyn Iterator<Item = Location>> {
+        let region = *self;
+        let bounds = bounding_rect(self.center, self.radius);
+        Box::new(wrap_unique(
+            bounds.locations().filter(move |&l| region.contains(l)),
+            dimension,
+        ))
+    }
+}
+
+impl Region for Ring {
+    fn contains(&self, location: Location) -> bool {
+        let distance = squared_distance(self.center, location);
+        distance >= (self.inner as i64).pow(2)
+            && distance <= (self.outer as i64).pow(2)
+    }
+
+    fn iter(&self, dimension: Dimension) -> Box<dyn Iterator<Item = Location>> {
+        let region = *self;
+        let bounds = bounding_rect(self.center, self.outer);
+        Box::new(wrap_unique(
+            bounds.locations().filter(move |&l| region.contains(l)),
+            dimension,
+        ))
+    }
+}
+
+impl Region for Line {
+    fn contains(&self, location: Location) -> bool {
+        self.locations().any(|l| l == location)
+    }
+
+    fn iter(&self, dimension: Dimension) -> Box<dyn Iterator<Item = Location>> {
+        Box::new(wrap_unique(self.locations(), dimension))
+    }
+}
+
+impl Region for Circle {
+    fn contains(&self, location: Location) -> bool {
+        self.locations().contains(&location)
+    }
+
+    fn iter(&self, dimension: Dimension) -> Box<dyn Iterator<Item = Location>> {
+        Box::new(wrap_unique(self.locations().into_iter(), dimension))
+    }
+}
+
+impl Region for Flood {
+    fn contains(&self, location: Location) -> bool {
+        self.locations
+            .binary_search_by_key(&(location.y, location.x), |l| (l.y, l.x))
+            .is_ok()
+    }
+
+    fn iter(&self, dimension: Dimension) -> Box<dyn Iterator<Item = Location>> {
+        let locations = self.locations.clone();
+        Box::new(wrap_unique(locations.into_iter(), dimension))
+    }
+}
+
+impl Region for Union {
+    fn contains(&self, location: Location) -> bool {
+        self.regions.iter().any(|region| region.contains(location))
+    }
+
+    fn iter(&self, dimension: Dimension) -> Box<dyn Iterator<Item = Location>> {
+        let locations: Vec<_> = self
+            .regions
+            .iter()
+            .flat_map(|region| region.iter(dimension))
+            .collect();
+        Box::new(wrap_unique(locations.into_iter(), dimension))
+    }
+}
+
+impl Rect {
+    /// Gets an iterator over all the locations of this Rect, on the unbounded
+    /// plane, sorted by row and then by column.
+    fn locations(self) -> impl Iterator<Item = Location> {
+        let origin = self.origin;
+        let columns = self.dimension.x.max(0);
+        (0..self.dimension.y.max(0)).flat_map(move |y| {
+            (0..columns).map(move |x| origin + Offset { x, y })
+        })
+    }
+}
+
+/// Gets the square of the Euclidean distance between the given locations.
+fn squared_distance(a: Location, b: Location) -> i64 {
+    let dx = (a.x as i64 - b.x as i64).pow(2);
+    let dy = (a.y as i64 - b.y as i64).pow(2);
+    dx + dy
+}
+
+/// Gets the smallest Rect that contains the circle of the given center and
+/// radius.
+fn bounding_rect(center: Location, radius: u32) -> Rect {
+    let radius = radius as i32;
+    let side = radius * 2 + 1;
+    Rect::new(
+        center
+            - Offset {
+                x: radius,
+                y: radius,
+            },
+        (side, side),
+    )
+}
+
+/// Wraps the given locations around the edges of the Torus of the given
+/// Dimension, discarding the locations already returned.
+fn wrap_unique(
+    locations: impl Iterator<Item = Location>,
+    dimension: Dimension,
+) -> impl Iterator<Item = Location> {
+    let mut seen = HashSet::new();
+    locations
+        .map(move |mut location| *location.translate((0, 0), dimension))
+        .filter(move |&location| seen.insert(location))
+}
+
 impl Add for Point<i32> {
     type Output = Self;
 
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use super::*;
+
+/// A grid of squared tiles of the same side length, where each tile stores a
+/// map of values identified by their IDs.
+///
+/// The SpatialGrid is the same spatial index used by the Environment to keep
+/// track of the location of its entities, with a generic payload, so that it
+/// can be reused outside of the Environment.
+/// As for the Environment, the geometry of the grid is defined as a Torus,
+/// therefore, out of bounds locations will be translated considering that the
+/// grid edges are joined.
+///
+/// # Example
+/// ```
+/// use semeion::*;
+///
+/// let mut grid = SpatialGrid::new((4, 4));
+/// grid.insert(0, (0, 0), "a");
+/// grid.insert(1, (-1, 0), "b");
+/// assert_eq!(grid.len(), 2);
+///
+/// // locations are wrapped around the edges of the grid
+/// assert_eq!(grid.get(1, (3, 0)), Some(&"b"));
+/// assert_eq!(grid.at((3, 0)).count(), 1);
+///
+/// // the value with ID 1 is within a scope of 1 from the origin
+/// assert_eq!(grid.neighbors((0, 0), 1).count(), 2);
+///
+/// assert!(grid.relocate(0, (0, 0), (2, 2)));
+/// assert_eq!(grid.neighbors((0, 0), 1).count(), 1);
+/// assert_eq!(grid.remove(0, (2, 2)), Some("a"));
+/// assert_eq!(grid.len(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpatialGrid<T> {
+    dimension: Dimension,
+    tiles: Vec<HashMap<Id, T>>,
+}
+
+impl<T> SpatialGrid<T> {
+    /// Constructs a new SpatialGrid of the given dimension with no values.
+    pub fn new(dimension: impl Into<Dimension>) -> Self {
+        let dimension = dimension.into();
+        let mut tiles = Vec::with_capacity(dimension.len());
+        tiles.resize_with(dimension.len(), HashMap::default);
+        Self { dimension, tiles }
+    }
+
+    /// Gets the Dimension of the grid.
+    pub fn dimension(&self) -> Dimension {
+        self.dimension
+    }
+
+    /// Gets the total number of values stored in the grid.
+    pub fn len(&self) -> usize {
+        self.tiles.iter().map(HashMap::len).sum()
+    }
+
+    /// Returns true only if no value is stored in the grid.
+    pub fn is_empty(&self) -> bool {
+        self.tiles.iter().all(HashMap::is_empty)
+    }
+
+    /// Removes all the values from the grid.
+    pub fn clear(&mut self) {
+        for tile in &mut self.tiles {
+            tile.clear();
+        }
+    }
+
+    /// Inserts the value with the given ID at the given location.
+    /// Returns the value previously stored with the same ID at the same
+    /// location, if any.
+    pub fn insert(
+        &mut self,
+        id: Id,
+        location: impl Into<Location>,
+        value: T,
+    ) -> Option<T> {
+        let index = self.index(location);
+        self.tiles[index].insert(id, value)
+    }
+
+    /// Removes the value with the given ID from the given location.
+    /// Returns the removed value, if any.
+    pub fn remove(
+        &mut self,
+        id: Id,
+        location: impl Into<Location>,
+    ) -> Option<T> {
+        let index = self.index(location);
+        self.tiles[index].remove(&id)
+    }
+
+    /// Moves the value with the given ID between a previous and a new location.
+    /// Returns whether the value was found in the previous location and moved.
+    pub fn relocate(
+        &mut self,
+        id: Id,
+        from: impl Into<Location>,
+        to: impl Into<Location>,
+    ) -> bool {
+        if let Some(value) = self.remove(id, from) {
+            let index = self.index(to);
+            self.tiles[index].insert(id, value);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Gets a reference to the value with the given ID at the given location.
+    pub fn get(&self, id: Id, location: impl Into<Location>) -> Option<&T> {
+        self.tiles[self.index(location)].get(&id)
+    }
+
+    /// Gets a mutable reference to the value with the given ID at the given
+    /// location.
+    pub fn get_mut(
+        &mut self,
+        id: Id,
+        location: impl Into<Location>,
+    ) -> Option<&mut T> {
+        let index = self.index(location);
+        self.tiles[index].get_mut(&id)
+    }
+
+    /// Gets an iterator over all the values (and their IDs) stored at the given
+    /// location, in arbitrary order.
+    pub fn at(
+        &self,
+        location: impl Into<Location>,
+    ) -> impl Iterator<Item = (Id, &T)> {
+        self.tiles[self.index(location)]
+            .iter()
+            .map(|(&id, value)| (id, value))
+    }
+
+    /// Gets an iterator over all the mutable values (and their IDs) stored at
+    /// the given location, in arbitrary order.
+    pub fn at_mut(
+        &mut self,
+        location: impl Into<Location>,
+    ) -> impl Iterator<Item = (Id, &mut T)> {
+        let index = self.index(location);
+        self.tiles[index].iter_mut().map(|(&id, value)| (id, value))
+    }
+
+    /// Gets an iterator over all the values (with their location and ID)
+    /// stored within the given Scope from the given center location, that is
+    /// the squared area of side `scope * 2 + 1` centered in the given location.
+    ///
+    /// The tiles are visited from the top-left to the bottom-right corner,
+    /// while the values of the same tile are returned in arbitrary order.
+    /// If the area wraps onto itself because the grid dimension is not big
+    /// enough to contain it, the same tile may be visited more than once.
+    pub fn neighbors(
+        &self,
+        center: impl Into<Location>,
+        scope: impl Into<Scope>,
+    ) -> impl Iterator<Item = (Location, Id, &T)> {
+        let center = center.into();
+        let scope = scope.into().magnitude() as i32;
+        let dimension = self.dimension;
+        (-scope..=scope)
+            .flat_map(move |y| (-scope..=scope).map(move |x| Offset { x, y }))
+            .flat_map(move |offset| {
+                let mut location = center;
+                location.translate(offset, dimension);
+                self.at(location).map(move |(id, v)| (location, id, v))
+            })
+    }
+
+    /// Gets the index of the tile at the given location, wrapped around the
+    /// edges of the grid.
+    fn index(&self, location: impl Into<Location>) -> usize {
+        let mut wrapped = Location::origin();
+        wrapped.translate(location.into(), self.dimension);
+        let index = wrapped.one_dimensional(self.dimension);
+        debug_assert!(index < self.tiles.len());
+        index
+    }
+}
@@ -0,0 +1,157 @@
+//! Utilities to test that a simulation is deterministic, that is that the same
+//! seeded Environment always evolves in the same way, regardless of the number
+//! of threads it is run on (with the `parallel` feature), so that the entities
+//! of a model can be validated before relying on the reproducibility of its
+//! results.
+//!
+//! # Example
+//! ```
+//! use semeion::*;
+//! use std::sync::atomic::{AtomicU64, Ordering};
+//!
+//! static RUNS: AtomicU64 = AtomicU64::new(0);
+//!
+//! let build = |seeded: bool| {
+//!     let mut env = Environment::<char>::new((10, 10));
+//!     // an unseeded simulation scatters its entities differently on each run
+//!     let seed = if seeded { 7 } else { RUNS.fetch_add(1, Ordering::Relaxed) };
+//!     Seeder::new(seed).scatter('a', 20).seed(&mut env, |&kind, location| {
+//!         let entity = QuickEntity::new(kind).at(location).on_react(|e, _| {
+//!             let mut location = e.location().unwrap();
+//!             location.translate((1, 0), Dimension { x: 10, y: 10 });
+//!             e.set_location(location);
+//!             Ok(())
+//!         });
+//!         Ok(entity.boxed())
+//!     })?;
+//!     Ok(env)
+//! };
+//!
+//! testing::check_deterministic(|| build(true), 10).unwrap();
+//! assert!(testing::check_deterministic(|| build(false), 10).is_err());
+//! ```
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::*;
+
+/// Computes the digest of the current configuration of the given Environment,
+/// that, unlike `Environment::digest`, also depends on the ID of each Entity
+/// and on the order of the entities within each tile, so that two runs of a
+/// deterministic simulation always produce the same digests.
+///
+/// The digest of each Entity depends on its ID, Location and the digest of its
+/// state (see `Entity::digest`), and the digest of the whole Environment also
+/// includes its `Environment::digest`.
+pub fn digest<K: Ord, C>(env: &Environment<'_, K, C>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    env.digest().hash(&mut hasher);
+    let dimension = env.dimension();
+    for index in 0..dimension.len() {
+        let location = Location::from_one_dimensional(index, dimension);
+        for entity in env.entities_at(location) {
+            (location, entity.id(), entity.digest()).hash(&mut hasher);
+        }
+    }
+    for entity in env.entities().filter(|e| e.location().is_none()) {
+        (entity.id(), entity.digest()).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Moves the given Environment forwards by the given number of generations,
+/// and gets the digest of each generation (see `testing::digest`), starting
+/// from the current one.
+///
+/// Returns an error if any of the generations returns an error.
+pub fn digests<K: Ord + ParallelKind, C>(
+    env: &mut Environment<'_, K, C>,
+    generations: u64,
+) -> Result<Vec<u64>, Error> {
+    let mut digests = vec![digest(env)];
+    for _ in 0..generations {
+        env.nextgen()?;
+        digests.push(digest(env));
+    }
+    Ok(digests)
+}
+
+/// Runs the Environment built by the given function twice for the given
+/// number of generations, and compares the digests of each generation of the
+/// two runs (see `testing::digest`).
+///
+/// Returns an error with the first generation whose digests differ, or the
+/// first error returned by the function or by any of the generations.
+#[cfg(not(feature = "parallel"))]
+pub fn check_deterministic<'e, K: Ord, C>(
+    build: impl Fn() -> Result<Environment<'e, K, C>, Error>,
+    generations: u64,
+) -> Result<(), Error> {
+    let run = || digests(&mut build()?, generations);
+    compare(run()?, run()?)
+}
+
+/// Runs the Environment built by the given function twice for the given
+/// number of generations, and compares the digests of each generation of the
+/// two runs (see `testing::digest`).
+///
+/// The first run is executed on a single thread, while the second one is
+/// executed on the current rayon thread pool, so that the simulation is
+/// verified to be independent from the number of threads.
+///
+/// Returns an error with the first generation whose digests differ, or the
+/// first error returned by the function or by any of the generations.
+#[cfg(feature = "parallel")]
+pub fn check_deterministic<'e, K: Ord + ParallelKind, C>(
+    build: impl Fn() -> Result<Environment<'e, K, C>, Error> + Send + Sync,
+    generations: u64,
+) -> Result<(), Error> {
+    let run = || digests(&mut build()?, generations);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .map_err(Error::with_message)?;
+    compare(pool.install(run)?, run()?)
+}
+
+/// Asserts that the Environment built by the given function is deterministic
+/// for the given number of generations (see `testing::check_deterministic`).
+///
+/// # Panics
+/// Panics if the two runs diverge, or if any of them returns an error.
+#[cfg(not(feature = "parallel"))]
+pub fn assert_deterministic<'e, K: Ord, C>(
+    build: impl Fn() -> Result<Environment<'e, K, C>, Error>,
+    generations: u64,
+) {
+    if let Err(err) = check_deterministic(build, generations) {
+        panic!("The simulation is not deterministic: {}", err);
+    }
+}
+
+/// Asserts that the Environment built by the given function is deterministic
+/// for the given number of generations (see `testing::check_deterministic`).
+///
+/// # Panics
+/// Panics if the two runs diverge, or if any of them returns an error.
+#[cfg(feature = "parallel")]
+pub fn assert_deterministic<'e, K: Ord + ParallelKind, C>(
+    build: impl Fn() -> Result<Environment<'e, K, C>, Error> + Send + Sync,
+    generations: u64,
+) {
+    if let Err(err) = check_deterministic(build, generations) {
+        panic!("The simulation is not deterministic: {}", err);
+    }
+}
+
+/// Compares the digests of two runs of the same simulation.
+fn compare(expected: Vec<u64>, actual: Vec<u64>) -> Result<(), Error> {
+    match expected.iter().zip(&actual).position(|(a, b)| a != b) {
+        Some(generation) => Err(Error::with_message(format!(
+            "The digests diverge at generation {}",
+            generation
+        ))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,26 @@
+//! Regression tests of the assignment of the entities to the parallel tasks,
+//! run with the `aliasing-checks` feature so that any aliasing mutable borrow
+//! handed out by the Scheduler makes the generation panic.
+#![cfg(feature = "aliasing-checks")]
+
+use semeion::*;
+
+#[test]
+fn scope_reaching_the_next_tile_is_unsync() {
+    // the scheduler splits the Environment into as many tiles as the threads
+    std::env::set_var("RAYON_NUM_THREADS", "4");
+
+    // a line split into the tiles [0, 2), [2, 4), [4, 6) and [6, 8), where the
+    // scope of the first Entity reaches the first column of the second tile
+    let mut env = Environment::<u8>::new((8, 1));
+    env.insert(QuickEntity::new(0).id(0).at((1, 0)).scope(1));
+    env.insert(QuickEntity::new(0).id(1).at((2, 0)));
+    env.nextgen().unwrap();
+
+    // the same applies to the rows of a 2-dimensional Environment, split into
+    // 2x2 tiles of 5x5 tiles each
+    let mut env = Environment::<u8>::new((10, 10));
+    env.insert(QuickEntity::new(0).id(0).at((2, 4)).scope(1));
+    env.insert(QuickEntity::new(0).id(1).at((2, 5)));
+    env.nextgen().unwrap();
+}
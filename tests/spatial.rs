@@ -0,0 +1,107 @@
+//! Tests of the behavior of the SpatialGrid as a standalone spatial index.
+
+use semeion::*;
+
+/// Gets the sorted IDs of the values within the given scope from the center.
+fn neighbors<T>(
+    grid: &SpatialGrid<T>,
+    center: impl Into<Location>,
+    scope: usize,
+) -> Vec<Id> {
+    let mut ids: Vec<_> =
+        grid.neighbors(center, scope).map(|(_, id, _)| id).collect();
+    ids.sort_unstable();
+    ids
+}
+
+#[test]
+fn relocate_across_tiles() {
+    let mut grid = SpatialGrid::new((8, 8));
+    grid.insert(0, (1, 1), 'a');
+    grid.insert(1, (1, 1), 'b');
+
+    // only the relocated value leaves its tile
+    assert!(grid.relocate(0, (1, 1), (6, 5)));
+    assert_eq!(grid.get(0, (1, 1)), None);
+    assert_eq!(grid.get(0, (6, 5)), Some(&'a'));
+    assert_eq!(grid.at((1, 1)).collect::<Vec<_>>(), vec![(1, &'b')]);
+    assert_eq!(grid.at((6, 5)).collect::<Vec<_>>(), vec![(0, &'a')]);
+    assert_eq!(grid.len(), 2);
+
+    // the neighbors are found in the new tile only
+    assert_eq!(neighbors(&grid, (1, 1), 1), vec![1]);
+    assert_eq!(neighbors(&grid, (7, 6), 1), vec![0]);
+
+    // a value cannot be relocated from a tile where it is not stored
+    assert!(!grid.relocate(0, (1, 1), (2, 2)));
+    assert!(!grid.relocate(2, (6, 5), (2, 2)));
+    assert_eq!(grid.get(0, (6, 5)), Some(&'a'));
+
+    // the destination is wrapped around the edges of the grid
+    assert!(grid.relocate(0, (6, 5), (9, -1)));
+    assert_eq!(grid.get(0, (1, 7)), Some(&'a'));
+    assert_eq!(grid.at((6, 5)).count(), 0);
+}
+
+#[test]
+fn neighbors_wrap_around_the_edges() {
+    let mut grid = SpatialGrid::new((8, 8));
+    grid.insert(0, (0, 0), ());
+    grid.insert(1, (7, 0), ());
+    grid.insert(2, (0, 7), ());
+    grid.insert(3, (7, 7), ());
+    grid.insert(4, (4, 4), ());
+
+    // the four corners are adjacent to each other across the edges
+    for corner in [(0, 0), (7, 0), (0, 7), (7, 7)] {
+        assert_eq!(neighbors(&grid, corner, 1), vec![0, 1, 2, 3]);
+    }
+
+    // the locations of the neighbors are wrapped as well
+    let mut locations: Vec<_> = grid
+        .neighbors((0, 0), 1)
+        .map(|(location, id, _)| (id, location))
+        .collect();
+    locations.sort_unstable_by_key(|&(id, _)| id);
+    assert_eq!(
+        locations,
+        vec![
+            (0, Location { x: 0, y: 0 }),
+            (1, Location { x: 7, y: 0 }),
+            (2, Location { x: 0, y: 7 }),
+            (3, Location { x: 7, y: 7 }),
+        ]
+    );
+
+    // the center itself can be out of bounds
+    assert_eq!(neighbors(&grid, (-4, 12), 0), vec![4]);
+
+    // an area bigger than the grid visits the same tiles more than once
+    let mut grid = SpatialGrid::new((2, 2));
+    grid.insert(0, (0, 0), ());
+    grid.insert(1, (1, 1), ());
+    assert_eq!(neighbors(&grid, (0, 0), 1), vec![0, 1, 1, 1, 1]);
+}
+
+#[test]
+fn remove_values() {
+    let mut grid = SpatialGrid::new((4, 4));
+    grid.insert(0, (1, 2), "a");
+    grid.insert(1, (1, 2), "b");
+    grid.insert(2, (3, 3), "c");
+
+    // the value is removed only from the given location
+    assert_eq!(grid.remove(0, (3, 3)), None);
+    assert_eq!(grid.remove(0, (1, 2)), Some("a"));
+    assert_eq!(grid.remove(0, (1, 2)), None);
+    assert_eq!(grid.get(1, (1, 2)), Some(&"b"));
+    assert_eq!(grid.len(), 2);
+    assert_eq!(neighbors(&grid, (1, 2), 0), vec![1]);
+
+    // the location of the removed value is wrapped around the edges
+    assert_eq!(grid.remove(2, (-1, 7)), Some("c"));
+    assert_eq!(neighbors(&grid, (3, 3), 1), Vec::<Id>::new());
+
+    assert_eq!(grid.remove(1, (5, -2)), Some("b"));
+    assert!(grid.is_empty());
+}